@@ -0,0 +1,69 @@
+struct PostFxMaterial {
+    // virtual pixels
+    screen_size: vec2<f32>;
+    // 0 or 1, scanlines and the bulge
+    crt: f32;
+    vignette: f32;
+    // pixels red and blue are pulled apart at the edges
+    aberration: f32;
+    // 0 to 1 towards the flashback palette
+    flashback: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> material: PostFxMaterial;
+// the world camera's view
+[[group(1), binding(1)]]
+var screen: texture_2d<f32>;
+[[group(1), binding(2)]]
+var screen_sampler: sampler;
+// flashback colors in a row, darkest to brightest
+[[group(1), binding(3)]]
+var palette: texture_2d<f32>;
+
+struct FragmentInput {
+    [[builtin(front_facing)]] is_front: bool;
+    [[location(0)]] world_position: vec4<f32>;
+    [[location(1)]] world_normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+};
+
+fn screen_color(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(screen, screen_sampler, uv, 0.0).rgb;
+}
+
+[[stage(fragment)]]
+fn fragment(in: FragmentInput) -> [[location(0)]] vec4<f32> {
+    var uv = in.uv;
+    let centered = in.uv * 2.0 - vec2<f32>(1.0);
+    if (material.crt > 0.0) {
+        // curved like a tube, the corners fall off the screen
+        uv = centered * (1.0 + dot(centered.yx, centered.yx) * 0.04) * 0.5 + vec2<f32>(0.5);
+        if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0))) {
+            return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        }
+    }
+
+    // red and blue pulled apart, more towards the edges
+    let offset = (uv - vec2<f32>(0.5)) * 2.0 * material.aberration / material.screen_size;
+    var rgb = vec3<f32>(
+        screen_color(uv + offset).r,
+        screen_color(uv).g,
+        screen_color(uv - offset).b,
+    );
+
+    if (material.flashback > 0.0) {
+        let gray = dot(rgb, vec3<f32>(0.299, 0.587, 0.114));
+        let shades = textureDimensions(palette).x;
+        let shade = clamp(i32(gray * f32(shades)), 0, shades - 1);
+        let swapped = textureLoad(palette, vec2<i32>(shade, 0), 0).rgb;
+        rgb = mix(rgb, swapped, material.flashback);
+    }
+
+    if (material.crt > 0.0 && fract(uv.y * material.screen_size.y) >= 0.5) {
+        // the lower half of every pixel row
+        rgb = rgb * 0.75;
+    }
+    rgb = rgb * (1.0 - clamp((length(centered) - 0.6) / 0.8, 0.0, 1.0) * material.vignette);
+    return vec4<f32>(rgb, 1.0);
+}
@@ -0,0 +1,108 @@
+struct SpriteEffectMaterial {
+    // min and max of the frame in the atlas, in uv
+    uv_rect: vec4<f32>;
+    tint: vec4<f32>;
+    outline_color: vec4<f32>;
+    texel_size: vec2<f32>;
+    // texels, 0 has no outline
+    outline_width: f32;
+    // radians
+    hue_shift: f32;
+    dissolve: f32;
+    flash: f32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> material: SpriteEffectMaterial;
+[[group(1), binding(1)]]
+var texture: texture_2d<f32>;
+[[group(1), binding(2)]]
+var texture_sampler: sampler;
+// a column per swapped color, the artwork's color above its replacement
+[[group(1), binding(3)]]
+var palette: texture_2d<f32>;
+
+struct FragmentInput {
+    [[builtin(front_facing)]] is_front: bool;
+    [[location(0)]] world_position: vec4<f32>;
+    [[location(1)]] world_normal: vec3<f32>;
+    [[location(2)]] uv: vec2<f32>;
+};
+
+// alpha of the frame at uv, nothing outside of it so neighbouring frames don't bleed in
+fn frame_alpha(uv: vec2<f32>) -> f32 {
+    let min_uv = min(material.uv_rect.xy, material.uv_rect.zw);
+    let max_uv = max(material.uv_rect.xy, material.uv_rect.zw);
+    if (any(uv < min_uv) || any(uv > max_uv)) {
+        return 0.0;
+    }
+    return textureSampleLevel(texture, texture_sampler, uv, 0.0).a;
+}
+
+// rotation around the gray axis in yiq space
+fn shift_hue(color: vec3<f32>, angle: f32) -> vec3<f32> {
+    let to_yiq = mat3x3<f32>(
+        vec3<f32>(0.299, 0.596, 0.211),
+        vec3<f32>(0.587, -0.274, -0.523),
+        vec3<f32>(0.114, -0.322, 0.312),
+    );
+    let to_rgb = mat3x3<f32>(
+        vec3<f32>(1.0, 1.0, 1.0),
+        vec3<f32>(0.956, -0.272, -1.106),
+        vec3<f32>(0.621, -0.647, 1.703),
+    );
+    let yiq = to_yiq * color;
+    let c = cos(angle);
+    let s = sin(angle);
+    let rotated = vec3<f32>(yiq.x, yiq.y * c - yiq.z * s, yiq.y * s + yiq.z * c);
+    return to_rgb * rotated;
+}
+
+// stable per texel noise in 0..1
+fn hash(texel: vec2<f32>) -> f32 {
+    return fract(sin(dot(texel, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+[[stage(fragment)]]
+fn fragment(in: FragmentInput) -> [[location(0)]] vec4<f32> {
+    let uv = mix(material.uv_rect.xy, material.uv_rect.zw, in.uv);
+    var color = textureSample(texture, texture_sampler, uv);
+
+    if (color.a < 0.5 && material.outline_width > 0.0) {
+        let offset = material.texel_size * material.outline_width;
+        let neighbors = frame_alpha(uv + vec2<f32>(offset.x, 0.0))
+            + frame_alpha(uv - vec2<f32>(offset.x, 0.0))
+            + frame_alpha(uv + vec2<f32>(0.0, offset.y))
+            + frame_alpha(uv - vec2<f32>(0.0, offset.y));
+        if (neighbors > 0.0) {
+            return material.outline_color;
+        }
+    }
+
+    var rgb = color.rgb;
+    let palette_size = textureDimensions(palette);
+    for (var i: i32 = 0; i < palette_size.x; i = i + 1) {
+        let from = textureLoad(palette, vec2<i32>(i, 0), 0);
+        if (from.a > 0.0 && distance(from.rgb, rgb) < 0.01) {
+            rgb = textureLoad(palette, vec2<i32>(i, 1), 0).rgb;
+            break;
+        }
+    }
+    if (material.hue_shift != 0.0) {
+        rgb = clamp(shift_hue(rgb, material.hue_shift), vec3<f32>(0.0), vec3<f32>(1.0));
+    }
+    rgb = mix(rgb * material.tint.rgb, vec3<f32>(1.0), material.flash);
+    var alpha = color.a * material.tint.a;
+
+    if (material.dissolve > 0.0) {
+        let noise = hash(floor(uv / material.texel_size));
+        if (noise < material.dissolve) {
+            discard;
+        }
+        // glowing edge just ahead of the dissolve
+        if (noise < material.dissolve + 0.1) {
+            rgb = vec3<f32>(1.0, 0.6, 0.2);
+        }
+    }
+    return vec4<f32>(rgb, alpha);
+}
@@ -0,0 +1,419 @@
+use crate::{
+    animation::{AnimationLayerOf, AnimationSprite, Aseprite},
+    bitmap_font::{BitmapText, BitmapTextBundle},
+    collision::{CollisionLayers, CollisionLookup},
+    debug::*,
+    ldtk::world::WorldMap,
+    sprite_effects::SpriteEffects,
+    Actor, Direction, RAPIER_SCALE, Z_ACTORS, Z_COLLISION,
+};
+use bevy::{
+    ecs::system::{EntityCommands, SystemParam},
+    prelude::*,
+};
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// the sprite pivot relative to the body center, the artwork isn't centered in its frame
+pub const SPRITE_OFFSET: Vec3 = Vec3::new(4.0, 6.0, Z_ACTORS);
+// from the body center past the bottom of the capsule, in pixels
+const GROUND_RAY_LENGTH: f32 = 14.0;
+
+pub struct ActorPlugin;
+impl Plugin for ActorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(foot_sensor_system)
+            .add_system(sprite_layers_system);
+    }
+}
+
+// actor roots carry the rigid body and the body collider, everything else hangs below
+// them in one of these children:
+// the animated sprite, mirrored around the root on facing changes
+#[derive(Component)]
+pub struct SpriteRoot;
+
+// parent of extra colliders like hurtboxes and sensors, moves with the root
+#[derive(Component)]
+pub struct ColliderRoot;
+
+// parent of debug shapes and labels
+#[derive(Component)]
+pub struct DebugRoot;
+
+// on the foot sensor collider, with the number of colliders it overlaps
+#[derive(Component, Default)]
+pub struct FootSensor {
+    contacts: usize,
+}
+
+// on actor roots while their foot sensor touches something solid
+#[derive(Component)]
+pub struct Grounded {
+    // world space, away from the ground
+    pub normal: Vec2,
+    // collider below the actor's center, if the ray along gravity hit one
+    pub ground: Option<Entity>,
+}
+
+#[derive(Clone, Copy)]
+enum ActorColliderKind {
+    Hurtbox,
+    Foot,
+}
+
+// box sensor below the collider root, in pixels
+#[derive(Clone, Copy)]
+struct ActorCollider {
+    kind: ActorColliderKind,
+    half_extents: Vec2,
+    offset: Vec2,
+}
+
+// rigid body and an actor's colliders: the solid body collider blocks, the hurtbox
+// sensor is what contact damage and attacks test against, the foot sensor tracks the ground
+pub struct ActorBundle {
+    position: Vec2,
+    body: ColliderShape,
+    // CollisionLayers::PLAYER, ENEMY or NPC
+    layer: u32,
+    colliders: Vec<ActorCollider>,
+    sprite_effects: Option<SpriteEffects>,
+}
+
+impl ActorBundle {
+    // position in pixels, the body shape in rapier units
+    pub fn new(position: Vec2, body: ColliderShape, layer: u32) -> Self {
+        Self {
+            position,
+            body,
+            layer,
+            colliders: vec![],
+            sprite_effects: None,
+        }
+    }
+
+    // draws the sprite through the effects material, see sprite_effects.rs
+    pub fn with_sprite_effects(mut self, sprite_effects: SpriteEffects) -> Self {
+        self.sprite_effects = Some(sprite_effects);
+        self
+    }
+
+    pub fn with_hurtbox(mut self, half_extents: Vec2, offset: Vec2) -> Self {
+        self.colliders.push(ActorCollider {
+            kind: ActorColliderKind::Hurtbox,
+            half_extents,
+            offset,
+        });
+        self
+    }
+
+    pub fn with_foot_sensor(mut self, half_extents: Vec2, offset: Vec2) -> Self {
+        self.colliders.push(ActorCollider {
+            kind: ActorColliderKind::Foot,
+            half_extents,
+            offset,
+        });
+        self
+    }
+
+    pub fn spawn<'w, 's, 'a>(
+        self,
+        commands: &'a mut Commands<'w, 's>,
+        animation_sprite: AnimationSprite,
+        label: &str,
+    ) -> EntityCommands<'w, 's, 'a> {
+        let mut entity_commands = commands.spawn();
+        entity_commands
+            .insert_bundle(RigidBodyBundle {
+                position: (self.position / RAPIER_SCALE).into(),
+                mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
+                ..Default::default()
+            })
+            .insert_bundle(ColliderBundle {
+                shape: self.body.into(),
+                material: ColliderMaterial::new(1.0, 0.0).into(),
+                flags: ColliderFlags {
+                    collision_groups: CollisionLayers::actor(self.layer),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            })
+            .insert(ColliderPositionSync::Discrete)
+            .insert(Actor::new())
+            .with_children(|parent| {
+                spawn_parts(
+                    parent,
+                    &self.colliders,
+                    animation_sprite,
+                    self.sprite_effects,
+                    label,
+                );
+            });
+        entity_commands
+    }
+}
+
+#[derive(SystemParam)]
+pub struct ActorParts<'w, 's> {
+    children: Query<'w, 's, &'static Children>,
+    sprite_roots: Query<'w, 's, Entity, With<SpriteRoot>>,
+    collider_roots: Query<'w, 's, Entity, With<ColliderRoot>>,
+    debug_roots: Query<'w, 's, Entity, With<DebugRoot>>,
+}
+
+impl<'w, 's> ActorParts<'w, 's> {
+    fn find<F: Fn(Entity) -> bool>(&self, actor: Entity, is_part: F) -> Option<Entity> {
+        self.children
+            .get(actor)
+            .ok()
+            .and_then(|children| children.iter().copied().find(|child| is_part(*child)))
+    }
+
+    pub fn sprite(&self, actor: Entity) -> Option<Entity> {
+        self.find(actor, |child| self.sprite_roots.get(child).is_ok())
+    }
+
+    pub fn collider(&self, actor: Entity) -> Option<Entity> {
+        self.find(actor, |child| self.collider_roots.get(child).is_ok())
+    }
+
+    pub fn debug(&self, actor: Entity) -> Option<Entity> {
+        self.find(actor, |child| self.debug_roots.get(child).is_ok())
+    }
+}
+
+// sheets exported with split layers show their bottom layer on the sprite root, every other
+// layer gets a sprite above it playing along, e.g. a weapon over the body
+fn sprite_layers_system(
+    mut commands: Commands,
+    aseprites: Res<Assets<Aseprite>>,
+    sprites: Query<
+        (Entity, &AnimationSprite, &Handle<TextureAtlas>),
+        (With<SpriteRoot>, Added<Handle<TextureAtlas>>),
+    >,
+    layer_sprites: Query<&AnimationLayerOf>,
+) {
+    for (entity, sprite, texture_atlas) in sprites.iter() {
+        // reloaded, the layer sprites are still there
+        if layer_sprites
+            .iter()
+            .any(|AnimationLayerOf(source)| *source == entity)
+        {
+            continue;
+        }
+        let aseprite = match aseprites.get(&sprite.aseprite) {
+            Some(aseprite) => aseprite,
+            None => continue,
+        };
+        let children = aseprite
+            .layers
+            .iter()
+            .skip(1)
+            .filter_map(|(layer, _)| layer.as_deref())
+            .enumerate()
+            .map(|(index, layer)| {
+                commands
+                    .spawn_bundle(SpriteSheetBundle {
+                        texture_atlas: texture_atlas.clone(),
+                        transform: Transform::from_xyz(0.0, 0.0, (index + 1) as f32 * 0.01),
+                        ..Default::default()
+                    })
+                    .insert(AnimationSprite::new(sprite.aseprite.clone()).with_layer(layer))
+                    .insert(AnimationLayerOf(entity))
+                    .id()
+            })
+            .collect::<Vec<_>>();
+        commands.entity(entity).push_children(&children);
+    }
+}
+
+// the artwork faces right, the offset mirrors with it
+pub fn mirror_sprite(
+    transform: &mut Transform,
+    texture_atlas_sprite: &mut TextureAtlasSprite,
+    direction: Direction,
+) {
+    let flip_x = if direction == Direction::Left {
+        -1.0
+    } else {
+        1.0
+    };
+    texture_atlas_sprite.flip_x = flip_x < 0.0;
+    transform.translation.x = transform.translation.x.abs() * flip_x;
+}
+
+// sprite, collider and debug children of an actor with the default capsule
+fn spawn_parts(
+    parent: &mut ChildBuilder,
+    colliders: &[ActorCollider],
+    animation_sprite: AnimationSprite,
+    sprite_effects: Option<SpriteEffects>,
+    label: &str,
+) {
+    let body = parent.parent_entity();
+    let mut sprite = parent.spawn_bundle(SpriteSheetBundle {
+        transform: Transform::from_translation(SPRITE_OFFSET),
+        ..Default::default()
+    });
+    sprite.insert(animation_sprite).insert(SpriteRoot);
+    if let Some(sprite_effects) = sprite_effects {
+        sprite.insert(sprite_effects);
+    }
+    parent
+        .spawn_bundle((Transform::identity(), GlobalTransform::identity()))
+        .insert(ColliderRoot)
+        .with_children(|parent| {
+            for collider in colliders {
+                let groups = match collider.kind {
+                    ActorColliderKind::Hurtbox => CollisionLayers::hurtbox(),
+                    ActorColliderKind::Foot => CollisionLayers::foot(),
+                };
+                let mut entity_commands = parent.spawn_bundle(ColliderBundle {
+                    collider_type: ColliderType::Sensor.into(),
+                    shape: ColliderShape::cuboid(
+                        collider.half_extents.x / RAPIER_SCALE,
+                        collider.half_extents.y / RAPIER_SCALE,
+                    )
+                    .into(),
+                    flags: ColliderFlags {
+                        collision_groups: groups,
+                        active_events: ActiveEvents::INTERSECTION_EVENTS,
+                        ..Default::default()
+                    }
+                    .into(),
+                    ..Default::default()
+                });
+                entity_commands
+                    .insert(ColliderParentComponent(ColliderParent {
+                        handle: body.handle(),
+                        pos_wrt_parent: (collider.offset / RAPIER_SCALE).into(),
+                    }))
+                    .insert_bundle((
+                        Transform::from_translation(collider.offset.extend(0.0)),
+                        GlobalTransform::identity(),
+                    ));
+                if let ActorColliderKind::Foot = collider.kind {
+                    entity_commands.insert(FootSensor::default());
+                }
+            }
+        });
+    parent
+        .spawn_bundle((Transform::identity(), GlobalTransform::identity()))
+        .insert(DebugRoot)
+        .with_children(|parent| {
+            // collider
+            parent
+                .spawn_bundle(
+                    GeometryBuilder::new()
+                        .add(&shapes::Circle {
+                            radius: 4.0,
+                            center: Vec2::new(0.0, 6.0),
+                        })
+                        .add(&shapes::Circle {
+                            radius: 4.0,
+                            center: Vec2::new(0.0, -6.0),
+                        })
+                        .add(&shapes::Rectangle {
+                            extents: Vec2::new(8.0, 12.0),
+                            origin: RectangleOrigin::Center,
+                        })
+                        .build(
+                            DrawMode::Fill(FillMode {
+                                options: FillOptions::non_zero(),
+                                color: Color::rgba(1.0, 0.0, 1.0, 0.2),
+                            }),
+                            Transform::from_xyz(0.0, 0.0, Z_COLLISION),
+                        ),
+                )
+                .insert(DebugTarget(DebugGroup::ActorColliders))
+                .insert(Visibility { is_visible: false });
+
+            // sensors
+            for collider in colliders {
+                parent
+                    .spawn_bundle(GeometryBuilder::build_as(
+                        &shapes::Rectangle {
+                            extents: collider.half_extents * 2.0,
+                            origin: RectangleOrigin::Center,
+                        },
+                        DrawMode::Outlined {
+                            fill_mode: FillMode::color(Color::NONE),
+                            outline_mode: StrokeMode::new(Color::rgba(0.0, 1.0, 1.0, 0.6), 0.5),
+                        },
+                        Transform::from_translation(collider.offset.extend(Z_COLLISION)),
+                    ))
+                    .insert(DebugTarget(DebugGroup::Sensors))
+                    .insert(Visibility { is_visible: false });
+            }
+
+            // label
+            parent
+                .spawn_bundle(BitmapTextBundle::new(
+                    BitmapText::new(label, Color::rgb(1.0, 0.0, 1.0)),
+                    Transform::from_xyz(0.0, 28.0, Z_COLLISION + 1.0),
+                ))
+                .insert(DebugTarget(DebugGroup::Labels))
+                .insert(Visibility { is_visible: false });
+        });
+}
+
+// counts what the foot sensors overlap and keeps `Grounded` on their actors in sync, with
+// the ground normal from a ray along gravity
+fn foot_sensor_system(
+    mut commands: Commands,
+    mut intersection_events: EventReader<IntersectionEvent>,
+    mut foot_sensors: Query<(Entity, &mut FootSensor)>,
+    bodies: Query<&RigidBodyPositionComponent>,
+    collision_lookup: CollisionLookup,
+    world_map: Res<WorldMap>,
+    query_pipeline: Res<QueryPipeline>,
+    collider_query: QueryPipelineColliderComponentsQuery,
+) {
+    for event in intersection_events.iter() {
+        for handle in [event.collider1, event.collider2] {
+            if let Ok((_, mut foot_sensor)) = foot_sensors.get_mut(handle.entity()) {
+                if event.intersecting {
+                    foot_sensor.contacts += 1;
+                } else {
+                    foot_sensor.contacts = foot_sensor.contacts.saturating_sub(1);
+                }
+            }
+        }
+    }
+
+    let collider_set = QueryPipelineColliderComponentsSet(&collider_query);
+    let down = world_map.gravity().down();
+    for (entity, foot_sensor) in foot_sensors.iter() {
+        let actor = match collision_lookup.actor(entity.handle()) {
+            Some(actor) => actor,
+            None => continue,
+        };
+        if foot_sensor.contacts == 0 {
+            commands.entity(actor).remove::<Grounded>();
+            continue;
+        }
+        let rb_position = match bodies.get(actor) {
+            Ok(rb_position) => rb_position,
+            Err(_) => continue,
+        };
+        let ray = Ray::new(rb_position.position.translation.vector.into(), down.into());
+        let filter = |handle: ColliderHandle| collision_lookup.actor(handle) != Some(actor);
+        let hit = query_pipeline.cast_ray_and_get_normal(
+            &collider_set,
+            &ray,
+            GROUND_RAY_LENGTH / RAPIER_SCALE,
+            true,
+            CollisionLayers::solid_query(),
+            Some(&filter),
+        );
+        let normal = hit
+            .map(|(_, intersection)| Vec2::new(intersection.normal.x, intersection.normal.y))
+            .unwrap_or(-down);
+        commands.entity(actor).insert(Grounded {
+            normal,
+            ground: hit.map(|(handle, _)| handle.entity()),
+        });
+    }
+}
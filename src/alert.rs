@@ -0,0 +1,149 @@
+use crate::{
+    bitmap_font::{BitmapText, BitmapTextBundle},
+    damage::DamageEvent,
+    game_time::GameTime,
+    nav::PathFollower,
+    vision::Vision,
+    Enemy, Player,
+};
+use bevy::prelude::*;
+
+// pixels around the alerting enemy
+const ALERT_RADIUS: f32 = 96.0;
+// seconds an enemy keeps chasing after it last saw the player or heard an alert
+const CHASE_MEMORY: f32 = 5.0;
+const INDICATOR_OFFSET: Vec3 = Vec3::new(0.0, 20.0, 1.0);
+const INDICATOR_DURATION: f32 = 1.0;
+
+// enemies that spot the player or get hit alert the ones around them, who all give chase
+pub struct AlertPlugin;
+impl Plugin for AlertPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AlertEvent>()
+            .add_system(spot_system)
+            .add_system(alert_system)
+            .add_system(chase_system)
+            .add_system(indicator_system);
+    }
+}
+
+pub struct AlertEvent {
+    pub source: Entity,
+    pub position: Vec2,
+}
+
+#[derive(Component)]
+pub struct Aggro {
+    pub chasing: bool,
+    memory: Timer,
+    // whether the vision saw the player last frame, alerts go out when it first does
+    saw_player: bool,
+}
+impl Default for Aggro {
+    fn default() -> Self {
+        Self {
+            chasing: false,
+            memory: Timer::from_seconds(CHASE_MEMORY, false),
+            saw_player: false,
+        }
+    }
+}
+
+#[derive(Component)]
+struct AlertIndicator(Timer);
+
+fn spot_system(
+    mut damage_events: EventReader<DamageEvent>,
+    mut alert_events: EventWriter<AlertEvent>,
+    mut enemies: Query<(Entity, &mut Aggro, &GlobalTransform, Option<&Vision>), With<Enemy>>,
+) {
+    let damaged = damage_events
+        .iter()
+        .map(|event| event.target)
+        .collect::<Vec<_>>();
+    for (entity, mut aggro, global_transform, vision) in enemies.iter_mut() {
+        let sees_player = vision.map_or(false, |vision| vision.sees_player);
+        let spotted = sees_player && !aggro.saw_player;
+        if aggro.saw_player != sees_player {
+            aggro.saw_player = sees_player;
+        }
+        if spotted || damaged.contains(&entity) {
+            alert_events.send(AlertEvent {
+                source: entity,
+                position: global_transform.translation.truncate(),
+            });
+        }
+    }
+}
+
+fn alert_system(
+    mut commands: Commands,
+    mut events: EventReader<AlertEvent>,
+    mut enemies: Query<(Entity, &mut Aggro, &GlobalTransform)>,
+) {
+    for event in events.iter() {
+        for (entity, mut aggro, global_transform) in enemies.iter_mut() {
+            let distance = global_transform
+                .translation
+                .truncate()
+                .distance(event.position);
+            if entity != event.source && distance > ALERT_RADIUS {
+                continue;
+            }
+            aggro.memory.reset();
+            if aggro.chasing {
+                continue;
+            }
+            aggro.chasing = true;
+            let indicator = commands
+                .spawn_bundle(BitmapTextBundle::new(
+                    BitmapText::new("!", Color::RED),
+                    Transform::from_translation(INDICATOR_OFFSET),
+                ))
+                .insert(AlertIndicator(Timer::from_seconds(
+                    INDICATOR_DURATION,
+                    false,
+                )))
+                .id();
+            commands.entity(entity).push_children(&[indicator]);
+        }
+    }
+}
+
+// chasers head for the player until they forget about it
+fn chase_system(
+    time: Res<GameTime>,
+    players: Query<&GlobalTransform, With<Player>>,
+    mut chasers: Query<(&mut Aggro, &mut PathFollower)>,
+) {
+    let player_position = players
+        .get_single()
+        .ok()
+        .map(|global_transform| global_transform.translation.truncate());
+    for (mut aggro, mut follower) in chasers.iter_mut() {
+        if !aggro.chasing {
+            continue;
+        }
+        if aggro.saw_player {
+            aggro.memory.reset();
+        }
+        if aggro.memory.tick(time.delta()).finished() || player_position.is_none() {
+            aggro.chasing = false;
+            follower.target = None;
+        } else {
+            follower.target = player_position;
+        }
+    }
+}
+
+fn indicator_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut indicators: Query<(Entity, &mut AlertIndicator)>,
+) {
+    for (entity, mut indicator) in indicators.iter_mut() {
+        if indicator.0.tick(time.delta()).just_finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,772 @@
+// .aseprite and .ase files read directly, without exporting a json and png pair first, see
+// https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md
+use super::data::{
+    AsepriteData, FrameTag, FrameValue, Frames, Layer, Meta, Pivot, Size, Slice, SliceKey,
+    SpriteSourceSizeClass,
+};
+use anyhow::{bail, ensure, Context, Result};
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+
+const HEADER_MAGIC: u16 = 0xa5e0;
+const FRAME_MAGIC: u16 = 0xf1fa;
+const HEADER_SIZE: usize = 128;
+const FRAME_HEADER_SIZE: usize = 16;
+
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+const CHUNK_USER_DATA: u16 = 0x2020;
+const CHUNK_SLICE: u16 = 0x2022;
+
+const LAYER_VISIBLE: u16 = 1;
+const LAYER_BACKGROUND: u16 = 8;
+const LAYER_GROUP: u16 = 1;
+
+const CEL_RAW: u16 = 0;
+const CEL_LINKED: u16 = 1;
+const CEL_COMPRESSED: u16 = 2;
+
+// the sheet data like the json export and its image. frames are trimmed to the bounds of every
+// frame together, so they all keep the same size and offset. with more than one visible layer
+// every layer also gets frames of its own, after the merged ones
+pub fn read(bytes: &[u8], title: &str) -> Result<(AsepriteData, Image)> {
+    let file = AseFile::parse(bytes)?;
+    let merged = (0..file.frames.len())
+        .map(|frame| file.compose(frame, None))
+        .collect::<Result<Vec<_>>>()?;
+    let trim = trim_rect(&merged, file.width, file.height);
+
+    let visible_layers = file
+        .layers
+        .iter()
+        .enumerate()
+        .filter(|(index, layer)| layer.layer_type != LAYER_GROUP && file.visible(*index))
+        .map(|(index, layer)| (index, layer.name.clone()))
+        .collect::<Vec<_>>();
+    let mut sheets = vec![(None, merged)];
+    if visible_layers.len() > 1 {
+        for (index, name) in visible_layers.iter() {
+            let frames = (0..file.frames.len())
+                .map(|frame| file.compose(frame, Some(*index)))
+                .collect::<Result<Vec<_>>>()?;
+            sheets.push((Some(name.clone()), frames));
+        }
+    }
+
+    // a grid of about as many columns as rows
+    let count = sheets.iter().map(|(_, frames)| frames.len()).sum::<usize>();
+    let columns = (count as f32).sqrt().ceil().max(1.0) as usize;
+    let rows = (count as f32 / columns as f32).ceil() as usize;
+    let sheet_width = columns * trim.w;
+    let sheet_height = rows.max(1) * trim.h;
+    let mut pixels = vec![0; sheet_width * sheet_height * 4];
+    let mut frames = std::collections::HashMap::new();
+    let mut cell = 0;
+    for (layer, layer_frames) in sheets.iter() {
+        for (index, frame) in layer_frames.iter().enumerate() {
+            let (x, y) = ((cell % columns) * trim.w, (cell / columns) * trim.h);
+            cell += 1;
+            for row in 0..trim.h {
+                let source = ((trim.y + row) * file.width + trim.x) * 4;
+                let destination = ((y + row) * sheet_width + x) * 4;
+                pixels[destination..destination + trim.w * 4]
+                    .copy_from_slice(&frame[source..source + trim.w * 4]);
+            }
+            let key = match layer {
+                Some(layer) => format!("{} ({}) {}.aseprite", title, layer, index),
+                None => format!("{} {}.aseprite", title, index),
+            };
+            frames.insert(
+                key,
+                FrameValue {
+                    filename: None,
+                    frame: rect(x, y, trim.w, trim.h),
+                    rotated: false,
+                    trimmed: trim.w != file.width || trim.h != file.height,
+                    sprite_source_size: rect(trim.x, trim.y, trim.w, trim.h),
+                    source_size: Size {
+                        w: file.width as i64,
+                        h: file.height as i64,
+                    },
+                    duration: file.frames[index].duration as i64,
+                },
+            );
+        }
+    }
+
+    let data = AsepriteData {
+        frames: Frames::Hash(frames),
+        meta: Meta {
+            app: "https://www.aseprite.org/".to_string(),
+            version: String::new(),
+            // the image is built here, there's no file
+            image: String::new(),
+            format: "RGBA8888".to_string(),
+            size: Size {
+                w: sheet_width as i64,
+                h: sheet_height as i64,
+            },
+            scale: "1".to_string(),
+            frame_tags: file
+                .tags
+                .iter()
+                .map(|tag| FrameTag {
+                    name: tag.name.clone(),
+                    from: tag.from as i64,
+                    to: tag.to as i64,
+                    direction: match tag.direction {
+                        1 => "reverse",
+                        2 => "pingpong",
+                        3 => "pingpong_reverse",
+                        _ => "forward",
+                    }
+                    .to_string(),
+                    color: format!(
+                        "#{:02x}{:02x}{:02x}ff",
+                        tag.color[0], tag.color[1], tag.color[2]
+                    ),
+                    data: tag.data.clone(),
+                    repeat: (tag.repeat > 0).then(|| tag.repeat.to_string()),
+                })
+                .collect(),
+            layers: visible_layers
+                .iter()
+                .map(|(index, name)| Layer {
+                    name: name.clone(),
+                    opacity: file.layers[*index].opacity as i64,
+                    blend_mode: "normal".to_string(),
+                })
+                .collect(),
+            slices: file.slices.clone(),
+        },
+    };
+    let image = Image::new(
+        Extent3d {
+            width: sheet_width as u32,
+            height: sheet_height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        pixels,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    Ok((data, image))
+}
+
+fn rect(x: usize, y: usize, w: usize, h: usize) -> SpriteSourceSizeClass {
+    SpriteSourceSizeClass {
+        x: x as i64,
+        y: y as i64,
+        w: w as i64,
+        h: h as i64,
+    }
+}
+
+struct TrimRect {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+// bounds of the opaque pixels of all frames, a single pixel when there are none
+fn trim_rect(frames: &[Vec<u8>], width: usize, height: usize) -> TrimRect {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+    for frame in frames {
+        for y in 0..height {
+            for x in 0..width {
+                if frame[(y * width + x) * 4 + 3] != 0 {
+                    min_x = min_x.min(x);
+                    min_y = min_y.min(y);
+                    max_x = max_x.max(x + 1);
+                    max_y = max_y.max(y + 1);
+                }
+            }
+        }
+    }
+    if min_x >= max_x || min_y >= max_y {
+        return TrimRect {
+            x: 0,
+            y: 0,
+            w: 1.min(width),
+            h: 1.min(height),
+        };
+    }
+    TrimRect {
+        x: min_x,
+        y: min_y,
+        w: max_x - min_x,
+        h: max_y - min_y,
+    }
+}
+
+struct AseLayer {
+    flags: u16,
+    layer_type: u16,
+    child_level: u16,
+    opacity: u8,
+    name: String,
+}
+
+enum CelContent {
+    // pixels in the file's color depth
+    Image {
+        width: usize,
+        height: usize,
+        pixels: Vec<u8>,
+    },
+    // the cel of the same layer in that frame
+    Linked(usize),
+    // tilemaps aren't supported, they're drawn empty
+    Unsupported,
+}
+
+struct Cel {
+    layer: usize,
+    x: i16,
+    y: i16,
+    opacity: u8,
+    content: CelContent,
+}
+
+struct AseFrame {
+    // milliseconds
+    duration: u16,
+    cels: Vec<Cel>,
+}
+
+struct AseTag {
+    from: u16,
+    to: u16,
+    direction: u8,
+    color: [u8; 3],
+    name: String,
+    data: Option<String>,
+    // 0 when unset
+    repeat: u16,
+}
+
+// the chunk that the next user data chunk belongs to
+enum UserDataTarget {
+    // tags take one each, in order
+    Tag(usize),
+    Slice(usize),
+    Other,
+}
+
+struct AseFile {
+    width: usize,
+    height: usize,
+    // bits per pixel, 32 rgba, 16 grayscale or 8 indexed
+    color_depth: u16,
+    transparent_index: u8,
+    palette: Vec<[u8; 4]>,
+    layers: Vec<AseLayer>,
+    frames: Vec<AseFrame>,
+    tags: Vec<AseTag>,
+    slices: Vec<Slice>,
+}
+
+impl AseFile {
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut header = Reader::new(bytes);
+        header.skip(4)?;
+        ensure!(
+            header.u16()? == HEADER_MAGIC,
+            "not an aseprite file, wrong magic number"
+        );
+        let frame_count = header.u16()? as usize;
+        let width = header.u16()? as usize;
+        let height = header.u16()? as usize;
+        let color_depth = header.u16()?;
+        ensure!(
+            matches!(color_depth, 8 | 16 | 32),
+            "unknown color depth: {}",
+            color_depth
+        );
+        header.skip(4 + 2 + 4 + 4)?;
+        let transparent_index = header.u8()?;
+
+        let mut file = AseFile {
+            width,
+            height,
+            color_depth,
+            transparent_index,
+            palette: vec![],
+            layers: vec![],
+            frames: vec![],
+            tags: vec![],
+            slices: vec![],
+        };
+        let mut offset = HEADER_SIZE;
+        for frame_index in 0..frame_count {
+            let mut frame_header = Reader::new(
+                bytes
+                    .get(offset..)
+                    .with_context(|| format!("frame {} is missing", frame_index))?,
+            );
+            let frame_size = frame_header.u32()? as usize;
+            ensure!(
+                frame_header.u16()? == FRAME_MAGIC,
+                "frame {} has the wrong magic number",
+                frame_index
+            );
+            let old_chunk_count = frame_header.u16()? as usize;
+            let duration = frame_header.u16()?;
+            frame_header.skip(2)?;
+            let chunk_count = match frame_header.u32()? as usize {
+                0 => old_chunk_count,
+                chunk_count => chunk_count,
+            };
+            let frame_bytes = bytes
+                .get(offset..offset + frame_size)
+                .with_context(|| format!("frame {} is cut off", frame_index))?;
+            file.frames.push(AseFrame {
+                duration,
+                cels: vec![],
+            });
+            file.parse_chunks(
+                &frame_bytes[FRAME_HEADER_SIZE.min(frame_size)..],
+                chunk_count,
+            )
+            .with_context(|| format!("failed to read frame {}", frame_index))?;
+            offset += frame_size;
+        }
+        Ok(file)
+    }
+
+    fn parse_chunks(&mut self, bytes: &[u8], chunk_count: usize) -> Result<()> {
+        let mut offset = 0;
+        let mut user_data_target = UserDataTarget::Other;
+        for _ in 0..chunk_count {
+            let mut chunk_header = Reader::new(&bytes[offset..]);
+            let chunk_size = chunk_header.u32()? as usize;
+            let chunk_type = chunk_header.u16()?;
+            ensure!(chunk_size >= 6, "chunk size {} is too small", chunk_size);
+            let mut chunk = Reader::new(
+                bytes
+                    .get(offset + 6..offset + chunk_size)
+                    .context("chunk is cut off")?,
+            );
+            offset += chunk_size;
+            match chunk_type {
+                CHUNK_OLD_PALETTE => {
+                    // only when there's no new palette chunk
+                    if !self.palette.is_empty() {
+                        continue;
+                    }
+                    let packets = chunk.u16()?;
+                    let mut index = 0;
+                    for _ in 0..packets {
+                        index += chunk.u8()? as usize;
+                        let count = match chunk.u8()? {
+                            0 => 256,
+                            count => count as usize,
+                        };
+                        for _ in 0..count {
+                            let color = [chunk.u8()?, chunk.u8()?, chunk.u8()?, 255];
+                            self.set_palette(index, color);
+                            index += 1;
+                        }
+                    }
+                }
+                CHUNK_PALETTE => {
+                    chunk.skip(4)?;
+                    let first = chunk.u32()? as usize;
+                    let last = chunk.u32()? as usize;
+                    chunk.skip(8)?;
+                    for index in first..=last {
+                        let flags = chunk.u16()?;
+                        let color = [chunk.u8()?, chunk.u8()?, chunk.u8()?, chunk.u8()?];
+                        if flags & 1 != 0 {
+                            chunk.string()?;
+                        }
+                        self.set_palette(index, color);
+                    }
+                }
+                CHUNK_LAYER => {
+                    let flags = chunk.u16()?;
+                    let layer_type = chunk.u16()?;
+                    let child_level = chunk.u16()?;
+                    // default size and blend mode, everything blends normally here
+                    chunk.skip(2 + 2 + 2)?;
+                    let opacity = chunk.u8()?;
+                    chunk.skip(3)?;
+                    let name = chunk.string()?;
+                    self.layers.push(AseLayer {
+                        flags,
+                        layer_type,
+                        child_level,
+                        opacity,
+                        name,
+                    });
+                    user_data_target = UserDataTarget::Other;
+                }
+                CHUNK_CEL => {
+                    let cel = self.parse_cel(&mut chunk)?;
+                    if let Some(frame) = self.frames.last_mut() {
+                        frame.cels.push(cel);
+                    }
+                    user_data_target = UserDataTarget::Other;
+                }
+                CHUNK_TAGS => {
+                    let count = chunk.u16()?;
+                    chunk.skip(8)?;
+                    let first_tag = self.tags.len();
+                    for _ in 0..count {
+                        let from = chunk.u16()?;
+                        let to = chunk.u16()?;
+                        let direction = chunk.u8()?;
+                        let repeat = chunk.u16()?;
+                        chunk.skip(6)?;
+                        let color = [chunk.u8()?, chunk.u8()?, chunk.u8()?];
+                        chunk.skip(1)?;
+                        let name = chunk.string()?;
+                        self.tags.push(AseTag {
+                            from,
+                            to,
+                            direction,
+                            color,
+                            name,
+                            data: None,
+                            repeat,
+                        });
+                    }
+                    user_data_target = UserDataTarget::Tag(first_tag);
+                }
+                CHUNK_USER_DATA => {
+                    let flags = chunk.u32()?;
+                    let text = if flags & 1 != 0 {
+                        Some(chunk.string()?)
+                    } else {
+                        None
+                    };
+                    match user_data_target {
+                        UserDataTarget::Tag(index) => {
+                            if let Some(tag) = self.tags.get_mut(index) {
+                                tag.data = text.filter(|text| !text.is_empty());
+                            }
+                            user_data_target = UserDataTarget::Tag(index + 1);
+                        }
+                        UserDataTarget::Slice(index) => {
+                            if let Some(slice) = self.slices.get_mut(index) {
+                                slice.data = text.filter(|text| !text.is_empty());
+                            }
+                            user_data_target = UserDataTarget::Other;
+                        }
+                        UserDataTarget::Other => {}
+                    }
+                }
+                CHUNK_SLICE => {
+                    let key_count = chunk.u32()?;
+                    let flags = chunk.u32()?;
+                    chunk.skip(4)?;
+                    let name = chunk.string()?;
+                    let mut keys = vec![];
+                    for _ in 0..key_count {
+                        let frame = chunk.u32()? as i64;
+                        let bounds = chunk.rect()?;
+                        let center = if flags & 1 != 0 {
+                            Some(chunk.rect()?)
+                        } else {
+                            None
+                        };
+                        let pivot = if flags & 2 != 0 {
+                            Some(Pivot {
+                                x: chunk.i32()? as i64,
+                                y: chunk.i32()? as i64,
+                            })
+                        } else {
+                            None
+                        };
+                        keys.push(SliceKey {
+                            frame,
+                            bounds,
+                            center,
+                            pivot,
+                        });
+                    }
+                    self.slices.push(Slice {
+                        name,
+                        color: "#0000ffff".to_string(),
+                        data: None,
+                        keys,
+                    });
+                    user_data_target = UserDataTarget::Slice(self.slices.len() - 1);
+                }
+                // color profiles, masks, tilesets and the like don't matter for sprites
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_cel(&self, chunk: &mut Reader) -> Result<Cel> {
+        let layer = chunk.u16()? as usize;
+        let x = chunk.i16()?;
+        let y = chunk.i16()?;
+        let opacity = chunk.u8()?;
+        let cel_type = chunk.u16()?;
+        // z index and reserved bytes
+        chunk.skip(7)?;
+        let content = match cel_type {
+            CEL_RAW | CEL_COMPRESSED => {
+                let width = chunk.u16()? as usize;
+                let height = chunk.u16()? as usize;
+                let size = width * height * self.bytes_per_pixel();
+                let pixels = if cel_type == CEL_RAW {
+                    chunk.bytes(size)?.to_vec()
+                } else {
+                    let mut pixels = Vec::with_capacity(size);
+                    ZlibDecoder::new(chunk.rest())
+                        .read_to_end(&mut pixels)
+                        .context("failed to decompress cel")?;
+                    pixels
+                };
+                ensure!(
+                    pixels.len() >= size,
+                    "cel has {} bytes of pixels, expected {}",
+                    pixels.len(),
+                    size
+                );
+                CelContent::Image {
+                    width,
+                    height,
+                    pixels,
+                }
+            }
+            CEL_LINKED => CelContent::Linked(chunk.u16()? as usize),
+            _ => CelContent::Unsupported,
+        };
+        Ok(Cel {
+            layer,
+            x,
+            y,
+            opacity,
+            content,
+        })
+    }
+
+    fn set_palette(&mut self, index: usize, color: [u8; 4]) {
+        if self.palette.len() <= index {
+            self.palette.resize(index + 1, [0, 0, 0, 0]);
+        }
+        self.palette[index] = color;
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        self.color_depth as usize / 8
+    }
+
+    // hidden layers and the layers of hidden groups aren't drawn
+    fn visible(&self, index: usize) -> bool {
+        let mut groups: Vec<bool> = vec![];
+        for (layer_index, layer) in self.layers.iter().enumerate() {
+            groups.truncate(layer.child_level as usize);
+            let visible = layer.flags & LAYER_VISIBLE != 0 && groups.iter().all(|visible| *visible);
+            if layer_index == index {
+                return visible;
+            }
+            if layer.layer_type == LAYER_GROUP {
+                groups.push(visible);
+            }
+        }
+        false
+    }
+
+    fn rgba(&self, pixel: &[u8], background: bool) -> [u8; 4] {
+        match self.color_depth {
+            32 => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            16 => [pixel[0], pixel[0], pixel[0], pixel[1]],
+            _ => {
+                let index = pixel[0];
+                if index == self.transparent_index && !background {
+                    [0, 0, 0, 0]
+                } else {
+                    self.palette.get(index as usize).copied().unwrap_or([0; 4])
+                }
+            }
+        }
+    }
+
+    // rgba pixels of a frame, of every visible layer or just one
+    fn compose(&self, frame_index: usize, only_layer: Option<usize>) -> Result<Vec<u8>> {
+        let mut canvas = vec![0; self.width * self.height * 4];
+        let frame = &self.frames[frame_index];
+        let mut cels = frame.cels.iter().collect::<Vec<_>>();
+        cels.sort_by_key(|cel| cel.layer);
+        for cel in cels {
+            let layer = match self.layers.get(cel.layer) {
+                Some(layer) => layer,
+                None => bail!("cel of unknown layer {}", cel.layer),
+            };
+            if only_layer.map_or(false, |only_layer| only_layer != cel.layer)
+                || layer.layer_type == LAYER_GROUP
+                || !self.visible(cel.layer)
+            {
+                continue;
+            }
+            // linked cels share the pixels, not the position or opacity
+            let content = match cel.content {
+                CelContent::Linked(linked_frame) => self
+                    .frames
+                    .get(linked_frame)
+                    .and_then(|frame| frame.cels.iter().find(|other| other.layer == cel.layer))
+                    .map(|other| &other.content)
+                    .with_context(|| format!("linked cel of missing frame {}", linked_frame))?,
+                ref content => content,
+            };
+            let (width, height, pixels) = match content {
+                CelContent::Image {
+                    width,
+                    height,
+                    pixels,
+                } => (*width, *height, pixels),
+                _ => continue,
+            };
+            let background = layer.flags & LAYER_BACKGROUND != 0;
+            let opacity = cel.opacity as f32 / 255.0 * layer.opacity as f32 / 255.0;
+            let bytes_per_pixel = self.bytes_per_pixel();
+            for y in 0..height {
+                let canvas_y = cel.y as i64 + y as i64;
+                if !(0..self.height as i64).contains(&canvas_y) {
+                    continue;
+                }
+                for x in 0..width {
+                    let canvas_x = cel.x as i64 + x as i64;
+                    if !(0..self.width as i64).contains(&canvas_x) {
+                        continue;
+                    }
+                    let pixel = &pixels[(y * width + x) * bytes_per_pixel..];
+                    let source = self.rgba(pixel, background);
+                    let index = (canvas_y as usize * self.width + canvas_x as usize) * 4;
+                    blend(&mut canvas[index..index + 4], source, opacity);
+                }
+            }
+        }
+        Ok(canvas)
+    }
+}
+
+// source over destination, straight alpha
+fn blend(destination: &mut [u8], source: [u8; 4], opacity: f32) {
+    let source_alpha = source[3] as f32 / 255.0 * opacity;
+    if source_alpha <= 0.0 {
+        return;
+    }
+    let destination_alpha = destination[3] as f32 / 255.0;
+    let alpha = source_alpha + destination_alpha * (1.0 - source_alpha);
+    for (channel, source_channel) in destination.iter_mut().zip(source).take(3) {
+        let value = (source_channel as f32 * source_alpha
+            + *channel as f32 * destination_alpha * (1.0 - source_alpha))
+            / alpha;
+        *channel = value.round() as u8;
+    }
+    destination[3] = (alpha * 255.0).round() as u8;
+}
+
+// little endian values from the front of a chunk
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        ensure!(self.bytes.len() >= count, "unexpected end of file");
+        let (bytes, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+    fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.bytes)
+    }
+    fn skip(&mut self, count: usize) -> Result<()> {
+        self.bytes(count).map(|_| ())
+    }
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+    fn i16(&mut self) -> Result<i16> {
+        Ok(self.u16()? as i16)
+    }
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+    fn i32(&mut self) -> Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+    // x, y, width and height
+    fn rect(&mut self) -> Result<SpriteSourceSizeClass> {
+        Ok(SpriteSourceSizeClass {
+            x: self.i32()? as i64,
+            y: self.i32()? as i64,
+            w: self.u32()? as i64,
+            h: self.u32()? as i64,
+        })
+    }
+    fn string(&mut self) -> Result<String> {
+        let length = self.u16()? as usize;
+        let bytes = self.bytes(length)?;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 4x4 rgba, one layer and two frames with a 2x2 cel at 1,1: red and raw, then green and
+    // compressed. a "blink" pingpong tag over both, with "loop" as its user data
+    const FIXTURE: &[u8] = include_bytes!("fixtures/blink.aseprite");
+
+    #[test]
+    fn reads_frames_and_tags() {
+        let (data, image) = read(FIXTURE, "blink").unwrap();
+
+        let frames = match &data.frames {
+            Frames::Hash(frames) => frames,
+            Frames::Array(_) => panic!("expected a hash of frames"),
+        };
+        assert_eq!(frames.len(), 2);
+        for (key, duration) in [("blink 0.aseprite", 100), ("blink 1.aseprite", 150)] {
+            let frame = &frames[key];
+            assert_eq!(frame.duration, duration);
+            assert!(frame.trimmed);
+            let source = &frame.sprite_source_size;
+            assert_eq!((source.x, source.y, source.w, source.h), (1, 1, 2, 2));
+            assert_eq!((frame.source_size.w, frame.source_size.h), (4, 4));
+        }
+
+        assert_eq!(data.meta.frame_tags.len(), 1);
+        let tag = &data.meta.frame_tags[0];
+        assert_eq!(tag.name, "blink");
+        assert_eq!((tag.from, tag.to), (0, 1));
+        assert_eq!(tag.direction, "pingpong");
+        assert_eq!(tag.color, "#ff0000ff");
+        assert_eq!(tag.data.as_deref(), Some("loop"));
+        assert_eq!(tag.repeat, None);
+
+        // both frames side by side, each trimmed to its cel
+        assert_eq!((data.meta.size.w, data.meta.size.h), (4, 2));
+        let pixel = |frame: &FrameValue| {
+            let index = (frame.frame.y as usize * 4 + frame.frame.x as usize) * 4;
+            image.data[index..index + 4].to_vec()
+        };
+        assert_eq!(pixel(&frames["blink 0.aseprite"]), [255, 0, 0, 255]);
+        assert_eq!(pixel(&frames["blink 1.aseprite"]), [0, 255, 0, 255]);
+    }
+}
@@ -16,12 +16,37 @@ use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AsepriteData {
-    pub frames: HashMap<String, FrameValue>,
+    pub frames: Frames,
     pub meta: Meta,
 }
 
+// "hash" exports key the frames by name, "array" ones list them in order with their name
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Frames {
+    Hash(HashMap<String, FrameValue>),
+    Array(Vec<FrameValue>),
+}
+impl Frames {
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (&str, &FrameValue)> + '_> {
+        match self {
+            Frames::Hash(frames) => {
+                Box::new(frames.iter().map(|(name, frame)| (name.as_str(), frame)))
+            }
+            Frames::Array(frames) => Box::new(
+                frames
+                    .iter()
+                    .map(|frame| (frame.filename.as_deref().unwrap_or(""), frame)),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrameValue {
+    // only in array exports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
     pub frame: SpriteSourceSizeClass,
     pub rotated: bool,
     pub trimmed: bool,
@@ -29,10 +54,12 @@ pub struct FrameValue {
     pub sprite_source_size: SpriteSourceSizeClass,
     #[serde(rename = "sourceSize")]
     pub source_size: Size,
+    // milliseconds, missing from texturepacker sheets
+    #[serde(default)]
     pub duration: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpriteSourceSizeClass {
     pub x: i64,
     pub y: i64,
@@ -40,7 +67,7 @@ pub struct SpriteSourceSizeClass {
     pub h: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Size {
     pub w: i64,
     pub h: i64,
@@ -57,7 +84,8 @@ pub struct Meta {
     #[serde(rename = "frameTags")]
     pub frame_tags: Vec<FrameTag>,
     pub layers: Vec<Layer>,
-    pub slices: Vec<Option<serde_json::Value>>,
+    #[serde(default)]
+    pub slices: Vec<Slice>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -67,6 +95,40 @@ pub struct FrameTag {
     pub to: i64,
     pub direction: String,
     pub color: String,
+    // tag user data, exported by aseprite 1.3
+    #[serde(default)]
+    pub data: Option<String>,
+    // aseprite 1.3's repeat count, left out when it's unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<String>,
+}
+
+// a named part of the sprite, keyed from the frame it changes on. 9-slices have a center
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Slice {
+    pub name: String,
+    pub color: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    pub keys: Vec<SliceKey>,
+}
+
+// in sprite pixels, not sheet ones
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SliceKey {
+    pub frame: i64,
+    pub bounds: SpriteSourceSizeClass,
+    // relative to the bounds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub center: Option<SpriteSourceSizeClass>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pivot: Option<Pivot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pivot {
+    pub x: i64,
+    pub y: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -76,4 +138,3 @@ pub struct Layer {
     #[serde(rename = "blendMode")]
     pub blend_mode: String,
 }
-
@@ -1,13 +1,20 @@
+mod ase;
 mod data;
+mod sheet;
 
-use self::data::AsepriteData;
+use self::data::{AsepriteData, FrameTag, Frames};
+use crate::{
+    asset_error::AssetErrorEvent, collision::CollisionLayers, damage::Slowed, debug::SystemTimings,
+    game_config::GameConfig, game_time::GameTime, VirtualPosition,
+};
 use anyhow::{anyhow, Context, Result};
 use bevy::{
-    asset::{AssetLoader, LoadContext, LoadedAsset},
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
     prelude::*,
     reflect::TypeUuid,
     utils::BoxedFuture,
 };
+use bevy_rapier2d::prelude::*;
 use regex::Regex;
 use std::{
     collections::HashMap,
@@ -15,12 +22,50 @@ use std::{
     time::Duration,
 };
 
-pub struct AsepritePlugin;
+// label of the sheet image in .aseprite files
+const SHEET_LABEL: &str = "sheet";
+// slice whose pivot moves the body along with the frames, e.g. lunging forward in an attack
+const ROOT_SLICE: &str = "root";
+// sprites this many pixels off screen only animate every OFFSCREEN_TICK seconds
+const OFFSCREEN_MARGIN: f32 = 32.0;
+const OFFSCREEN_TICK: f32 = 0.25;
+
+// aseprite's default names, "{title} {frame}.{extension}" and with split layers
+// "{title} ({layer}) {frame}.{extension}", and any other title and frame separator
+const DEFAULT_FRAME_NAME_FORMATS: [&str; 3] = [
+    "{title} ({layer}) {frame}.{extension}",
+    "{title} {frame}.{extension}",
+    "{title}{frame}.{extension}",
+];
+
+pub struct AsepritePlugin {
+    // the --filename-format of the json exports, see FrameNameFormat
+    pub frame_name_formats: Vec<String>,
+}
+impl Default for AsepritePlugin {
+    fn default() -> Self {
+        Self {
+            frame_name_formats: DEFAULT_FRAME_NAME_FORMATS
+                .iter()
+                .map(|format| format.to_string())
+                .collect(),
+        }
+    }
+}
 impl Plugin for AsepritePlugin {
     fn build(&self, app: &mut App) {
         app.add_asset::<Aseprite>()
-            .init_asset_loader::<AsepriteLoader>()
+            .add_asset_loader(AsepriteLoader {
+                formats: self
+                    .frame_name_formats
+                    .iter()
+                    .map(|format| FrameNameFormat::new(format))
+                    .collect(),
+            })
+            .add_event::<AnimationFrameEvent>()
             .add_system(animation_sprite_system)
+            .add_system(root_motion_system.after(animation_sprite_system))
+            .add_system(animation_layer_system)
             .add_system(on_asset_event_system);
     }
 }
@@ -29,45 +74,169 @@ impl Plugin for AsepritePlugin {
 pub struct AnimationFrame {
     pub index: usize,
     pub duration: f32,
+    pub events: Vec<String>,
+    // pixels the sprite's body moves when the frame starts, facing right with y up
+    pub motion: Vec2,
     // pub collision_rect: Option<Rect>,
 }
+
+// sent when a sprite enters a frame with events attached
+#[derive(Debug)]
+pub struct AnimationFrameEvent {
+    pub entity: Entity,
+    pub event_name: String,
+}
 #[derive(Debug)]
 pub struct Animation {
     pub name: String,
     pub frames: Vec<AnimationFrame>,
+    // times it plays before stopping on its last frame, `None` loops
+    pub repeat: Option<u32>,
+    // multiplies the frame rate
+    pub speed: f32,
 }
 
-#[derive(Component)]
+// options after the tag name, "attack@x1.5@1" plays "attack" once at one and a half times the
+// speed. tag user data like "speed:1.5 repeat:1" or aseprite 1.3's tag repeat setting work too,
+// repeating 0 times loops
+struct TagOptions {
+    name: String,
+    repeat: Option<u32>,
+    speed: f32,
+}
+impl TagOptions {
+    fn new(tag: &FrameTag) -> Self {
+        let mut repeat: Option<u32> = tag.repeat.as_deref().and_then(|repeat| repeat.parse().ok());
+        let mut speed: Option<f32> = None;
+        for (key, value) in tag
+            .data
+            .as_deref()
+            .unwrap_or("")
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter_map(|entry| entry.split_once(':'))
+        {
+            match key {
+                "repeat" => repeat = value.parse().ok().or(repeat),
+                "speed" => speed = value.parse().ok().or(speed),
+                _ => {}
+            }
+        }
+        // the name is used as is when any option doesn't parse
+        let mut parts = tag.name.split('@');
+        let name = parts.next().unwrap_or_default();
+        let options = parts
+            .map(|option| match option.strip_prefix('x') {
+                Some(speed) => speed.parse().ok().map(|speed| (None, Some(speed))),
+                None => option.parse().ok().map(|repeat| (Some(repeat), None)),
+            })
+            .collect::<Option<Vec<_>>>();
+        let name = match options {
+            Some(options) if !name.is_empty() => {
+                for (name_repeat, name_speed) in options {
+                    repeat = name_repeat.or(repeat);
+                    speed = name_speed.or(speed);
+                }
+                name
+            }
+            _ => tag.name.as_str(),
+        };
+        Self {
+            name: name.to_owned(),
+            repeat: repeat.filter(|repeat| *repeat > 0),
+            speed: speed.filter(|speed| *speed > 0.0).unwrap_or(1.0),
+        }
+    }
+}
+
+// animations played in between two others, e.g. "stop" when going from "walk" to "wait"
+#[derive(Debug, Default, Clone)]
+pub struct TransitionTable {
+    transitions: HashMap<(String, String), String>,
+}
+impl TransitionTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with(mut self, from: &str, to: &str, via: &str) -> Self {
+        self.insert(from, to, via);
+        self
+    }
+    pub fn insert(&mut self, from: &str, to: &str, via: &str) {
+        self.transitions
+            .insert((from.to_owned(), to.to_owned()), via.to_owned());
+    }
+    pub fn get(&self, from: &str, to: &str) -> Option<&str> {
+        self.transitions
+            .get(&(from.to_owned(), to.to_owned()))
+            .map(|via| via.as_str())
+    }
+}
+
+#[derive(Component, Clone)]
 pub struct AnimationSprite {
     pub aseprite: Handle<Aseprite>,
     timer: Timer,
     current_animation_name: String,
+    previous_animation_name: String,
+    // animation to play after the current transition
+    pending_animation: Option<String>,
     current_frame_index: usize,
-    loop_animation: bool,
+    // times the current animation has left to play, `None` loops
+    repeats_left: Option<u32>,
     is_dirty: bool,
-    speed: f32,
+    layer: Option<String>,
+    transitions: TransitionTable,
+    // root motion of the frames entered since root_motion_system last ran
+    motion: Vec2,
+    // time skipped while off screen, caught up on in coarse ticks
+    offscreen_time: Duration,
     //paused
 }
 
+// animates the sprite, or the sprites of the actor, every frame even off screen
+#[derive(Component)]
+pub struct AlwaysAnimate;
+
+// a sprite showing another layer of its source's aseprite, kept in sync with the source. actor
+// sprites get one per extra layer, see actor.rs
+#[derive(Component)]
+pub struct AnimationLayerOf(pub Entity);
+
 impl AnimationSprite {
     pub fn new(aseprite: Handle<Aseprite>) -> Self {
         Self {
             aseprite,
             timer: Timer::new(Duration::from_secs(0), false),
             current_animation_name: "".to_string(),
+            previous_animation_name: "".to_string(),
+            pending_animation: None,
             current_frame_index: 0,
-            loop_animation: true,
+            repeats_left: None,
             is_dirty: true,
-            speed: 2.0,
+            layer: None,
+            transitions: TransitionTable::default(),
+            motion: Vec2::ZERO,
+            offscreen_time: Duration::ZERO,
         }
     }
-    pub fn set_animation(&mut self, name: &str, loop_animation: bool) {
-        if self.current_animation_name == name {
+    // overrides the transitions defined by the aseprite tags
+    pub fn with_transitions(mut self, transitions: TransitionTable) -> Self {
+        self.transitions = transitions;
+        self
+    }
+    pub fn with_layer(mut self, layer: &str) -> Self {
+        self.layer = Some(layer.to_owned());
+        self
+    }
+    // loops or plays as often as its tag says
+    pub fn set_animation(&mut self, name: &str) {
+        if self.current_animation_name == name || self.pending_animation.as_deref() == Some(name) {
             return;
         }
-        self.current_animation_name = name.to_owned();
+        self.previous_animation_name =
+            std::mem::replace(&mut self.current_animation_name, name.to_owned());
+        self.pending_animation = None;
         self.current_frame_index = 0;
-        self.loop_animation = loop_animation;
         self.is_dirty = true;
     }
 }
@@ -79,30 +248,91 @@ pub struct Aseprite {
     pub file_path: PathBuf,
     pub rects: Vec<bevy::sprite::Rect>,
     pub animations: HashMap<String, Animation>,
+    // atlas index of every frame per layer, `None` when exported without split layers
+    pub layers: Vec<(Option<String>, Vec<usize>)>,
+    // from tags named "from>to"
+    pub transitions: TransitionTable,
+    // the sheet built by the loader for .aseprite files, which have no image next to them
+    pub texture: Option<Handle<Image>>,
+    // by name, in sheet pixels
+    pub slices: HashMap<String, SheetSlice>,
+}
+
+// where a slice's first key is on the sheet, for cutting it out of the texture like the nine
+// slice panels do
+#[derive(Debug, Clone)]
+pub struct SheetSlice {
+    pub bounds: bevy::sprite::Rect,
+    // the part that stretches, `None` for plain slices
+    pub center: Option<bevy::sprite::Rect>,
 }
 
 impl Aseprite {
-    pub fn new(file_path: &Path, data: AsepriteData) -> Self {
-        // animations
-        let frames = {
-            let re = Regex::new(r".*\D(\d+).aseprite").expect("Failed to parse regex");
-            let mut frames = data
-                .frames
-                .iter()
-                .filter_map(|(key, value)| {
-                    usize::from_str_radix(re.replace(key, "$1").as_ref(), 10)
-                        .map(|index| (index, value))
-                        .ok()
+    // frame names are matched against the formats in turn, array exports fall back to their
+    // order for names matching none
+    pub fn new(file_path: &Path, data: AsepriteData, formats: &[FrameNameFormat]) -> Self {
+        // frames grouped by layer
+        let layer_frames = {
+            let ordered = matches!(data.frames, Frames::Array(_));
+            let mut layer_frames: Vec<(Option<String>, Vec<(usize, &self::data::FrameValue)>)> =
+                vec![];
+            for (position, (key, value)) in data.frames.iter().enumerate() {
+                let (layer, index) = match formats
+                    .iter()
+                    .find_map(|format| format.parse(key, &data.meta.frame_tags))
+                {
+                    Some(frame_name) => frame_name,
+                    None if ordered => (None, position),
+                    None => continue,
+                };
+                match layer_frames.iter_mut().find(|(name, _)| *name == layer) {
+                    Some((_, frames)) => frames.push((index, value)),
+                    None => layer_frames.push((layer, vec![(index, value)])),
+                }
+            }
+            // same order as the aseprite layers, from bottom to top
+            let layer_order = |layer: &Option<String>| {
+                layer.as_ref().and_then(|layer| {
+                    data.meta
+                        .layers
+                        .iter()
+                        .position(|meta_layer| &meta_layer.name == layer)
                 })
-                .collect::<Vec<_>>();
-            frames.sort_by(|a, b| a.0.cmp(&b.0));
-            frames
+            };
+            layer_frames.sort_by_key(|(layer, _)| layer_order(layer));
+            for (_, frames) in layer_frames.iter_mut() {
+                frames.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+            layer_frames
                 .into_iter()
-                .map(|(_, value)| value)
+                .map(|(layer, frames)| {
+                    (
+                        layer,
+                        frames
+                            .into_iter()
+                            .map(|(_, value)| value)
+                            .collect::<Vec<_>>(),
+                    )
+                })
                 .collect::<Vec<_>>()
         };
-        let rects = frames
+        let mut layers = vec![];
+        let mut atlas_index = 0;
+        for (layer, frames) in layer_frames.iter() {
+            layers.push((
+                layer.clone(),
+                (atlas_index..atlas_index + frames.len()).collect(),
+            ));
+            atlas_index += frames.len();
+        }
+        // every layer shares the frame durations
+        let frames = layer_frames
+            .first()
+            .map(|(_, frames)| frames.clone())
+            .unwrap_or_default();
+        let rects = layer_frames
             .iter()
+            .flat_map(|(_, frames)| frames.iter())
             .map(|self::data::FrameValue { frame, .. }| {
                 let min = Vec2::new(frame.x as f32, frame.y as f32);
                 let size = Vec2::new(frame.w as f32, frame.h as f32);
@@ -112,41 +342,240 @@ impl Aseprite {
                 }
             })
             .collect();
+        // the root slice's pivot in sprite pixels, its keys last until the next one
+        let root_pivot = |index: i64| {
+            let slice = data
+                .meta
+                .slices
+                .iter()
+                .find(|slice| slice.name == ROOT_SLICE)?;
+            let key = slice
+                .keys
+                .iter()
+                .filter(|key| key.frame <= index)
+                .max_by_key(|key| key.frame)?;
+            let pivot = key.pivot.as_ref()?;
+            Some(Vec2::new(
+                (key.bounds.x + pivot.x) as f32,
+                (key.bounds.y + pivot.y) as f32,
+            ))
+        };
+        // tags named "@event" mark the frames that emit "event"
+        let marker_tags = data
+            .meta
+            .frame_tags
+            .iter()
+            .filter_map(|tag| tag.name.strip_prefix('@').map(|name| (tag, name)))
+            .collect::<Vec<_>>();
         let animations = data
             .meta
             .frame_tags
             .iter()
+            .filter(|tag| !tag.name.starts_with('@'))
             .map(|tag| {
+                let options = TagOptions::new(tag);
+                // tag user data like "2:footstep 5:footstep", offsets from the first frame
+                let data_events = tag
+                    .data
+                    .as_deref()
+                    .unwrap_or("")
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .filter_map(|entry| entry.split_once(':'))
+                    .filter_map(|(offset, name)| {
+                        offset
+                            .parse::<i64>()
+                            .ok()
+                            .map(|offset| (tag.from + offset, name))
+                    })
+                    .collect::<Vec<_>>();
                 let frames = (tag.from..=tag.to)
                     .into_iter()
                     .filter_map(|index| {
                         frames.get(index as usize).map(|frame| AnimationFrame {
                             index: index as usize,
                             duration: (frame.duration as f32) / 1000.0,
+                            // from the previous frame of the tag, the first one starts in place
+                            motion: match (root_pivot(index - 1), root_pivot(index)) {
+                                (Some(from), Some(to)) if index > tag.from => {
+                                    (to - from) * Vec2::new(1.0, -1.0)
+                                }
+                                _ => Vec2::ZERO,
+                            },
+                            events: marker_tags
+                                .iter()
+                                .filter(|(marker, _)| (marker.from..=marker.to).contains(&index))
+                                .map(|(_, name)| name.to_string())
+                                .chain(
+                                    data_events
+                                        .iter()
+                                        .filter(|(frame_index, _)| *frame_index == index)
+                                        .map(|(_, name)| name.to_string()),
+                                )
+                                .collect(),
                         })
                     })
                     .collect();
                 (
-                    tag.name.to_owned(),
+                    options.name.clone(),
                     Animation {
-                        name: tag.name.to_owned(),
+                        name: options.name,
                         frames,
+                        repeat: options.repeat,
+                        speed: options.speed,
                     },
                 )
             })
             .collect::<HashMap<_, _>>();
 
+        let mut transitions = TransitionTable::new();
+        for name in animations.keys() {
+            if let Some((from, to)) = name.split_once('>') {
+                transitions.insert(from, to, name);
+            }
+        }
+
+        // slice bounds are in sprite pixels, moved onto the frame they are keyed on
+        let slices = data
+            .meta
+            .slices
+            .iter()
+            .filter_map(|slice| {
+                let key = slice.keys.first()?;
+                let frame = frames.get(key.frame as usize)?;
+                let origin = Vec2::new(
+                    (frame.frame.x - frame.sprite_source_size.x) as f32,
+                    (frame.frame.y - frame.sprite_source_size.y) as f32,
+                );
+                let to_rect = |x: i64, y: i64, w: i64, h: i64| {
+                    let min = origin + Vec2::new(x as f32, y as f32);
+                    bevy::sprite::Rect {
+                        min,
+                        max: min + Vec2::new(w as f32, h as f32),
+                    }
+                };
+                let bounds = &key.bounds;
+                let center = key.center.as_ref().map(|center| {
+                    to_rect(bounds.x + center.x, bounds.y + center.y, center.w, center.h)
+                });
+                Some((
+                    slice.name.clone(),
+                    SheetSlice {
+                        bounds: to_rect(bounds.x, bounds.y, bounds.w, bounds.h),
+                        center,
+                    },
+                ))
+            })
+            .collect();
+
         Self {
             data,
             file_path: file_path.to_path_buf(),
             rects,
             animations,
+            layers,
+            transitions,
+            texture: None,
+            slices,
+        }
+    }
+    // the sheet image, next to the json
+    pub fn texture_path(&self) -> Result<PathBuf> {
+        let base_path = self
+            .file_path
+            .parent()
+            .with_context(|| format!("failed to get parent directory, {:?}", self.file_path))?;
+        Ok(base_path.join(&self.data.meta.image))
+    }
+
+    pub fn texture(&self, asset_server: &AssetServer) -> Result<Handle<Image>> {
+        match &self.texture {
+            Some(texture) => Ok(texture.clone()),
+            None => Ok(asset_server.load(self.texture_path()?.as_path())),
         }
     }
+
+    // of the sheet image in pixels
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.data.meta.size.w as f32, self.data.meta.size.h as f32)
+    }
+
+    pub fn atlas_index(&self, layer: Option<&str>, frame_index: usize) -> Option<usize> {
+        self.layers
+            .iter()
+            .find(|(name, _)| name.as_deref() == layer)
+            .or_else(|| self.layers.first().filter(|_| layer.is_none()))
+            .and_then(|(_, indices)| indices.get(frame_index).copied())
+    }
 }
 
-#[derive(Default)]
-pub struct AsepriteLoader;
+// an exported frame name pattern in aseprite's --filename-format syntax, {title}, {layer},
+// {frame}, {tag}, {tagframe} and {extension}. frames named by tag are counted from its first
+// frame, zero padded numbers like {frame001} work too
+#[derive(Debug, Clone)]
+pub struct FrameNameFormat {
+    regex: Regex,
+}
+impl FrameNameFormat {
+    pub fn new(format: &str) -> Self {
+        let placeholder = Regex::new(r"\{([a-z]+)\d*\}").expect("Failed to parse regex");
+        let mut pattern = "^".to_string();
+        let mut rest = 0;
+        for captures in placeholder.captures_iter(format) {
+            let (whole, name) = match (captures.get(0), captures.get(1)) {
+                (Some(whole), Some(name)) => (whole, name.as_str()),
+                _ => continue,
+            };
+            pattern.push_str(&regex::escape(&format[rest..whole.start()]));
+            pattern.push_str(match name {
+                "frame" => r"(?P<frame>\d+)",
+                "tagframe" => r"(?P<tagframe>\d+)",
+                "layer" => r"(?P<layer>.*?)",
+                "tag" => r"(?P<tag>.*?)",
+                "extension" => r"[^.]*",
+                _ => r".*?",
+            });
+            rest = whole.end();
+        }
+        pattern.push_str(&regex::escape(&format[rest..]));
+        pattern.push('$');
+        Self {
+            regex: Regex::new(&pattern).expect("Failed to parse regex"),
+        }
+    }
+
+    // layer and frame index of a frame name
+    fn parse(&self, name: &str, tags: &[FrameTag]) -> Option<(Option<String>, usize)> {
+        let captures = self.regex.captures(name)?;
+        let number = |group: &str| {
+            captures
+                .name(group)
+                .and_then(|value| value.as_str().parse::<usize>().ok())
+        };
+        let index = match number("frame") {
+            Some(index) => index,
+            None => {
+                let tag = captures.name("tag")?.as_str();
+                let from = tags.iter().find(|frame_tag| frame_tag.name == tag)?.from;
+                from as usize + number("tagframe")?
+            }
+        };
+        let layer = captures
+            .name("layer")
+            .map(|layer| layer.as_str().to_owned());
+        Some((layer, index))
+    }
+
+    fn defaults() -> Vec<Self> {
+        DEFAULT_FRAME_NAME_FORMATS
+            .iter()
+            .map(|format| Self::new(format))
+            .collect()
+    }
+}
+
+pub struct AsepriteLoader {
+    formats: Vec<FrameNameFormat>,
+}
 impl AssetLoader for AsepriteLoader {
     fn load<'a>(
         &'a self,
@@ -154,15 +583,34 @@ impl AssetLoader for AsepriteLoader {
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
-            let data = serde_json::from_slice::<AsepriteData>(bytes)?;
-            let aseprite = Aseprite::new(&load_context.path(), data);
+            let path = load_context.path().to_path_buf();
+            let aseprite = match path.extension().and_then(|extension| extension.to_str()) {
+                Some("aseprite") | Some("ase") => {
+                    let title = path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    let (data, image) = ase::read(bytes, &title)
+                        .with_context(|| format!("failed to read {:?}", path))?;
+                    load_context.set_labeled_asset(SHEET_LABEL, LoadedAsset::new(image));
+                    let texture =
+                        load_context.get_handle(AssetPath::new_ref(&path, Some(SHEET_LABEL)));
+                    Aseprite {
+                        texture: Some(texture),
+                        // named by the loader, whatever the exports use
+                        ..Aseprite::new(&path, data, &FrameNameFormat::defaults())
+                    }
+                }
+                // aseprite, texturepacker or grid sheets
+                _ => Aseprite::new(&path, sheet::parse(bytes)?, &self.formats),
+            };
             load_context.set_default_asset(LoadedAsset::new(aseprite));
             Ok(())
         })
     }
 
     fn extensions(&self) -> &[&str] {
-        &["json"]
+        &["json", "aseprite", "ase"]
     }
 }
 
@@ -171,71 +619,278 @@ fn create_texture_atlas(
     asset_server: &Res<AssetServer>,
 ) -> Result<TextureAtlas> {
     // create texture atlas
-    let base_path = aseprite
-        .file_path
-        .parent()
-        .with_context(|| format!("failed to get parent directory, {:?}", aseprite.file_path))?;
-    let mut texture_path = std::path::PathBuf::new();
-    texture_path.push(base_path);
-    texture_path.push(&aseprite.data.meta.image);
-
-    let texture_handle = asset_server.load(texture_path.as_path());
-    let mut texture_atlas = TextureAtlas::new_empty(
-        texture_handle,
-        Vec2::new(
-            aseprite.data.meta.size.w as f32,
-            aseprite.data.meta.size.h as f32,
-        ),
-    );
+    let texture_handle = aseprite.texture(asset_server)?;
+    let mut texture_atlas = TextureAtlas::new_empty(texture_handle, aseprite.size());
     for rect in &aseprite.rects {
         texture_atlas.add_texture(rect.to_owned());
     }
     Ok(texture_atlas)
 }
 fn animation_sprite_system(
-    time: Res<Time>,
-    mut query: Query<(&mut AnimationSprite, &mut TextureAtlasSprite)>,
+    time: Res<GameTime>,
+    mut query: Query<
+        (
+            Entity,
+            &mut AnimationSprite,
+            &mut TextureAtlasSprite,
+            &GlobalTransform,
+            Option<&Parent>,
+        ),
+        Without<AnimationLayerOf>,
+    >,
+    always_animate: Query<(), With<AlwaysAnimate>>,
+    slowed: Query<&Slowed>,
+    cameras: Query<(&GlobalTransform, &OrthographicProjection), With<VirtualPosition>>,
     aseprites: ResMut<Assets<Aseprite>>,
+    mut event_writer: EventWriter<AnimationFrameEvent>,
+    config: Res<GameConfig>,
+    timings: Res<SystemTimings>,
 ) {
-    let set_new_frame = |sprite: &mut Mut<AnimationSprite>,
-                         texture_atlas_sprite: &mut Mut<TextureAtlasSprite>,
-                         animation: &Animation| {
+    let _span = timings.span("animation");
+    // the camera's view in its own frame, which turns with the level's gravity
+    let view = cameras.get_single().ok().map(|(transform, projection)| {
+        let half_size = Vec2::new(
+            projection.right - projection.left,
+            projection.top - projection.bottom,
+        ) * transform.scale.truncate()
+            / 2.0;
+        (*transform, half_size + Vec2::splat(OFFSCREEN_MARGIN))
+    });
+    let on_screen = |position: Vec3| {
+        view.map_or(true, |(transform, half_size)| {
+            let offset = (transform.rotation.inverse() * (position - transform.translation))
+                .truncate()
+                .abs();
+            offset.x <= half_size.x && offset.y <= half_size.y
+        })
+    };
+    let mut frame_events = vec![];
+    let mut set_new_frame = |sprite: &mut Mut<AnimationSprite>,
+                             texture_atlas_sprite: &mut Mut<TextureAtlasSprite>,
+                             aseprite: &Aseprite,
+                             animation: &Animation,
+                             entity: Entity| {
         if let Some(frame) = animation.frames.get(sprite.current_frame_index) {
-            let time = frame.duration / sprite.speed;
+            for event_name in frame.events.iter() {
+                frame_events.push(AnimationFrameEvent {
+                    entity,
+                    event_name: event_name.clone(),
+                });
+            }
+            sprite.motion += frame.motion;
+            let time = frame.duration / (config.sprite_speed * animation.speed);
             sprite.timer.set_duration(Duration::from_secs_f32(time));
             sprite.timer.reset();
-            texture_atlas_sprite.index = frame.index;
+            texture_atlas_sprite.index = aseprite
+                .atlas_index(sprite.layer.as_deref(), frame.index)
+                .unwrap_or(frame.index);
         }
     };
-    for (mut sprite, mut texture_atlas_sprite) in query.iter_mut() {
+    for (entity, mut sprite, mut texture_atlas_sprite, global_transform, parent) in query.iter_mut()
+    {
         if let Some(aseprite) = aseprites.get(&sprite.aseprite) {
             // get animation frame
             if sprite.is_dirty {
+                // play a transition animation first if there is one
+                let via = sprite
+                    .transitions
+                    .get(
+                        &sprite.previous_animation_name,
+                        &sprite.current_animation_name,
+                    )
+                    .or_else(|| {
+                        aseprite.transitions.get(
+                            &sprite.previous_animation_name,
+                            &sprite.current_animation_name,
+                        )
+                    })
+                    .filter(|via| aseprite.animations.contains_key(*via))
+                    .map(|via| via.to_owned());
+                let transition = via.is_some();
+                if let Some(via) = via {
+                    let target = std::mem::replace(&mut sprite.current_animation_name, via);
+                    sprite.pending_animation = Some(target);
+                }
+                sprite.previous_animation_name.clear();
                 if let Some(animation) = aseprite.animations.get(&sprite.current_animation_name) {
-                    set_new_frame(&mut sprite, &mut texture_atlas_sprite, animation);
+                    // transitions play once
+                    sprite.repeats_left = if transition {
+                        Some(1)
+                    } else {
+                        animation.repeat
+                    };
+                    set_new_frame(
+                        &mut sprite,
+                        &mut texture_atlas_sprite,
+                        aseprite,
+                        animation,
+                        entity,
+                    );
                 }
                 sprite.is_dirty = false;
             } else {
-                sprite.timer.tick(time.delta());
+                let always = always_animate.get(entity).is_ok()
+                    || parent.map_or(false, |parent| always_animate.get(parent.0).is_ok());
+                let delta = if always || on_screen(global_transform.translation) {
+                    time.delta() + std::mem::take(&mut sprite.offscreen_time)
+                } else {
+                    sprite.offscreen_time += time.delta();
+                    if sprite.offscreen_time.as_secs_f32() < OFFSCREEN_TICK {
+                        continue;
+                    }
+                    std::mem::take(&mut sprite.offscreen_time)
+                };
+                // slowed actors animate slower too
+                let factor = slowed
+                    .get(entity)
+                    .ok()
+                    .or_else(|| parent.and_then(|parent| slowed.get(parent.0).ok()))
+                    .map_or(1.0, |slowed| slowed.factor);
+                sprite.timer.tick(delta.mul_f32(factor));
                 if sprite.timer.just_finished() {
                     if let Some(animation) = aseprite.animations.get(&sprite.current_animation_name)
                     {
                         if sprite.current_frame_index + 1 > animation.frames.len() - 1 {
-                            if sprite.loop_animation {
+                            let repeat = match &mut sprite.repeats_left {
+                                Some(repeats_left) => {
+                                    *repeats_left = repeats_left.saturating_sub(1);
+                                    *repeats_left > 0
+                                }
+                                None => true,
+                            };
+                            if repeat {
+                                sprite.current_frame_index = 0;
+                                set_new_frame(
+                                    &mut sprite,
+                                    &mut texture_atlas_sprite,
+                                    aseprite,
+                                    animation,
+                                    entity,
+                                );
+                            } else if let Some(name) = sprite.pending_animation.take() {
+                                // transition finished
+                                sprite.current_animation_name = name;
                                 sprite.current_frame_index = 0;
-                                set_new_frame(&mut sprite, &mut texture_atlas_sprite, animation);
+                                if let Some(animation) =
+                                    aseprite.animations.get(&sprite.current_animation_name)
+                                {
+                                    sprite.repeats_left = animation.repeat;
+                                    set_new_frame(
+                                        &mut sprite,
+                                        &mut texture_atlas_sprite,
+                                        aseprite,
+                                        animation,
+                                        entity,
+                                    );
+                                }
                             } else {
                                 // pause
                             }
                         } else {
                             sprite.current_frame_index += 1;
-                            set_new_frame(&mut sprite, &mut texture_atlas_sprite, animation);
+                            set_new_frame(
+                                &mut sprite,
+                                &mut texture_atlas_sprite,
+                                aseprite,
+                                animation,
+                                entity,
+                            );
                         }
                     }
                 }
             }
         }
     }
+    for event in frame_events {
+        event_writer.send(event);
+    }
+}
+
+// moves dynamic bodies by the root motion of their sprites, mirrored with the sprite and turned
+// with it, e.g. in levels with sideways gravity. the body's shape is cast along the motion so it
+// stops at whatever solid is in the way instead of ending up inside it
+fn root_motion_system(
+    mut sprites: Query<(
+        &mut AnimationSprite,
+        &TextureAtlasSprite,
+        &GlobalTransform,
+        &Parent,
+    )>,
+    mut bodies: Query<(
+        &mut RigidBodyPositionComponent,
+        &RigidBodyTypeComponent,
+        &ColliderShapeComponent,
+    )>,
+    rapier_config: Res<RapierConfiguration>,
+    query_pipeline: Res<QueryPipeline>,
+    collider_query: QueryPipelineColliderComponentsQuery,
+) {
+    let collider_set = QueryPipelineColliderComponentsSet(&collider_query);
+    for (mut sprite, texture_atlas_sprite, global_transform, parent) in sprites.iter_mut() {
+        if sprite.motion == Vec2::ZERO {
+            continue;
+        }
+        let mut motion = std::mem::replace(&mut sprite.motion, Vec2::ZERO);
+        if texture_atlas_sprite.flip_x {
+            motion.x = -motion.x;
+        }
+        let (mut rb_position, rb_type, collider_shape) = match bodies.get_mut(parent.0) {
+            Ok(body) => body,
+            Err(_) => continue,
+        };
+        if rb_type.0 != RigidBodyType::Dynamic {
+            continue;
+        }
+        let delta =
+            (global_transform.rotation * motion.extend(0.0)).truncate() / rapier_config.scale;
+        let delta = Vector::new(delta.x, delta.y);
+        let own_collider = parent.0.handle();
+        let filter = |handle: ColliderHandle| handle != own_collider;
+        let toi = query_pipeline
+            .cast_shape(
+                &collider_set,
+                &rb_position.position,
+                &delta,
+                &*collider_shape.0,
+                1.0,
+                CollisionLayers::solid_query(),
+                Some(&filter),
+            )
+            .map_or(1.0, |(_, toi)| toi.toi);
+        rb_position.position.translation.vector += delta * toi;
+        rb_position.next_position.translation.vector += delta * toi;
+    }
+}
+
+fn animation_layer_system(
+    sources: Query<(&AnimationSprite, &TextureAtlasSprite), Without<AnimationLayerOf>>,
+    mut layers: Query<(
+        &AnimationLayerOf,
+        &mut AnimationSprite,
+        &mut TextureAtlasSprite,
+    )>,
+    aseprites: Res<Assets<Aseprite>>,
+) {
+    for (AnimationLayerOf(source), mut sprite, mut texture_atlas_sprite) in layers.iter_mut() {
+        if let Ok((source_sprite, source_texture_atlas_sprite)) = sources.get(*source) {
+            if sprite.current_animation_name != source_sprite.current_animation_name {
+                sprite.current_animation_name = source_sprite.current_animation_name.clone();
+            }
+            sprite.current_frame_index = source_sprite.current_frame_index;
+            sprite.is_dirty = false;
+            if let Some(index) = aseprites.get(&sprite.aseprite).and_then(|aseprite| {
+                aseprite
+                    .animations
+                    .get(&sprite.current_animation_name)
+                    .and_then(|animation| animation.frames.get(sprite.current_frame_index))
+                    .and_then(|frame| aseprite.atlas_index(sprite.layer.as_deref(), frame.index))
+            }) {
+                texture_atlas_sprite.index = index;
+            }
+            texture_atlas_sprite.flip_x = source_texture_atlas_sprite.flip_x;
+            texture_atlas_sprite.flip_y = source_texture_atlas_sprite.flip_y;
+        }
+    }
 }
 
 fn on_asset_event_system(
@@ -245,14 +900,29 @@ fn on_asset_event_system(
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut commands: Commands,
     mut query: Query<(Entity, &mut AnimationSprite)>,
+    mut error_events: EventWriter<AssetErrorEvent>,
 ) {
     for event in event_asset.iter() {
         match event {
             AssetEvent::Created { handle } => {
-                let aseprite = aseprites.get(handle).unwrap();
-                let texture_atlas_handle = create_texture_atlas(&aseprite, &asset_server)
-                    .map(|texture_atlas| texture_atlases.add(texture_atlas))
-                    .unwrap();
+                let aseprite = match aseprites.get(handle) {
+                    Some(aseprite) => aseprite,
+                    None => continue,
+                };
+                let _span = info_span!("aseprite", path = ?aseprite.file_path).entered();
+                let texture_atlas_handle = match create_texture_atlas(aseprite, &asset_server) {
+                    Ok(texture_atlas) => {
+                        debug!("{} frames", texture_atlas.len());
+                        texture_atlases.add(texture_atlas)
+                    }
+                    Err(err) => {
+                        error_events.send(AssetErrorEvent::new(
+                            aseprite.file_path.display().to_string(),
+                            &err,
+                        ));
+                        continue;
+                    }
+                };
 
                 for (entity, _) in query
                     .iter_mut()
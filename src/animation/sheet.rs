@@ -0,0 +1,265 @@
+// sprite sheets from other tools, turned into the same data as an aseprite json export so they
+// play through AnimationSprite too
+use super::data::{AsepriteData, FrameTag, FrameValue, Frames, Meta, Size, SpriteSourceSizeClass};
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// milliseconds, when the sheet doesn't say
+const DEFAULT_DURATION: i64 = 100;
+
+// texturepacker's "json (hash)" and "json (array)" exports, also written by most other packers.
+// the optional "animations" of the pixijs export list frame names per animation, without it
+// frames are grouped by name with the number at the end dropped, "walk_0.png" plays in "walk"
+#[derive(Debug, Deserialize)]
+pub struct TexturePackerData {
+    frames: Frames,
+    #[serde(default)]
+    animations: BTreeMap<String, Vec<String>>,
+    meta: TexturePackerMeta,
+}
+
+#[derive(Debug, Deserialize)]
+struct TexturePackerMeta {
+    #[serde(default)]
+    app: String,
+    image: String,
+    size: Size,
+}
+
+impl TexturePackerData {
+    pub fn into_aseprite_data(self) -> Result<AsepriteData> {
+        let mut named = match self.frames {
+            Frames::Hash(frames) => {
+                let mut frames = frames.into_iter().collect::<Vec<_>>();
+                frames.sort_by(|(a, _), (b, _)| natural_key(a).cmp(&natural_key(b)));
+                frames
+            }
+            Frames::Array(frames) => frames
+                .into_iter()
+                .map(|frame| (frame.filename.clone().unwrap_or_default(), frame))
+                .collect(),
+        };
+        if let Some((name, _)) = named.iter().find(|(_, frame)| frame.rotated) {
+            bail!("rotated frames aren't supported, {}", name);
+        }
+        for (_, frame) in named.iter_mut() {
+            if frame.duration <= 0 {
+                frame.duration = DEFAULT_DURATION;
+            }
+        }
+        let animations = if self.animations.is_empty() {
+            let mut animations: BTreeMap<String, Vec<String>> = BTreeMap::new();
+            for (name, _) in named.iter() {
+                animations
+                    .entry(animation_name(name))
+                    .or_default()
+                    .push(name.clone());
+            }
+            animations
+        } else {
+            self.animations
+        };
+
+        // tags are frame ranges, so every animation gets its frames in a row
+        let mut frames = vec![];
+        let mut frame_tags = vec![];
+        for (animation, names) in animations.iter() {
+            let from = frames.len();
+            for name in names {
+                match named.iter().find(|(frame_name, _)| frame_name == name) {
+                    // read by position, names would be matched against the export formats
+                    Some((_, frame)) => frames.push(FrameValue {
+                        filename: None,
+                        ..frame.clone()
+                    }),
+                    None => bail!("{} has no frame {}", animation, name),
+                }
+            }
+            if frames.len() > from {
+                frame_tags.push(tag(animation, from, frames.len() - 1));
+            }
+        }
+        Ok(AsepriteData {
+            frames: Frames::Array(frames),
+            meta: meta(self.meta.app, self.meta.image, self.meta.size, frame_tags),
+        })
+    }
+}
+
+// a sheet of equally sized frames, read left to right and top to bottom, e.g.
+// {"image": "coin.png", "tile_size": [16, 16], "columns": 8, "rows": 1,
+// "animations": {"spin": {"from": 0, "to": 7, "duration": 80}}}
+#[derive(Debug, Deserialize)]
+pub struct GridSheet {
+    image: String,
+    tile_size: [u32; 2],
+    columns: u32,
+    rows: u32,
+    // pixels between the frames
+    #[serde(default)]
+    padding: [u32; 2],
+    // milliseconds per frame of animations that don't set theirs
+    #[serde(default)]
+    duration: Option<i64>,
+    #[serde(default)]
+    animations: BTreeMap<String, GridAnimation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GridAnimation {
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    duration: Option<i64>,
+}
+
+impl GridSheet {
+    pub fn into_aseprite_data(self) -> Result<AsepriteData> {
+        let count = (self.columns * self.rows) as usize;
+        let [tile_width, tile_height] = self.tile_size;
+        let [padding_x, padding_y] = self.padding;
+        let size = Size {
+            w: (self.columns * (tile_width + padding_x)).saturating_sub(padding_x) as i64,
+            h: (self.rows * (tile_height + padding_y)).saturating_sub(padding_y) as i64,
+        };
+        let grid_frame = |index: usize| {
+            let (column, row) = (index as u32 % self.columns, index as u32 / self.columns);
+            let rect = SpriteSourceSizeClass {
+                x: (column * (tile_width + padding_x)) as i64,
+                y: (row * (tile_height + padding_y)) as i64,
+                w: tile_width as i64,
+                h: tile_height as i64,
+            };
+            FrameValue {
+                filename: None,
+                frame: rect,
+                rotated: false,
+                trimmed: false,
+                sprite_source_size: SpriteSourceSizeClass {
+                    x: 0,
+                    y: 0,
+                    w: tile_width as i64,
+                    h: tile_height as i64,
+                },
+                source_size: Size {
+                    w: tile_width as i64,
+                    h: tile_height as i64,
+                },
+                duration: self.duration.unwrap_or(DEFAULT_DURATION),
+            }
+        };
+
+        // every animation gets its frames in a row like texturepacker sheets, the whole sheet
+        // plays as "default" without any
+        let animations = if self.animations.is_empty() {
+            [(
+                "default".to_string(),
+                GridAnimation {
+                    from: 0,
+                    to: count.saturating_sub(1),
+                    duration: None,
+                },
+            )]
+            .into_iter()
+            .collect()
+        } else {
+            self.animations
+        };
+        let mut frames = vec![];
+        let mut frame_tags = vec![];
+        for (name, animation) in animations.iter() {
+            if animation.from > animation.to || animation.to >= count {
+                bail!(
+                    "{} runs from {} to {}, the sheet has {} frames",
+                    name,
+                    animation.from,
+                    animation.to,
+                    count
+                );
+            }
+            let from = frames.len();
+            for index in animation.from..=animation.to {
+                let mut frame = grid_frame(index);
+                if let Some(duration) = animation.duration {
+                    frame.duration = duration;
+                }
+                frames.push(frame);
+            }
+            frame_tags.push(tag(name, from, frames.len() - 1));
+        }
+        Ok(AsepriteData {
+            frames: Frames::Array(frames),
+            meta: meta("grid".to_string(), self.image, size, frame_tags),
+        })
+    }
+}
+
+fn meta(app: String, image: String, size: Size, frame_tags: Vec<FrameTag>) -> Meta {
+    Meta {
+        app,
+        version: String::new(),
+        image,
+        format: "RGBA8888".to_string(),
+        size,
+        scale: "1".to_string(),
+        frame_tags,
+        layers: vec![],
+        slices: vec![],
+    }
+}
+
+fn tag(name: &str, from: usize, to: usize) -> FrameTag {
+    FrameTag {
+        name: name.to_string(),
+        from: from as i64,
+        to: to as i64,
+        direction: "forward".to_string(),
+        color: "#000000ff".to_string(),
+        data: None,
+        repeat: None,
+    }
+}
+
+// "walk_01.png" to "walk"
+fn animation_name(name: &str) -> String {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let trimmed = stem
+        .trim_end_matches(|c: char| c.is_ascii_digit())
+        .trim_end_matches(|c: char| c == '_' || c == '-' || c == ' ');
+    if trimmed.is_empty() {
+        stem.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+// "walk_2" before "walk_10"
+fn natural_key(name: &str) -> (String, u64) {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let digits = stem.len()
+        - stem
+            .chars()
+            .rev()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+    (
+        stem[..digits].to_string(),
+        stem[digits..].parse().unwrap_or(0),
+    )
+}
+
+// which kind of sheet a json file is, aseprite exports have frame tags
+pub fn parse(bytes: &[u8]) -> Result<AsepriteData> {
+    let value = serde_json::from_slice::<serde_json::Value>(bytes)?;
+    if value.get("frames").is_none() {
+        return serde_json::from_value::<GridSheet>(value)?.into_aseprite_data();
+    }
+    let is_aseprite = value
+        .get("meta")
+        .map_or(false, |meta| meta.get("frameTags").is_some());
+    if is_aseprite {
+        return Ok(serde_json::from_value::<AsepriteData>(value)?);
+    }
+    serde_json::from_value::<TexturePackerData>(value)?.into_aseprite_data()
+}
@@ -0,0 +1,95 @@
+use crate::bitmap_font::{BitmapText, BitmapTextBundle, ScreenPosition, SCREEN_HALF_SIZE};
+use bevy::prelude::*;
+
+// seconds a banner line stays up
+const BANNER_DURATION: f32 = 8.0;
+const BANNER_LINES: usize = 4;
+// glyphs across the screen
+const BANNER_WIDTH: usize = 78;
+const BANNER_COLOR: Color = Color::rgb(1.0, 0.4, 0.4);
+
+// assets that failed to load are logged and listed at the bottom of the screen, the game keeps
+// running with whatever did load
+pub struct AssetErrorPlugin;
+impl Plugin for AssetErrorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AssetErrorEvent>()
+            .init_resource::<AssetErrors>()
+            .add_startup_system(setup_system)
+            .add_system(error_system)
+            .add_system(banner_system.after(error_system));
+    }
+}
+
+pub struct AssetErrorEvent {
+    // path or name of what failed, e.g. a level identifier
+    pub asset: String,
+    pub message: String,
+}
+impl AssetErrorEvent {
+    pub fn new(asset: impl Into<String>, err: &anyhow::Error) -> Self {
+        Self {
+            asset: asset.into(),
+            // the context chain on one line
+            message: format!("{:#}", err),
+        }
+    }
+}
+
+// shown lines, oldest first, with the seconds they have left
+#[derive(Default)]
+struct AssetErrors(Vec<(String, Timer)>);
+
+#[derive(Component)]
+struct AssetErrorBanner;
+
+fn setup_system(mut commands: Commands) {
+    commands
+        .spawn_bundle(BitmapTextBundle::new(
+            BitmapText::new("", BANNER_COLOR).with_anchor(Vec2::new(0.0, -0.5)),
+            Transform::identity(),
+        ))
+        .insert(ScreenPosition(Vec2::new(0.0, -SCREEN_HALF_SIZE.y + 2.0)))
+        .insert(AssetErrorBanner);
+}
+
+fn error_system(mut events: EventReader<AssetErrorEvent>, mut errors: ResMut<AssetErrors>) {
+    for event in events.iter() {
+        error!("{}: {}", event.asset, event.message);
+        let line = format!("{}: {}", event.asset, event.message)
+            .chars()
+            .take(BANNER_WIDTH)
+            .collect::<String>();
+        errors
+            .0
+            .push((line, Timer::from_seconds(BANNER_DURATION, false)));
+    }
+    let overflow = errors.0.len().saturating_sub(BANNER_LINES);
+    errors.0.drain(..overflow);
+}
+
+// real time, the game may well be paused behind the banner
+fn banner_system(
+    time: Res<Time>,
+    mut errors: ResMut<AssetErrors>,
+    mut banners: Query<&mut BitmapText, With<AssetErrorBanner>>,
+) {
+    if errors.0.is_empty() {
+        return;
+    }
+    for (_, timer) in errors.0.iter_mut() {
+        timer.tick(time.delta());
+    }
+    errors.0.retain(|(_, timer)| !timer.finished());
+    let text = errors
+        .0
+        .iter()
+        .map(|(line, _)| line.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    for mut banner in banners.iter_mut() {
+        if banner.text != text {
+            banner.text = text.clone();
+        }
+    }
+}
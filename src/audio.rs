@@ -0,0 +1,261 @@
+use crate::{
+    bitmap_font::SCREEN_HALF_SIZE,
+    ldtk::{plugin::LdtkEvent, world::WorldMap},
+};
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioChannel, AudioPlugin, AudioSource};
+
+const SFX_CHANNELS: usize = 8;
+// positional sfx are silent beyond this distance in pixels, so a screen or so off screen
+const SFX_MAX_DISTANCE: f32 = 480.0;
+// horizontal distance in pixels which pans fully to one side
+const SFX_PAN_DISTANCE: f32 = 160.0;
+
+pub struct SoundPlugin;
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(AudioPlugin)
+            .init_resource::<Mixer>()
+            .add_event::<MusicEvent>()
+            .add_event::<StingerEvent>()
+            .add_event::<SfxEvent>()
+            .add_system(level_music_system)
+            .add_system(music_system.after(level_music_system))
+            .add_system(stinger_system)
+            .add_system(sfx_system)
+            .add_system(mixer_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stinger {
+    ItemGet,
+    Checkpoint,
+    BossPhase,
+    Death,
+}
+impl Stinger {
+    fn path(&self) -> &'static str {
+        match self {
+            Stinger::ItemGet => "audio/stingers/item_get.wav",
+            Stinger::Checkpoint => "audio/stingers/checkpoint.wav",
+            Stinger::BossPhase => "audio/stingers/boss_phase.wav",
+            Stinger::Death => "audio/stingers/death.wav",
+        }
+    }
+    // seconds, matches the length of the asset
+    fn duration(&self) -> f32 {
+        match self {
+            Stinger::ItemGet => 0.48,
+            Stinger::Checkpoint => 0.4,
+            Stinger::BossPhase => 0.8,
+            Stinger::Death => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MusicEvent {
+    Play(String),
+    Stop,
+}
+
+#[derive(Debug)]
+pub struct StingerEvent(pub Stinger);
+
+#[derive(Debug)]
+pub struct SfxEvent {
+    pub path: &'static str,
+    // panned and attenuated relative to the AudioListener when given
+    pub emitter: Option<Entity>,
+    // playback rate, which shifts the pitch
+    pub pitch: f32,
+}
+impl SfxEvent {
+    pub fn new(path: &'static str) -> Self {
+        Self {
+            path,
+            emitter: None,
+            pitch: 1.0,
+        }
+    }
+    pub fn with_emitter(mut self, emitter: Entity) -> Self {
+        self.emitter = Some(emitter);
+        self
+    }
+    pub fn with_pitch(mut self, pitch: f32) -> Self {
+        self.pitch = pitch;
+        self
+    }
+}
+
+// where positional sfx are heard from, on the camera. sounds on screen play at full volume,
+// off screen ones fade out towards max_distance
+#[derive(Component)]
+pub struct AudioListener {
+    pub inner_distance: f32,
+    pub max_distance: f32,
+    pub pan_distance: f32,
+}
+impl Default for AudioListener {
+    fn default() -> Self {
+        Self {
+            inner_distance: SCREEN_HALF_SIZE.x,
+            max_distance: SFX_MAX_DISTANCE,
+            pan_distance: SFX_PAN_DISTANCE,
+        }
+    }
+}
+impl AudioListener {
+    // volume and panning (0.0 left, 0.5 center, 1.0 right) for an offset from the listener
+    fn positional(&self, offset: Vec2) -> (f32, f32) {
+        let fade = (self.max_distance - self.inner_distance).max(1.0);
+        let volume = (1.0 - (offset.length() - self.inner_distance) / fade).clamp(0.0, 1.0);
+        let panning = 0.5 + 0.5 * (offset.x / self.pan_distance).clamp(-1.0, 1.0);
+        (volume, panning)
+    }
+}
+
+pub struct Mixer {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    // music volume ratio while a stinger is playing
+    pub duck_volume: f32,
+    music_channel: AudioChannel,
+    stinger_channel: AudioChannel,
+    sfx_channels: Vec<AudioChannel>,
+    next_sfx_channel: usize,
+    duck_timer: Timer,
+    duck_gain: f32,
+    applied_volumes: Option<(f32, f32)>,
+}
+impl Default for Mixer {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            music_volume: 0.6,
+            sfx_volume: 1.0,
+            duck_volume: 0.3,
+            music_channel: AudioChannel::new("music".to_string()),
+            stinger_channel: AudioChannel::new("stinger".to_string()),
+            sfx_channels: (0..SFX_CHANNELS)
+                .map(|index| AudioChannel::new(format!("sfx_{}", index)))
+                .collect(),
+            next_sfx_channel: 0,
+            duck_timer: Timer::from_seconds(0.0, false),
+            duck_gain: 1.0,
+            applied_volumes: None,
+        }
+    }
+}
+
+// keeps the track playing across levels with the same music
+fn level_music_system(
+    mut events: EventReader<LdtkEvent>,
+    world_map: Res<WorldMap>,
+    mut music_events: EventWriter<MusicEvent>,
+    mut playing: Local<Option<String>>,
+) {
+    for event in events.iter() {
+        if let LdtkEvent::LevelActivated(_) = event {
+            let music = world_map
+                .active_level()
+                .and_then(|level| level.music.clone());
+            if music == *playing {
+                continue;
+            }
+            music_events.send(match &music {
+                Some(path) => MusicEvent::Play(path.clone()),
+                None => MusicEvent::Stop,
+            });
+            *playing = music;
+        }
+    }
+}
+
+fn music_system(
+    mut events: EventReader<MusicEvent>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    mixer: Res<Mixer>,
+) {
+    for event in events.iter() {
+        audio.stop_channel(&mixer.music_channel);
+        if let MusicEvent::Play(path) = event {
+            let source: Handle<AudioSource> = asset_server.load(path.as_str());
+            audio.play_looped_in_channel(source, &mixer.music_channel);
+        }
+    }
+}
+
+fn stinger_system(
+    mut events: EventReader<StingerEvent>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    mut mixer: ResMut<Mixer>,
+) {
+    for StingerEvent(stinger) in events.iter() {
+        let source: Handle<AudioSource> = asset_server.load(stinger.path());
+        audio.play_in_channel(source, &mixer.stinger_channel);
+        mixer.duck_timer = Timer::from_seconds(stinger.duration(), false);
+    }
+}
+
+fn sfx_system(
+    mut events: EventReader<SfxEvent>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    mut mixer: ResMut<Mixer>,
+    listeners: Query<(&GlobalTransform, &AudioListener)>,
+    emitters: Query<&GlobalTransform>,
+) {
+    let listener = listeners.get_single().ok();
+    for event in events.iter() {
+        let (volume, panning) = event
+            .emitter
+            .and_then(|emitter| emitters.get(emitter).ok())
+            .zip(listener)
+            .map(|(emitter, (transform, listener))| {
+                listener.positional((emitter.translation - transform.translation).truncate())
+            })
+            .unwrap_or((1.0, 0.5));
+        if volume <= 0.0 {
+            continue;
+        }
+
+        // round robin over the sfx channels, so each sound gets its own panning
+        let index = mixer.next_sfx_channel;
+        mixer.next_sfx_channel = (index + 1) % SFX_CHANNELS;
+        let channel = &mixer.sfx_channels[index];
+        let source: Handle<AudioSource> = asset_server.load(event.path);
+        audio.stop_channel(channel);
+        audio.set_volume_in_channel(mixer.master_volume * mixer.sfx_volume * volume, channel);
+        audio.set_panning_in_channel(panning, channel);
+        audio.set_playback_rate_in_channel(event.pitch, channel);
+        audio.play_in_channel(source, channel);
+    }
+}
+
+fn mixer_system(time: Res<Time>, audio: Res<Audio>, mut mixer: ResMut<Mixer>) {
+    mixer.duck_timer.tick(time.delta());
+
+    // ease the music in and out of the ducked volume
+    let target = if mixer.duck_timer.finished() {
+        1.0
+    } else {
+        mixer.duck_volume
+    };
+    let ratio = (time.delta_seconds() * 8.0).min(1.0);
+    mixer.duck_gain += (target - mixer.duck_gain) * ratio;
+
+    let volumes = (
+        mixer.master_volume * mixer.music_volume * mixer.duck_gain,
+        mixer.master_volume * mixer.sfx_volume,
+    );
+    if mixer.applied_volumes != Some(volumes) {
+        audio.set_volume_in_channel(volumes.0, &mixer.music_channel);
+        audio.set_volume_in_channel(volumes.1, &mixer.stinger_channel);
+        mixer.applied_volumes = Some(volumes);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::VirtualPosition;
+use bevy::{prelude::*, transform::TransformSystem};
+
+// assets/fonts/pixel.png, 3x5 glyphs in 4x6 cells from ' ' to '~', 16 per row
+const GLYPH_SIZE: Vec2 = Vec2::new(4.0, 6.0);
+const COLUMNS: usize = 16;
+const ROWS: usize = 6;
+const FIRST_GLYPH: char = ' ';
+const LAST_GLYPH: char = '~';
+// half the view in pixels, for placing hud text in the corners
+pub const SCREEN_HALF_SIZE: Vec2 = Vec2::new(160.0, 120.0);
+const Z_HUD: f32 = 100.0;
+
+// crisp pixel text from per character sprites, for the hud and anything in the world
+pub struct BitmapFontPlugin;
+impl Plugin for BitmapFontPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_system)
+            .add_system(layout_system)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                screen_position_system.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+pub struct BitmapFont {
+    atlas: Handle<TextureAtlas>,
+}
+
+#[derive(Component)]
+pub struct BitmapText {
+    pub text: String,
+    pub color: Color,
+    // point of the text block at the origin, from (-0.5, -0.5) bottom left to (0.5, 0.5)
+    pub anchor: Vec2,
+    // text of the current glyph sprites
+    laid_out: Option<String>,
+}
+impl BitmapText {
+    pub fn new(text: impl Into<String>, color: Color) -> Self {
+        Self {
+            text: text.into(),
+            color,
+            anchor: Vec2::ZERO,
+            laid_out: None,
+        }
+    }
+
+    pub fn with_anchor(mut self, anchor: Vec2) -> Self {
+        self.anchor = anchor;
+        self
+    }
+}
+
+#[derive(Bundle)]
+pub struct BitmapTextBundle {
+    pub text: BitmapText,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+}
+impl BitmapTextBundle {
+    pub fn new(text: BitmapText, transform: Transform) -> Self {
+        Self {
+            text,
+            transform,
+            global_transform: GlobalTransform::identity(),
+            visibility: Visibility::default(),
+        }
+    }
+}
+
+// keeps the entity at a fixed offset from the camera center in screen pixels, through its
+// zoom and rotation
+#[derive(Component)]
+pub struct ScreenPosition(pub Vec2);
+
+#[derive(Component)]
+struct Glyph;
+
+fn setup_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let texture = asset_server.load("fonts/pixel.png");
+    let atlas = TextureAtlas::from_grid(texture, GLYPH_SIZE, COLUMNS, ROWS);
+    commands.insert_resource(BitmapFont {
+        atlas: texture_atlases.add(atlas),
+    });
+}
+
+// of a laid out text block in pixels, e.g. for a panel behind it
+pub fn text_size(text: &str) -> Vec2 {
+    let columns = text
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    Vec2::new(columns as f32, text.lines().count() as f32) * GLYPH_SIZE
+}
+
+fn glyph_index(char: char) -> usize {
+    let char = if (FIRST_GLYPH..=LAST_GLYPH).contains(&char) {
+        char
+    } else {
+        '?'
+    };
+    char as usize - FIRST_GLYPH as usize
+}
+
+fn layout_system(
+    mut commands: Commands,
+    font: Res<BitmapFont>,
+    mut texts: Query<
+        (Entity, &mut BitmapText, &Visibility, Option<&Children>),
+        Or<(Changed<BitmapText>, Changed<Visibility>)>,
+    >,
+    mut glyphs: Query<
+        (&mut TextureAtlasSprite, &mut Visibility),
+        (With<Glyph>, Without<BitmapText>),
+    >,
+) {
+    for (entity, mut text, visibility, children) in texts.iter_mut() {
+        let glyph_children = children
+            .map(|children| {
+                children
+                    .iter()
+                    .copied()
+                    .filter(|child| glyphs.get(*child).is_ok())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        // only color or visibility changed, e.g. fading out
+        if text.laid_out.as_ref() == Some(&text.text) {
+            for child in glyph_children {
+                if let Ok((mut sprite, mut glyph_visibility)) = glyphs.get_mut(child) {
+                    sprite.color = text.color;
+                    glyph_visibility.is_visible = visibility.is_visible;
+                }
+            }
+            continue;
+        }
+
+        for child in glyph_children {
+            commands.entity(child).despawn_recursive();
+        }
+        let lines = text.text.lines().collect::<Vec<_>>();
+        let height = lines.len() as f32 * GLYPH_SIZE.y;
+        let top = height * (0.5 - text.anchor.y);
+        let color = text.color;
+        let anchor_x = text.anchor.x;
+        commands.entity(entity).with_children(|parent| {
+            for (row, line) in lines.iter().enumerate() {
+                let width = line.chars().count() as f32 * GLYPH_SIZE.x;
+                let left = -width * (anchor_x + 0.5);
+                for (column, char) in line.chars().enumerate() {
+                    if char == ' ' {
+                        continue;
+                    }
+                    let position = Vec2::new(
+                        left + (column as f32 + 0.5) * GLYPH_SIZE.x,
+                        top - (row as f32 + 0.5) * GLYPH_SIZE.y,
+                    );
+                    parent
+                        .spawn_bundle(SpriteSheetBundle {
+                            sprite: TextureAtlasSprite {
+                                index: glyph_index(char),
+                                color,
+                                ..Default::default()
+                            },
+                            texture_atlas: font.atlas.clone(),
+                            transform: Transform::from_translation(position.extend(0.0)),
+                            visibility: Visibility {
+                                is_visible: visibility.is_visible,
+                            },
+                            ..Default::default()
+                        })
+                        .insert(Glyph);
+                }
+            }
+        });
+        text.laid_out = Some(text.text.clone());
+    }
+}
+
+fn screen_position_system(
+    cameras: Query<&Transform, (With<Camera>, With<VirtualPosition>)>,
+    mut query: Query<(&ScreenPosition, &mut Transform), Without<Camera>>,
+) {
+    let camera_transform = match cameras.get_single() {
+        Ok(camera_transform) => camera_transform,
+        Err(_) => return,
+    };
+    for (ScreenPosition(offset), mut transform) in query.iter_mut() {
+        let offset =
+            camera_transform.rotation * (*offset * camera_transform.scale.truncate()).extend(0.0);
+        transform.translation =
+            (camera_transform.translation.truncate() + offset.truncate()).extend(Z_HUD);
+        transform.rotation = camera_transform.rotation;
+        transform.scale = camera_transform.scale;
+    }
+}
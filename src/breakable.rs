@@ -0,0 +1,71 @@
+use crate::{ldtk::runtime::LdtkLevelRuntime, respawn::PlayerDeathEvent};
+use bevy::prelude::*;
+
+// tiles with "breakable" custom data are knocked out where shots land, and come back when the
+// player respawns so a level can't be locked for good
+pub struct BreakablePlugin;
+impl Plugin for BreakablePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BrokenTiles>()
+            .add_event::<BreakTilesEvent>()
+            .add_system(break_system)
+            .add_system(restore_system);
+    }
+}
+
+// a shot or blast landed at this world position, in pixels
+pub struct BreakTilesEvent(pub Vec2);
+
+// level root, layer, cell, tile id and flip bits of the tiles knocked out since the last respawn
+#[derive(Default)]
+struct BrokenTiles(Vec<(Entity, String, IVec2, i64, i64)>);
+
+fn break_system(
+    mut commands: Commands,
+    mut events: EventReader<BreakTilesEvent>,
+    mut broken: ResMut<BrokenTiles>,
+    mut levels: Query<(Entity, &mut LdtkLevelRuntime)>,
+) {
+    for BreakTilesEvent(position) in events.iter() {
+        for (root, mut level) in levels.iter_mut() {
+            let cells = level
+                .layers()
+                .filter_map(|layer| {
+                    let grid_pos = level.grid_pos(layer, *position)?;
+                    let tile_id = level.tile(layer, grid_pos)?;
+                    let flip = level.tile_flip(layer, grid_pos)?;
+                    level
+                        .breakable(layer, grid_pos)
+                        .then(|| (layer.to_string(), grid_pos, tile_id, flip))
+                })
+                .collect::<Vec<_>>();
+            for (layer, grid_pos, tile_id, flip) in cells {
+                match level.remove_tile(&mut commands, &layer, grid_pos) {
+                    Ok(()) => broken.0.push((root, layer, grid_pos, tile_id, flip)),
+                    Err(err) => error!("{:?}", err),
+                }
+            }
+        }
+    }
+}
+
+fn restore_system(
+    mut commands: Commands,
+    mut events: EventReader<PlayerDeathEvent>,
+    mut broken: ResMut<BrokenTiles>,
+    mut levels: Query<&mut LdtkLevelRuntime>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    for (root, layer, grid_pos, tile_id, flip) in broken.0.drain(..) {
+        // gone with its level
+        let mut level = match levels.get_mut(root) {
+            Ok(level) => level,
+            Err(_) => continue,
+        };
+        if let Err(err) = level.set_tile(&mut commands, &layer, grid_pos, tile_id, flip) {
+            error!("{:?}", err);
+        }
+    }
+}
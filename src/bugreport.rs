@@ -0,0 +1,126 @@
+use crate::{ldtk::plugin::LdtkEvent, rng::GameRng, save::SaveData, Enemy, Player};
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// F10 writes bug-reports/<unix time>/ with the game state, the save, the rng and the input
+// since level start, for testers to attach instead of describing what they did
+pub struct BugReportPlugin;
+impl Plugin for BugReportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputLog>()
+            .add_system(input_log_system)
+            .add_system(bug_report_system);
+    }
+}
+
+// key presses and releases per frame, cleared on level activation
+#[derive(Default)]
+struct InputLog {
+    level: Option<String>,
+    frame: usize,
+    // "<frame> <+|-><key>"
+    lines: Vec<String>,
+}
+
+fn input_log_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut events: EventReader<LdtkEvent>,
+    mut input_log: ResMut<InputLog>,
+) {
+    for event in events.iter() {
+        if let LdtkEvent::LevelActivated(identifier) = event {
+            *input_log = InputLog {
+                level: Some(identifier.clone()),
+                ..Default::default()
+            };
+        }
+    }
+    let frame = input_log.frame;
+    input_log.frame += 1;
+    for key_code in keyboard_input.get_just_pressed() {
+        input_log.lines.push(format!("{} +{:?}", frame, key_code));
+    }
+    for key_code in keyboard_input.get_just_released() {
+        input_log.lines.push(format!("{} -{:?}", frame, key_code));
+    }
+}
+
+fn bug_report_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    input_log: Res<InputLog>,
+    rng: Res<GameRng>,
+    save: Res<SaveData>,
+    rapier_config: Res<RapierConfiguration>,
+    bodies: Query<(
+        Entity,
+        &RigidBodyPositionComponent,
+        &RigidBodyVelocityComponent,
+        Option<&Player>,
+        Option<&Enemy>,
+    )>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F10) {
+        return;
+    }
+    let state = bodies
+        .iter()
+        .filter_map(|(entity, rb_position, rb_velocity, player, enemy)| {
+            let name = match (player, enemy) {
+                (Some(_), _) => "player".to_string(),
+                (_, Some(enemy)) => format!("enemy {}", enemy.name),
+                _ => return None,
+            };
+            let translation = rb_position.position.translation.vector * rapier_config.scale;
+            let linvel = rb_velocity.linvel * rapier_config.scale;
+            Some(format!(
+                "{:?} {}: position {:?} {:?}, velocity {:?} {:?}",
+                entity, name, translation.x, translation.y, linvel.x, linvel.y
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    match write_report(&input_log, state, &rng, &save) {
+        Ok(path) => info!("bug report: written to {:?}", path),
+        Err(err) => error!("bug report: {:?}", err),
+    }
+}
+
+fn write_report(
+    input_log: &InputLog,
+    state: String,
+    rng: &GameRng,
+    save: &SaveData,
+) -> Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let path = PathBuf::from("bug-reports").join(timestamp.to_string());
+    fs::create_dir_all(&path).with_context(|| format!("failed to create {:?}", path))?;
+
+    // state.txt holds the bodies a savestate would, rng.json the generator as it is now, so
+    // a replay can pick up from here. there is no screenshot, bevy can't read the frame back
+    let summary = format!(
+        "level: {}\nframes since level start: {}\nrng seed: {}\n",
+        input_log.level.as_deref().unwrap_or("none"),
+        input_log.frame,
+        rng.seed
+    );
+    for (name, content) in [
+        ("report.txt", summary),
+        ("state.txt", state),
+        ("input.txt", input_log.lines.join("\n")),
+        ("rng.json", serde_json::to_string(rng)?),
+        ("save.json", serde_json::to_string_pretty(save)?),
+    ] {
+        let file = path.join(name);
+        fs::write(&file, content).with_context(|| format!("failed to write {:?}", file))?;
+    }
+    Ok(path)
+}
@@ -0,0 +1,209 @@
+use crate::{bitmap_font::SCREEN_HALF_SIZE, ldtk::world::WorldMap, Player};
+use bevy::{ecs::system::SystemParam, prelude::*, sprite::Rect as SpriteRect};
+
+// weight per second when entering or leaving a zone, or starting and stopping framing
+const BLEND_SPEED: f32 = 3.0;
+// pixels kept between framed targets and the screen edges
+const FRAMING_MARGIN: f32 = 24.0;
+// furthest framing zooms out
+const MIN_FRAMING_ZOOM: f32 = 0.5;
+// how fast the framing zoom follows the targets spreading out, per second
+const FRAMING_ZOOM_SPEED: f32 = 4.0;
+
+pub struct CameraZonePlugin;
+impl Plugin for CameraZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraZoneState>()
+            .init_resource::<CameraFraming>()
+            .add_system(camera_zone_system)
+            .add_system(camera_framing_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraZoneMode {
+    // keep following, only the zoom changes
+    Follow,
+    // x stays at the zone center, e.g. vertical shafts
+    LockX,
+    LockY,
+    // boss rooms
+    Fixed,
+}
+impl CameraZoneMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Follow" => Some(Self::Follow),
+            "LockX" => Some(Self::LockX),
+            "LockY" => Some(Self::LockY),
+            "Fixed" => Some(Self::Fixed),
+            _ => None,
+        }
+    }
+}
+
+// overrides the camera while the player is inside the rect, world space in pixels
+#[derive(Component)]
+pub struct CameraZone {
+    pub rect: SpriteRect,
+    pub mode: CameraZoneMode,
+    pub zoom: f32,
+}
+impl CameraZone {
+    fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.rect.min.x
+            && point.x <= self.rect.max.x
+            && point.y >= self.rect.min.y
+            && point.y <= self.rect.max.y
+    }
+
+    fn target(&self, follow: Vec2) -> Vec2 {
+        let center = (self.rect.min + self.rect.max) * 0.5;
+        match self.mode {
+            CameraZoneMode::Follow => follow,
+            CameraZoneMode::LockX => Vec2::new(center.x, follow.y),
+            CameraZoneMode::LockY => Vec2::new(follow.x, center.y),
+            CameraZoneMode::Fixed => center,
+        }
+    }
+}
+
+// framed together with the player while any are around, e.g. a second player or a boss.
+// heavier targets pull the camera closer to them
+#[derive(Component)]
+pub struct CameraTarget {
+    pub weight: f32,
+}
+
+// the zone being blended in or out, only one at a time
+#[derive(Default)]
+pub struct CameraZoneState {
+    zone: Option<Entity>,
+    weight: f32,
+}
+
+// centroid of the player and the camera targets, and the zoom that keeps all of them on
+// screen. blended in while there are targets, on top of whatever the zones do
+pub struct CameraFraming {
+    center: Vec2,
+    zoom: f32,
+    weight: f32,
+}
+impl Default for CameraFraming {
+    fn default() -> Self {
+        Self {
+            center: Vec2::ZERO,
+            zoom: 1.0,
+            weight: 0.0,
+        }
+    }
+}
+
+#[derive(SystemParam)]
+pub struct CameraZones<'w, 's> {
+    state: Res<'w, CameraZoneState>,
+    framing: Res<'w, CameraFraming>,
+    zones: Query<'w, 's, &'static CameraZone>,
+}
+impl<'w, 's> CameraZones<'w, 's> {
+    // camera center and zoom given where the camera would follow to. zooming scales the
+    // camera's transform, so the overlays parented to it keep covering the view
+    pub fn apply(&self, follow: Vec2) -> (Vec2, f32) {
+        let (translation, zoom) = match self.state.zone.and_then(|zone| self.zones.get(zone).ok()) {
+            Some(zone) => {
+                let weight = self.state.weight;
+                (
+                    follow.lerp(zone.target(follow), weight),
+                    1.0 + (zone.zoom - 1.0) * weight,
+                )
+            }
+            None => (follow, 1.0),
+        };
+        let framing = &self.framing;
+        (
+            translation.lerp(framing.center, framing.weight),
+            zoom + (zoom.min(framing.zoom) - zoom) * framing.weight,
+        )
+    }
+}
+
+pub fn spawn_camera_zone(
+    commands: &mut Commands,
+    rect: SpriteRect,
+    mode: CameraZoneMode,
+    zoom: f32,
+) -> Entity {
+    commands
+        .spawn()
+        .insert(CameraZone { rect, mode, zoom })
+        .id()
+}
+
+fn camera_zone_system(
+    time: Res<Time>,
+    mut state: ResMut<CameraZoneState>,
+    players: Query<&Transform, With<Player>>,
+    zones: Query<(Entity, &CameraZone)>,
+) {
+    let inside = players.get_single().ok().and_then(|player_transform| {
+        let position = player_transform.translation.truncate();
+        zones
+            .iter()
+            .find(|(_, zone)| zone.contains(position))
+            .map(|(entity, _)| entity)
+    });
+
+    // blend the current zone out before switching to the next one
+    let step = BLEND_SPEED * time.delta_seconds();
+    if inside == state.zone {
+        state.weight = (state.weight + step).min(1.0);
+    } else if state.weight > 0.0 && state.zone.map_or(false, |zone| zones.get(zone).is_ok()) {
+        state.weight = (state.weight - step).max(0.0);
+    } else {
+        state.zone = inside;
+        state.weight = 0.0;
+    }
+}
+
+fn camera_framing_system(
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    mut framing: ResMut<CameraFraming>,
+    players: Query<&GlobalTransform, With<Player>>,
+    targets: Query<(&GlobalTransform, &CameraTarget), Without<Player>>,
+) {
+    let step = BLEND_SPEED * time.delta_seconds();
+    let points = players
+        .iter()
+        .map(|global_transform| (global_transform.translation.truncate(), 1.0))
+        .chain(targets.iter().map(|(global_transform, target)| {
+            (global_transform.translation.truncate(), target.weight)
+        }))
+        .filter(|(_, weight)| *weight > 0.0)
+        .collect::<Vec<_>>();
+    let total = points.iter().map(|(_, weight)| weight).sum::<f32>();
+    if targets.is_empty() || points.len() < 2 || total <= 0.0 {
+        framing.weight = (framing.weight - step).max(0.0);
+        return;
+    }
+
+    let center = points.iter().fold(Vec2::ZERO, |sum, (position, weight)| {
+        sum + *position * *weight
+    }) / total;
+    // measured along the screen's axes, which turn with the level's gravity
+    let to_view = world_map.gravity().rotation().transpose();
+    let extent = points.iter().fold(Vec2::ONE, |extent, (position, _)| {
+        extent.max((to_view * (*position - center)).abs())
+    });
+    let room = SCREEN_HALF_SIZE - Vec2::splat(FRAMING_MARGIN);
+    let zoom = (room / extent).min_element().clamp(MIN_FRAMING_ZOOM, 1.0);
+
+    if framing.weight == 0.0 {
+        framing.zoom = zoom;
+    } else {
+        let ratio = (FRAMING_ZOOM_SPEED * time.delta_seconds()).min(1.0);
+        framing.zoom += (zoom - framing.zoom) * ratio;
+    }
+    framing.center = center;
+    framing.weight = (framing.weight + step).min(1.0);
+}
@@ -0,0 +1,451 @@
+use crate::{
+    damage::DamageType,
+    debug::{DebugGroup, DebugGroups},
+    despawn::MarkedForDespawn,
+    equipment::EquipEvent,
+    ldtk::{registry::LdtkEntityRegistry, world::WorldMap},
+    score::PickupEvent,
+    Enemy, Player,
+};
+use bevy::{prelude::*, window::ReceivedCharacter};
+use bevy_rapier2d::prelude::*;
+
+pub struct CheatPlugin;
+impl Plugin for CheatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Cheats>()
+            .init_resource::<CheatBindings>()
+            .init_resource::<CheatConsole>()
+            .add_event::<CheatEvent>()
+            .add_event::<WarpEvent>()
+            .add_startup_system(setup_system)
+            .add_system(input_system)
+            .add_system(console_system)
+            .add_system(cheat_event_system)
+            .add_system(panel_system)
+            .add_system(noclip_system)
+            .add_system(stamina_system)
+            .add_system(abilities_system)
+            .add_system(warp_system);
+    }
+}
+
+#[derive(Debug)]
+pub struct Cheats {
+    // the player takes no damage
+    pub god_mode: bool,
+    // dashes without waiting for the cooldown
+    pub infinite_stamina: bool,
+    pub noclip: bool,
+    // pixels per second, adjusted with -/= while flying
+    pub noclip_speed: f32,
+    // fire and ice attacks outside of the sandbox, see CheatBindings::cycle_attack_type
+    pub all_abilities: bool,
+}
+impl Default for Cheats {
+    fn default() -> Self {
+        Self {
+            god_mode: false,
+            infinite_stamina: false,
+            noclip: false,
+            noclip_speed: 160.0,
+            all_abilities: false,
+        }
+    }
+}
+
+// physics state of the player before noclip, restored on exit
+#[derive(Component)]
+struct Noclip {
+    body_type: RigidBodyType,
+    collider_type: ColliderType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatEvent {
+    ToggleGodMode,
+    ToggleInfiniteStamina,
+    ToggleNoclip,
+    ToggleAllAbilities,
+    GiveCoins(u32),
+    KillAllEnemies,
+}
+impl CheatEvent {
+    fn label(&self) -> String {
+        match self {
+            CheatEvent::ToggleGodMode => "god mode".to_string(),
+            CheatEvent::ToggleInfiniteStamina => "infinite stamina".to_string(),
+            CheatEvent::ToggleNoclip => "noclip".to_string(),
+            CheatEvent::ToggleAllAbilities => "all abilities".to_string(),
+            CheatEvent::GiveCoins(coins) => format!("give {} coins", coins),
+            CheatEvent::KillAllEnemies => "kill all enemies".to_string(),
+        }
+    }
+    fn state(&self, cheats: &Cheats) -> Option<bool> {
+        match self {
+            CheatEvent::ToggleGodMode => Some(cheats.god_mode),
+            CheatEvent::ToggleInfiniteStamina => Some(cheats.infinite_stamina),
+            CheatEvent::ToggleNoclip => Some(cheats.noclip),
+            CheatEvent::ToggleAllAbilities => Some(cheats.all_abilities),
+            _ => None,
+        }
+    }
+}
+
+pub struct CheatBindings {
+    pub toggle_panel: KeyCode,
+    // with all abilities on
+    pub cycle_attack_type: KeyCode,
+    pub cheats: Vec<(KeyCode, CheatEvent)>,
+}
+impl Default for CheatBindings {
+    fn default() -> Self {
+        Self {
+            toggle_panel: KeyCode::F1,
+            cycle_attack_type: KeyCode::Tab,
+            cheats: vec![
+                (KeyCode::F2, CheatEvent::ToggleGodMode),
+                (KeyCode::F3, CheatEvent::ToggleInfiniteStamina),
+                (KeyCode::F4, CheatEvent::ToggleNoclip),
+                (KeyCode::F6, CheatEvent::ToggleAllAbilities),
+                (KeyCode::F7, CheatEvent::GiveCoins(100)),
+                (KeyCode::F8, CheatEvent::KillAllEnemies),
+            ],
+        }
+    }
+}
+
+#[derive(Component)]
+struct CheatPanel;
+
+// one line command prompt, e.g. "warp boss", "debug terrain" or a cheat label like "noclip"
+#[derive(Default)]
+struct CheatConsole {
+    open: bool,
+    input: String,
+}
+
+#[derive(Component)]
+struct CheatConsoleText;
+
+// teleport the player to an ldtk entity, by iid, identifier or name field
+#[derive(Debug)]
+pub struct WarpEvent(pub String);
+
+fn setup_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(2.0),
+                    bottom: Val::Px(2.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/hack.ttf"),
+                    font_size: 8.0,
+                    color: Color::YELLOW,
+                },
+                Default::default(),
+            ),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(CheatPanel);
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(2.0),
+                    top: Val::Px(2.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/hack.ttf"),
+                    font_size: 8.0,
+                    color: Color::YELLOW,
+                },
+                Default::default(),
+            ),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(CheatConsoleText);
+}
+
+fn input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<CheatBindings>,
+    mut event_writer: EventWriter<CheatEvent>,
+) {
+    for (key_code, event) in bindings.cheats.iter() {
+        if keyboard_input.just_pressed(*key_code) {
+            event_writer.send(*event);
+        }
+    }
+}
+
+fn console_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut console: ResMut<CheatConsole>,
+    bindings: Res<CheatBindings>,
+    mut cheat_events: EventWriter<CheatEvent>,
+    mut warp_events: EventWriter<WarpEvent>,
+    mut equip_events: EventWriter<EquipEvent>,
+    mut debug_groups: ResMut<DebugGroups>,
+    mut query: Query<(&mut Text, &mut Visibility), With<CheatConsoleText>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Grave) {
+        console.open = !console.open;
+        console.input.clear();
+    }
+    // always drain, so characters typed while closed don't show up later
+    let characters = characters
+        .iter()
+        .map(|event| event.char)
+        .filter(|char| !char.is_control() && *char != '`')
+        .collect::<String>();
+    if console.open {
+        if !characters.is_empty() {
+            console.input.push_str(&characters);
+        }
+        if keyboard_input.just_pressed(KeyCode::Back) {
+            console.input.pop();
+        }
+        if keyboard_input.just_pressed(KeyCode::Return) {
+            let input = std::mem::take(&mut console.input);
+            let mut words = input.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("warp"), Some(target)) => warp_events.send(WarpEvent(target.to_string())),
+                (Some("equip"), Some(weapon)) => {
+                    equip_events.send(EquipEvent(Some(weapon.to_string())))
+                }
+                (Some("unequip"), None) => equip_events.send(EquipEvent(None)),
+                (Some("debug"), Some("all")) => debug_groups.set_all(true),
+                (Some("debug"), Some("none")) => debug_groups.set_all(false),
+                (Some("debug"), Some(name)) => match DebugGroup::from_name(name) {
+                    Some(group) => debug_groups.toggle(group),
+                    None => warn!("unknown debug group: {}", name),
+                },
+                (Some(_), _) => {
+                    match bindings
+                        .cheats
+                        .iter()
+                        .find(|(_, event)| event.label() == input.trim())
+                    {
+                        Some((_, event)) => cheat_events.send(*event),
+                        None => warn!("unknown command: {}", input.trim()),
+                    }
+                }
+                (None, _) => {}
+            }
+        }
+    }
+    if console.is_changed() {
+        for (mut text, mut visibility) in query.iter_mut() {
+            visibility.is_visible = console.open;
+            text.sections[0].value = format!("> {}", console.input);
+        }
+    }
+}
+
+fn cheat_event_system(
+    mut commands: Commands,
+    mut cheats: ResMut<Cheats>,
+    mut events: EventReader<CheatEvent>,
+    mut pickup_events: EventWriter<PickupEvent>,
+    enemies: Query<Entity, (With<Enemy>, Without<MarkedForDespawn>)>,
+) {
+    for event in events.iter() {
+        info!("cheat: {}", event.label());
+        match event {
+            CheatEvent::ToggleGodMode => cheats.god_mode = !cheats.god_mode,
+            CheatEvent::ToggleInfiniteStamina => cheats.infinite_stamina = !cheats.infinite_stamina,
+            CheatEvent::ToggleNoclip => cheats.noclip = !cheats.noclip,
+            CheatEvent::ToggleAllAbilities => cheats.all_abilities = !cheats.all_abilities,
+            // as if picked up, a point per coin
+            CheatEvent::GiveCoins(coins) => pickup_events.send(PickupEvent { points: *coins }),
+            CheatEvent::KillAllEnemies => {
+                for entity in enemies.iter() {
+                    commands.entity(entity).insert(MarkedForDespawn);
+                }
+            }
+        }
+    }
+}
+
+fn panel_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<CheatBindings>,
+    cheats: Res<Cheats>,
+    mut query: Query<(&mut Text, &mut Visibility), With<CheatPanel>>,
+) {
+    for (mut text, mut visibility) in query.iter_mut() {
+        let toggled = keyboard_input.just_pressed(bindings.toggle_panel);
+        if toggled {
+            visibility.is_visible = !visibility.is_visible;
+        }
+        if !visibility.is_visible || !(toggled || cheats.is_changed() || bindings.is_changed()) {
+            continue;
+        }
+        text.sections[0].value = bindings
+            .cheats
+            .iter()
+            .map(|(key_code, event)| match event.state(&cheats) {
+                Some(state) => format!(
+                    "{:?} {} [{}]",
+                    key_code,
+                    event.label(),
+                    if state { "on" } else { "off" }
+                ),
+                None => format!("{:?} {}", key_code, event.label()),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
+
+fn noclip_system(
+    mut commands: Commands,
+    mut cheats: ResMut<Cheats>,
+    keyboard_input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    rapier_config: Res<RapierConfiguration>,
+    mut players: Query<
+        (
+            Entity,
+            &mut RigidBodyTypeComponent,
+            &mut RigidBodyPositionComponent,
+            &mut RigidBodyVelocityComponent,
+            &mut ColliderTypeComponent,
+            Option<&Noclip>,
+        ),
+        With<Player>,
+    >,
+) {
+    let (entity, mut rb_type, mut rb_position, mut rb_velocity, mut collider_type, noclip) =
+        match players.get_single_mut() {
+            Ok(player) => player,
+            Err(_) => return,
+        };
+    match (cheats.noclip, noclip) {
+        // enter, the player becomes a kinematic ghost
+        (true, None) => {
+            commands.entity(entity).insert(Noclip {
+                body_type: rb_type.0,
+                collider_type: collider_type.0,
+            });
+            rb_type.0 = RigidBodyType::KinematicPositionBased;
+            collider_type.0 = ColliderType::Sensor;
+        }
+        // exit
+        (false, Some(noclip)) => {
+            rb_type.0 = noclip.body_type;
+            collider_type.0 = noclip.collider_type;
+            rb_velocity.linvel = Vec2::ZERO.into();
+            commands.entity(entity).remove::<Noclip>();
+        }
+        // fly
+        (true, Some(_)) => {
+            if keyboard_input.just_pressed(KeyCode::Equals) {
+                cheats.noclip_speed *= 1.5;
+            }
+            if keyboard_input.just_pressed(KeyCode::Minus) {
+                cheats.noclip_speed /= 1.5;
+            }
+            let pressed = |a: KeyCode, b: KeyCode| {
+                (keyboard_input.pressed(a) || keyboard_input.pressed(b)) as i8 as f32
+            };
+            let direction = Vec2::new(
+                pressed(KeyCode::D, KeyCode::Right) - pressed(KeyCode::A, KeyCode::Left),
+                pressed(KeyCode::W, KeyCode::Up) - pressed(KeyCode::S, KeyCode::Down),
+            );
+            let delta: Vector<Real> =
+                (direction.normalize_or_zero() * cheats.noclip_speed * time.delta_seconds()
+                    / rapier_config.scale)
+                    .into();
+            rb_position.next_position.translation.vector =
+                rb_position.position.translation.vector + delta;
+        }
+        (false, None) => {}
+    }
+}
+
+fn stamina_system(cheats: Res<Cheats>, mut players: Query<&mut Player>) {
+    if !cheats.infinite_stamina {
+        return;
+    }
+    for mut player in players.iter_mut() {
+        player.dash_cooldown = Timer::from_seconds(0.0, false);
+    }
+}
+
+// back to plain attacks when turned off
+fn abilities_system(
+    cheats: Res<Cheats>,
+    bindings: Res<CheatBindings>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut players: Query<&mut Player>,
+    mut enabled: Local<bool>,
+) {
+    let disabled = *enabled && !cheats.all_abilities;
+    *enabled = cheats.all_abilities;
+    for mut player in players.iter_mut() {
+        if disabled {
+            player.attack_type = DamageType::Physical;
+        } else if cheats.all_abilities && keyboard_input.just_pressed(bindings.cycle_attack_type) {
+            player.attack_type = player.attack_type.next();
+            info!("cheat: attack type {:?}", player.attack_type);
+        }
+    }
+}
+
+// cross level warps need no extra work, level activation follows the player
+fn warp_system(
+    mut events: EventReader<WarpEvent>,
+    registry: Res<LdtkEntityRegistry>,
+    world_map: Res<WorldMap>,
+    rapier_config: Res<RapierConfiguration>,
+    mut players: Query<
+        (
+            &mut RigidBodyPositionComponent,
+            &mut RigidBodyVelocityComponent,
+        ),
+        With<Player>,
+    >,
+) {
+    for WarpEvent(target) in events.iter() {
+        let record = match registry.find(target) {
+            Some(record) => record,
+            None => {
+                warn!("warp: no entity matches {}", target);
+                continue;
+            }
+        };
+        if let Ok((mut rb_position, mut rb_velocity)) = players.get_single_mut() {
+            let translation: Vector<Real> = (record.position / rapier_config.scale).into();
+            rb_position.position.translation.vector = translation;
+            rb_position.next_position.translation.vector = translation;
+            rb_velocity.linvel = Vec2::ZERO.into();
+            info!(
+                "warp: {} {} in {}",
+                record.identifier,
+                record.iid,
+                world_map
+                    .get(record.level_uid)
+                    .map_or("unknown level", |level| level.identifier.as_str())
+            );
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use bevy_rapier2d::prelude::*;
+
+// named interaction groups, every collider is a member of exactly one layer and filters on
+// the layers it interacts with. queries are members of all layers and filter too.
+pub struct CollisionLayers;
+impl CollisionLayers {
+    pub const PLAYER: u32 = 1 << 0;
+    pub const ENEMY: u32 = 1 << 1;
+    pub const TERRAIN: u32 = 1 << 2;
+    // damaging volumes, actors pass through them
+    pub const HAZARD: u32 = 1 << 3;
+    // trigger volumes like water
+    pub const SENSOR: u32 = 1 << 4;
+    pub const PROJECTILE: u32 = 1 << 5;
+    // actor parts, see actor.rs
+    pub const HURTBOX: u32 = 1 << 6;
+    pub const FOOT: u32 = 1 << 7;
+    // drops, they rest on terrain and are collected by distance
+    pub const PICKUP: u32 = 1 << 8;
+    // friendly actors, out of combat and only standing on terrain
+    pub const NPC: u32 = 1 << 9;
+    // everything that blocks movement
+    pub const SOLID: u32 = Self::PLAYER | Self::ENEMY | Self::TERRAIN;
+
+    // level geometry, doors and props
+    pub fn terrain() -> InteractionGroups {
+        InteractionGroups::new(Self::TERRAIN, u32::MAX)
+    }
+
+    // actor bodies, layer is PLAYER, ENEMY or NPC
+    pub fn actor(layer: u32) -> InteractionGroups {
+        if layer == Self::NPC {
+            return InteractionGroups::new(Self::NPC, Self::TERRAIN);
+        }
+        InteractionGroups::new(
+            layer,
+            Self::SOLID | Self::HAZARD | Self::SENSOR | Self::PROJECTILE,
+        )
+    }
+
+    // hurtboxes only overlap other hurtboxes, attacks find them by query
+    pub fn hurtbox() -> InteractionGroups {
+        InteractionGroups::new(Self::HURTBOX, Self::HURTBOX)
+    }
+
+    pub fn foot() -> InteractionGroups {
+        InteractionGroups::new(Self::FOOT, Self::SOLID)
+    }
+
+    pub fn hazard() -> InteractionGroups {
+        InteractionGroups::new(Self::HAZARD, Self::PLAYER | Self::ENEMY)
+    }
+
+    pub fn sensor() -> InteractionGroups {
+        InteractionGroups::new(Self::SENSOR, Self::PLAYER | Self::ENEMY | Self::PROJECTILE)
+    }
+
+    // pressure plates, also weighed down by crates and other props
+    pub fn plate() -> InteractionGroups {
+        InteractionGroups::new(Self::SENSOR, Self::PLAYER | Self::ENEMY | Self::TERRAIN)
+    }
+
+    // wind zones, pushing everything that moves
+    pub fn wind() -> InteractionGroups {
+        InteractionGroups::new(
+            Self::SENSOR,
+            Self::PLAYER | Self::ENEMY | Self::TERRAIN | Self::PROJECTILE | Self::PICKUP,
+        )
+    }
+
+    pub fn projectile() -> InteractionGroups {
+        InteractionGroups::new(Self::PROJECTILE, Self::SOLID | Self::SENSOR)
+    }
+
+    pub fn pickup() -> InteractionGroups {
+        InteractionGroups::new(Self::PICKUP, Self::TERRAIN)
+    }
+
+    // enemy shots, they only hit the player and terrain
+    pub fn hostile_projectile() -> InteractionGroups {
+        InteractionGroups::new(Self::PROJECTILE, Self::PLAYER | Self::TERRAIN)
+    }
+
+    // ray and shape casts against whatever blocks movement
+    pub fn solid_query() -> InteractionGroups {
+        InteractionGroups::new(u32::MAX, Self::SOLID)
+    }
+
+    // line of sight, only level geometry blocks it
+    pub fn sight_query() -> InteractionGroups {
+        InteractionGroups::new(u32::MAX, Self::TERRAIN)
+    }
+
+    // attack shapes, against hurtboxes and nothing else
+    pub fn hurtbox_query() -> InteractionGroups {
+        InteractionGroups::new(u32::MAX, Self::HURTBOX)
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{damage::Health, despawn::MarkedForDespawn, Actor, Enemy, Player};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_rapier2d::prelude::*;
+
+pub mod layers;
+pub use layers::CollisionLayers;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionKind {
+    Player,
+    Enemy,
+    // terrain, props and anything else without an actor root
+    Other,
+}
+
+// resolves rapier collider handles to the gameplay entities owning them, climbing from
+// collider children up to the actor root. actors marked for despawn are ignored.
+#[derive(SystemParam)]
+pub struct CollisionLookup<'w, 's> {
+    parents: Query<'w, 's, &'static Parent>,
+    actors: Query<
+        'w,
+        's,
+        (Option<&'static Player>, Option<&'static Enemy>),
+        (With<Actor>, Without<MarkedForDespawn>),
+    >,
+    breakables: Query<'w, 's, (), (With<Health>, Without<Actor>, Without<MarkedForDespawn>)>,
+}
+
+impl<'w, 's> CollisionLookup<'w, 's> {
+    // the nearest actor above the entity, or the top of its hierarchy
+    pub fn root(&self, entity: Entity) -> Entity {
+        let mut entity = entity;
+        while self.actors.get(entity).is_err() {
+            match self.parents.get(entity) {
+                Ok(parent) => entity = parent.0,
+                Err(_) => break,
+            }
+        }
+        entity
+    }
+
+    pub fn actor(&self, handle: ColliderHandle) -> Option<Entity> {
+        let root = self.root(handle.entity());
+        self.actors.get(root).ok().map(|_| root)
+    }
+
+    pub fn kind(&self, handle: ColliderHandle) -> CollisionKind {
+        match self
+            .actor(handle)
+            .and_then(|actor| self.actors.get(actor).ok())
+        {
+            Some((Some(_), _)) => CollisionKind::Player,
+            Some((_, Some(_))) => CollisionKind::Enemy,
+            _ => CollisionKind::Other,
+        }
+    }
+
+    pub fn player(&self, handle: ColliderHandle) -> Option<Entity> {
+        self.actor(handle)
+            .filter(|_| self.kind(handle) == CollisionKind::Player)
+    }
+
+    pub fn enemy(&self, handle: ColliderHandle) -> Option<Entity> {
+        self.actor(handle)
+            .filter(|_| self.kind(handle) == CollisionKind::Enemy)
+    }
+
+    // props with health, like crates
+    pub fn breakable(&self, handle: ColliderHandle) -> Option<Entity> {
+        let root = self.root(handle.entity());
+        self.breakables.get(root).ok().map(|_| root)
+    }
+}
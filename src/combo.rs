@@ -0,0 +1,57 @@
+use crate::{audio::SfxEvent, game_time::GameTime};
+use bevy::prelude::*;
+
+// seconds without a hit until the combo drops
+const COMBO_TIMEOUT: f32 = 1.5;
+// hit counts from which a lower impact is layered below the hit sound, with its pitch
+const IMPACT_LAYERS: [(u32, f32); 2] = [(4, 0.5), (8, 0.35)];
+// score multiplier gained per hit
+const MULTIPLIER_STEP: f32 = 0.25;
+const MAX_MULTIPLIER: f32 = 4.0;
+
+pub struct ComboPlugin;
+impl Plugin for ComboPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Combo>().add_system(combo_system);
+    }
+}
+
+#[derive(Default)]
+pub struct Combo {
+    pub count: u32,
+    timer: Timer,
+}
+impl Combo {
+    pub fn hit(&mut self) {
+        self.count += 1;
+        self.timer = Timer::from_seconds(COMBO_TIMEOUT, false);
+    }
+
+    // a semitone higher per hit, up to an octave
+    pub fn pitch(&self) -> f32 {
+        let semitones = self.count.saturating_sub(1).min(12);
+        2.0_f32.powf(semitones as f32 / 12.0)
+    }
+
+    // what points are worth while the combo lasts, back to 1 when it drops
+    pub fn multiplier(&self) -> f32 {
+        (1.0 + self.count as f32 * MULTIPLIER_STEP).min(MAX_MULTIPLIER)
+    }
+
+    // the hit sound for the current count, with its impact layers
+    pub fn hit_sfx(&self) -> impl Iterator<Item = SfxEvent> + '_ {
+        let hit = SfxEvent::new("audio/sfx/hit.wav").with_pitch(self.pitch());
+        let layers = IMPACT_LAYERS
+            .into_iter()
+            .filter(|(count, _)| self.count >= *count)
+            .map(|(_, pitch)| SfxEvent::new("audio/sfx/hit.wav").with_pitch(pitch));
+        std::iter::once(hit).chain(layers)
+    }
+}
+
+fn combo_system(time: Res<GameTime>, mut combo: ResMut<Combo>) {
+    combo.timer.tick(time.delta());
+    if combo.timer.finished() && combo.count > 0 {
+        combo.count = 0;
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{actor::Grounded, game_time::GameTime};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// animated tiles step through their frames at this rate
+const TILE_FPS: f32 = 8.0;
+
+// tiles with "conveyor" custom data carry actors standing on them, see TileCustomData
+pub struct ConveyorPlugin;
+impl Plugin for ConveyorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(conveyor_system)
+            .add_system(animated_tile_system);
+    }
+}
+
+// on the merged collider of conveyor tiles
+#[derive(Component)]
+pub struct Conveyor {
+    // pixels per second along the ground, positive is to the right of the tile
+    pub speed: f32,
+}
+
+// a tile that cycles through `frames` consecutive atlas indices from `first`
+#[derive(Component)]
+pub struct AnimatedTile {
+    pub first: usize,
+    pub frames: usize,
+}
+
+// moves bodies instead of touching their velocity, so it neither piles up on bodies that
+// keep theirs nor gets lost on ones whose movement sets it every frame
+fn conveyor_system(
+    time: Res<GameTime>,
+    rapier_config: Res<RapierConfiguration>,
+    conveyors: Query<&Conveyor>,
+    mut actors: Query<(&Grounded, &mut RigidBodyPositionComponent)>,
+) {
+    for (grounded, mut rb_position) in actors.iter_mut() {
+        let conveyor = match grounded
+            .ground
+            .and_then(|ground| conveyors.get(ground).ok())
+        {
+            Some(conveyor) => conveyor,
+            None => continue,
+        };
+        // along the surface, to its right when looking away from it
+        let tangent = Vec2::new(grounded.normal.y, -grounded.normal.x);
+        let offset: Vector<Real> =
+            (tangent * conveyor.speed * time.delta_seconds() / rapier_config.scale).into();
+        rb_position.position.translation.vector += offset;
+        rb_position.next_position.translation.vector += offset;
+    }
+}
+
+fn animated_tile_system(
+    time: Res<GameTime>,
+    mut tiles: Query<(&AnimatedTile, &mut TextureAtlasSprite)>,
+) {
+    let frame = (time.seconds_since_startup() * TILE_FPS as f64) as usize;
+    for (tile, mut sprite) in tiles.iter_mut() {
+        let index = tile.first + frame % tile.frames.max(1);
+        if sprite.index != index {
+            sprite.index = index;
+        }
+    }
+}
@@ -0,0 +1,337 @@
+#[cfg(debug_assertions)]
+use crate::cheat::Cheats;
+use crate::{
+    audio::{Stinger, StingerEvent},
+    despawn::MarkedForDespawn,
+    effects::{spawn_floating_text, SpriteFlash},
+    game_time::GameTime,
+    pickup::PickupKind,
+    respawn::{DeathCause, PlayerDeathEvent},
+    vision::VisionDefinition,
+    Player,
+};
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+
+const ENEMY_DEFINITIONS_PATH: &str = "assets/enemies.json";
+const BURN_DURATION: f32 = 2.0;
+const BURN_INTERVAL: f32 = 0.5;
+const BURN_DAMAGE: f32 = 0.25;
+const SLOW_DURATION: f32 = 1.5;
+const SLOW_FACTOR: f32 = 0.5;
+
+pub struct DamagePlugin;
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        let definitions = match EnemyDefinitions::load(Path::new(ENEMY_DEFINITIONS_PATH)) {
+            Ok(definitions) => definitions,
+            Err(err) => {
+                error!("{:?}", err);
+                EnemyDefinitions::default()
+            }
+        };
+        app.insert_resource(definitions)
+            .add_event::<DamageEvent>()
+            .add_event::<KillEvent>()
+            .add_system(damage_system.label(DamageSystem))
+            .add_system(burn_system)
+            .add_system(slow_system)
+            .add_system(boss_phase_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Ice,
+}
+impl DamageType {
+    pub fn next(self) -> Self {
+        match self {
+            DamageType::Physical => DamageType::Fire,
+            DamageType::Fire => DamageType::Ice,
+            DamageType::Ice => DamageType::Physical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnemyDefinition {
+    pub health: f32,
+    // damage multiplier per type, below 1 resists and above 1 is a weakness
+    #[serde(default)]
+    pub resistances: HashMap<DamageType, f32>,
+    // one entry is rolled on death
+    #[serde(default)]
+    pub drops: Vec<DropEntry>,
+    // blind without one
+    #[serde(default)]
+    pub vision: Option<VisionDefinition>,
+    // spawned and animated like this other enemy, for recolored variants
+    #[serde(default)]
+    pub variant_of: Option<String>,
+    // "#rrggbb" colors of the artwork to the ones this enemy is drawn with
+    #[serde(default)]
+    pub palette: HashMap<String, String>,
+    // framed on screen together with the player at this weight, for bosses
+    #[serde(default)]
+    pub camera_weight: Option<f32>,
+    // health ratios at which a boss moves on to its next phase, e.g. [0.66, 0.33]
+    #[serde(default)]
+    pub phases: Vec<f32>,
+}
+impl Default for EnemyDefinition {
+    fn default() -> Self {
+        Self {
+            health: 1.0,
+            resistances: HashMap::new(),
+            drops: Vec::new(),
+            vision: None,
+            variant_of: None,
+            palette: HashMap::new(),
+            camera_weight: None,
+            phases: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DropEntry {
+    // nothing drops for entries without an item
+    #[serde(default)]
+    pub item: Option<PickupKind>,
+    pub weight: u32,
+    #[serde(default = "default_drop_count")]
+    pub count: u32,
+}
+
+fn default_drop_count() -> u32 {
+    1
+}
+
+// keyed by enemy name, like the "name" field of ldtk enemies
+#[derive(Debug, Default, Deserialize)]
+pub struct EnemyDefinitions(pub HashMap<String, EnemyDefinition>);
+impl EnemyDefinitions {
+    fn load(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("failed to read {:?}", path))?;
+        serde_json::from_slice(&bytes).with_context(|| format!("failed to parse {:?}", path))
+    }
+
+    pub fn get(&self, name: &str) -> EnemyDefinition {
+        self.0.get(name).cloned().unwrap_or_default()
+    }
+
+    // the enemy a variant is based on, or the name itself
+    pub fn base<'a>(&'a self, name: &'a str) -> &'a str {
+        self.0
+            .get(name)
+            .and_then(|definition| definition.variant_of.as_deref())
+            .unwrap_or(name)
+    }
+}
+
+#[derive(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Resistances(pub HashMap<DamageType, f32>);
+impl Resistances {
+    pub fn multiplier(&self, damage_type: DamageType) -> f32 {
+        self.0.get(&damage_type).copied().unwrap_or(1.0)
+    }
+}
+
+// burn damage over time, reapplying refreshes it
+#[derive(Component, Clone)]
+pub struct Burning {
+    timer: Timer,
+    interval: Timer,
+}
+
+// movement speed ratio while the timer runs, for whoever moves the entity
+#[derive(Component, Clone)]
+pub struct Slowed {
+    pub factor: f32,
+    timer: Timer,
+}
+
+// on bosses with phases, for their behaviour to read
+#[derive(Component)]
+pub struct BossPhases {
+    thresholds: Vec<f32>,
+    // 0 until health drops below the first threshold
+    pub phase: usize,
+}
+impl BossPhases {
+    pub fn new(thresholds: Vec<f32>) -> Self {
+        Self {
+            thresholds,
+            phase: 0,
+        }
+    }
+}
+
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub damage_type: DamageType,
+}
+
+// kill events are sent here and the target is despawned at the end of the frame. readers that
+// look the target up run after this label
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub struct DamageSystem;
+
+// health dropped to zero, sent once per target
+pub struct KillEvent {
+    pub target: Entity,
+}
+
+fn damage_system(
+    mut commands: Commands,
+    mut events: EventReader<DamageEvent>,
+    mut kill_events: EventWriter<KillEvent>,
+    mut death_events: EventWriter<PlayerDeathEvent>,
+    // only there with the cheats enabled
+    #[cfg(debug_assertions)] cheats: Option<Res<Cheats>>,
+    mut targets: Query<
+        (
+            &mut Health,
+            Option<&Resistances>,
+            &GlobalTransform,
+            Option<&Player>,
+        ),
+        Without<MarkedForDespawn>,
+    >,
+) {
+    for event in events.iter() {
+        let (mut health, resistances, global_transform, player) =
+            match targets.get_mut(event.target) {
+                Ok(target) => target,
+                Err(_) => continue,
+            };
+        #[cfg(debug_assertions)]
+        if player.is_some() && cheats.as_ref().map_or(false, |cheats| cheats.god_mode) {
+            continue;
+        }
+        // the dash is the player's invulnerability window
+        if player.map_or(false, |player| !player.dash.finished()) {
+            continue;
+        }
+        let multiplier =
+            resistances.map_or(1.0, |resistances| resistances.multiplier(event.damage_type));
+        let alive = health.current > 0.0;
+        health.current -= event.amount * multiplier;
+
+        let mut target = commands.entity(event.target);
+        target.insert(SpriteFlash::damage());
+        match event.damage_type {
+            DamageType::Physical => {}
+            DamageType::Fire => {
+                target.insert(Burning {
+                    timer: Timer::from_seconds(BURN_DURATION, false),
+                    interval: Timer::from_seconds(BURN_INTERVAL, true),
+                });
+            }
+            DamageType::Ice => {
+                target.insert(Slowed {
+                    factor: SLOW_FACTOR,
+                    timer: Timer::from_seconds(SLOW_DURATION, false),
+                });
+            }
+        }
+        // the player isn't despawned, it respawns with full health
+        if alive && health.current <= 0.0 && player.is_some() {
+            death_events.send(PlayerDeathEvent {
+                cause: DeathCause::Killed,
+            });
+        } else if alive && health.current <= 0.0 {
+            target.insert(MarkedForDespawn);
+            kill_events.send(KillEvent {
+                target: event.target,
+            });
+        }
+
+        let text = if multiplier < 1.0 {
+            Some(("resist", Color::GRAY))
+        } else if multiplier > 1.0 {
+            Some(("weak", Color::YELLOW))
+        } else {
+            None
+        };
+        if let Some((text, color)) = text {
+            spawn_floating_text(
+                &mut commands,
+                global_transform.translation + Vec3::new(0.0, 16.0, 0.0),
+                text,
+                color,
+            );
+        }
+    }
+}
+
+fn boss_phase_system(
+    mut bosses: Query<(&Health, &mut BossPhases), Changed<Health>>,
+    mut stinger_events: EventWriter<StingerEvent>,
+) {
+    for (health, mut phases) in bosses.iter_mut() {
+        let ratio = health.current / health.max;
+        // a heavy hit can skip a phase, the stinger plays once
+        let phase = phases
+            .thresholds
+            .iter()
+            .filter(|threshold| ratio <= **threshold)
+            .count();
+        if phase > phases.phase && health.current > 0.0 {
+            phases.phase = phase;
+            stinger_events.send(StingerEvent(Stinger::BossPhase));
+        }
+    }
+}
+
+fn burn_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut burning: Query<(Entity, &mut Burning)>,
+    mut event_writer: EventWriter<DamageEvent>,
+) {
+    for (entity, mut burn) in burning.iter_mut() {
+        burn.timer.tick(time.delta());
+        if burn.interval.tick(time.delta()).just_finished() {
+            // physical, so burns don't keep reapplying themselves
+            event_writer.send(DamageEvent {
+                target: entity,
+                amount: BURN_DAMAGE,
+                damage_type: DamageType::Physical,
+            });
+        }
+        if burn.timer.finished() {
+            commands.entity(entity).remove::<Burning>();
+        }
+    }
+}
+
+fn slow_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut slowed: Query<(Entity, &mut Slowed)>,
+) {
+    for (entity, mut slow) in slowed.iter_mut() {
+        if slow.timer.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Slowed>();
+        }
+    }
+}
@@ -1,28 +1,421 @@
-use bevy::prelude::*;
+use crate::{input::key_from_name, settings::Settings};
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DebugGroup {
+    TerrainColliders,
+    ActorColliders,
+    Sensors,
+    AiVision,
+    Labels,
+    Origin,
+}
+impl DebugGroup {
+    pub const ALL: [DebugGroup; 6] = [
+        DebugGroup::TerrainColliders,
+        DebugGroup::ActorColliders,
+        DebugGroup::Sensors,
+        DebugGroup::AiVision,
+        DebugGroup::Labels,
+        DebugGroup::Origin,
+    ];
+    // used by the console, e.g. "debug terrain"
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugGroup::TerrainColliders => "terrain",
+            DebugGroup::ActorColliders => "actors",
+            DebugGroup::Sensors => "sensors",
+            DebugGroup::AiVision => "vision",
+            DebugGroup::Labels => "labels",
+            DebugGroup::Origin => "origin",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|group| group.name() == name)
+    }
+}
+
+#[derive(Component)]
+pub struct DebugTarget(pub DebugGroup);
+
+// which debug tools run and their keys, the "debug" section of the settings. unset tools are
+// on in debug builds and off in release builds, cheats, savestates and bug reports are only
+// compiled into debug builds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebugConfig {
+    // shapes of the debug groups: colliders, sensors, vision cones, labels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlays: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<bool>,
+    // the cheat panel and console
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cheats: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub savestates: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bug_reports: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_on_esc: Option<bool>,
+    // the devtools window, only in builds with the devtools feature
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inspector: Option<bool>,
+    // the entity hierarchy dump to the log
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hierarchy: Option<bool>,
+    pub keys: DebugKeys,
+}
+impl DebugConfig {
+    pub fn enabled(value: Option<bool>) -> bool {
+        value.unwrap_or(cfg!(debug_assertions))
+    }
+}
+
+// key names like the action bindings, see input.rs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebugKeys {
+    pub show_all: String,
+    pub hide_all: String,
+    pub timings: String,
+    pub inspector: String,
+    pub hierarchy: String,
+    // per group name, e.g. "sensors": "Key6"
+    pub groups: BTreeMap<String, String>,
+}
+impl Default for DebugKeys {
+    fn default() -> Self {
+        Self {
+            show_all: "Key1".to_string(),
+            hide_all: "Key2".to_string(),
+            timings: "Key3".to_string(),
+            inspector: "F12".to_string(),
+            hierarchy: "F10".to_string(),
+            groups: DebugGroup::ALL
+                .into_iter()
+                .zip(["Key4", "Key5", "Key6", "Key7", "Key8", "Key9"])
+                .map(|(group, key)| (group.name().to_string(), key.to_string()))
+                .collect(),
+        }
+    }
+}
+
+fn debug_key(name: &str) -> Option<KeyCode> {
+    let key_code = key_from_name(name);
+    if key_code.is_none() {
+        warn!("unknown debug key in settings: {}", name);
+    }
+    key_code
+}
+
+// the show all, hide all, timing overlay and hierarchy dump keys
+struct DebugBindings {
+    show_all: Option<KeyCode>,
+    hide_all: Option<KeyCode>,
+    timings: Option<KeyCode>,
+    hierarchy: Option<KeyCode>,
+}
+
+// the show all and hide all keys change every group, the bindings toggle a single one
+pub struct DebugGroups {
+    pub visible: HashSet<DebugGroup>,
+    pub bindings: Vec<(KeyCode, DebugGroup)>,
+}
+impl Default for DebugGroups {
+    fn default() -> Self {
+        Self::new(&DebugKeys::default())
+    }
+}
+impl DebugGroups {
+    fn new(keys: &DebugKeys) -> Self {
+        Self {
+            visible: HashSet::new(),
+            bindings: keys
+                .groups
+                .iter()
+                .filter_map(|(name, key)| {
+                    let group = DebugGroup::from_name(name);
+                    if group.is_none() {
+                        warn!("unknown debug group in settings: {}", name);
+                    }
+                    Some((debug_key(key)?, group?))
+                })
+                .collect(),
+        }
+    }
+
+    pub fn toggle(&mut self, group: DebugGroup) {
+        if !self.visible.remove(&group) {
+            self.visible.insert(group);
+        }
+    }
+    pub fn set_all(&mut self, visible: bool) {
+        if visible {
+            self.visible = DebugGroup::ALL.into_iter().collect();
+        } else {
+            self.visible.clear();
+        }
+    }
+}
 
 #[derive(Component)]
-pub struct DebugTarget;
+struct TimingOverlay;
 
 pub struct DebugPlugin;
 impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(debug_system)
-            .add_system(bevy::input::system::exit_on_esc_system);
+        // needs the settings loaded already
+        let config = app
+            .world
+            .get_resource::<Settings>()
+            .map(|settings| settings.debug.clone())
+            .unwrap_or_default();
+        app.insert_resource(DebugGroups::new(&config.keys))
+            .insert_resource(DebugBindings {
+                show_all: debug_key(&config.keys.show_all),
+                hide_all: debug_key(&config.keys.hide_all),
+                timings: debug_key(&config.keys.timings),
+                hierarchy: debug_key(&config.keys.hierarchy),
+            })
+            .init_resource::<SystemTimings>();
+        if DebugConfig::enabled(config.overlays) {
+            app.add_system(debug_system);
+        }
+        if DebugConfig::enabled(config.timings) {
+            app.add_plugin(FrameTimeDiagnosticsPlugin::default())
+                .add_startup_system(setup_system)
+                .add_system(timing_overlay_system);
+        }
+        if DebugConfig::enabled(config.hierarchy) {
+            app.add_system(hierarchy_system.exclusive_system());
+        }
+        if DebugConfig::enabled(config.exit_on_esc) {
+            app.add_system(bevy::input::system::exit_on_esc_system);
+        }
+        #[cfg(feature = "devtools")]
+        if DebugConfig::enabled(config.inspector) {
+            app.add_plugin(crate::devtools::DevtoolsPlugin {
+                toggle: debug_key(&config.keys.inspector),
+            });
+        }
+        #[cfg(debug_assertions)]
+        {
+            if DebugConfig::enabled(config.cheats) {
+                app.add_plugin(crate::cheat::CheatPlugin);
+            }
+            if DebugConfig::enabled(config.savestates) {
+                app.add_plugin(crate::savestate::SavestatePlugin);
+            }
+            if DebugConfig::enabled(config.bug_reports) {
+                app.add_plugin(crate::bugreport::BugReportPlugin);
+            }
+        }
+        app.insert_resource(config);
+    }
+}
+
+// smoothed cpu time per measured system, written through `SystemTimings::span`
+#[derive(Default)]
+pub struct SystemTimings {
+    timings: Mutex<BTreeMap<&'static str, f32>>,
+}
+impl SystemTimings {
+    pub fn span(&self, name: &'static str) -> TimingSpan<'_> {
+        TimingSpan {
+            timings: self,
+            name,
+            start: Instant::now(),
+        }
+    }
+    fn record(&self, name: &'static str, elapsed: Duration) {
+        let ratio = 0.1;
+        let elapsed = elapsed.as_secs_f32();
+        if let Ok(mut timings) = self.timings.lock() {
+            let timing = timings.entry(name).or_insert(elapsed);
+            *timing = *timing * (1.0 - ratio) + elapsed * ratio;
+        }
+    }
+}
+
+pub struct TimingSpan<'a> {
+    timings: &'a SystemTimings,
+    name: &'static str,
+    start: Instant,
+}
+impl Drop for TimingSpan<'_> {
+    fn drop(&mut self) {
+        self.timings.record(self.name, self.start.elapsed());
+    }
+}
+
+fn setup_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(2.0),
+                    top: Val::Px(2.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/hack.ttf"),
+                    font_size: 8.0,
+                    color: Color::rgb(1.0, 0.0, 1.0),
+                },
+                Default::default(),
+            ),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(TimingOverlay);
+}
+
+fn timing_overlay_system(
+    mut query: Query<(&mut Text, &mut Visibility), With<TimingOverlay>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<DebugBindings>,
+    diagnostics: Res<Diagnostics>,
+    timings: Res<SystemTimings>,
+) {
+    let toggle = bindings
+        .timings
+        .map_or(false, |key_code| keyboard_input.just_pressed(key_code));
+    for (mut text, mut visibility) in query.iter_mut() {
+        if toggle {
+            visibility.is_visible = !visibility.is_visible;
+        }
+        if !visibility.is_visible {
+            continue;
+        }
+        let fps = diagnostics
+            .get(FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|fps| fps.average())
+            .unwrap_or(0.0);
+        let mut value = format!("fps {:.1}\n", fps);
+        if let Ok(timings) = timings.timings.lock() {
+            for (name, timing) in timings.iter() {
+                value.push_str(&format!("{} {:.3}ms\n", name, timing * 1000.0));
+            }
+        }
+        text.sections[0].value = value;
     }
 }
 
 fn debug_system(
-    mut query: Query<&mut Visibility, With<DebugTarget>>,
+    mut query: Query<(&DebugTarget, &mut Visibility, ChangeTrackers<DebugTarget>)>,
     keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<DebugBindings>,
+    mut groups: ResMut<DebugGroups>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Key1) {
-        for mut visibility in query.iter_mut() {
-            visibility.is_visible = true;
+    let pressed = |key_code: Option<KeyCode>| {
+        key_code.map_or(false, |key_code| keyboard_input.just_pressed(key_code))
+    };
+    if pressed(bindings.show_all) {
+        groups.set_all(true);
+    }
+    if pressed(bindings.hide_all) {
+        groups.set_all(false);
+    }
+    let toggled = groups
+        .bindings
+        .iter()
+        .filter(|(key_code, _)| keyboard_input.just_pressed(*key_code))
+        .map(|(_, group)| *group)
+        .collect::<Vec<_>>();
+    for group in toggled {
+        groups.toggle(group);
+    }
+
+    // newly spawned targets follow the current groups too
+    let groups_changed = groups.is_changed();
+    for (target, mut visibility, tracker) in query.iter_mut() {
+        if groups_changed || tracker.is_added() {
+            visibility.is_visible = groups.visible.contains(&target.0);
+        }
+    }
+}
+
+// "bevy_transform::components::transform::Transform" to "Transform", generics included
+pub fn short_name(name: &str) -> String {
+    let mut short = String::new();
+    let mut segment = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            segment.clear();
+        } else if c.is_alphanumeric() || c == '_' {
+            segment.push(c);
+        } else {
+            short.push_str(&segment);
+            segment.clear();
+            short.push(c);
         }
     }
-    if keyboard_input.just_pressed(KeyCode::Key2) {
-        for mut visibility in query.iter_mut() {
-            visibility.is_visible = false;
+    short.push_str(&segment);
+    short
+}
+
+// every entity under its parent with its component names, for pasting into bug reports
+fn hierarchy_system(world: &mut World) {
+    let pressed = match (
+        world.get_resource::<DebugBindings>(),
+        world.get_resource::<Input<KeyCode>>(),
+    ) {
+        (Some(bindings), Some(keyboard_input)) => bindings
+            .hierarchy
+            .map_or(false, |key_code| keyboard_input.just_pressed(key_code)),
+        _ => false,
+    };
+    if !pressed {
+        return;
+    }
+    let mut roots = world.query_filtered::<Entity, Without<Parent>>();
+    let roots = roots.iter(world).collect::<Vec<_>>();
+    let mut dump = String::new();
+    for root in roots {
+        dump_entity(world, root, 0, &mut dump);
+    }
+    info!("entity hierarchy:\n{}", dump);
+}
+
+fn dump_entity(world: &World, entity: Entity, depth: usize, dump: &mut String) {
+    let entity_ref = match world.get_entity(entity) {
+        Some(entity_ref) => entity_ref,
+        None => return,
+    };
+    let components = world.components();
+    let mut names = entity_ref
+        .archetype()
+        .components()
+        .filter_map(|id| components.get_info(id))
+        .map(|info| short_name(info.name()))
+        .collect::<Vec<_>>();
+    names.sort();
+    dump.push_str(&format!(
+        "{}{:?} {}\n",
+        "  ".repeat(depth),
+        entity,
+        names.join(", ")
+    ));
+    if let Some(children) = entity_ref.get::<Children>() {
+        for child in children.iter() {
+            dump_entity(world, *child, depth + 1, dump);
         }
     }
 }
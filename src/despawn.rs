@@ -0,0 +1,27 @@
+use crate::ldtk::registry::LdtkEntityRegistry;
+use bevy::prelude::*;
+
+pub struct DespawnPlugin;
+impl Plugin for DespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::Last, cleanup_system);
+    }
+}
+
+// despawned at the end of the frame, so systems running later this frame
+// can still use the entity. filter queries with `Without<MarkedForDespawn>`
+// to skip it in the meantime.
+#[derive(Component)]
+pub struct MarkedForDespawn;
+
+fn cleanup_system(
+    mut commands: Commands,
+    mut registry: ResMut<LdtkEntityRegistry>,
+    query: Query<Entity, With<MarkedForDespawn>>,
+) {
+    for entity in query.iter() {
+        // rapier drops the body and colliders of despawned entities by itself
+        registry.unbind(entity);
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,413 @@
+use crate::{Enemy, Player};
+use bevy::{app::AppLabel, prelude::*};
+use bevy_rapier2d::prelude::*;
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+};
+
+// `--determinism-audit` steps a second physics world in lockstep with the game's and compares
+// the hashes of both every tick. bodies are copied over once rapier has them, after that the
+// second world only gets what the game systems write before a step: velocities, positions,
+// forces and body types. everything else it works out on its own, so any divergence is the
+// step itself not being reproducible. keep off the keyboard, the shadow only mirrors physics
+pub struct DeterminismPlugin;
+impl Plugin for DeterminismPlugin {
+    fn build(&self, app: &mut App) {
+        if !std::env::args().any(|arg| arg == "--determinism-audit") {
+            return;
+        }
+        info!("determinism audit: stepping a shadow physics world in lockstep");
+        let mut shadow = App::new();
+        shadow
+            .insert_resource(Time::default())
+            .insert_resource(RapierConfiguration {
+                timestep_mode: TimestepMode::FixedTimestep,
+                ..Default::default()
+            })
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::default());
+        app.init_resource::<DeterminismAudit>()
+            .add_startup_system(setup_system)
+            .add_stage_before(
+                PhysicsStages::StepWorld,
+                DeterminismStage,
+                SystemStage::single_threaded().with_system(input_system),
+            )
+            .add_sub_app(ShadowApp, shadow, shadow_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AppLabel)]
+struct ShadowApp;
+
+// right before the game's physics step, after every system that moves bodies
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+struct DeterminismStage;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BodyState {
+    position: Vec2,
+    rotation: f32,
+    linvel: Vec2,
+    angvel: f32,
+}
+impl BodyState {
+    fn new(position: &RigidBodyPosition, velocity: &RigidBodyVelocity) -> Self {
+        let translation = position.position.translation.vector;
+        Self {
+            position: Vec2::new(translation.x, translation.y),
+            rotation: position.position.rotation.angle(),
+            linvel: Vec2::new(velocity.linvel.x, velocity.linvel.y),
+            angvel: velocity.angvel,
+        }
+    }
+}
+
+// what the game systems left on a body for the coming step
+struct BodyInput {
+    body_type: RigidBodyType,
+    forces: RigidBodyForces,
+    // only when a game system changed them since the last step
+    position: Option<RigidBodyPosition>,
+    velocity: Option<RigidBodyVelocity>,
+}
+
+// a body or solid collider rapier didn't have on the last step, copied over as is
+enum NewShape {
+    Body(RigidBodyBundle),
+    Collider {
+        bundle: ColliderBundle,
+        // none for colliders standing on their own
+        parent: Option<(Entity, Isometry<Real>)>,
+    },
+}
+
+#[derive(Default)]
+struct DeterminismAudit {
+    tick: usize,
+    // game body to its copy in the shadow world
+    bodies: HashMap<Entity, Entity>,
+    // game collider to the shadow entity holding its copy, the body's copy when they share one
+    colliders: HashMap<Entity, Entity>,
+    // bodies after the last step, to tell what the game systems changed since
+    stepped: HashMap<Entity, (RigidBodyPosition, RigidBodyVelocity)>,
+    new_shapes: Vec<(Entity, NewShape)>,
+    inputs: BTreeMap<Entity, BodyInput>,
+    diverged: bool,
+}
+
+fn setup_system(mut rapier_config: ResMut<RapierConfiguration>) {
+    // exactly one physics step per frame, independent of the frame time
+    rapier_config.timestep_mode = TimestepMode::FixedTimestep;
+}
+
+fn input_system(
+    mut audit: ResMut<DeterminismAudit>,
+    bodies: Query<(
+        Entity,
+        &RigidBodyTypeComponent,
+        &RigidBodyPositionComponent,
+        &RigidBodyVelocityComponent,
+        &RigidBodyMassPropsComponent,
+        &RigidBodyForcesComponent,
+        &RigidBodyDampingComponent,
+        &RigidBodyDominanceComponent,
+        &RigidBodyCcdComponent,
+        ChangeTrackers<RigidBodyPositionComponent>,
+    )>,
+    colliders: Query<(
+        Entity,
+        &ColliderTypeComponent,
+        &ColliderShapeComponent,
+        &ColliderPositionComponent,
+        &ColliderMaterialComponent,
+        &ColliderFlagsComponent,
+        &ColliderMassPropsComponent,
+        Option<&ColliderParentComponent>,
+        ChangeTrackers<ColliderShapeComponent>,
+    )>,
+) {
+    let audit = &mut *audit;
+    for (
+        entity,
+        body_type,
+        position,
+        velocity,
+        mass_props,
+        forces,
+        damping,
+        dominance,
+        ccd,
+        trackers,
+    ) in bodies.iter()
+    {
+        // rapier only picks up what was spawned this frame on the next one, neither world
+        // steps it yet
+        if trackers.is_added() {
+            continue;
+        }
+        if !audit.bodies.contains_key(&entity) {
+            audit.new_shapes.push((
+                entity,
+                NewShape::Body(RigidBodyBundle {
+                    body_type: body_type.0.into(),
+                    position: position.0.into(),
+                    velocity: velocity.0.into(),
+                    mass_properties: mass_props.0.into(),
+                    forces: forces.0.into(),
+                    damping: damping.0.into(),
+                    dominance: dominance.0.into(),
+                    ccd: ccd.0.into(),
+                    ..Default::default()
+                }),
+            ));
+            continue;
+        }
+        let stepped = audit.stepped.get(&entity);
+        audit.inputs.insert(
+            entity,
+            BodyInput {
+                body_type: body_type.0,
+                forces: forces.0,
+                position: (stepped.map(|(position, _)| position) != Some(&position.0))
+                    .then(|| position.0),
+                velocity: (stepped.map(|(_, velocity)| velocity) != Some(&velocity.0))
+                    .then(|| velocity.0),
+            },
+        );
+    }
+
+    for (entity, collider_type, shape, position, material, flags, mass_props, parent, trackers) in
+        colliders.iter()
+    {
+        // sensors don't push anything around
+        if trackers.is_added()
+            || collider_type.0 == ColliderType::Sensor
+            || audit.colliders.contains_key(&entity)
+        {
+            continue;
+        }
+        let parent = parent.map(|parent| (parent.handle.entity(), parent.pos_wrt_parent));
+        audit.new_shapes.push((
+            entity,
+            NewShape::Collider {
+                bundle: ColliderBundle {
+                    collider_type: collider_type.0.into(),
+                    shape: shape.0.clone().into(),
+                    position: position.0.into(),
+                    material: material.0.into(),
+                    flags: flags.0.into(),
+                    mass_properties: mass_props.0.into(),
+                    ..Default::default()
+                },
+                parent,
+            },
+        ));
+    }
+}
+
+fn shadow_system(world: &mut World, shadow: &mut App) {
+    let (gravity, scale) = {
+        let rapier_config = world.get_resource::<RapierConfiguration>().unwrap();
+        (rapier_config.gravity, rapier_config.scale)
+    };
+    {
+        let mut shadow_config = shadow
+            .world
+            .get_resource_mut::<RapierConfiguration>()
+            .unwrap();
+        shadow_config.gravity = gravity;
+        shadow_config.scale = scale;
+    }
+
+    let mut audit = world.remove_resource::<DeterminismAudit>().unwrap();
+    copy_inputs(&mut audit, &mut shadow.world);
+    shadow.update();
+
+    let mut game = BTreeMap::new();
+    let mut copies = BTreeMap::new();
+    let mut bodies = world.query::<(
+        Entity,
+        &RigidBodyPositionComponent,
+        &RigidBodyVelocityComponent,
+    )>();
+    audit.stepped.clear();
+    for (entity, position, velocity) in bodies.iter(world) {
+        audit.stepped.insert(entity, (position.0, velocity.0));
+        let shadow_body = match audit
+            .bodies
+            .get(&entity)
+            .and_then(|shadow_entity| shadow.world.get_entity(*shadow_entity))
+        {
+            Some(shadow_body) => shadow_body,
+            None => continue,
+        };
+        if let (Some(shadow_position), Some(shadow_velocity)) = (
+            shadow_body.get::<RigidBodyPositionComponent>(),
+            shadow_body.get::<RigidBodyVelocityComponent>(),
+        ) {
+            game.insert(entity, BodyState::new(position, velocity));
+            copies.insert(entity, BodyState::new(shadow_position, shadow_velocity));
+        }
+    }
+
+    // the game despawned these
+    for shadows in [&mut audit.bodies, &mut audit.colliders] {
+        let gone = shadows
+            .keys()
+            .filter(|entity| world.get_entity(**entity).is_none())
+            .copied()
+            .collect::<Vec<_>>();
+        for entity in gone {
+            let shadow_entity = shadows.remove(&entity).unwrap();
+            if shadow.world.get_entity(shadow_entity).is_some() {
+                shadow.world.despawn(shadow_entity);
+            }
+        }
+    }
+
+    let tick = audit.tick;
+    audit.tick += 1;
+    if !audit.diverged && hash_bodies(&game) != hash_bodies(&copies) {
+        let keys = body_keys(world);
+        error!("{}", diff_report(tick, &keys, &game, &copies, scale));
+        audit.diverged = true;
+    } else if !audit.diverged && tick > 0 && tick % 3600 == 0 {
+        // once a minute
+        info!("determinism audit: {} ticks match", tick);
+    }
+    world.insert_resource(audit);
+}
+
+fn copy_inputs(audit: &mut DeterminismAudit, shadow_world: &mut World) {
+    // bodies before their colliders, so those have something to attach to, and otherwise in
+    // the game's entity order so contacts come up in the same order in both worlds
+    let mut new_shapes = std::mem::take(&mut audit.new_shapes);
+    new_shapes.sort_by_key(|(entity, shape)| (matches!(shape, NewShape::Collider { .. }), *entity));
+    for (entity, shape) in new_shapes {
+        match shape {
+            NewShape::Body(bundle) => {
+                let shadow_entity = shadow_world.spawn().insert_bundle(bundle).id();
+                audit.bodies.insert(entity, shadow_entity);
+            }
+            NewShape::Collider { bundle, parent } => {
+                let shadow_entity = match parent {
+                    // on its body's entity, rapier attaches it by itself
+                    Some((parent, _)) if parent == entity => match audit.bodies.get(&parent) {
+                        Some(shadow_body) => shadow_world
+                            .entity_mut(*shadow_body)
+                            .insert_bundle(bundle)
+                            .id(),
+                        None => continue,
+                    },
+                    Some((parent, pos_wrt_parent)) => match audit.bodies.get(&parent) {
+                        Some(shadow_body) => shadow_world
+                            .spawn()
+                            .insert_bundle(bundle)
+                            .insert(ColliderParentComponent(ColliderParent {
+                                handle: shadow_body.handle(),
+                                pos_wrt_parent,
+                            }))
+                            .id(),
+                        // on a body that isn't copied yet, tried again after the next step
+                        None => continue,
+                    },
+                    None => shadow_world.spawn().insert_bundle(bundle).id(),
+                };
+                audit.colliders.insert(entity, shadow_entity);
+            }
+        }
+    }
+
+    for (entity, input) in std::mem::take(&mut audit.inputs) {
+        let mut shadow_body = match audit.bodies.get(&entity) {
+            Some(shadow_entity) => shadow_world.entity_mut(*shadow_entity),
+            None => continue,
+        };
+        if let Some(mut body_type) = shadow_body.get_mut::<RigidBodyTypeComponent>() {
+            if body_type.0 != input.body_type {
+                body_type.0 = input.body_type;
+            }
+        }
+        if let Some(mut forces) = shadow_body.get_mut::<RigidBodyForcesComponent>() {
+            forces.0 = input.forces;
+        }
+        if let Some(position) = input.position {
+            shadow_body.insert(RigidBodyPositionComponent(position));
+        }
+        if let Some(velocity) = input.velocity {
+            shadow_body.insert(RigidBodyVelocityComponent(velocity));
+        }
+    }
+}
+
+fn hash_bodies(bodies: &BTreeMap<Entity, BodyState>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for (entity, body) in bodies {
+        entity.hash(&mut hasher);
+        for value in [
+            body.position.x,
+            body.position.y,
+            body.rotation,
+            body.linvel.x,
+            body.linvel.y,
+            body.angvel,
+        ] {
+            value.to_bits().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+// body entity to a readable key, e.g. "player:3"
+fn body_keys(world: &mut World) -> HashMap<Entity, String> {
+    let mut bodies = world.query_filtered::<
+        (Entity, Option<&Player>, Option<&Enemy>),
+        With<RigidBodyPositionComponent>,
+    >();
+    bodies
+        .iter(world)
+        .map(|(entity, player, enemy)| {
+            let key = match (player, enemy) {
+                (Some(_), _) => format!("player:{}", entity.id()),
+                (_, Some(enemy)) => format!(
+                    "enemy:{}:{}",
+                    enemy.name.replace(char::is_whitespace, "_"),
+                    entity.id()
+                ),
+                _ => format!("body:{}", entity.id()),
+            };
+            (entity, key)
+        })
+        .collect()
+}
+
+fn diff_report(
+    tick: usize,
+    keys: &HashMap<Entity, String>,
+    game: &BTreeMap<Entity, BodyState>,
+    copies: &BTreeMap<Entity, BodyState>,
+    scale: f32,
+) -> String {
+    let lines = game
+        .iter()
+        .filter_map(|(entity, expected)| {
+            let actual = copies.get(entity)?;
+            (expected != actual).then(|| {
+                format!(
+                    "  {}: game at {:?}, shadow at {:?}, delta {:?}, velocity delta {:?}",
+                    keys.get(entity).map_or("?", |key| key.as_str()),
+                    expected.position * scale,
+                    actual.position * scale,
+                    (actual.position - expected.position) * scale,
+                    (actual.linvel - expected.linvel) * scale
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    format!(
+        "determinism audit: first divergence at tick {}\n{}",
+        tick,
+        lines.join("\n")
+    )
+}
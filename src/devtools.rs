@@ -0,0 +1,294 @@
+use crate::{
+    animation::Aseprite,
+    debug::{short_name, DebugConfig},
+    game_config::{GameConfig, MovementConfig},
+    save::SaveData,
+    settings::Settings,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext, EguiPlugin};
+
+// egui texture id of the previewed aseprite's sheet
+const PREVIEW_TEXTURE: u64 = 0;
+// times the frame's size in pixels
+const PREVIEW_SCALE: f32 = 4.0;
+// entities listed at most, the list is rebuilt every frame
+const MAX_ENTITIES: usize = 500;
+
+// egui window with the entities, resources, animations and movement tuning of the running
+// game, opened with the debug inspector key
+pub struct DevtoolsPlugin {
+    pub toggle: Option<KeyCode>,
+}
+impl Plugin for DevtoolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(EguiPlugin)
+            .insert_resource(Devtools {
+                toggle: self.toggle,
+                open: false,
+                tab: Tab::Entities,
+                aseprite: None,
+                animation: None,
+                frame: 0,
+            })
+            .add_system(devtools_system.exclusive_system());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Entities,
+    Resources,
+    Animation,
+    Physics,
+}
+impl Tab {
+    const ALL: [Tab; 4] = [Tab::Entities, Tab::Resources, Tab::Animation, Tab::Physics];
+    fn name(&self) -> &'static str {
+        match self {
+            Tab::Entities => "entities",
+            Tab::Resources => "resources",
+            Tab::Animation => "animation",
+            Tab::Physics => "physics",
+        }
+    }
+}
+
+struct Devtools {
+    toggle: Option<KeyCode>,
+    open: bool,
+    tab: Tab,
+    // the previewed animation and its frame
+    aseprite: Option<Handle<Aseprite>>,
+    animation: Option<String>,
+    frame: usize,
+}
+
+// the window needs the whole world for listing entities and resources
+fn devtools_system(world: &mut World) {
+    let toggled = match (
+        world.get_resource::<Devtools>(),
+        world.get_resource::<Input<KeyCode>>(),
+    ) {
+        (Some(devtools), Some(keyboard_input)) => devtools
+            .toggle
+            .map_or(false, |key_code| keyboard_input.just_pressed(key_code)),
+        _ => return,
+    };
+    world.resource_scope(|world, mut devtools: Mut<Devtools>| {
+        if toggled {
+            devtools.open = !devtools.open;
+        }
+        if !devtools.open {
+            return;
+        }
+        world.resource_scope(|world, mut egui_context: Mut<EguiContext>| {
+            if let Some(texture) = preview_texture(world, &devtools) {
+                egui_context.set_egui_texture(PREVIEW_TEXTURE, texture);
+            }
+            let mut open = true;
+            egui::Window::new("devtools")
+                .open(&mut open)
+                .show(egui_context.ctx_mut(), |ui| {
+                    ui.horizontal(|ui| {
+                        for tab in Tab::ALL {
+                            ui.selectable_value(&mut devtools.tab, tab, tab.name());
+                        }
+                    });
+                    ui.separator();
+                    match devtools.tab {
+                        Tab::Entities => entities_ui(ui, world),
+                        Tab::Resources => resources_ui(ui, world),
+                        Tab::Animation => animation_ui(ui, world, &mut devtools),
+                        Tab::Physics => physics_ui(ui, world),
+                    }
+                });
+            devtools.open = open;
+        });
+    });
+}
+
+fn entities_ui(ui: &mut egui::Ui, world: &World) {
+    let components = world.components();
+    ui.label(format!("{} entities", world.entities().len()));
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        let entities = world.archetypes().iter().flat_map(|archetype| {
+            archetype
+                .entities()
+                .iter()
+                .map(move |entity| (*entity, archetype))
+        });
+        for (entity, archetype) in entities.take(MAX_ENTITIES) {
+            let mut names = archetype
+                .components()
+                .filter_map(|id| components.get_info(id))
+                .map(|info| short_name(info.name()))
+                .collect::<Vec<_>>();
+            names.sort();
+            ui.collapsing(format!("{:?}", entity), |ui| {
+                for name in names {
+                    ui.label(name);
+                }
+            });
+        }
+    });
+}
+
+fn resources_ui(ui: &mut egui::Ui, world: &World) {
+    let components = world.components();
+    let mut names = world
+        .archetypes()
+        .resource()
+        .components()
+        .filter_map(|id| components.get_info(id))
+        .map(|info| short_name(info.name()))
+        .collect::<Vec<_>>();
+    names.sort();
+    // values of the ones worth reading
+    let values = [
+        (
+            "Settings",
+            world
+                .get_resource::<Settings>()
+                .map(|r| format!("{:#?}", r)),
+        ),
+        (
+            "SaveData",
+            world
+                .get_resource::<SaveData>()
+                .map(|r| format!("{:#?}", r)),
+        ),
+        (
+            "DebugConfig",
+            world
+                .get_resource::<DebugConfig>()
+                .map(|r| format!("{:#?}", r)),
+        ),
+    ];
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for name in names {
+            match values.iter().find(|(known, _)| *known == name) {
+                Some((_, Some(value))) => {
+                    ui.collapsing(name, |ui| {
+                        ui.monospace(value.as_str());
+                    });
+                }
+                _ => {
+                    ui.label(name);
+                }
+            }
+        }
+    });
+}
+
+fn preview_texture(world: &World, devtools: &Devtools) -> Option<Handle<Image>> {
+    let aseprite = world
+        .get_resource::<Assets<Aseprite>>()?
+        .get(devtools.aseprite.as_ref()?)?;
+    aseprite.texture(world.get_resource::<AssetServer>()?).ok()
+}
+
+// scrubs through the frames of any loaded aseprite's animations
+fn animation_ui(ui: &mut egui::Ui, world: &World, devtools: &mut Devtools) {
+    let aseprites = match world.get_resource::<Assets<Aseprite>>() {
+        Some(aseprites) => aseprites,
+        None => return,
+    };
+    let label = |aseprite: &Aseprite| aseprite.file_path.display().to_string();
+    let selected = devtools
+        .aseprite
+        .as_ref()
+        .and_then(|handle| aseprites.get(handle));
+    egui::ComboBox::from_label("aseprite")
+        .selected_text(selected.map_or_else(String::new, label))
+        .show_ui(ui, |ui| {
+            for (id, aseprite) in aseprites.iter() {
+                ui.selectable_value(
+                    &mut devtools.aseprite,
+                    Some(Handle::weak(id)),
+                    label(aseprite),
+                );
+            }
+        });
+    let aseprite = match selected {
+        Some(aseprite) => aseprite,
+        None => return,
+    };
+
+    let mut names = aseprite.animations.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+    egui::ComboBox::from_label("animation")
+        .selected_text(devtools.animation.clone().unwrap_or_default())
+        .show_ui(ui, |ui| {
+            for name in names {
+                ui.selectable_value(&mut devtools.animation, Some(name.clone()), name);
+            }
+        });
+    let frames = match devtools
+        .animation
+        .as_ref()
+        .and_then(|name| aseprite.animations.get(name))
+    {
+        Some(animation) if !animation.frames.is_empty() => &animation.frames,
+        _ => return,
+    };
+    devtools.frame = devtools.frame.min(frames.len() - 1);
+    ui.add(egui::Slider::new(&mut devtools.frame, 0..=frames.len() - 1).text("frame"));
+
+    let frame = &frames[devtools.frame];
+    ui.label(format!(
+        "{:.3}s {}",
+        frame.duration,
+        frame.events.join(", ")
+    ));
+    let atlas_index = aseprite
+        .atlas_index(None, frame.index)
+        .unwrap_or(frame.index);
+    if let Some(rect) = aseprite.rects.get(atlas_index) {
+        let size = aseprite.size();
+        let (min, max) = (rect.min / size, rect.max / size);
+        let frame_size = (rect.max - rect.min) * PREVIEW_SCALE;
+        ui.add(
+            egui::Image::new(
+                egui::TextureId::User(PREVIEW_TEXTURE),
+                [frame_size.x, frame_size.y],
+            )
+            .uv(egui::Rect::from_min_max(
+                egui::pos2(min.x, min.y),
+                egui::pos2(max.x, max.y),
+            )),
+        );
+    }
+}
+
+// player movement, applied from the next frame on until config.ron is loaded again
+fn physics_ui(ui: &mut egui::Ui, world: &mut World) {
+    let mut config = match world.get_resource_mut::<GameConfig>() {
+        Some(config) => config,
+        None => return,
+    };
+    let movement = &mut config.movement;
+    let sliders = [
+        (&mut movement.walk_speed, 0.0..=128.0, "walk speed"),
+        (&mut movement.dash_speed, 0.0..=480.0, "dash speed"),
+        (
+            &mut movement.crouch_speed_ratio,
+            0.0..=1.0,
+            "crouch speed ratio",
+        ),
+        (
+            &mut movement.swim_speed_ratio,
+            0.0..=1.0,
+            "swim speed ratio",
+        ),
+        (&mut movement.jump_impulse, 0.0..=24.0, "jump impulse"),
+        (&mut movement.swim_stroke, 0.0..=16.0, "swim stroke"),
+        (&mut movement.attack_impulse, 0.0..=128.0, "attack impulse"),
+    ];
+    for (value, range, text) in sliders {
+        ui.add(egui::Slider::new(value, range).text(text));
+    }
+    if ui.button("reset").clicked() {
+        *movement = MovementConfig::default();
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    actor::ActorParts,
+    bitmap_font::{BitmapText, BitmapTextBundle},
+    game_time::GameTime,
+};
+use bevy::prelude::*;
+
+pub struct EffectsPlugin;
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(sprite_flash_system)
+            .add_system(afterimage_system)
+            .add_system(ghost_system)
+            .add_system(floating_text_system);
+    }
+}
+
+// tints the sprite of an entity or its actor sprite root, removed when the timer finishes
+#[derive(Component)]
+pub struct SpriteFlash {
+    pub color: Color,
+    pub timer: Timer,
+    // flashes per second, 0 keeps the tint for the whole duration
+    pub frequency: f32,
+}
+impl SpriteFlash {
+    pub fn new(color: Color, duration: f32, frequency: f32) -> Self {
+        Self {
+            color,
+            timer: Timer::from_seconds(duration, false),
+            frequency,
+        }
+    }
+    pub fn damage() -> Self {
+        Self::new(Color::rgb(1.0, 0.2, 0.2), 0.3, 15.0)
+    }
+    // blinking while invulnerable
+    pub fn blink(duration: f32) -> Self {
+        Self::new(Color::rgba(1.0, 1.0, 1.0, 0.2), duration, 10.0)
+    }
+    fn current_color(&self) -> Color {
+        let elapsed = self.timer.elapsed_secs();
+        if self.timer.finished() {
+            Color::WHITE
+        } else if self.frequency <= 0.0 || (elapsed * self.frequency).fract() < 0.5 {
+            self.color
+        } else {
+            Color::WHITE
+        }
+    }
+}
+
+// leaves fading copies of the current sprite frame of an entity or its actor sprite root
+#[derive(Component)]
+pub struct Afterimage {
+    pub timer: Timer,
+    pub interval: Timer,
+    // seconds until a ghost has faded out
+    pub lifetime: f32,
+    pub color: Color,
+}
+impl Afterimage {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(duration, false),
+            interval: Timer::from_seconds(0.05, true),
+            lifetime: 0.2,
+            color: Color::rgba(0.6, 0.8, 1.0, 0.6),
+        }
+    }
+}
+
+#[derive(Component)]
+struct Ghost {
+    timer: Timer,
+    alpha: f32,
+}
+
+// rises and fades out, e.g. "resist" over a hit enemy
+#[derive(Component)]
+struct FloatingText {
+    timer: Timer,
+}
+
+pub fn spawn_floating_text(commands: &mut Commands, position: Vec3, text: &str, color: Color) {
+    commands
+        .spawn_bundle(BitmapTextBundle::new(
+            BitmapText::new(text, color),
+            Transform::from_translation(position + Vec3::Z * 20.0),
+        ))
+        .insert(FloatingText {
+            timer: Timer::from_seconds(0.8, false),
+        });
+}
+
+fn sprite_flash_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flashes: Query<(Entity, &mut SpriteFlash)>,
+    mut sprites: Query<&mut TextureAtlasSprite>,
+    actor_parts: ActorParts,
+) {
+    for (entity, mut flash) in flashes.iter_mut() {
+        flash.timer.tick(time.delta());
+        let color = flash.current_color();
+        for sprite_entity in std::iter::once(entity).chain(actor_parts.sprite(entity)) {
+            if let Ok(mut sprite) = sprites.get_mut(sprite_entity) {
+                sprite.color = color;
+            }
+        }
+        if flash.timer.finished() {
+            commands.entity(entity).remove::<SpriteFlash>();
+        }
+    }
+}
+
+fn afterimage_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut afterimages: Query<(Entity, &mut Afterimage)>,
+    sprites: Query<(
+        &TextureAtlasSprite,
+        &Handle<TextureAtlas>,
+        &GlobalTransform,
+        &Visibility,
+    )>,
+    actor_parts: ActorParts,
+) {
+    for (entity, mut afterimage) in afterimages.iter_mut() {
+        afterimage.timer.tick(time.delta());
+        if afterimage.interval.tick(time.delta()).just_finished() {
+            for sprite_entity in std::iter::once(entity).chain(actor_parts.sprite(entity)) {
+                let (sprite, texture_atlas, global_transform) = match sprites.get(sprite_entity) {
+                    Ok((sprite, texture_atlas, global_transform, visibility))
+                        if visibility.is_visible =>
+                    {
+                        (sprite, texture_atlas, global_transform)
+                    }
+                    _ => continue,
+                };
+                commands
+                    .spawn_bundle(SpriteSheetBundle {
+                        sprite: TextureAtlasSprite {
+                            index: sprite.index,
+                            flip_x: sprite.flip_x,
+                            flip_y: sprite.flip_y,
+                            color: afterimage.color,
+                            ..Default::default()
+                        },
+                        texture_atlas: texture_atlas.clone(),
+                        // just behind the original
+                        transform: Transform {
+                            translation: global_transform.translation - Vec3::Z * 0.01,
+                            rotation: global_transform.rotation,
+                            scale: global_transform.scale,
+                        },
+                        ..Default::default()
+                    })
+                    .insert(Ghost {
+                        timer: Timer::from_seconds(afterimage.lifetime, false),
+                        alpha: afterimage.color.a(),
+                    });
+            }
+        }
+        if afterimage.timer.finished() {
+            commands.entity(entity).remove::<Afterimage>();
+        }
+    }
+}
+
+fn ghost_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut ghosts: Query<(Entity, &mut Ghost, &mut TextureAtlasSprite)>,
+) {
+    for (entity, mut ghost, mut sprite) in ghosts.iter_mut() {
+        ghost.timer.tick(time.delta());
+        let alpha = ghost.alpha * (1.0 - ghost.timer.percent());
+        sprite.color.set_a(alpha);
+        if ghost.timer.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn floating_text_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut texts: Query<(Entity, &mut FloatingText, &mut Transform, &mut BitmapText)>,
+) {
+    for (entity, mut floating_text, mut transform, mut text) in texts.iter_mut() {
+        floating_text.timer.tick(time.delta());
+        transform.translation.y += 16.0 * time.delta_seconds();
+        text.color.set_a(1.0 - floating_text.timer.percent());
+        if floating_text.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
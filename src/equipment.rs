@@ -0,0 +1,122 @@
+use crate::Player;
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+
+const WEAPON_DEFINITIONS_PATH: &str = "assets/weapons.json";
+
+pub struct EquipmentPlugin;
+impl Plugin for EquipmentPlugin {
+    fn build(&self, app: &mut App) {
+        let definitions = match WeaponDefinitions::load(Path::new(WEAPON_DEFINITIONS_PATH)) {
+            Ok(definitions) => definitions,
+            Err(err) => {
+                error!("{:?}", err);
+                WeaponDefinitions::default()
+            }
+        };
+        app.insert_resource(definitions)
+            .add_event::<EquipEvent>()
+            .add_system(equip_system);
+    }
+}
+
+// attack box in pixels, relative to the body when facing right
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Hitbox {
+    pub half_extents: [f32; 2],
+    pub offset: [f32; 2],
+    #[serde(default)]
+    pub frames: FrameData,
+}
+impl Hitbox {
+    pub fn half_extents(&self) -> Vec2 {
+        Vec2::from(self.half_extents)
+    }
+    pub fn offset(&self) -> Vec2 {
+        Vec2::from(self.offset)
+    }
+}
+
+// attack timing in 60fps frames, shown in the practice room for tuning
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub struct FrameData {
+    pub startup: u32,
+    pub active: u32,
+    pub recovery: u32,
+}
+impl FrameData {
+    pub fn total(&self) -> u32 {
+        self.startup + self.active + self.recovery
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WeaponDefinition {
+    // animation tag played for the attack, e.g. "attack_spear"
+    pub animation: String,
+    pub damage: f32,
+    pub hitbox: Hitbox,
+}
+impl Default for WeaponDefinition {
+    // the unarmed slash
+    fn default() -> Self {
+        Self {
+            animation: "attack".to_string(),
+            damage: 1.0,
+            hitbox: Hitbox {
+                half_extents: [16.0, 16.0],
+                offset: [16.0, 0.0],
+                frames: FrameData {
+                    startup: 3,
+                    active: 3,
+                    recovery: 8,
+                },
+            },
+        }
+    }
+}
+
+// keyed by item name
+#[derive(Debug, Default, Deserialize)]
+pub struct WeaponDefinitions(pub HashMap<String, WeaponDefinition>);
+impl WeaponDefinitions {
+    fn load(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("failed to read {:?}", path))?;
+        serde_json::from_slice(&bytes).with_context(|| format!("failed to parse {:?}", path))
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Equipment {
+    pub weapon: Option<String>,
+    // copied from the definition when equipping
+    pub weapon_definition: WeaponDefinition,
+}
+
+// equips the named weapon on the player, None unequips
+pub struct EquipEvent(pub Option<String>);
+
+fn equip_system(
+    mut events: EventReader<EquipEvent>,
+    definitions: Res<WeaponDefinitions>,
+    mut players: Query<&mut Equipment, With<Player>>,
+) {
+    for EquipEvent(weapon) in events.iter() {
+        let definition = match weapon {
+            Some(weapon) => match definitions.0.get(weapon) {
+                Some(definition) => definition.clone(),
+                None => {
+                    warn!("unknown weapon: {}", weapon);
+                    continue;
+                }
+            },
+            None => WeaponDefinition::default(),
+        };
+        for mut equipment in players.iter_mut() {
+            equipment.weapon = weapon.clone();
+            equipment.weapon_definition = definition.clone();
+        }
+    }
+}
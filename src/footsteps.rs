@@ -0,0 +1,188 @@
+use crate::{
+    actor::Grounded, animation::AnimationFrameEvent, audio::SfxEvent, game_time::GameTime,
+};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+const FOOTSTEP_EVENT: &str = "footstep";
+// from the actor's center to its feet, like the foot sensors
+const FOOT_OFFSET: f32 = 10.0;
+const DUST_PARTICLES: usize = 3;
+const DUST_LIFETIME: f32 = 0.3;
+
+// "footstep" animation events play a sound and kick up dust depending on the ground
+pub struct FootstepPlugin;
+impl Plugin for FootstepPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(footstep_system).add_system(dust_system);
+    }
+}
+
+// on terrain colliders, from the "surface" custom data of their tiles
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Surface {
+    Grass,
+    Stone,
+}
+impl Surface {
+    // played in turn, so repeated steps don't sound the same
+    fn sfx(surface: Option<Surface>) -> &'static [&'static str] {
+        match surface {
+            Some(Surface::Grass) => &[
+                "audio/sfx/footstep_grass_1.wav",
+                "audio/sfx/footstep_grass_2.wav",
+                "audio/sfx/footstep_grass_3.wav",
+            ],
+            Some(Surface::Stone) => &[
+                "audio/sfx/footstep_stone_1.wav",
+                "audio/sfx/footstep_stone_2.wav",
+                "audio/sfx/footstep_stone_3.wav",
+            ],
+            None => &["audio/sfx/footstep_1.wav", "audio/sfx/footstep_2.wav"],
+        }
+    }
+    fn dust_color(surface: Option<Surface>) -> Color {
+        match surface {
+            Some(Surface::Grass) => Color::rgba(0.45, 0.6, 0.3, 0.8),
+            Some(Surface::Stone) => Color::rgba(0.6, 0.6, 0.6, 0.8),
+            None => Color::rgba(0.7, 0.6, 0.5, 0.8),
+        }
+    }
+}
+
+#[derive(Component)]
+struct DustParticle {
+    velocity: Vec2,
+    timer: Timer,
+}
+
+fn footstep_system(
+    mut commands: Commands,
+    mut events: EventReader<AnimationFrameEvent>,
+    parents: Query<&Parent>,
+    actors: Query<(&Grounded, &GlobalTransform)>,
+    surfaces: Query<&Surface>,
+    mut sfx_events: EventWriter<SfxEvent>,
+    mut step: Local<usize>,
+) {
+    for event in events.iter() {
+        if event.event_name != FOOTSTEP_EVENT {
+            continue;
+        }
+        // the event comes from the sprite below the actor
+        let actor = parents
+            .get(event.entity)
+            .map_or(event.entity, |parent| parent.0);
+        let (grounded, global_transform) = match actors.get(actor) {
+            Ok(actor) => actor,
+            Err(_) => continue,
+        };
+        let surface = grounded
+            .ground
+            .and_then(|ground| surfaces.get(ground).ok())
+            .copied();
+
+        let sfx = Surface::sfx(surface);
+        *step = step.wrapping_add(1);
+        sfx_events.send(SfxEvent::new(sfx[*step % sfx.len()]).with_emitter(actor));
+
+        // a puff to both sides along the ground
+        let position = global_transform.translation.truncate() - grounded.normal * FOOT_OFFSET;
+        let tangent = Vec2::new(grounded.normal.y, -grounded.normal.x);
+        let color = Surface::dust_color(surface);
+        for index in 0..DUST_PARTICLES {
+            let side = index as f32 / (DUST_PARTICLES - 1) as f32 * 2.0 - 1.0;
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(2.0)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(position.extend(5.0)),
+                    ..Default::default()
+                })
+                .insert(DustParticle {
+                    velocity: tangent * side * 24.0 + grounded.normal * 12.0,
+                    timer: Timer::from_seconds(DUST_LIFETIME, false),
+                });
+        }
+    }
+}
+
+fn dust_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut particles: Query<(Entity, &mut DustParticle, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+        particle.timer.tick(time.delta());
+        // settles quickly
+        particle.velocity *= (1.0 - 6.0 * time.delta_seconds()).max(0.0);
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.0);
+        sprite.color.set_a(0.8 * (1.0 - particle.timer.percent()));
+        if particle.timer.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::{Events, ManualEventReader};
+    use std::path::Path;
+
+    #[test]
+    fn steps_on_grass() {
+        let mut app = App::new();
+        app.add_event::<AnimationFrameEvent>()
+            .add_event::<SfxEvent>()
+            .add_system(footstep_system);
+
+        let ground = app.world.spawn().insert(Surface::Grass).id();
+        let actor = app
+            .world
+            .spawn()
+            .insert(Grounded {
+                normal: Vec2::Y,
+                ground: Some(ground),
+            })
+            .insert(GlobalTransform::default())
+            .id();
+        let sprite = app.world.spawn().insert(Parent(actor)).id();
+        app.world
+            .get_resource_mut::<Events<AnimationFrameEvent>>()
+            .unwrap()
+            .send(AnimationFrameEvent {
+                entity: sprite,
+                event_name: FOOTSTEP_EVENT.to_string(),
+            });
+        app.update();
+
+        let sfx_events = app.world.get_resource::<Events<SfxEvent>>().unwrap();
+        let sfx = ManualEventReader::<SfxEvent>::default()
+            .iter(sfx_events)
+            .collect::<Vec<_>>();
+        assert_eq!(sfx.len(), 1);
+        assert!(Surface::sfx(Some(Surface::Grass)).contains(&sfx[0].path));
+        assert_eq!(sfx[0].emitter, Some(actor));
+        // the sound ships with the game
+        assert!(Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join(sfx[0].path)
+            .exists());
+
+        let colors = app
+            .world
+            .query_filtered::<&Sprite, With<DustParticle>>()
+            .iter(&app.world)
+            .map(|sprite| sprite.color)
+            .collect::<Vec<_>>();
+        assert_eq!(colors.len(), DUST_PARTICLES);
+        assert!(colors
+            .iter()
+            .all(|color| *color == Surface::dust_color(Some(Surface::Grass))));
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::Context;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+const CONFIG_PATH: &str = "config.ron";
+
+// gameplay tuning from assets/config.ron, reloaded whenever the file is saved so the feel can
+// be tuned while the game runs
+pub struct GameConfigPlugin;
+impl Plugin for GameConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<GameConfig>()
+            .init_asset_loader::<GameConfigLoader>()
+            .init_resource::<GameConfig>()
+            .add_startup_system(setup_system)
+            .add_system(reload_system);
+    }
+}
+
+// the values in use are the resource, the asset replaces them when it's loaded or changed
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "8f1d2c44-6b0e-4f3a-b5d7-2e9c1a7f4b60"]
+#[serde(default)]
+pub struct GameConfig {
+    pub movement: MovementConfig,
+    // share of the distance to the player the camera closes each frame
+    pub camera_lerp: f32,
+    // animation frames play this many times faster than their aseprite durations
+    pub sprite_speed: f32,
+}
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            movement: MovementConfig::default(),
+            camera_lerp: 0.05,
+            sprite_speed: 2.0,
+        }
+    }
+}
+
+// how the player moves, also tunable live from the devtools window
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MovementConfig {
+    // px/s
+    pub walk_speed: f32,
+    pub dash_speed: f32,
+    pub crouch_speed_ratio: f32,
+    pub swim_speed_ratio: f32,
+    // upwards impulses
+    pub jump_impulse: f32,
+    pub swim_stroke: f32,
+    // forwards impulse when attacking, none for attack animations with root motion
+    pub attack_impulse: f32,
+}
+impl Default for MovementConfig {
+    fn default() -> Self {
+        Self {
+            walk_speed: 24.0,
+            dash_speed: 160.0,
+            crouch_speed_ratio: 0.5,
+            swim_speed_ratio: 0.6,
+            jump_impulse: 8.0,
+            swim_stroke: 4.0,
+            attack_impulse: 32.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GameConfigLoader;
+impl AssetLoader for GameConfigLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let config = ron::de::from_bytes::<GameConfig>(bytes)
+                .with_context(|| format!("failed to parse {:?}", load_context.path()))?;
+            load_context.set_default_asset(LoadedAsset::new(config));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+// kept so the asset stays loaded
+struct GameConfigHandle(Handle<GameConfig>);
+
+fn setup_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    if let Err(err) = asset_server.watch_for_changes() {
+        warn!("game config won't reload on changes: {:?}", err);
+    }
+    commands.insert_resource(GameConfigHandle(asset_server.load(CONFIG_PATH)));
+}
+
+fn reload_system(
+    mut events: EventReader<AssetEvent<GameConfig>>,
+    handle: Option<Res<GameConfigHandle>>,
+    assets: Res<Assets<GameConfig>>,
+    mut config: ResMut<GameConfig>,
+) {
+    let handle = match handle {
+        Some(handle) => handle,
+        None => return,
+    };
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle: changed } | AssetEvent::Modified { handle: changed }
+                if *changed == handle.0 =>
+            {
+                if let Some(loaded) = assets.get(changed) {
+                    *config = loaded.clone();
+                    info!("game config loaded");
+                }
+            }
+            _ => {}
+        }
+    }
+}
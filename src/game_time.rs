@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::time::Duration;
+
+pub struct GameTimePlugin;
+impl Plugin for GameTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameTime>()
+            .add_system_to_stage(CoreStage::PreUpdate, game_time_system);
+    }
+}
+
+// Time for gameplay systems, which stands still during hitstop and pause along with physics.
+// ui, audio and the camera keep using Time.
+#[derive(Default)]
+pub struct GameTime {
+    delta: Duration,
+    seconds_since_startup: f64,
+    // real seconds left in the current hitstop
+    hitstop: f32,
+    // set while a menu is open
+    pub paused: bool,
+}
+impl GameTime {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.seconds_since_startup
+    }
+
+    pub fn frozen(&self) -> bool {
+        self.hitstop > 0.0 || self.paused
+    }
+
+    // freezes gameplay for a moment, overlapping hitstops don't add up
+    pub fn hitstop(&mut self, seconds: f32) {
+        self.hitstop = self.hitstop.max(seconds);
+    }
+}
+
+fn game_time_system(
+    time: Res<Time>,
+    mut game_time: ResMut<GameTime>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    if game_time.frozen() {
+        game_time.delta = Duration::ZERO;
+        if !game_time.paused {
+            game_time.hitstop -= time.delta_seconds();
+        }
+    } else {
+        game_time.delta = time.delta();
+    }
+    game_time.seconds_since_startup += game_time.delta.as_secs_f64();
+    rapier_config.physics_pipeline_active = !game_time.frozen();
+}
@@ -0,0 +1,93 @@
+use crate::{
+    actor::{ActorParts, SPRITE_OFFSET},
+    replay::BestReplay,
+    settings::Settings,
+    speedrun::RunTimer,
+    Player,
+};
+use bevy::prelude::*;
+
+const GHOST_COLOR: Color = Color::rgba(0.6, 0.8, 1.0, 0.4);
+// just behind the player
+const GHOST_Z: f32 = -0.5;
+
+// the best run of the level played back next to the live player, only a sprite so it
+// can't touch anything
+pub struct GhostPlugin;
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_system)
+            .add_system(ghost_system);
+    }
+}
+
+#[derive(Component)]
+struct Ghost;
+
+fn setup_system(mut commands: Commands) {
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                color: GHOST_COLOR,
+                ..Default::default()
+            },
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(Ghost);
+}
+
+fn ghost_system(
+    settings: Res<Settings>,
+    run_timer: Res<RunTimer>,
+    best: Res<BestReplay>,
+    actor_parts: ActorParts,
+    players: Query<(Entity, &Transform), (With<Player>, Without<Ghost>)>,
+    player_sprites: Query<&Handle<TextureAtlas>, Without<Ghost>>,
+    mut ghosts: Query<
+        (
+            &mut TextureAtlasSprite,
+            &mut Handle<TextureAtlas>,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        With<Ghost>,
+    >,
+) {
+    let player = players.get_single().ok();
+    // drawn with the player's atlas, the replay only has frame indices
+    let texture_atlas = player
+        .and_then(|(player, _)| actor_parts.sprite(player))
+        .and_then(|sprite| player_sprites.get(sprite).ok());
+    let frame = best
+        .0
+        .as_ref()
+        .filter(|_| settings.ghost && !run_timer.finished)
+        .and_then(|replay| replay.sample(run_timer.elapsed));
+    for (mut sprite, mut ghost_atlas, mut transform, mut visibility) in ghosts.iter_mut() {
+        let (frame, texture_atlas, (_, player_transform)) = match (frame, texture_atlas, player) {
+            (Some(frame), Some(texture_atlas), Some(player)) => (frame, texture_atlas, player),
+            _ => {
+                if visibility.is_visible {
+                    visibility.is_visible = false;
+                }
+                continue;
+            }
+        };
+        visibility.is_visible = true;
+        if *ghost_atlas != *texture_atlas {
+            *ghost_atlas = texture_atlas.clone();
+        }
+        sprite.index = frame.index;
+        sprite.flip_x = frame.flip_x;
+        let offset = if frame.flip_x {
+            SPRITE_OFFSET * Vec3::new(-1.0, 1.0, 1.0)
+        } else {
+            SPRITE_OFFSET
+        };
+        transform.translation = frame
+            .position
+            .extend(player_transform.translation.z + GHOST_Z)
+            + offset;
+    }
+}
@@ -0,0 +1,226 @@
+use crate::VirtualPosition;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, marker::PhantomData};
+
+// keys that can be bound to actions, also what key names in the settings file resolve to
+pub const BINDABLE_KEYS: [KeyCode; 69] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Space,
+    KeyCode::Return,
+    KeyCode::Tab,
+    KeyCode::LShift,
+    KeyCode::RShift,
+    KeyCode::LControl,
+    KeyCode::RControl,
+    KeyCode::LAlt,
+    KeyCode::RAlt,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::LBracket,
+    KeyCode::RBracket,
+    KeyCode::Grave,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+];
+
+pub fn key_name(key_code: KeyCode) -> String {
+    format!("{:?}", key_code)
+}
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .into_iter()
+        .find(|key_code| key_name(*key_code) == name)
+}
+
+pub struct InputMapPlugin;
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMap>()
+            .init_resource::<Cursor>()
+            .add_system_to_stage(CoreStage::PreUpdate, cursor_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Left,
+    Right,
+    Up,
+    Down,
+    Jump,
+    Attack,
+    Dash,
+    // keep facing while moving
+    Hold,
+}
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::Left,
+        Action::Right,
+        Action::Up,
+        Action::Down,
+        Action::Jump,
+        Action::Attack,
+        Action::Dash,
+        Action::Hold,
+    ];
+}
+
+// keys per action, any of them triggers it
+pub struct InputMap {
+    pub bindings: HashMap<Action, Vec<KeyCode>>,
+    // aim at the cursor and attack with the left button
+    pub mouse: bool,
+}
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            bindings: [
+                (Action::Left, vec![KeyCode::A, KeyCode::Left]),
+                (Action::Right, vec![KeyCode::D, KeyCode::Right]),
+                (Action::Up, vec![KeyCode::W, KeyCode::Up]),
+                (Action::Down, vec![KeyCode::S, KeyCode::Down]),
+                (Action::Jump, vec![KeyCode::Space]),
+                (Action::Attack, vec![KeyCode::Z]),
+                (Action::Dash, vec![KeyCode::X]),
+                (Action::Hold, vec![KeyCode::LShift]),
+            ]
+            .into_iter()
+            .collect(),
+            mouse: false,
+        }
+    }
+}
+impl InputMap {
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings
+            .get(&action)
+            .map_or(&[], |keys| keys.as_slice())
+    }
+}
+
+// the mouse cursor in world space, none while it's outside the window
+#[derive(Default)]
+pub struct Cursor(pub Option<Vec2>);
+
+// from window coordinates through the game camera's projection, with its zoom and rotation
+fn cursor_system(
+    windows: Res<Windows>,
+    mut cursor: ResMut<Cursor>,
+    cameras: Query<(&Camera, &GlobalTransform), With<VirtualPosition>>,
+) {
+    let position = windows.get_primary().and_then(|window| {
+        let (camera, global_transform) = cameras.get_single().ok()?;
+        let size = Vec2::new(window.width(), window.height());
+        let ndc = window.cursor_position()? / size * 2.0 - Vec2::ONE;
+        let world = global_transform.compute_matrix() * camera.projection_matrix.inverse();
+        Some(world.project_point3(ndc.extend(0.0)).truncate())
+    });
+    if cursor.0 != position {
+        cursor.0 = position;
+    }
+}
+
+// keyboard input read through the input map, and the mouse when it's enabled
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    mouse_input: Res<'w, Input<MouseButton>>,
+    input_map: Res<'w, InputMap>,
+    cursor: Res<'w, Cursor>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+impl<'w, 's> ActionInput<'w, 's> {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.input_map
+            .keys(action)
+            .iter()
+            .any(|key_code| self.keyboard_input.pressed(*key_code))
+            || self
+                .mouse_button(action)
+                .map_or(false, |button| self.mouse_input.pressed(button))
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.input_map
+            .keys(action)
+            .iter()
+            .any(|key_code| self.keyboard_input.just_pressed(*key_code))
+            || self
+                .mouse_button(action)
+                .map_or(false, |button| self.mouse_input.just_pressed(button))
+    }
+
+    fn mouse_button(&self, action: Action) -> Option<MouseButton> {
+        match action {
+            Action::Attack if self.input_map.mouse => Some(MouseButton::Left),
+            _ => None,
+        }
+    }
+
+    // world space direction from a point towards the cursor, none without mouse controls
+    pub fn aim(&self, from: Vec2) -> Option<Vec2> {
+        if !self.input_map.mouse {
+            return None;
+        }
+        self.cursor
+            .0
+            .and_then(|cursor| (cursor - from).try_normalize())
+    }
+}
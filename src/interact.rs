@@ -0,0 +1,140 @@
+use crate::{
+    game_time::GameTime,
+    input::{Action, ActionInput},
+    ldtk::world::WorldMap,
+    Actor, Direction, Player,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// how far in front of the player an interactable can be, in pixels
+const INTERACT_RANGE: f32 = 24.0;
+// slack behind the player, so standing on top of a sign still works
+const INTERACT_BEHIND: f32 = 4.0;
+const PROMPT_OFFSET: f32 = 20.0;
+
+pub struct InteractPlugin;
+impl Plugin for InteractPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<InteractEvent>()
+            .init_resource::<InteractTarget>()
+            .add_startup_system(setup_system)
+            .add_system(interact_system)
+            .add_system(prompt_system);
+    }
+}
+
+// doors, npcs, levers, signs...
+#[derive(Component, Default)]
+pub struct Interactable {
+    // shown next to the prompt when set, e.g. "open"
+    pub label: Option<String>,
+}
+
+// the player pressed up next to the interactable
+pub struct InteractEvent {
+    pub entity: Entity,
+}
+
+// the closest interactable in front of the player
+#[derive(Default)]
+pub struct InteractTarget(pub Option<Entity>);
+
+#[derive(Component)]
+struct InteractPrompt;
+
+fn setup_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section(
+                "↑",
+                TextStyle {
+                    font: asset_server.load("fonts/hack.ttf"),
+                    font_size: 8.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment {
+                    horizontal: HorizontalAlign::Center,
+                    vertical: VerticalAlign::Bottom,
+                },
+            ),
+            ..Default::default()
+        })
+        .insert(InteractPrompt)
+        .insert(Visibility { is_visible: false });
+}
+
+fn interact_system(
+    actions: ActionInput,
+    game_time: Res<GameTime>,
+    rapier_config: Res<RapierConfiguration>,
+    world_map: Res<WorldMap>,
+    mut target: ResMut<InteractTarget>,
+    players: Query<(&RigidBodyPositionComponent, &Actor), With<Player>>,
+    interactables: Query<(Entity, &GlobalTransform), With<Interactable>>,
+    mut event_writer: EventWriter<InteractEvent>,
+) {
+    target.0 = None;
+    let (rb_position, actor) = match players.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let translation = rb_position.position.translation.vector;
+    let player_position = Vec2::new(translation.x, translation.y) * rapier_config.scale;
+    let facing = match actor.direction {
+        Direction::Left => -1.0,
+        Direction::Right => 1.0,
+    };
+    // offsets in the level's frame, so "in front" follows rotated gravity
+    let to_local = world_map.gravity().rotation().transpose();
+
+    target.0 = interactables
+        .iter()
+        .filter_map(|(entity, global_transform)| {
+            let offset = to_local * (global_transform.translation.truncate() - player_position);
+            let ahead = offset.x * facing;
+            let in_front = ahead >= -INTERACT_BEHIND && offset.length() <= INTERACT_RANGE;
+            in_front.then(|| (entity, offset.length()))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity);
+
+    if let Some(entity) = target.0 {
+        if actions.just_pressed(Action::Up) && !game_time.frozen() {
+            event_writer.send(InteractEvent { entity });
+        }
+    }
+}
+
+fn prompt_system(
+    time: Res<Time>,
+    target: Res<InteractTarget>,
+    world_map: Res<WorldMap>,
+    interactables: Query<(&GlobalTransform, &Interactable)>,
+    mut prompts: Query<(&mut Transform, &mut Visibility, &mut Text), With<InteractPrompt>>,
+) {
+    let (mut transform, mut visibility, mut text) = match prompts.get_single_mut() {
+        Ok(prompt) => prompt,
+        Err(_) => return,
+    };
+    let (global_transform, interactable) = match target.0.and_then(|e| interactables.get(e).ok()) {
+        Some(target) => target,
+        None => {
+            visibility.is_visible = false;
+            return;
+        }
+    };
+    visibility.is_visible = true;
+    text.sections[0].value = match &interactable.label {
+        Some(label) => format!("↑ {}", label),
+        None => "↑".to_string(),
+    };
+
+    // floats above the target, bobbing a little
+    let gravity = world_map.gravity();
+    let bob = (time.seconds_since_startup() as f32 * 4.0).sin() * 1.5;
+    let up = -gravity.down();
+    transform.translation = (global_transform.translation.truncate() + up * (PROMPT_OFFSET + bob))
+        .extend(global_transform.translation.z + 1.0);
+    transform.rotation = Quat::from_rotation_z(gravity.angle());
+}
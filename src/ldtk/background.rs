@@ -0,0 +1,154 @@
+use super::{data::Level, world::WorldMap};
+use crate::{
+    bitmap_font::SCREEN_HALF_SIZE,
+    world_clock::{Tinted, WorldClock},
+    VirtualPosition,
+};
+use bevy::{prelude::*, sprite::Rect as SpriteRect};
+use std::path::Path;
+
+// behind every level layer and background image, at the far end of what the 2d camera sees,
+// the images go between it and the bottom layer
+const Z_BACKGROUND_COLOR: f32 = 0.0;
+
+// a quad on the camera in the active level's background color, so levels don't show the clear
+// color
+#[derive(Component)]
+pub struct BackgroundColor;
+
+// background image of a level, its sprite is created once the texture has loaded and the
+// crop rect can be turned into an atlas
+#[derive(Component)]
+pub struct BackgroundImage {
+    texture: Handle<Image>,
+    // in image pixels, y down
+    crop: SpriteRect,
+}
+
+// "#rrggbb" as exported by ldtk
+pub fn parse_color(hex: &str) -> Option<Color> {
+    Color::hex(hex.trim_start_matches('#')).ok()
+}
+
+// the image placed and scaled by ldtk's `__bgPos`, with `origin` the level's top left corner
+pub fn spawn_background_image(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    base_path: &Path,
+    level: &Level,
+    origin: Vec2,
+    z: f32,
+) -> Option<Entity> {
+    let (rel_path, bg_pos) = match (&level.bg_rel_path, &level.bg_pos) {
+        (Some(rel_path), Some(bg_pos)) => (rel_path, bg_pos),
+        _ => return None,
+    };
+    let (crop, scale, top_left) = match (
+        bg_pos.crop_rect.as_slice(),
+        bg_pos.scale.as_slice(),
+        bg_pos.top_left_px.as_slice(),
+    ) {
+        (&[x, y, width, height], &[scale_x, scale_y], &[left, top]) => (
+            SpriteRect {
+                min: Vec2::new(x as f32, y as f32),
+                max: Vec2::new((x + width) as f32, (y + height) as f32),
+            },
+            Vec2::new(scale_x as f32, scale_y as f32),
+            Vec2::new(left as f32, -top as f32),
+        ),
+        _ => {
+            warn!("invalid background position of {}", level.identifier);
+            return None;
+        }
+    };
+    // sprites are centered
+    let half_size = (crop.max - crop.min) * scale * 0.5;
+    let position = origin + top_left + Vec2::new(half_size.x, -half_size.y);
+    let background = commands
+        .spawn_bundle((
+            Transform {
+                translation: position.extend(z),
+                scale: scale.extend(1.0),
+                ..Default::default()
+            },
+            GlobalTransform::identity(),
+        ))
+        .insert(BackgroundImage {
+            texture: asset_server.load(base_path.join(rel_path).as_path()),
+            crop,
+        })
+        .id();
+    Some(background)
+}
+
+pub fn background_color_setup_system(
+    mut commands: Commands,
+    cameras: Query<(Entity, &Transform), (Added<Camera>, With<VirtualPosition>)>,
+) {
+    for (camera, transform) in cameras.iter() {
+        // covers the view through the camera's zoom and rotation
+        let size = Vec2::splat(SCREEN_HALF_SIZE.length() * 2.0);
+        let background = commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::NONE,
+                    custom_size: Some(size),
+                    ..Default::default()
+                },
+                transform: Transform::from_xyz(
+                    0.0,
+                    0.0,
+                    Z_BACKGROUND_COLOR - transform.translation.z,
+                ),
+                ..Default::default()
+            })
+            .insert(BackgroundColor)
+            .id();
+        commands.entity(camera).push_children(&[background]);
+    }
+}
+
+pub fn background_color_system(
+    world_map: Res<WorldMap>,
+    clock: Res<WorldClock>,
+    mut query: Query<&mut Sprite, With<BackgroundColor>>,
+) {
+    let color = match world_map.active_level() {
+        Some(level) if world_map.is_changed() || clock.is_changed() => {
+            clock.apply(level.background)
+        }
+        _ => return,
+    };
+    for mut sprite in query.iter_mut() {
+        sprite.color = color;
+    }
+}
+
+pub fn background_image_system(
+    mut commands: Commands,
+    images: Res<Assets<Image>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    query: Query<(Entity, &BackgroundImage)>,
+) {
+    for (entity, background) in query.iter() {
+        let image = match images.get(&background.texture) {
+            Some(image) => image,
+            None => continue,
+        };
+        let size = image.texture_descriptor.size;
+        let mut texture_atlas = TextureAtlas::new_empty(
+            background.texture.clone(),
+            Vec2::new(size.width as f32, size.height as f32),
+        );
+        let index = texture_atlas.add_texture(background.crop);
+        commands
+            .entity(entity)
+            .remove::<BackgroundImage>()
+            .insert_bundle((
+                TextureAtlasSprite::new(index),
+                texture_atlases.add(texture_atlas),
+                Visibility::default(),
+                Tinted(Color::WHITE),
+            ));
+    }
+}
@@ -568,6 +568,20 @@ pub struct LevelBackgroundPosition {
     pub top_left_px: Vec<i64>,
 }
 
+/// Value of an `EntityRef` field instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntityReferenceInfos {
+    /// IID of the refered EntityInstance
+    #[serde(rename = "entityIid")]
+    pub entity_iid: String,
+    /// IID of the LayerInstance containing the refered EntityInstance
+    #[serde(rename = "layerIid")]
+    pub layer_iid: String,
+    /// IID of the Level containing the refered EntityInstance
+    #[serde(rename = "levelIid")]
+    pub level_iid: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FieldInstance {
     /// Field definition identifier
@@ -712,6 +726,9 @@ pub struct EntityInstance {
     /// Pixel coordinates (`[x,y]` format) in current level coordinate space. Don't forget
     /// optional layer offsets, if they exist!
     pub px: Vec<i64>,
+    /// Unique instance identifier, exported since LDtk 1.0
+    #[serde(default)]
+    pub iid: Option<String>,
     /// Entity width in pixels. For non-resizable entities, it will be the same as Entity
     /// definition.
     pub width: i64,
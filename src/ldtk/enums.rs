@@ -0,0 +1,82 @@
+use super::data::{FieldInstance, LdtkData};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct LdtkEnumTile {
+    pub texture_atlas: Handle<TextureAtlas>,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct LdtkEnumValue {
+    pub id: String,
+    pub color: Color,
+    pub tile: Option<LdtkEnumTile>,
+}
+
+#[derive(Debug, Default)]
+pub struct LdtkEnums {
+    enums: HashMap<String, Vec<LdtkEnumValue>>,
+}
+
+impl LdtkEnums {
+    pub fn new(
+        data: &LdtkData,
+        texture_atlas_handles: &HashMap<i64, Handle<TextureAtlas>>,
+    ) -> Self {
+        let enums = data
+            .defs
+            .enums
+            .iter()
+            .chain(data.defs.external_enums.iter())
+            .map(|enum_def| {
+                let texture_atlas = enum_def
+                    .icon_tileset_uid
+                    .and_then(|uid| texture_atlas_handles.get(&uid));
+                let values = enum_def
+                    .values
+                    .iter()
+                    .map(|value| LdtkEnumValue {
+                        id: value.id.clone(),
+                        color: Color::rgb_u8(
+                            (value.color >> 16 & 0xff) as u8,
+                            (value.color >> 8 & 0xff) as u8,
+                            (value.color & 0xff) as u8,
+                        ),
+                        tile: texture_atlas
+                            .zip(value.tile_id)
+                            .map(|(texture_atlas, tile_id)| LdtkEnumTile {
+                                texture_atlas: texture_atlas.clone(),
+                                index: tile_id as usize,
+                            }),
+                    })
+                    .collect::<Vec<_>>();
+                (enum_def.identifier.clone(), values)
+            })
+            .collect();
+        Self { enums }
+    }
+
+    pub fn variants(&self, enum_identifier: &str) -> Option<&[LdtkEnumValue]> {
+        self.enums
+            .get(enum_identifier)
+            .map(|values| values.as_slice())
+    }
+
+    pub fn get(&self, enum_identifier: &str, value_id: &str) -> Option<&LdtkEnumValue> {
+        self.variants(enum_identifier)
+            .and_then(|values| values.iter().find(|value| value.id == value_id))
+    }
+
+    // resolve a field whose type is `LocalEnum.Name` or `ExternEnum.Name`
+    pub fn resolve(&self, field_instance: &FieldInstance) -> Option<&LdtkEnumValue> {
+        let enum_identifier = field_instance
+            .field_instance_type
+            .rsplit('.')
+            .next()
+            .filter(|_| field_instance.field_instance_type.contains("Enum"))?;
+        let value_id = field_instance.value.as_ref()?.as_str()?;
+        self.get(enum_identifier, value_id)
+    }
+}
@@ -0,0 +1,76 @@
+use std::{fmt, path::PathBuf};
+
+// what's wrong with an ldtk file, loading logs these instead of panicking
+#[derive(Debug)]
+pub enum LdtkError {
+    // not json, or not the ldtk schema
+    Parse(serde_json::Error),
+    LevelNotFound(String),
+    // external levels aren't supported
+    NoLayers(String),
+    NotInWorldMap(String),
+    NoParentDirectory(PathBuf),
+    TilesetNotFound(i64),
+    UnsupportedLayer {
+        level: String,
+        layer: String,
+        layer_type: String,
+    },
+    MissingField {
+        entity: String,
+        field: String,
+    },
+    // editing tiles at runtime, see LdtkLevelRuntime
+    LayerNotFound(String),
+    TileOutOfBounds {
+        layer: String,
+        x: i32,
+        y: i32,
+    },
+}
+
+impl fmt::Display for LdtkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LdtkError::Parse(err) => write!(f, "failed to parse ldtk file: {}", err),
+            LdtkError::LevelNotFound(level) => write!(f, "identifier {} not found", level),
+            LdtkError::NoLayers(level) => write!(f, "{} has no layers", level),
+            LdtkError::NotInWorldMap(level) => write!(f, "{} is not in world map", level),
+            LdtkError::NoParentDirectory(path) => {
+                write!(f, "failed to get parent directory, {:?}", path)
+            }
+            LdtkError::TilesetNotFound(uid) => write!(f, "failed to find tileset: {}", uid),
+            LdtkError::UnsupportedLayer {
+                level,
+                layer,
+                layer_type,
+            } => write!(
+                f,
+                "{} layer {} of {} is not supported",
+                layer_type, layer, level
+            ),
+            LdtkError::MissingField { entity, field } => {
+                write!(f, "{} has no {} field", entity, field)
+            }
+            LdtkError::LayerNotFound(layer) => write!(f, "no tile layer {}", layer),
+            LdtkError::TileOutOfBounds { layer, x, y } => {
+                write!(f, "{}, {} is outside of layer {}", x, y, layer)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LdtkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LdtkError::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for LdtkError {
+    fn from(err: serde_json::Error) -> Self {
+        LdtkError::Parse(err)
+    }
+}
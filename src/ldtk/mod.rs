@@ -1,3 +1,8 @@
+pub mod background;
 pub mod data;
+pub mod enums;
+pub mod error;
 pub mod plugin;
-
+pub mod registry;
+pub mod runtime;
+pub mod world;
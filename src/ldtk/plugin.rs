@@ -1,19 +1,46 @@
-use super::data::LdtkData;
-use crate::debug::DebugTarget;
+use super::{
+    background::{
+        background_color_setup_system, background_color_system, background_image_system,
+        spawn_background_image,
+    },
+    data::{EntityInstance, EntityInstanceTile, EntityReferenceInfos, LdtkData, TilesetDefinition},
+    enums::LdtkEnums,
+    error::LdtkError,
+    registry::{entity_iid, LdtkEntityRecord, LdtkEntityRegistry, LdtkTarget},
+    runtime::{LdtkLevelRuntime, RuntimeLayer, TileCollision},
+    world::WorldMap,
+};
+use crate::{
+    asset_error::AssetErrorEvent,
+    camera_zone::{spawn_camera_zone, CameraZoneMode},
+    debug::SystemTimings,
+    footsteps::Surface,
+    lighting::spawn_light,
+    nav::spawn_nav_grid,
+    pickup::{spawn_pickup, PickupKind},
+    portal::spawn_portal,
+    props::spawn_crate,
+    puzzle::{spawn_lever, spawn_pressure_plate, spawn_trigger_door},
+    rope::spawn_rope,
+    speedrun::spawn_goal,
+    trigger::spawn_trigger,
+    water::spawn_water,
+    wind::spawn_wind_zone,
+    world_flags::WorldFlags,
+    Player,
+};
 use anyhow::{Context, Result};
 use bevy::{
-    asset::{AssetLoader, LoadContext, LoadedAsset},
+    asset::{AssetLoader, LoadContext, LoadState, LoadedAsset},
     prelude::*,
     reflect::TypeUuid,
+    sprite::Rect as SpriteRect,
     utils::BoxedFuture,
 };
-use bevy_prototype_lyon::prelude::*;
-use bevy_rapier2d::{prelude::*, rapier::parry::transformation::vhacd::VHACDParameters};
-use geo_booleanop::boolean::BooleanOp;
+use bevy_rapier2d::prelude::*;
 use serde::Deserialize;
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
-const Z_COLLISION: f32 = 10.0;
 const COLLIDER_MATERIAL: ColliderMaterial = ColliderMaterial {
     friction: 0.0,
     restitution: 0.0,
@@ -26,8 +53,22 @@ impl Plugin for LdtkPlugin {
     fn build(&self, app: &mut App) {
         app.add_asset::<Ldtk>()
             .init_asset_loader::<LdtkLoader>()
+            .init_resource::<LdtkEnums>()
+            .init_resource::<WorldMap>()
+            .init_resource::<LdtkLayerZConfig>()
+            .init_resource::<LdtkSpawnConfig>()
+            .init_resource::<LdtkColliderConfig>()
+            .init_resource::<LdtkEntityRegistry>()
             .add_event::<LdtkEvent>()
-            .add_system(on_asset_event_system);
+            .add_event::<DespawnLevel>()
+            .add_system(on_asset_event_system)
+            .add_system(despawn_level_system)
+            .add_system(load_state_system)
+            .add_system(level_activation_system)
+            .add_system(level_gravity_system)
+            .add_system(background_color_setup_system)
+            .add_system(background_color_system)
+            .add_system(background_image_system);
     }
 }
 
@@ -36,35 +77,235 @@ impl Plugin for LdtkPlugin {
 pub struct Ldtk {
     pub file_path: PathBuf,
     pub data: LdtkData,
+    #[serde(skip)]
+    pub world_map: WorldMap,
+}
+
+// z of tile layers, lower layers in the ldtk file go behind the upper ones. the 2d camera
+// doesn't see below 0, so the layers count down from 1 towards it, under the actors
+pub struct LdtkLayerZConfig {
+    pub top: f32,
+    pub step: f32,
+    // fixed z per layer identifier, e.g. to render a foreground above actors
+    pub overrides: HashMap<String, f32>,
+}
+impl Default for LdtkLayerZConfig {
+    fn default() -> Self {
+        Self {
+            top: 1.0,
+            step: 0.01,
+            overrides: [("Foreground".to_string(), 5.0)].into_iter().collect(),
+        }
+    }
+}
+impl LdtkLayerZConfig {
+    fn z(&self, layer_identifier: &str, layer_index: usize) -> f32 {
+        self.overrides
+            .get(layer_identifier)
+            .copied()
+            .unwrap_or(self.top - layer_index as f32 * self.step)
+    }
+}
+
+// whether the levels are spawned at their world coordinates once the project loads, procgen runs
+// place them themselves
+pub struct LdtkSpawnConfig {
+    pub spawn_world: bool,
+}
+impl Default for LdtkSpawnConfig {
+    fn default() -> Self {
+        Self { spawn_world: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TerrainColliderMode {
+    // convex parts, solid inside
+    ConvexDecomposition,
+    // the outline as is, cheaper and without seams for capsules to catch on, but hollow
+    Polyline,
+}
+
+// how the merged tile outlines become colliders
+#[derive(Clone)]
+pub struct LdtkColliderConfig {
+    // pixels an outline may move when simplified, 0 keeps every corner
+    pub simplify_epsilon: f32,
+    pub mode: TerrainColliderMode,
+    // per layer identifier
+    pub overrides: HashMap<String, TerrainColliderMode>,
+}
+impl Default for LdtkColliderConfig {
+    fn default() -> Self {
+        Self {
+            simplify_epsilon: 1.0,
+            mode: TerrainColliderMode::ConvexDecomposition,
+            overrides: HashMap::new(),
+        }
+    }
+}
+impl LdtkColliderConfig {
+    pub(super) fn mode(&self, layer_identifier: &str) -> TerrainColliderMode {
+        self.overrides
+            .get(layer_identifier)
+            .copied()
+            .unwrap_or(self.mode)
+    }
 }
 
 #[derive(Debug)]
 pub enum LdtkEvent {
     SpawnPlayer(Vec3),
-    SpawnEnemy { name: String, position: Vec3 },
+    SpawnEnemy {
+        name: String,
+        position: Vec3,
+        // set for enemies placed in ldtk
+        iid: Option<String>,
+    },
+    SpawnNpc {
+        name: String,
+        position: Vec3,
+        // lines said in turn on each interaction
+        dialogue: Vec<String>,
+        // how far it wanders from its position, in pixels
+        radius: f32,
+        iid: Option<String>,
+    },
+    LevelActivated(String),
+}
+
+// despawns a loaded level by identifier, see Ldtk::unload
+#[derive(Debug)]
+pub struct DespawnLevel(pub String);
+
+// parent of everything spawned for a level, tiles, terrain, debug shapes and entities alike
+#[derive(Component, Debug)]
+pub struct LdtkLevelRoot {
+    pub uid: i64,
+    pub identifier: String,
+    // from the level's place in the world map, see LevelPlacement
+    pub offset: Vec2,
+}
+
+// where Ldtk::load spawns a level, moved by `offset` pixels from its place in the world map and
+// optionally under `parent`. bodies are placed in world space and don't follow transforms, so the
+// parent should stay at the origin. loading a level again spawns another instance, the registry
+// binds the iids to the last one's entities
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LevelPlacement {
+    pub offset: Vec2,
+    pub parent: Option<Entity>,
 }
 
 impl Ldtk {
-    fn load(
+    pub fn from_bytes(bytes: &[u8], file_path: PathBuf) -> Result<Self, LdtkError> {
+        let data = serde_json::from_slice::<LdtkData>(bytes)?;
+        Ok(Ldtk {
+            world_map: WorldMap::new(&data),
+            data,
+            file_path,
+        })
+    }
+    fn create_texture_atlas(
+        &self,
+        tileset_def: &TilesetDefinition,
+        asset_server: &Res<AssetServer>,
+        texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    ) -> Result<Handle<TextureAtlas>> {
+        let base_path = self
+            .file_path
+            .parent()
+            .ok_or_else(|| LdtkError::NoParentDirectory(self.file_path.clone()))?;
+        let tile_size = Vec2::splat(tileset_def.tile_grid_size as f32);
+
+        let mut texture_path = std::path::PathBuf::new();
+        texture_path.push(base_path);
+        texture_path.push(tileset_def.rel_path.clone());
+
+        let texture_handle = asset_server.load(texture_path.as_path());
+        let texture_atlas = TextureAtlas::from_grid(
+            texture_handle,
+            tile_size,
+            tileset_def.c_wid as usize,
+            tileset_def.c_hei as usize,
+        );
+        Ok(texture_atlases.add(texture_atlas))
+    }
+    // atlas and index of an entity's tile
+    fn tile_frame(
+        &self,
+        tile: &EntityInstanceTile,
+        asset_server: &Res<AssetServer>,
+        texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    ) -> Result<(Handle<TextureAtlas>, usize)> {
+        let tileset_def = self
+            .data
+            .defs
+            .tilesets
+            .iter()
+            .find(|tileset_def| tileset_def.uid == tile.tileset_uid)
+            .ok_or(LdtkError::TilesetNotFound(tile.tileset_uid))?;
+        let grid_size = tileset_def.tile_grid_size.max(1);
+        let (x, y) = match tile.src_rect.as_slice() {
+            &[x, y, ..] => (x / grid_size, y / grid_size),
+            _ => (0, 0),
+        };
+        let texture_atlas_handle =
+            self.create_texture_atlas(tileset_def, asset_server, texture_atlases)?;
+        Ok((texture_atlas_handle, (y * tileset_def.c_wid + x) as usize))
+    }
+    fn create_enums(
+        &self,
+        asset_server: &Res<AssetServer>,
+        texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    ) -> Result<LdtkEnums> {
+        let texture_atlas_handles = self
+            .data
+            .defs
+            .tilesets
+            .iter()
+            .filter(|tileset_def| {
+                self.data
+                    .defs
+                    .enums
+                    .iter()
+                    .chain(self.data.defs.external_enums.iter())
+                    .any(|enum_def| enum_def.icon_tileset_uid == Some(tileset_def.uid))
+            })
+            .map(|tileset_def| {
+                self.create_texture_atlas(tileset_def, asset_server, texture_atlases)
+                    .map(|texture_atlas_handle| (tileset_def.uid, texture_atlas_handle))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        Ok(LdtkEnums::new(&self.data, &texture_atlas_handles))
+    }
+    // spawns a level, returning its root
+    pub fn load(
         &self,
         level_identifier: &str,
+        placement: &LevelPlacement,
         asset_server: &Res<AssetServer>,
         texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
         commands: &mut Commands,
         rapier_config: &Res<RapierConfiguration>,
+        z_config: &LdtkLayerZConfig,
+        collider_config: &LdtkColliderConfig,
+        registry: &mut LdtkEntityRegistry,
+        flags: &WorldFlags,
         event_writer: &mut EventWriter<LdtkEvent>,
-    ) -> Result<()> {
+    ) -> Result<Entity> {
+        let _span = info_span!("level", identifier = level_identifier).entered();
         let level = self
             .data
             .levels
             .iter()
             .find(|level| level.identifier == level_identifier)
-            .with_context(|| format!("identifier {} not found", level_identifier))?;
+            .ok_or_else(|| LdtkError::LevelNotFound(level_identifier.to_string()))?;
 
         let layer_instances = level
             .layer_instances
             .as_ref()
-            .with_context(|| format!("{} has no layers", level_identifier))?;
+            .ok_or_else(|| LdtkError::NoLayers(level_identifier.to_string()))?;
 
         // tileset
         let mut tileset_defs = layer_instances
@@ -83,31 +324,13 @@ impl Ldtk {
         tileset_defs.dedup_by(|a, b| a.uid == b.uid);
 
         // create texture atlas
-        let base_path = self
-            .file_path
-            .parent()
-            .with_context(|| format!("failed to get parent directory, {:?}", self.file_path))?;
-
         let texture_atlas_handles = tileset_defs
             .iter()
             .map(|tileset_def| {
-                let tile_size = Vec2::splat(tileset_def.tile_grid_size as f32);
-
-                let mut texture_path = std::path::PathBuf::new();
-                texture_path.push(base_path);
-                texture_path.push(tileset_def.rel_path.clone());
-
-                let texture_handle = asset_server.load(texture_path.as_path());
-                let texture_atlas = TextureAtlas::from_grid(
-                    texture_handle,
-                    tile_size,
-                    tileset_def.c_wid as usize,
-                    tileset_def.c_hei as usize,
-                );
-                let texture_atlas_handle = texture_atlases.add(texture_atlas);
-                (tileset_def.uid, texture_atlas_handle)
+                self.create_texture_atlas(tileset_def, asset_server, texture_atlases)
+                    .map(|texture_atlas_handle| (tileset_def.uid, texture_atlas_handle))
             })
-            .collect::<HashMap<_, _>>();
+            .collect::<Result<HashMap<_, _>>>()?;
 
         // get tileset collision data
         let tileset_collisions = tileset_defs
@@ -128,199 +351,508 @@ impl Ldtk {
                             .get("data")
                             .and_then(|value| {
                                 if let Some(serde_json::Value::String(value)) = value.as_ref() {
-                                    serde_json::from_str::<Vec<(f32, f32)>>(value).ok()
+                                    serde_json::from_str::<TileCustomData>(value).ok()
                                 } else {
                                     None
                                 }
                             })
                             .map(|data| {
-                                data.into_iter()
-                                    .map(|(x, y)| {
-                                        Vec2::new(x, -y) * tileset_def.tile_grid_size as f32
-                                    })
-                                    .collect::<Vec<_>>()
+                                let (polygon, material, conveyor, surface, frames, breakable) =
+                                    data.into_parts();
+                                TileCollision {
+                                    polygon: polygon
+                                        .into_iter()
+                                        .map(|(x, y)| {
+                                            Vec2::new(x, -y) * tileset_def.tile_grid_size as f32
+                                        })
+                                        .collect(),
+                                    material,
+                                    conveyor,
+                                    surface,
+                                    frames,
+                                    breakable,
+                                }
                             });
-                        if tile_id.is_none() || data.is_none() {
-                            None
-                        } else {
-                            Some((tile_id.unwrap(), data.unwrap()))
-                        }
+                        tile_id.zip(data)
                     })
                     .collect::<HashMap<_, _>>();
-                (tileset_def.uid, tileset_collision)
+                (tileset_def.uid, Arc::new(tileset_collision))
             })
             .collect::<HashMap<_, _>>();
 
-        let level_position = Vec3::new(level.world_x as f32, -level.world_y as f32, 0.0);
+        let level_position = self
+            .world_map
+            .get(level.uid)
+            .map(|world_level| (world_level.origin() + placement.offset).extend(0.0))
+            .ok_or_else(|| LdtkError::NotInWorldMap(level_identifier.to_string()))?;
+
+        // everything spawned for the level goes under its root, at the world's origin
+        let root = commands
+            .spawn_bundle((Transform::identity(), GlobalTransform::identity()))
+            .insert(LdtkLevelRoot {
+                uid: level.uid,
+                identifier: level.identifier.clone(),
+                offset: placement.offset,
+            })
+            .id();
+        if let Some(parent) = placement.parent {
+            commands.entity(parent).push_children(&[root]);
+        }
+
+        // below the bottom layer
+        let base_path = self
+            .file_path
+            .parent()
+            .ok_or_else(|| LdtkError::NoParentDirectory(self.file_path.clone()))?;
+        if let Some(background) = spawn_background_image(
+            commands,
+            asset_server,
+            base_path,
+            level,
+            level_position.truncate(),
+            z_config.z("", layer_instances.len()),
+        ) {
+            commands.entity(root).push_children(&[background]);
+        }
 
         // layers
-        for layer_instance in layer_instances {
+        let mut runtime = LdtkLevelRuntime::new(
+            root,
+            level_position,
+            rapier_config.scale,
+            collider_config.clone(),
+        );
+        for (layer_index, layer_instance) in layer_instances.iter().enumerate() {
+            let layer_position = level_position
+                + Vec3::new(
+                    layer_instance.px_total_offset_x as f32,
+                    -layer_instance.px_total_offset_y as f32,
+                    0.0,
+                );
             match layer_instance.layer_instance_type.as_str() {
                 "Entities" => {
-                    for entity_instance in &layer_instance.entity_instances {
+                    for (index, entity_instance) in
+                        layer_instance.entity_instances.iter().enumerate()
+                    {
                         let position = Vec3::new(
                             entity_instance.px[0] as f32,
                             -entity_instance.px[1] as f32,
                             0.0,
-                        ) + level_position;
-                        match entity_instance.identifier.as_str() {
+                        ) + layer_position;
+                        let name = field_value(entity_instance, "name")
+                            .and_then(|field| field.as_str())
+                            .map(|s| s.to_string());
+                        let iid = entity_iid(
+                            entity_instance,
+                            level.uid,
+                            layer_instance.layer_def_uid,
+                            index,
+                        );
+                        let _span =
+                            debug_span!("entity", identifier = %entity_instance.identifier, %iid)
+                                .entered();
+                        debug!("spawning at {:?}", position);
+                        registry.insert(LdtkEntityRecord {
+                            iid: iid.clone(),
+                            identifier: entity_instance.identifier.clone(),
+                            name: name.clone(),
+                            level_uid: level.uid,
+                            position: position.truncate(),
+                        });
+                        // actors are spawned from the events, and bound to their iid
+                        let spawned = match entity_instance.identifier.as_str() {
                             "PlayerStart" => {
                                 event_writer.send(LdtkEvent::SpawnPlayer(position));
+                                None
                             }
                             "Enemy" => {
-                                let name = entity_instance
-                                    .field_instances
-                                    .iter()
-                                    .find(|field_instance| field_instance.identifier == "name")
-                                    .and_then(|field_instance| field_instance.value.as_ref())
-                                    .and_then(|field| field.as_str())
-                                    .map(|s| s.to_string())
-                                    .with_context(|| {
-                                        format!(
-                                            "no name field: {:?}",
-                                            entity_instance.field_instances
-                                        )
-                                    })?;
-                                event_writer.send(LdtkEvent::SpawnEnemy { name, position });
+                                let name = name.ok_or_else(|| LdtkError::MissingField {
+                                    entity: entity_instance.identifier.clone(),
+                                    field: "name".to_string(),
+                                })?;
+                                event_writer.send(LdtkEvent::SpawnEnemy {
+                                    name,
+                                    position,
+                                    iid: Some(iid),
+                                });
+                                None
+                            }
+                            "Npc" => {
+                                // an Array<String>, or a single String
+                                let dialogue = match field_value(entity_instance, "dialogue") {
+                                    Some(serde_json::Value::Array(lines)) => lines
+                                        .iter()
+                                        .filter_map(|line| line.as_str())
+                                        .map(|line| line.to_string())
+                                        .collect(),
+                                    Some(serde_json::Value::String(line)) => vec![line.clone()],
+                                    _ => vec![],
+                                };
+                                let radius = field_value(entity_instance, "radius")
+                                    .and_then(|value| value.as_f64())
+                                    .unwrap_or(32.0)
+                                    as f32;
+                                event_writer.send(LdtkEvent::SpawnNpc {
+                                    name: name.unwrap_or_else(|| "npc".to_string()),
+                                    position,
+                                    dialogue,
+                                    radius,
+                                    iid: Some(iid),
+                                });
+                                None
+                            }
+                            "Water" => Some(spawn_water(
+                                commands,
+                                rapier_config,
+                                &entity_rect(entity_instance, position),
+                            )),
+                            "Portal" => {
+                                let destination = entity_target(entity_instance, "destination");
+                                let preserve_velocity =
+                                    field_value(entity_instance, "preserve_velocity")
+                                        .and_then(|value| value.as_bool())
+                                        .unwrap_or(false);
+                                let portal = spawn_portal(
+                                    commands,
+                                    rapier_config,
+                                    &entity_rect(entity_instance, position),
+                                    destination,
+                                    preserve_velocity,
+                                );
+                                registry.bind(&iid, portal);
+                                Some(portal)
+                            }
+                            "WindZone" => {
+                                // pixels per second squared, y up
+                                let force = Vec2::new(
+                                    field_value(entity_instance, "force_x")
+                                        .and_then(|value| value.as_f64())
+                                        .unwrap_or(0.0) as f32,
+                                    field_value(entity_instance, "force_y")
+                                        .and_then(|value| value.as_f64())
+                                        .unwrap_or(0.0) as f32,
+                                );
+                                Some(spawn_wind_zone(
+                                    commands,
+                                    rapier_config,
+                                    &entity_rect(entity_instance, position),
+                                    force,
+                                ))
+                            }
+                            "Lever" => {
+                                let target = entity_target(entity_instance, "target");
+                                let pulled = flags.bool(&iid, "pulled").unwrap_or(false);
+                                let lever = spawn_lever(commands, position, target, pulled);
+                                registry.bind(&iid, lever);
+                                Some(lever)
+                            }
+                            "CameraZone" => {
+                                let mode = field_value(entity_instance, "mode")
+                                    .and_then(|value| value.as_str())
+                                    .map(|name| {
+                                        CameraZoneMode::from_name(name).with_context(|| {
+                                            format!("unknown camera zone mode: {}", name)
+                                        })
+                                    })
+                                    .transpose()?
+                                    .unwrap_or(CameraZoneMode::Follow);
+                                let zoom = field_value(entity_instance, "zoom")
+                                    .and_then(|value| value.as_f64())
+                                    .unwrap_or(1.0)
+                                    as f32;
+                                Some(spawn_camera_zone(
+                                    commands,
+                                    entity_rect(entity_instance, position),
+                                    mode,
+                                    zoom,
+                                ))
+                            }
+                            "Trigger" => {
+                                let id = field_value(entity_instance, "id")
+                                    .and_then(|value| value.as_str())
+                                    .map_or_else(|| iid.clone(), |id| id.to_string());
+                                Some(spawn_trigger(
+                                    commands,
+                                    rapier_config,
+                                    &entity_rect(entity_instance, position),
+                                    id,
+                                ))
+                            }
+                            "Light" => {
+                                let value = |identifier: &str, default: f64| {
+                                    field_value(entity_instance, identifier)
+                                        .and_then(|value| value.as_f64())
+                                        .unwrap_or(default)
+                                        as f32
+                                };
+                                Some(spawn_light(
+                                    commands,
+                                    position,
+                                    value("radius", 48.0),
+                                    value("flicker", 0.1),
+                                ))
+                            }
+                            "Rope" => {
+                                let rect = entity_rect(entity_instance, position);
+                                // Bool "platform", as wide as the entity
+                                let platform = field_value(entity_instance, "platform")
+                                    .and_then(|value| value.as_bool())
+                                    .unwrap_or(false)
+                                    .then(|| rect.max.x - rect.min.x);
+                                Some(spawn_rope(
+                                    commands,
+                                    rapier_config,
+                                    Vec2::new((rect.min.x + rect.max.x) * 0.5, rect.max.y),
+                                    entity_instance.height as f32,
+                                    platform,
+                                ))
                             }
-                            _ => {}
+                            "Crate" => Some(spawn_crate(
+                                commands,
+                                &entity_rect(entity_instance, position),
+                            )),
+                            "PressurePlate" => {
+                                let weight = field_value(entity_instance, "weight")
+                                    .and_then(|value| value.as_f64())
+                                    .unwrap_or(0.0)
+                                    as f32;
+                                // an array of references in "targets", or a single "target"
+                                let mut targets = field_value(entity_instance, "targets")
+                                    .and_then(|value| value.as_array())
+                                    .map(|values| {
+                                        values
+                                            .iter()
+                                            .filter_map(|value| {
+                                                serde_json::from_value(value.clone()).ok()
+                                            })
+                                            .map(LdtkTarget::Reference)
+                                            .collect::<Vec<_>>()
+                                    })
+                                    .unwrap_or_default();
+                                targets.extend(entity_target(entity_instance, "target"));
+                                let frame = entity_instance
+                                    .tile
+                                    .as_ref()
+                                    .map(|tile| {
+                                        self.tile_frame(tile, asset_server, texture_atlases)
+                                    })
+                                    .transpose()?;
+                                Some(spawn_pressure_plate(
+                                    commands,
+                                    rapier_config,
+                                    &entity_rect(entity_instance, position),
+                                    weight,
+                                    targets,
+                                    frame,
+                                ))
+                            }
+                            "Goal" => {
+                                Some(spawn_goal(commands, entity_rect(entity_instance, position)))
+                            }
+                            "Key" => Some(spawn_pickup(
+                                commands,
+                                rapier_config,
+                                position.truncate(),
+                                Vec2::ZERO,
+                                PickupKind::Key,
+                            )),
+                            "TriggerDoor" => {
+                                // opened or closed by a lever in an earlier session
+                                let open = flags.bool(&iid, "open").unwrap_or_else(|| {
+                                    field_value(entity_instance, "open")
+                                        .and_then(|value| value.as_bool())
+                                        .unwrap_or(false)
+                                });
+                                let door = spawn_trigger_door(
+                                    commands,
+                                    rapier_config,
+                                    &entity_rect(entity_instance, position),
+                                    open,
+                                );
+                                registry.bind(&iid, door);
+                                Some(door)
+                            }
+                            _ => None,
+                        };
+                        if let Some(spawned) = spawned {
+                            commands.entity(root).push_children(&[spawned]);
                         }
                     }
                 }
-                "Tiles" if layer_instance.tileset_def_uid.is_some() => {
-                    let tileset_def_uid = layer_instance.tileset_def_uid.unwrap();
+                "Tiles" => {
+                    // nothing to draw without a tileset
+                    let tileset_def_uid = match layer_instance.tileset_def_uid {
+                        Some(tileset_def_uid) => tileset_def_uid,
+                        None => continue,
+                    };
                     let texture_atlas_handle = texture_atlas_handles
                         .get(&tileset_def_uid)
-                        .with_context(|| {
-                            format!("failed to find tile identifier: {}", tileset_def_uid)
-                        })?;
+                        .ok_or(LdtkError::TilesetNotFound(tileset_def_uid))?;
 
-                    let grid_tile_offset = Vec3::new(
+                    let layer = RuntimeLayer::spawn(
+                        commands,
+                        layer_instance,
+                        level_position,
+                        z_config.z(&layer_instance.identifier, layer_index),
+                        texture_atlas_handle.clone(),
+                        tileset_collisions
+                            .get(&tileset_def_uid)
+                            .cloned()
+                            .unwrap_or_default(),
+                    );
+                    // hidden layers keep their collisions only
+                    if let Some(entity) = layer.entity() {
+                        commands.entity(root).push_children(&[entity]);
+                    }
+                    runtime.add_layer(layer);
+                }
+                "IntGrid" if layer_instance.identifier == "Collision" => {
+                    // any value is solid, for actors finding their way around
+                    let nav_grid = spawn_nav_grid(
+                        commands,
+                        layer_position.truncate(),
                         layer_instance.grid_size as f32,
-                        -layer_instance.grid_size as f32,
-                        0.0,
-                    ) * 0.5;
-
-                    // create collision bundles with debug geometry
-                    let collisions = tileset_collisions
-                        .get(&tileset_def_uid)
-                        .and_then(|tileset_collision| {
-                            let polygons = layer_instance
-                                .grid_tiles
-                                .iter()
-                                .filter_map(|grid_tile| {
-                                    let grid_tile_position =
-                                        Vec2::new(grid_tile.px[0] as f32, -grid_tile.px[1] as f32);
-                                    tileset_collision.get(&grid_tile.t).map(|collision| {
-                                        collision
-                                            .iter()
-                                            .map(|v| *v + grid_tile_position)
-                                            .collect::<Vec<_>>()
-                                    })
-                                })
-                                .collect::<Vec<_>>();
-                            merge_polygons(&polygons)
+                        layer_instance.c_wid.max(1) as usize,
+                        layer_instance
+                            .int_grid_csv
+                            .iter()
+                            .map(|value| *value != 0)
+                            .collect(),
+                    );
+                    commands.entity(root).push_children(&[nav_grid]);
+                }
+                "IntGrid" => {
+                    // only water cells so far, merged per row like the minimap
+                    let water_values = self
+                        .data
+                        .defs
+                        .layers
+                        .iter()
+                        .find(|layer_def| layer_def.uid == layer_instance.layer_def_uid)
+                        .into_iter()
+                        .flat_map(|layer_def| layer_def.int_grid_values.iter())
+                        .filter(|value| {
+                            value.identifier.as_deref().map_or(false, |identifier| {
+                                identifier.eq_ignore_ascii_case("water")
+                            })
                         })
-                        .map(|polygons| {
-                            polygons
-                                .into_iter()
-                                .map(|polygon| {
-                                    let vertices = polygon
-                                        .iter()
-                                        .map(|v| point!(v.x, v.y) / rapier_config.scale)
-                                        .collect::<Vec<_>>();
-                                    let indices = (0..polygon.len()).collect::<Vec<_>>();
-                                    let mut indices = indices
-                                        .iter()
-                                        .zip(indices.iter().skip(1))
-                                        .map(|(a, b)| [*a as u32, *b as u32])
-                                        .collect::<Vec<_>>();
-                                    indices.push([polygon.len() as u32 - 1, 0]);
-                                    (
-                                        ColliderBundle {
-                                            shape: ColliderShape::convex_decomposition_with_params(
-                                                vertices.as_slice(),
-                                                indices.as_slice(),
-                                                &VHACDParameters {
-                                                    concavity: 0.0025,
-                                                    //convex_hull_approximation: false,
-                                                    ..Default::default()
-                                                },
-                                            )
-                                            .into(),
-                                            material: COLLIDER_MATERIAL.into(),
-                                            position: (level_position / rapier_config.scale).into(),
-                                            ..Default::default()
-                                        },
-                                        GeometryBuilder::build_as(
-                                            &shapes::Polygon {
-                                                points: polygon,
-                                                closed: true,
-                                            },
-                                            DrawMode::Outlined {
-                                                fill_mode: FillMode::color(Color::rgba(
-                                                    1.0, 1.0, 1.0, 0.2,
-                                                )),
-                                                outline_mode: StrokeMode::new(
-                                                    Color::rgba(1.0, 1.0, 1.0, 1.0),
-                                                    1.0,
-                                                ),
-                                            },
-                                            Transform::from_xyz(0.0, 0.0, Z_COLLISION),
-                                        ),
-                                    )
-                                })
-                                .collect::<Vec<_>>()
-                        });
-
-                    // spawn layer
-                    commands
-                        .spawn()
-                        .insert(ColliderPositionComponent(
-                            ColliderPosition::from(level_position / rapier_config.scale).into(),
-                        ))
-                        .insert(ColliderPositionSync::Discrete)
-                        .insert(GlobalTransform::identity())
-                        .with_children(|parent| {
-                            // spawn tiles
-                            for grid_tile in &layer_instance.grid_tiles {
-                                let grid_tile_position =
-                                    Vec3::new(grid_tile.px[0] as f32, -grid_tile.px[1] as f32, 1.0)
-                                        + grid_tile_offset;
-                                let transform = Transform::from_translation(grid_tile_position);
-                                parent.spawn_bundle(SpriteSheetBundle {
-                                    texture_atlas: texture_atlas_handle.clone(),
-                                    sprite: TextureAtlasSprite {
-                                        index: grid_tile.t as usize,
-                                        ..Default::default()
-                                    },
-                                    transform,
-                                    ..Default::default()
-                                });
+                        .map(|value| value.value)
+                        .collect::<Vec<_>>();
+                    let grid_size = layer_instance.grid_size as f32;
+                    let c_wid = layer_instance.c_wid.max(1) as usize;
+                    for (y, row) in layer_instance.int_grid_csv.chunks(c_wid).enumerate() {
+                        let mut x = 0;
+                        while x < row.len() {
+                            if !water_values.contains(&row[x]) {
+                                x += 1;
+                                continue;
                             }
-                            // spawn collision
-                            if let Some(collisions) = collisions {
-                                for (collision, geometry) in collisions {
-                                    parent
-                                        .spawn_bundle(geometry)
-                                        .insert(DebugTarget)
-                                        .insert(Visibility { is_visible: false });
-                                    parent
-                                        .spawn_bundle(collision)
-                                        .insert(ColliderPositionSync::Discrete);
-                                }
+                            let start = x;
+                            while x < row.len() && water_values.contains(&row[x]) {
+                                x += 1;
                             }
-                        });
+                            let min = layer_position.truncate()
+                                + Vec2::new(
+                                    start as f32 * grid_size,
+                                    -((y + 1) as f32) * grid_size,
+                                );
+                            let max = min + Vec2::new((x - start) as f32 * grid_size, grid_size);
+                            let water =
+                                spawn_water(commands, rapier_config, &SpriteRect { min, max });
+                            commands.entity(root).push_children(&[water]);
+                        }
+                    }
                 }
-                _ => {
-                    todo!("not implemented");
+                layer_type => {
+                    return Err(LdtkError::UnsupportedLayer {
+                        level: level_identifier.to_string(),
+                        layer: layer_instance.identifier.clone(),
+                        layer_type: layer_type.to_string(),
+                    }
+                    .into());
                 }
             }
         }
+        runtime.spawn_terrain(commands);
+        commands.entity(root).insert(runtime);
+        info!("loaded {} layers", layer_instances.len());
+        Ok(root)
+    }
+    // despawns the level's root and the actors spawned for its entities, the player stays
+    pub fn unload(
+        &self,
+        level_identifier: &str,
+        commands: &mut Commands,
+        roots: &Query<(Entity, &LdtkLevelRoot)>,
+        parents: &Query<(), With<Parent>>,
+        registry: &mut LdtkEntityRegistry,
+    ) -> Result<()> {
+        let level = self
+            .data
+            .levels
+            .iter()
+            .find(|level| level.identifier == level_identifier)
+            .ok_or_else(|| LdtkError::LevelNotFound(level_identifier.to_string()))?;
+        for (root, _) in roots.iter().filter(|(_, root)| root.uid == level.uid) {
+            commands.entity(root).despawn_recursive();
+        }
+        // the rest are under the root already
+        for entity in registry.remove_level(level.uid) {
+            if parents.get(entity).is_err() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        info!("unloaded {}", level_identifier);
         Ok(())
     }
 }
 
+fn field_value<'a>(
+    entity_instance: &'a EntityInstance,
+    identifier: &str,
+) -> Option<&'a serde_json::Value> {
+    entity_instance
+        .field_instances
+        .iter()
+        .find(|field_instance| field_instance.identifier == identifier)
+        .and_then(|field_instance| field_instance.value.as_ref())
+}
+
+// value of an EntityRef field, None for other field types
+fn entity_reference(
+    entity_instance: &EntityInstance,
+    identifier: &str,
+) -> Option<EntityReferenceInfos> {
+    field_value(entity_instance, identifier)
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+}
+
+// an entity reference, or the iid or name as a string
+fn entity_target(entity_instance: &EntityInstance, identifier: &str) -> Option<LdtkTarget> {
+    entity_reference(entity_instance, identifier)
+        .map(LdtkTarget::Reference)
+        .or_else(|| {
+            field_value(entity_instance, identifier)
+                .and_then(|value| value.as_str())
+                .map(|s| LdtkTarget::Query(s.to_string()))
+        })
+}
+
+// world space bounds of a resizable entity placed at `position`
+fn entity_rect(entity_instance: &EntityInstance, position: Vec3) -> SpriteRect {
+    let size = Vec2::new(entity_instance.width as f32, entity_instance.height as f32);
+    // the pivot is from the top left, y down
+    let pivot = Vec2::new(
+        entity_instance.pivot[0] as f32,
+        1.0 - entity_instance.pivot[1] as f32,
+    );
+    let min = position.truncate() - pivot * size;
+    SpriteRect {
+        min,
+        max: min + size,
+    }
+}
+
 #[derive(Default)]
 pub struct LdtkLoader;
 
@@ -331,11 +863,7 @@ impl AssetLoader for LdtkLoader {
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
-            let data = serde_json::from_slice::<LdtkData>(bytes)?;
-            let ldtk = Ldtk {
-                data,
-                file_path: load_context.path().to_path_buf(),
-            };
+            let ldtk = Ldtk::from_bytes(bytes, load_context.path().to_path_buf())?;
             load_context.set_default_asset(LoadedAsset::new(ldtk));
             Ok(())
         })
@@ -352,23 +880,50 @@ fn on_asset_event_system(
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut commands: Commands,
     rapier_config: Res<RapierConfiguration>,
+    z_config: Res<LdtkLayerZConfig>,
+    collider_config: Res<LdtkColliderConfig>,
+    spawn_config: Res<LdtkSpawnConfig>,
     mut event_writer: EventWriter<LdtkEvent>,
+    flags: Res<WorldFlags>,
+    timings: Res<SystemTimings>,
+    mut error_events: EventWriter<AssetErrorEvent>,
 ) {
+    let _span = timings.span("ldtk");
     for event in event_asset.iter() {
         match event {
             AssetEvent::Created { handle } => {
                 if let Some(ldtk) = ldtks.get_mut(handle) {
-                    for level_name in ["Level_0"] {
-                        ldtk.load(
-                            &level_name,
+                    match ldtk.create_enums(&asset_server, &mut texture_atlases) {
+                        Ok(enums) => commands.insert_resource(enums),
+                        Err(err) => error_events.send(AssetErrorEvent::new(
+                            ldtk.file_path.display().to_string(),
+                            &err,
+                        )),
+                    }
+                    if !spawn_config.spawn_world {
+                        continue;
+                    }
+                    commands.insert_resource(ldtk.world_map.clone());
+                    // spawn every level at its world coordinates, a broken one is skipped
+                    let mut registry = LdtkEntityRegistry::default();
+                    for level in &ldtk.data.levels {
+                        if let Err(err) = ldtk.load(
+                            &level.identifier,
+                            &LevelPlacement::default(),
                             &asset_server,
                             &mut texture_atlases,
                             &mut commands,
                             &rapier_config,
+                            &z_config,
+                            &collider_config,
+                            &mut registry,
+                            &flags,
                             &mut event_writer,
-                        )
-                        .unwrap();
+                        ) {
+                            error_events.send(AssetErrorEvent::new(&level.identifier, &err));
+                        }
                     }
+                    commands.insert_resource(registry);
                 }
             }
             _ => {}
@@ -376,35 +931,546 @@ fn on_asset_event_system(
     }
 }
 
-fn merge_polygons(polygons: &Vec<Vec<Vec2>>) -> Option<Vec<Vec<Vec2>>> {
-    polygons
-        .iter()
-        .map(|polygon| {
-            geo::MultiPolygon(vec![geo::Polygon::new(
-                geo::LineString::from(
-                    polygon
-                        .iter()
-                        .map(|v| geo::Coordinate {
-                            x: v.x as f64,
-                            y: v.y as f64,
-                        })
-                        .collect::<Vec<_>>(),
-                ),
-                vec![],
-            )])
+fn despawn_level_system(
+    mut events: EventReader<DespawnLevel>,
+    handle: Option<Res<Handle<Ldtk>>>,
+    ldtks: Res<Assets<Ldtk>>,
+    mut commands: Commands,
+    roots: Query<(Entity, &LdtkLevelRoot)>,
+    parents: Query<(), With<Parent>>,
+    mut registry: ResMut<LdtkEntityRegistry>,
+) {
+    let ldtk = match handle.and_then(|handle| ldtks.get(&*handle)) {
+        Some(ldtk) => ldtk,
+        None => return,
+    };
+    for DespawnLevel(level_identifier) in events.iter() {
+        if let Err(err) = ldtk.unload(
+            level_identifier,
+            &mut commands,
+            &roots,
+            &parents,
+            &mut registry,
+        ) {
+            warn!("despawn level: {:?}", err);
+        }
+    }
+}
+
+// files that don't even parse never get an asset event, the loader's error is in the log
+fn load_state_system(
+    asset_server: Res<AssetServer>,
+    handle: Option<Res<Handle<Ldtk>>>,
+    mut reported: Local<Option<Handle<Ldtk>>>,
+    mut error_events: EventWriter<AssetErrorEvent>,
+) {
+    let handle = match handle {
+        Some(handle) => handle,
+        None => return,
+    };
+    if reported.as_ref() == Some(&*handle)
+        || asset_server.get_load_state(&*handle) != LoadState::Failed
+    {
+        return;
+    }
+    *reported = Some(handle.clone());
+    let path = asset_server.get_handle_path(&*handle).map_or_else(
+        || "ldtk".to_string(),
+        |path| path.path().display().to_string(),
+    );
+    error_events.send(AssetErrorEvent::new(
+        path,
+        &anyhow::anyhow!("failed to load, see the log"),
+    ));
+}
+
+fn level_activation_system(
+    mut world_map: ResMut<WorldMap>,
+    players: Query<&Transform, With<Player>>,
+    mut event_writer: EventWriter<LdtkEvent>,
+) {
+    if let Ok(transform) = players.get_single() {
+        let level = world_map
+            .level_at(transform.translation.truncate())
+            .map(|level| (level.uid, level.identifier.clone()));
+        if let Some((uid, identifier)) = level {
+            if world_map.active != Some(uid) {
+                world_map.active = Some(uid);
+                event_writer.send(LdtkEvent::LevelActivated(identifier));
+            }
+        }
+    }
+}
+
+// point gravity and the upright player along the active level's gravity direction
+fn level_gravity_system(
+    world_map: Res<WorldMap>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut players: Query<&mut RigidBodyPositionComponent, With<Player>>,
+) {
+    if !world_map.is_changed() {
+        return;
+    }
+    let gravity = world_map.gravity();
+    let strength = rapier_config.gravity.norm();
+    rapier_config.gravity = (gravity.down() * strength).into();
+    for mut rb_position in players.iter_mut() {
+        let rotation = Rotation::new(gravity.angle());
+        rb_position.position.rotation = rotation;
+        rb_position.next_position.rotation = rotation;
+    }
+}
+
+// the "data" custom data of a tile, either just its collision polygon in tile units or an
+// object with the polygon and a physics material, e.g.
+// {"polygon": [[0, 0], [1, 0], [1, 1], [0, 1]], "friction": 0.02} for ice, "conveyor" carries
+// whatever stands on the tile at that many pixels per second, to the left when negative,
+// "surface" ("grass" or "stone") picks the footsteps on it, "frames" animates the tile over
+// that many consecutive tiles of the tileset and "breakable" tiles are knocked out by shots
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TileCustomData {
+    Polygon(Vec<(f32, f32)>),
+    Tile {
+        polygon: Vec<(f32, f32)>,
+        friction: Option<f32>,
+        restitution: Option<f32>,
+        #[serde(default)]
+        conveyor: Option<f32>,
+        #[serde(default)]
+        surface: Option<Surface>,
+        #[serde(default)]
+        frames: Option<usize>,
+        #[serde(default)]
+        breakable: bool,
+    },
+}
+impl TileCustomData {
+    fn into_parts(
+        self,
+    ) -> (
+        Vec<(f32, f32)>,
+        ColliderMaterial,
+        Option<f32>,
+        Option<Surface>,
+        usize,
+        bool,
+    ) {
+        match self {
+            TileCustomData::Polygon(polygon) => (polygon, COLLIDER_MATERIAL, None, None, 1, false),
+            TileCustomData::Tile {
+                polygon,
+                friction,
+                restitution,
+                conveyor,
+                surface,
+                frames,
+                breakable,
+            } => (
+                polygon,
+                ColliderMaterial {
+                    friction: friction.unwrap_or(COLLIDER_MATERIAL.friction),
+                    restitution: restitution.unwrap_or(COLLIDER_MATERIAL.restitution),
+                    ..COLLIDER_MATERIAL
+                },
+                conveyor,
+                surface,
+                frames.unwrap_or(1),
+                breakable,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{debug::DebugTarget, world_clock::Tinted};
+    use bevy::{app::Events, asset::AssetPlugin, ecs::system::SystemState};
+
+    // one 64x32 level with a player, an enemy and four tiles, three of them solid
+    const FIXTURE: &[u8] = include_bytes!("fixtures/level.ldtk");
+
+    // headless, just what loading a level touches
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin)
+            .add_asset::<TextureAtlas>()
+            .add_event::<LdtkEvent>()
+            .init_resource::<LdtkColliderConfig>()
+            .insert_resource(RapierConfiguration::default());
+        app
+    }
+
+    fn fixture() -> serde_json::Value {
+        serde_json::from_slice(FIXTURE).unwrap()
+    }
+
+    fn ldtk(value: &serde_json::Value) -> Ldtk {
+        Ldtk::from_bytes(
+            &serde_json::to_vec(value).unwrap(),
+            PathBuf::from("fixtures/level.ldtk"),
+        )
+        .unwrap()
+    }
+
+    // like on_asset_event_system, with the spawned entities applied to the world
+    fn load(app: &mut App, ldtk: &Ldtk, level_identifier: &str) -> Result<Entity> {
+        load_at(app, ldtk, level_identifier, &LevelPlacement::default())
+    }
+
+    fn load_at(
+        app: &mut App,
+        ldtk: &Ldtk,
+        level_identifier: &str,
+        placement: &LevelPlacement,
+    ) -> Result<Entity> {
+        let mut state: SystemState<(
+            Res<AssetServer>,
+            ResMut<Assets<TextureAtlas>>,
+            Commands,
+            Res<RapierConfiguration>,
+            Res<LdtkColliderConfig>,
+            EventWriter<LdtkEvent>,
+        )> = SystemState::new(&mut app.world);
+        let result = {
+            let (
+                asset_server,
+                mut texture_atlases,
+                mut commands,
+                rapier_config,
+                collider_config,
+                mut event_writer,
+            ) = state.get_mut(&mut app.world);
+            ldtk.load(
+                level_identifier,
+                placement,
+                &asset_server,
+                &mut texture_atlases,
+                &mut commands,
+                &rapier_config,
+                &LdtkLayerZConfig::default(),
+                &collider_config,
+                &mut LdtkEntityRegistry::default(),
+                &WorldFlags::default(),
+                &mut event_writer,
+            )
+        };
+        state.apply(&mut app.world);
+        result
+    }
+
+    // like despawn_level_system
+    fn unload(app: &mut App, ldtk: &Ldtk, level_identifier: &str) -> Result<()> {
+        let mut state: SystemState<(
+            Commands,
+            Query<(Entity, &LdtkLevelRoot)>,
+            Query<(), With<Parent>>,
+        )> = SystemState::new(&mut app.world);
+        let result = {
+            let (mut commands, roots, parents) = state.get_mut(&mut app.world);
+            ldtk.unload(
+                level_identifier,
+                &mut commands,
+                &roots,
+                &parents,
+                &mut LdtkEntityRegistry::default(),
+            )
+        };
+        state.apply(&mut app.world);
+        result
+    }
+
+    // runs an edit on the level's runtime, like a system would
+    fn edit(
+        app: &mut App,
+        root: Entity,
+        edit: impl FnOnce(&mut LdtkLevelRuntime, &mut Commands) -> Result<(), LdtkError>,
+    ) -> Result<(), LdtkError> {
+        let mut state: SystemState<(Commands, Query<&mut LdtkLevelRuntime>)> =
+            SystemState::new(&mut app.world);
+        let result = {
+            let (mut commands, mut runtimes) = state.get_mut(&mut app.world);
+            let mut runtime = runtimes.get_mut(root).unwrap();
+            edit(&mut runtime, &mut commands)
+        };
+        state.apply(&mut app.world);
+        result
+    }
+
+    fn load_error(value: &serde_json::Value) -> LdtkError {
+        let mut app = app();
+        load(&mut app, &ldtk(value), "Fixture")
+            .unwrap_err()
+            .downcast::<LdtkError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn emits_entity_events() {
+        let mut app = app();
+        load(&mut app, &ldtk(&fixture()), "Fixture").unwrap();
+        let events = app.world.get_resource::<Events<LdtkEvent>>().unwrap();
+        let mut reader = events.get_reader();
+        let events = reader.iter(events).collect::<Vec<_>>();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[0],
+            LdtkEvent::SpawnPlayer(position) if *position == Vec3::new(16.0, -16.0, 0.0)
+        ));
+        assert!(matches!(
+            events[1],
+            LdtkEvent::SpawnEnemy { name, position, iid: Some(_) }
+                if name == "test" && *position == Vec3::new(32.0, -16.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn spawns_every_tile() {
+        let mut app = app();
+        load(&mut app, &ldtk(&fixture()), "Fixture").unwrap();
+        let mut tiles = app
+            .world
+            .query_filtered::<&TextureAtlasSprite, With<Tinted>>();
+        let mut indices = tiles
+            .iter(&app.world)
+            .map(|sprite| sprite.index)
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        assert_eq!(indices, vec![1, 17, 17, 33]);
+        // the solid neighbours merge, into a single collider with the other one
+        let mut colliders = app.world.query::<&ColliderShapeComponent>();
+        assert_eq!(colliders.iter(&app.world).count(), 1);
+        let mut outlines = app.world.query::<&DebugTarget>();
+        assert_eq!(outlines.iter(&app.world).count(), 2);
+    }
+
+    #[test]
+    fn tiles_are_in_view() {
+        let mut app = app();
+        load(&mut app, &ldtk(&fixture()), "Fixture").unwrap();
+        // what the 2d camera sees along z
+        let camera = OrthographicCameraBundle::new_2d();
+        let camera_z = camera.transform.translation.z;
+        let near = camera_z - camera.orthographic_projection.far;
+        let far = camera_z - camera.orthographic_projection.near;
+        let mut tiles = app
+            .world
+            .query_filtered::<(&Transform, &Parent), With<Tinted>>();
+        let mut layers = app.world.query::<&Transform>();
+        let z = tiles
+            .iter(&app.world)
+            .map(|(transform, parent)| {
+                layers.get(&app.world, parent.0).unwrap().translation.z + transform.translation.z
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(z.len(), 4);
+        assert!(z.iter().all(|z| (near..=far).contains(z)));
+    }
+
+    #[test]
+    fn hidden_layers_keep_collisions() {
+        let mut value = fixture();
+        value["levels"][0]["layerInstances"][1]["visible"] = false.into();
+        let mut app = app();
+        load(&mut app, &ldtk(&value), "Fixture").unwrap();
+        let mut tiles = app.world.query_filtered::<Entity, With<Tinted>>();
+        assert_eq!(tiles.iter(&app.world).count(), 0);
+        let mut colliders = app.world.query::<&ColliderShapeComponent>();
+        assert_eq!(colliders.iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn splits_colliders_by_surface() {
+        let mut value = fixture();
+        value["defs"]["tilesets"][0]["customData"][1]["data"] =
+            r#"{"polygon": [[0, 0], [1, 0], [1, 1], [0, 1]], "surface": "grass"}"#.into();
+        let mut app = app();
+        load(&mut app, &ldtk(&value), "Fixture").unwrap();
+        let mut colliders = app
+            .world
+            .query_filtered::<Option<&Surface>, With<ColliderShapeComponent>>();
+        let mut surfaces = colliders.iter(&app.world).collect::<Vec<_>>();
+        surfaces.sort_by_key(|surface| surface.is_some());
+        assert_eq!(surfaces, vec![None, Some(&Surface::Grass)]);
+    }
+
+    #[test]
+    fn spawns_polylines() {
+        let mut app = app();
+        let mut config = LdtkColliderConfig::default();
+        config
+            .overrides
+            .insert("Tiles".to_string(), TerrainColliderMode::Polyline);
+        app.insert_resource(config);
+        load(&mut app, &ldtk(&fixture()), "Fixture").unwrap();
+        // one per outline, they don't go into compounds
+        let mut colliders = app.world.query::<&ColliderShapeComponent>();
+        let shapes = colliders.iter(&app.world).collect::<Vec<_>>();
+        assert_eq!(shapes.len(), 2);
+        assert!(shapes.iter().all(|shape| shape.as_polyline().is_some()));
+    }
+
+    #[test]
+    fn spawns_at_offset() {
+        let mut app = app();
+        let parent = app
+            .world
+            .spawn()
+            .insert_bundle((Transform::identity(), GlobalTransform::identity()))
+            .id();
+        let placement = LevelPlacement {
+            offset: Vec2::new(100.0, -50.0),
+            parent: Some(parent),
+        };
+        let root = load_at(&mut app, &ldtk(&fixture()), "Fixture", &placement).unwrap();
+        assert_eq!(
+            app.world.get::<Parent>(root).map(|parent| parent.0),
+            Some(parent)
+        );
+        let events = app.world.get_resource::<Events<LdtkEvent>>().unwrap();
+        let mut reader = events.get_reader();
+        assert!(matches!(
+            reader.iter(events).next(),
+            Some(LdtkEvent::SpawnPlayer(position)) if *position == Vec3::new(116.0, -66.0, 0.0)
+        ));
+        let mut bodies = app.world.query::<&RigidBodyPositionComponent>();
+        let body = bodies.iter(&app.world).next().unwrap();
+        assert_eq!(
+            body.position.translation.vector,
+            Vector::new(100.0, -50.0) / RapierConfiguration::default().scale
+        );
+    }
+
+    #[test]
+    fn unload_despawns_everything() {
+        let mut app = app();
+        let ldtk = ldtk(&fixture());
+        let before = app.world.entities().len();
+        let root = load(&mut app, &ldtk, "Fixture").unwrap();
+        let mut roots = app.world.query::<&LdtkLevelRoot>();
+        assert_eq!(roots.get(&app.world, root).unwrap().identifier, "Fixture");
+        assert!(app.world.entities().len() > before);
+        unload(&mut app, &ldtk, "Fixture").unwrap();
+        assert_eq!(app.world.entities().len(), before);
+    }
+
+    #[test]
+    fn edits_tiles() {
+        let mut app = app();
+        let root = load(&mut app, &ldtk(&fixture()), "Fixture").unwrap();
+        let mut tiles = app.world.query_filtered::<Entity, With<Tinted>>();
+        let mut outlines = app.world.query::<&DebugTarget>();
+        // a solid tile in the gap joins all four, flipped horizontally
+        edit(&mut app, root, |runtime, commands| {
+            runtime.set_tile(commands, "Tiles", IVec2::new(2, 1), 17, 1)
         })
-        .reduce(|acc, polygon| acc.union(&polygon))
-        .map(|multi_polygon| {
-            multi_polygon
-                .0
-                .iter()
-                .map(|polygon| {
-                    let exterior = polygon.exterior();
-                    return exterior
-                        .points()
-                        .map(|p| Vec2::new(p.x() as f32, p.y() as f32))
-                        .collect::<Vec<_>>();
-                })
-                .collect::<Vec<_>>()
+        .unwrap();
+        assert_eq!(tiles.iter(&app.world).count(), 4);
+        assert_eq!(outlines.iter(&app.world).count(), 1);
+        edit(&mut app, root, |runtime, commands| {
+            runtime.remove_tile(commands, "Tiles", IVec2::new(1, 1))
         })
+        .unwrap();
+        assert_eq!(tiles.iter(&app.world).count(), 3);
+        assert_eq!(outlines.iter(&app.world).count(), 2);
+        let mut runtimes = app.world.query::<&LdtkLevelRuntime>();
+        let runtime = runtimes.get(&app.world, root).unwrap();
+        assert_eq!(runtime.tile("Tiles", IVec2::new(1, 1)), None);
+        assert_eq!(runtime.tile("Tiles", IVec2::new(2, 1)), Some(17));
+        assert_eq!(runtime.tile_flip("Tiles", IVec2::new(2, 1)), Some(1));
+        assert!(matches!(
+            edit(&mut app, root, |runtime, commands| {
+                runtime.set_tile(commands, "Tiles", IVec2::new(4, 0), 17, 0)
+            }),
+            Err(LdtkError::TileOutOfBounds { x: 4, y: 0, .. })
+        ));
+        assert!(matches!(
+            edit(&mut app, root, |runtime, commands| {
+                runtime.remove_tile(commands, "Walls", IVec2::ZERO)
+            }),
+            Err(LdtkError::LayerNotFound(layer)) if layer == "Walls"
+        ));
+    }
+
+    #[test]
+    fn finds_breakable_tiles() {
+        let mut value = fixture();
+        value["defs"]["tilesets"][0]["customData"][1]["data"] =
+            r#"{"polygon": [[0, 0], [1, 0], [1, 1], [0, 1]], "breakable": true}"#.into();
+        let mut app = app();
+        let root = load(&mut app, &ldtk(&value), "Fixture").unwrap();
+        let mut runtimes = app.world.query::<&LdtkLevelRuntime>();
+        let runtime = runtimes.get(&app.world, root).unwrap();
+        assert_eq!(
+            runtime.grid_pos("Tiles", Vec2::new(56.0, -24.0)),
+            Some(IVec2::new(3, 1))
+        );
+        assert_eq!(runtime.grid_pos("Tiles", Vec2::new(-8.0, -8.0)), None);
+        assert!(runtime.breakable("Tiles", IVec2::new(3, 1)));
+        assert!(!runtime.breakable("Tiles", IVec2::new(0, 1)));
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        let path = PathBuf::from("fixtures/level.ldtk");
+        assert!(matches!(
+            Ldtk::from_bytes(b"{", path.clone()),
+            Err(LdtkError::Parse(_))
+        ));
+        // valid json, but not an ldtk project
+        assert!(matches!(
+            Ldtk::from_bytes(br#"{"levels": []}"#, path),
+            Err(LdtkError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_missing_level() {
+        let mut app = app();
+        let err = load(&mut app, &ldtk(&fixture()), "Missing")
+            .unwrap_err()
+            .downcast::<LdtkError>()
+            .unwrap();
+        assert!(matches!(err, LdtkError::LevelNotFound(level) if level == "Missing"));
+    }
+
+    #[test]
+    fn rejects_external_levels() {
+        let mut value = fixture();
+        value["levels"][0]["layerInstances"] = serde_json::Value::Null;
+        assert!(matches!(load_error(&value), LdtkError::NoLayers(_)));
+    }
+
+    #[test]
+    fn rejects_unsupported_layers() {
+        let mut value = fixture();
+        value["levels"][0]["layerInstances"][1]["__type"] = "AutoLayer".into();
+        assert!(matches!(
+            load_error(&value),
+            LdtkError::UnsupportedLayer { layer_type, .. } if layer_type == "AutoLayer"
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_tilesets() {
+        let mut value = fixture();
+        value["levels"][0]["layerInstances"][1]["__tilesetDefUid"] = 100.into();
+        assert!(matches!(
+            load_error(&value),
+            LdtkError::TilesetNotFound(100)
+        ));
+    }
+
+    #[test]
+    fn rejects_enemies_without_name() {
+        let mut value = fixture();
+        value["levels"][0]["layerInstances"][0]["entityInstances"][1]["fieldInstances"] =
+            serde_json::Value::Array(vec![]);
+        assert!(matches!(
+            load_error(&value),
+            LdtkError::MissingField { field, .. } if field == "name"
+        ));
+    }
 }
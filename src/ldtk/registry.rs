@@ -0,0 +1,120 @@
+use super::data::{EntityInstance, EntityReferenceInfos};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct LdtkEntityRecord {
+    pub iid: String,
+    pub identifier: String,
+    // value of the "name" field, if the entity has one
+    pub name: Option<String>,
+    pub level_uid: i64,
+    // world space in pixels, y up
+    pub position: Vec2,
+}
+
+// what an entity field points at, e.g. the door of a lever
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LdtkTarget {
+    // an EntityRef field
+    Reference(EntityReferenceInfos),
+    // iid, identifier or name in a string field, see LdtkEntityRegistry::find
+    Query(String),
+}
+
+// every entity instance of the loaded levels, keyed by iid
+#[derive(Debug, Default)]
+pub struct LdtkEntityRegistry {
+    records: HashMap<String, LdtkEntityRecord>,
+    // bevy entities spawned for an iid, while they are alive
+    entities: HashMap<String, Entity>,
+}
+
+impl LdtkEntityRegistry {
+    pub fn insert(&mut self, record: LdtkEntityRecord) {
+        self.records.insert(record.iid.clone(), record);
+    }
+
+    pub fn get(&self, iid: &str) -> Option<&LdtkEntityRecord> {
+        self.records.get(iid)
+    }
+
+    // by iid first, then by identifier or name field, e.g. "Checkpoint", "boss"
+    pub fn find(&self, query: &str) -> Option<&LdtkEntityRecord> {
+        self.get(query).or_else(|| {
+            let mut records = self
+                .records
+                .values()
+                .filter(|record| {
+                    record.identifier.eq_ignore_ascii_case(query)
+                        || record
+                            .name
+                            .as_deref()
+                            .map_or(false, |name| name.eq_ignore_ascii_case(query))
+                })
+                .collect::<Vec<_>>();
+            // stable pick when several entities match
+            records.sort_by(|a, b| a.iid.cmp(&b.iid));
+            records.into_iter().next()
+        })
+    }
+
+    pub fn resolve(&self, target: &LdtkTarget) -> Option<&LdtkEntityRecord> {
+        match target {
+            LdtkTarget::Reference(reference) => self.get(&reference.entity_iid),
+            LdtkTarget::Query(query) => self.find(query),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LdtkEntityRecord> {
+        self.records.values()
+    }
+
+    pub fn bind(&mut self, iid: &str, entity: Entity) {
+        self.entities.insert(iid.to_string(), entity);
+    }
+
+    pub fn unbind(&mut self, entity: Entity) {
+        self.entities.retain(|_, bound| *bound != entity);
+    }
+
+    // forgets the level's records, returning the entities still bound to them
+    pub fn remove_level(&mut self, level_uid: i64) -> Vec<Entity> {
+        let iids = self
+            .records
+            .values()
+            .filter(|record| record.level_uid == level_uid)
+            .map(|record| record.iid.clone())
+            .collect::<Vec<_>>();
+        iids.iter()
+            .filter_map(|iid| {
+                self.records.remove(iid);
+                self.entities.remove(iid)
+            })
+            .collect()
+    }
+
+    pub fn entity(&self, iid: &str) -> Option<Entity> {
+        self.entities.get(iid).copied()
+    }
+
+    pub fn iid(&self, entity: Entity) -> Option<&str> {
+        self.entities
+            .iter()
+            .find(|(_, bound)| **bound == entity)
+            .map(|(iid, _)| iid.as_str())
+    }
+}
+
+// projects exported before ldtk 1.0 have no iids, so fall back to the position in the file
+pub fn entity_iid(
+    entity_instance: &EntityInstance,
+    level_uid: i64,
+    layer_def_uid: i64,
+    index: usize,
+) -> String {
+    entity_instance
+        .iid
+        .clone()
+        .unwrap_or_else(|| format!("{}-{}-{}", level_uid, layer_def_uid, index))
+}
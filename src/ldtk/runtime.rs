@@ -0,0 +1,887 @@
+use super::{
+    data::LayerInstance,
+    error::LdtkError,
+    plugin::{LdtkColliderConfig, TerrainColliderMode},
+};
+use crate::{
+    collision::CollisionLayers,
+    conveyor::{AnimatedTile, Conveyor},
+    debug::{DebugGroup, DebugTarget},
+    footsteps::Surface,
+    world_clock::Tinted,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::{prelude::*, rapier::parry::transformation::vhacd::VHACDParameters};
+use geo_booleanop::boolean::BooleanOp;
+use std::{collections::HashMap, sync::Arc};
+
+const FLIP_X: i64 = 1;
+const FLIP_Y: i64 = 2;
+const Z_COLLISION: f32 = 10.0;
+// in tiles, the terrain is merged and decomposed per chunk so an edit only redoes its own
+const CHUNK_SIZE: i32 = 16;
+
+pub(super) struct TileCollision {
+    pub(super) polygon: Vec<Vec2>,
+    pub(super) material: ColliderMaterial,
+    // pixels per second, see TileCustomData
+    pub(super) conveyor: Option<f32>,
+    pub(super) surface: Option<Surface>,
+    pub(super) frames: usize,
+    pub(super) breakable: bool,
+}
+
+// what sets a collider apart besides its shape, tiles only merge with tiles of the same kind
+#[derive(Clone, Copy)]
+struct TerrainKind {
+    material: ColliderMaterial,
+    conveyor: Option<f32>,
+    surface: Option<Surface>,
+}
+impl TerrainKind {
+    fn same(&self, other: &TerrainKind) -> bool {
+        same_material(&self.material, &other.material)
+            && self.conveyor == other.conveyor
+            && self.surface == other.surface
+    }
+}
+
+struct RuntimeTile {
+    id: i64,
+    flip: i64,
+    sprite: Option<Entity>,
+}
+
+// a tile layer as spawned, with the tiles ldtk stacks in a cell
+pub(super) struct RuntimeLayer {
+    identifier: String,
+    // parent of the sprites, hidden layers have none
+    entity: Option<Entity>,
+    grid_size: f32,
+    width: i32,
+    height: i32,
+    // from the level's top left corner
+    offset: Vec2,
+    z: f32,
+    color: Color,
+    texture_atlas: Handle<TextureAtlas>,
+    // by tile id, shared by the layers of a tileset
+    collisions: Arc<HashMap<i64, TileCollision>>,
+    // row by row from the top
+    cells: Vec<Vec<RuntimeTile>>,
+}
+impl RuntimeLayer {
+    // spawns the layer and its tiles, or only keeps them for the collisions of hidden layers
+    pub(super) fn spawn(
+        commands: &mut Commands,
+        layer_instance: &LayerInstance,
+        level_position: Vec3,
+        z: f32,
+        texture_atlas: Handle<TextureAtlas>,
+        collisions: Arc<HashMap<i64, TileCollision>>,
+    ) -> Self {
+        let offset = Vec2::new(
+            layer_instance.px_total_offset_x as f32,
+            -layer_instance.px_total_offset_y as f32,
+        );
+        let entity = layer_instance.visible.then(|| {
+            commands
+                .spawn_bundle((
+                    Transform::from_translation(level_position + offset.extend(0.0)),
+                    GlobalTransform::identity(),
+                ))
+                .id()
+        });
+        let width = layer_instance.c_wid.max(1) as i32;
+        let height = layer_instance.c_hei.max(1) as i32;
+        let mut layer = RuntimeLayer {
+            identifier: layer_instance.identifier.clone(),
+            entity,
+            grid_size: layer_instance.grid_size as f32,
+            width,
+            height,
+            offset,
+            z,
+            color: Color::rgba(1.0, 1.0, 1.0, layer_instance.opacity as f32),
+            texture_atlas,
+            collisions,
+            cells: (0..width * height).map(|_| vec![]).collect(),
+        };
+        for grid_tile in layer_instance.grid_tiles.iter() {
+            let grid_pos = IVec2::new(
+                grid_tile.px[0] as i32 / layer_instance.grid_size as i32,
+                grid_tile.px[1] as i32 / layer_instance.grid_size as i32,
+            );
+            layer.push_tile(commands, grid_pos, grid_tile.t, grid_tile.f);
+        }
+        layer
+    }
+    pub(super) fn entity(&self) -> Option<Entity> {
+        self.entity
+    }
+    fn index(&self, grid_pos: IVec2) -> Option<usize> {
+        (grid_pos.x >= 0 && grid_pos.y >= 0 && grid_pos.x < self.width && grid_pos.y < self.height)
+            .then(|| (grid_pos.y * self.width + grid_pos.x) as usize)
+    }
+    fn push_tile(&mut self, commands: &mut Commands, grid_pos: IVec2, id: i64, flip: i64) {
+        let index = match self.index(grid_pos) {
+            Some(index) => index,
+            None => return,
+        };
+        let sprite = self.entity.map(|layer| {
+            let position = Vec3::new(
+                (grid_pos.x as f32 + 0.5) * self.grid_size,
+                -(grid_pos.y as f32 + 0.5) * self.grid_size,
+                self.z,
+            );
+            let mut tile = commands.spawn_bundle(SpriteSheetBundle {
+                texture_atlas: self.texture_atlas.clone(),
+                sprite: TextureAtlasSprite {
+                    index: id as usize,
+                    flip_x: flip & FLIP_X != 0,
+                    flip_y: flip & FLIP_Y != 0,
+                    color: self.color,
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(position),
+                ..Default::default()
+            });
+            tile.insert(Tinted(self.color));
+            let frames = self
+                .collisions
+                .get(&id)
+                .map_or(1, |collision| collision.frames);
+            if frames > 1 {
+                tile.insert(AnimatedTile {
+                    first: id as usize,
+                    frames,
+                });
+            }
+            let tile = tile.id();
+            commands.entity(layer).push_children(&[tile]);
+            tile
+        });
+        self.cells[index].push(RuntimeTile { id, flip, sprite });
+    }
+    fn chunks(&self) -> impl Iterator<Item = IVec2> {
+        let width = (self.width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let height = (self.height + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        (0..height).flat_map(move |y| (0..width).map(move |x| IVec2::new(x, y)))
+    }
+    // merged outlines of the chunk's tiles in level pixels
+    fn chunk_outlines(&self, chunk: IVec2) -> Vec<(TerrainKind, Outline)> {
+        let mut groups: Vec<(TerrainKind, Vec<Vec<Vec2>>)> = vec![];
+        let min = chunk * CHUNK_SIZE;
+        let max = (min + IVec2::splat(CHUNK_SIZE)).min(IVec2::new(self.width, self.height));
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                for tile in self.cells[(y * self.width + x) as usize].iter() {
+                    let collision = match self.collisions.get(&tile.id) {
+                        Some(collision) => collision,
+                        None => continue,
+                    };
+                    let tile_position = Vec2::new(x as f32, -y as f32) * self.grid_size;
+                    let polygon = flip_polygon(&collision.polygon, tile.flip, self.grid_size)
+                        .into_iter()
+                        .map(|v| v + tile_position)
+                        .collect::<Vec<_>>();
+                    // flipped tiles run the other way
+                    let conveyor = collision.conveyor.map(|speed| {
+                        if tile.flip & FLIP_X != 0 {
+                            -speed
+                        } else {
+                            speed
+                        }
+                    });
+                    let kind = TerrainKind {
+                        material: collision.material,
+                        conveyor,
+                        surface: collision.surface,
+                    };
+                    match groups.iter_mut().find(|(other, _)| other.same(&kind)) {
+                        Some((_, polygons)) => polygons.push(polygon),
+                        None => groups.push((kind, vec![polygon])),
+                    }
+                }
+            }
+        }
+        let offset = self.offset;
+        groups
+            .into_iter()
+            .flat_map(|(kind, polygons)| {
+                merge_polygons(&polygons)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(move |outline| {
+                        let outline =
+                            outline.map(|ring| ring.iter().map(|v| *v + offset).collect());
+                        (kind, outline)
+                    })
+            })
+            .collect()
+    }
+}
+
+struct ChunkCollider {
+    kind: TerrainKind,
+    shape: TerrainShape,
+    outline: Outline,
+}
+
+#[derive(Clone)]
+enum TerrainShape {
+    Convex(Vec<(Isometry<Real>, ColliderShape)>),
+    Polyline(ColliderShape),
+}
+
+// the tiles and terrain of a spawned level, on its root. editing a tile swaps its sprite and
+// decomposes the terrain of its chunk again, for bombable walls or blocks built by the player
+#[derive(Component)]
+pub struct LdtkLevelRuntime {
+    root: Entity,
+    // top left corner
+    position: Vec3,
+    // rapier's
+    scale: f32,
+    config: LdtkColliderConfig,
+    layers: Vec<RuntimeLayer>,
+    // by layer index and chunk
+    chunks: HashMap<(usize, IVec2), Vec<ChunkCollider>>,
+    terrain: Option<Entity>,
+}
+impl LdtkLevelRuntime {
+    pub(super) fn new(
+        root: Entity,
+        position: Vec3,
+        scale: f32,
+        config: LdtkColliderConfig,
+    ) -> Self {
+        LdtkLevelRuntime {
+            root,
+            position,
+            scale,
+            config,
+            layers: vec![],
+            chunks: HashMap::new(),
+            terrain: None,
+        }
+    }
+    pub(super) fn add_layer(&mut self, layer: RuntimeLayer) {
+        let layer_index = self.layers.len();
+        let chunks = layer.chunks().collect::<Vec<_>>();
+        self.layers.push(layer);
+        for chunk in chunks {
+            self.build_chunk(layer_index, chunk);
+        }
+    }
+    pub fn layers(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|layer| layer.identifier.as_str())
+    }
+    // the top tile of a cell
+    pub fn tile(&self, layer: &str, grid_pos: IVec2) -> Option<i64> {
+        let layer = self.layers.iter().find(|other| other.identifier == layer)?;
+        layer.cells[layer.index(grid_pos)?]
+            .last()
+            .map(|tile| tile.id)
+    }
+    // the flip bits of the top tile of a cell
+    pub fn tile_flip(&self, layer: &str, grid_pos: IVec2) -> Option<i64> {
+        let layer = self.layers.iter().find(|other| other.identifier == layer)?;
+        layer.cells[layer.index(grid_pos)?]
+            .last()
+            .map(|tile| tile.flip)
+    }
+    // the cell of a layer under a world position
+    pub fn grid_pos(&self, layer: &str, position: Vec2) -> Option<IVec2> {
+        let layer = self.layers.iter().find(|other| other.identifier == layer)?;
+        let local = (position - self.position.truncate() - layer.offset) / layer.grid_size;
+        let grid_pos = IVec2::new(local.x.floor() as i32, (-local.y).floor() as i32);
+        layer.index(grid_pos).map(|_| grid_pos)
+    }
+    // whether the top tile of the cell has "breakable" custom data
+    pub fn breakable(&self, layer: &str, grid_pos: IVec2) -> bool {
+        let collisions = match self.layers.iter().find(|other| other.identifier == layer) {
+            Some(layer) => &layer.collisions,
+            None => return false,
+        };
+        self.tile(layer, grid_pos)
+            .and_then(|id| collisions.get(&id))
+            .map_or(false, |collision| collision.breakable)
+    }
+    // replaces every tile of the cell, flipped by ldtk's flip bits
+    pub fn set_tile(
+        &mut self,
+        commands: &mut Commands,
+        layer: &str,
+        grid_pos: IVec2,
+        tile_id: i64,
+        flip: i64,
+    ) -> Result<(), LdtkError> {
+        self.replace_tiles(commands, layer, grid_pos, Some((tile_id, flip)))
+    }
+    pub fn remove_tile(
+        &mut self,
+        commands: &mut Commands,
+        layer: &str,
+        grid_pos: IVec2,
+    ) -> Result<(), LdtkError> {
+        self.replace_tiles(commands, layer, grid_pos, None)
+    }
+    fn replace_tiles(
+        &mut self,
+        commands: &mut Commands,
+        layer: &str,
+        grid_pos: IVec2,
+        tile: Option<(i64, i64)>,
+    ) -> Result<(), LdtkError> {
+        let layer_index = self
+            .layers
+            .iter()
+            .position(|other| other.identifier == layer)
+            .ok_or_else(|| LdtkError::LayerNotFound(layer.to_string()))?;
+        let runtime_layer = &mut self.layers[layer_index];
+        let index = runtime_layer
+            .index(grid_pos)
+            .ok_or_else(|| LdtkError::TileOutOfBounds {
+                layer: layer.to_string(),
+                x: grid_pos.x,
+                y: grid_pos.y,
+            })?;
+        let old_tiles = std::mem::take(&mut runtime_layer.cells[index]);
+        // the terrain only changes when a tile with collision comes or goes
+        let solid = old_tiles
+            .iter()
+            .map(|tile| tile.id)
+            .chain(tile.map(|(id, _)| id))
+            .any(|id| runtime_layer.collisions.contains_key(&id));
+        for tile in old_tiles {
+            if let Some(sprite) = tile.sprite {
+                commands.entity(sprite).despawn();
+            }
+        }
+        if let Some((id, flip)) = tile {
+            runtime_layer.push_tile(commands, grid_pos, id, flip);
+        }
+        if solid {
+            self.build_chunk(layer_index, grid_pos / CHUNK_SIZE);
+            self.spawn_terrain(commands);
+        }
+        Ok(())
+    }
+    fn build_chunk(&mut self, layer_index: usize, chunk: IVec2) {
+        let mode = self.config.mode(&self.layers[layer_index].identifier);
+        let colliders = self.layers[layer_index]
+            .chunk_outlines(chunk)
+            .into_iter()
+            .map(|(kind, outline)| {
+                // fewer points for the decomposition, without the seams of every tile corner
+                let outline =
+                    outline.map(|ring| simplify_polygon(ring, self.config.simplify_epsilon));
+                let shape = match mode {
+                    TerrainColliderMode::ConvexDecomposition => {
+                        TerrainShape::Convex(outline_shapes(&outline, self.scale))
+                    }
+                    TerrainColliderMode::Polyline => {
+                        TerrainShape::Polyline(polyline_shape(&outline, self.scale))
+                    }
+                };
+                ChunkCollider {
+                    kind,
+                    shape,
+                    outline,
+                }
+            })
+            .collect::<Vec<_>>();
+        if colliders.is_empty() {
+            self.chunks.remove(&(layer_index, chunk));
+        } else {
+            self.chunks.insert((layer_index, chunk), colliders);
+        }
+    }
+    // one body for all chunks, replacing the one from before an edit
+    pub(super) fn spawn_terrain(&mut self, commands: &mut Commands) {
+        if let Some(terrain) = self.terrain.take() {
+            commands.entity(terrain).despawn_recursive();
+        }
+        let mut terrain = LevelTerrain::default();
+        for collider in self.chunks.values().flatten() {
+            terrain.add(collider.kind, collider.shape.clone());
+            terrain.geometry.push(collider.outline.clone());
+        }
+        self.terrain = terrain.spawn(commands, self.position, self.scale);
+        if let Some(terrain) = self.terrain {
+            commands.entity(self.root).push_children(&[terrain]);
+        }
+    }
+}
+
+// the collision of every tile layer of a level, one static body with a compound collider per
+// kind instead of an entity per merged polygon
+#[derive(Default)]
+struct LevelTerrain {
+    colliders: Vec<(TerrainKind, Vec<(Isometry<Real>, ColliderShape)>)>,
+    // a collider each, compounds can't hold polylines
+    polylines: Vec<(TerrainKind, ColliderShape)>,
+    // outlines in level pixels, drawn by the terrain debug view
+    geometry: Vec<Outline>,
+}
+impl LevelTerrain {
+    fn add(&mut self, kind: TerrainKind, shape: TerrainShape) {
+        let shapes = match shape {
+            TerrainShape::Convex(shapes) => shapes,
+            TerrainShape::Polyline(shape) => {
+                self.polylines.push((kind, shape));
+                return;
+            }
+        };
+        match self
+            .colliders
+            .iter_mut()
+            .find(|(other, _)| other.same(&kind))
+        {
+            Some((_, colliders)) => colliders.extend(shapes),
+            None => self.colliders.push((kind, shapes)),
+        }
+    }
+    fn spawn(self, commands: &mut Commands, level_position: Vec3, scale: f32) -> Option<Entity> {
+        if self.colliders.is_empty() && self.polylines.is_empty() {
+            return None;
+        }
+        let body = commands
+            .spawn_bundle(RigidBodyBundle {
+                body_type: RigidBodyType::Static.into(),
+                position: (level_position.truncate() / scale).into(),
+                ..Default::default()
+            })
+            .insert_bundle((
+                Transform::from_translation(level_position),
+                GlobalTransform::identity(),
+            ))
+            .with_children(|parent| {
+                let body = parent.parent_entity();
+                for outline in self.geometry {
+                    let mut path_builder = PathBuilder::new();
+                    for ring in outline.rings() {
+                        path_builder.move_to(ring[0]);
+                        for point in &ring[1..] {
+                            path_builder.line_to(*point);
+                        }
+                        path_builder.close();
+                    }
+                    parent
+                        .spawn_bundle(GeometryBuilder::build_as(
+                            &path_builder.build(),
+                            DrawMode::Outlined {
+                                // holes stay empty
+                                fill_mode: FillMode {
+                                    options: FillOptions::even_odd(),
+                                    color: Color::rgba(1.0, 1.0, 1.0, 0.2),
+                                },
+                                outline_mode: StrokeMode::new(Color::rgba(1.0, 1.0, 1.0, 1.0), 1.0),
+                            },
+                            Transform::from_xyz(0.0, 0.0, Z_COLLISION),
+                        ))
+                        .insert(DebugTarget(DebugGroup::TerrainColliders))
+                        .insert(Visibility { is_visible: false });
+                }
+                let colliders = self
+                    .colliders
+                    .into_iter()
+                    .map(|(kind, shapes)| (kind, ColliderShape::compound(shapes)))
+                    .chain(self.polylines);
+                for (kind, shape) in colliders {
+                    let mut collider = parent.spawn_bundle(ColliderBundle {
+                        shape: shape.into(),
+                        material: kind.material.into(),
+                        flags: ColliderFlags {
+                            collision_groups: CollisionLayers::terrain(),
+                            ..Default::default()
+                        }
+                        .into(),
+                        ..Default::default()
+                    });
+                    collider.insert(ColliderParentComponent(ColliderParent {
+                        handle: body.handle(),
+                        pos_wrt_parent: Isometry::identity(),
+                    }));
+                    if let Some(speed) = kind.conveyor {
+                        collider.insert(Conveyor { speed });
+                    }
+                    if let Some(surface) = kind.surface {
+                        collider.insert(surface);
+                    }
+                }
+            })
+            .id();
+        Some(body)
+    }
+}
+
+// the convex parts of an outline in pixels, in physics units
+fn outline_shapes(outline: &Outline, scale: f32) -> Vec<(Isometry<Real>, ColliderShape)> {
+    if outline.holes.is_empty() {
+        return polygon_shapes(&outline.exterior, scale);
+    }
+    // the decomposition fills holes, it gets the pieces around them instead
+    split_holes(outline.polygon())
+        .iter()
+        .flat_map(|piece| polygon_shapes(&remove_collinear(&ring_points(piece.exterior())), scale))
+        .collect()
+}
+
+// hole free pieces of a polygon, cut through its holes
+fn split_holes(polygon: geo::Polygon<f64>) -> Vec<geo::Polygon<f64>> {
+    let hole = match polygon.interiors().first() {
+        Some(hole) => hole,
+        None => return vec![polygon],
+    };
+    let bounds = |ring: &geo::LineString<f64>| {
+        ring.points().fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(min_x, min_y, max_x, max_y), point| {
+                (
+                    min_x.min(point.x()),
+                    min_y.min(point.y()),
+                    max_x.max(point.x()),
+                    max_y.max(point.y()),
+                )
+            },
+        )
+    };
+    // a vertical line through the middle of the hole crosses it
+    let (hole_min_x, _, hole_max_x, _) = bounds(hole);
+    let x = (hole_min_x + hole_max_x) * 0.5;
+    let (min_x, min_y, max_x, max_y) = bounds(polygon.exterior());
+    let rect = |min_x: f64, max_x: f64| {
+        geo::MultiPolygon(vec![geo::Polygon::new(
+            geo::LineString::from(vec![
+                (min_x, min_y - 1.0),
+                (max_x, min_y - 1.0),
+                (max_x, max_y + 1.0),
+                (min_x, max_y + 1.0),
+                (min_x, min_y - 1.0),
+            ]),
+            vec![],
+        )])
+    };
+    let polygon = geo::MultiPolygon(vec![polygon]);
+    let left = polygon.intersection(&rect(min_x - 1.0, x));
+    let right = polygon.intersection(&rect(x, max_x + 1.0));
+    left.0
+        .into_iter()
+        .chain(right.0)
+        .flat_map(split_holes)
+        .collect()
+}
+
+// the convex parts of a polygon in pixels, in physics units
+fn polygon_shapes(polygon: &[Vec2], scale: f32) -> Vec<(Isometry<Real>, ColliderShape)> {
+    // nothing left of a ring whose points were all on one line
+    if polygon.len() < 3 {
+        return vec![];
+    }
+    let vertices = polygon
+        .iter()
+        .map(|v| point!(v.x, v.y) / scale)
+        .collect::<Vec<_>>();
+    let indices = (0..polygon.len()).collect::<Vec<_>>();
+    let mut indices = indices
+        .iter()
+        .zip(indices.iter().skip(1))
+        .map(|(a, b)| [*a as u32, *b as u32])
+        .collect::<Vec<_>>();
+    indices.push([polygon.len() as u32 - 1, 0]);
+    let shape = ColliderShape::convex_decomposition_with_params(
+        vertices.as_slice(),
+        indices.as_slice(),
+        &VHACDParameters {
+            concavity: 0.0025,
+            //convex_hull_approximation: false,
+            ..Default::default()
+        },
+    );
+    // compounds don't nest, the level's terrain takes the parts
+    match shape.as_compound() {
+        Some(compound) => compound.shapes().to_vec(),
+        None => vec![(Isometry::identity(), shape)],
+    }
+}
+
+// the closed rings of an outline in pixels, in physics units
+fn polyline_shape(outline: &Outline, scale: f32) -> ColliderShape {
+    let mut vertices = vec![];
+    let mut indices = vec![];
+    for ring in outline.rings() {
+        let start = vertices.len() as u32;
+        let len = ring.len() as u32;
+        vertices.extend(ring.iter().map(|v| point!(v.x, v.y) / scale));
+        indices.extend((0..len).map(|i| [start + i, start + (i + 1) % len]));
+    }
+    ColliderShape::polyline(vertices, Some(indices))
+}
+
+fn same_material(a: &ColliderMaterial, b: &ColliderMaterial) -> bool {
+    a.friction == b.friction && a.restitution == b.restitution
+}
+
+fn flip_polygon(polygon: &[Vec2], flip_bits: i64, size: f32) -> Vec<Vec2> {
+    let flip_x = flip_bits & FLIP_X != 0;
+    let flip_y = flip_bits & FLIP_Y != 0;
+    let mut polygon = polygon
+        .iter()
+        .map(|v| {
+            Vec2::new(
+                if flip_x { size - v.x } else { v.x },
+                if flip_y { -size - v.y } else { v.y },
+            )
+        })
+        .collect::<Vec<_>>();
+    // keep the winding order
+    if flip_x != flip_y {
+        polygon.reverse();
+    }
+    polygon
+}
+
+// a closed polygon without repeated points, the ones on straight edges and the ones within
+// epsilon of the outline without them
+fn simplify_polygon(polygon: &[Vec2], epsilon: f32) -> Vec<Vec2> {
+    let polygon = remove_collinear(polygon);
+    if polygon.len() <= 3 || epsilon <= 0.0 {
+        return polygon;
+    }
+    // split the ring at the point farthest from the first one, and close it
+    let farthest = (1..polygon.len())
+        .max_by(|a, b| {
+            let a = polygon[*a].distance_squared(polygon[0]);
+            let b = polygon[*b].distance_squared(polygon[0]);
+            a.total_cmp(&b)
+        })
+        .unwrap();
+    let mut ring = polygon.clone();
+    ring.push(polygon[0]);
+    let mut simplified = vec![];
+    douglas_peucker(&ring[..=farthest], epsilon, &mut simplified);
+    douglas_peucker(&ring[farthest..], epsilon, &mut simplified);
+    if simplified.len() < 3 {
+        return polygon;
+    }
+    simplified
+}
+
+fn remove_collinear(polygon: &[Vec2]) -> Vec<Vec2> {
+    let mut polygon = polygon.to_vec();
+    // geo closes its rings
+    if polygon.len() > 1 && polygon.first() == polygon.last() {
+        polygon.pop();
+    }
+    loop {
+        let len = polygon.len();
+        if len <= 3 {
+            return polygon;
+        }
+        let keep = (0..len)
+            .map(|i| {
+                let previous = polygon[(i + len - 1) % len];
+                let next = polygon[(i + 1) % len];
+                (polygon[i] - previous).perp_dot(next - polygon[i]).abs() > 1e-3
+            })
+            .collect::<Vec<_>>();
+        if keep.iter().all(|keep| *keep) {
+            return polygon;
+        }
+        polygon = polygon
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(point, keep)| keep.then(|| point))
+            .collect();
+    }
+}
+
+// pushes the points of an open polyline that stay after simplifying it, except its last one
+fn douglas_peucker(points: &[Vec2], epsilon: f32, simplified: &mut Vec<Vec2>) {
+    let first = points[0];
+    let last = points[points.len() - 1];
+    let line = last - first;
+    let distance = |point: Vec2| {
+        if line == Vec2::ZERO {
+            point.distance(first)
+        } else {
+            line.perp_dot(point - first).abs() / line.length()
+        }
+    };
+    let farthest =
+        (1..points.len() - 1).max_by(|a, b| distance(points[*a]).total_cmp(&distance(points[*b])));
+    match farthest {
+        Some(farthest) if distance(points[farthest]) > epsilon => {
+            douglas_peucker(&points[..=farthest], epsilon, simplified);
+            douglas_peucker(&points[farthest..], epsilon, simplified);
+        }
+        _ => simplified.push(first),
+    }
+}
+
+// a merged polygon, with the holes of e.g. donut shaped terrain
+#[derive(Clone)]
+struct Outline {
+    exterior: Vec<Vec2>,
+    holes: Vec<Vec<Vec2>>,
+}
+impl Outline {
+    fn rings(&self) -> impl Iterator<Item = &Vec<Vec2>> {
+        std::iter::once(&self.exterior).chain(self.holes.iter())
+    }
+    // holes left with less than 3 points are dropped
+    fn map(self, f: impl Fn(&[Vec2]) -> Vec<Vec2>) -> Self {
+        Outline {
+            exterior: f(&self.exterior),
+            holes: self
+                .holes
+                .iter()
+                .map(|hole| f(hole))
+                .filter(|hole| hole.len() >= 3)
+                .collect(),
+        }
+    }
+    fn polygon(&self) -> geo::Polygon<f64> {
+        geo::Polygon::new(
+            line_string(&self.exterior),
+            self.holes.iter().map(|hole| line_string(hole)).collect(),
+        )
+    }
+}
+
+fn line_string(ring: &[Vec2]) -> geo::LineString<f64> {
+    geo::LineString::from(
+        ring.iter()
+            .map(|v| geo::Coordinate {
+                x: v.x as f64,
+                y: v.y as f64,
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn ring_points(ring: &geo::LineString<f64>) -> Vec<Vec2> {
+    ring.points()
+        .map(|p| Vec2::new(p.x() as f32, p.y() as f32))
+        .collect()
+}
+
+fn merge_polygons(polygons: &Vec<Vec<Vec2>>) -> Option<Vec<Outline>> {
+    polygons
+        .iter()
+        .map(|polygon| geo::MultiPolygon(vec![geo::Polygon::new(line_string(polygon), vec![])]))
+        .reduce(|acc, polygon| acc.union(&polygon))
+        .map(|multi_polygon| {
+            multi_polygon
+                .0
+                .iter()
+                .map(|polygon| Outline {
+                    exterior: ring_points(polygon.exterior()),
+                    holes: polygon.interiors().iter().map(ring_points).collect(),
+                })
+                .collect::<Vec<_>>()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_rapier2d::rapier::parry::query::PointQuery;
+
+    #[test]
+    fn simplifies_outlines() {
+        // two tiles as geo closes them, with the corner between them and a slight bump
+        let polygon = [
+            (0.0, 0.0),
+            (16.0, 0.0),
+            (32.0, 0.0),
+            (32.0, -16.0),
+            (16.0, -16.4),
+            (0.0, -16.0),
+            (0.0, 0.0),
+        ]
+        .into_iter()
+        .map(|(x, y)| Vec2::new(x, y))
+        .collect::<Vec<_>>();
+        assert_eq!(simplify_polygon(&polygon, 0.0).len(), 5);
+        assert_eq!(
+            simplify_polygon(&polygon, 1.0),
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(32.0, 0.0),
+                Vec2::new(32.0, -16.0),
+                Vec2::new(0.0, -16.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_holes() {
+        // a ring of eight tiles around an empty one
+        let squares = (0..9)
+            .filter(|i| *i != 4)
+            .map(|i| {
+                let corner = Vec2::new((i % 3) as f32, -((i / 3) as f32)) * 16.0;
+                [(0.0, 0.0), (16.0, 0.0), (16.0, -16.0), (0.0, -16.0)]
+                    .into_iter()
+                    .map(|(x, y)| corner + Vec2::new(x, y))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let outlines = merge_polygons(&squares).unwrap();
+        assert_eq!(outlines.len(), 1);
+        assert_eq!(outlines[0].holes.len(), 1);
+        let shapes = outline_shapes(&outlines[0], 1.0);
+        let solid = |v: Vec2| {
+            shapes
+                .iter()
+                .any(|(position, shape)| shape.contains_point(position, &point!(v.x, v.y)))
+        };
+        assert!(solid(Vec2::new(8.0, -8.0)));
+        assert!(!solid(Vec2::new(24.0, -24.0)));
+        let outline = outlines[0].clone().map(|ring| simplify_polygon(ring, 0.0));
+        assert_eq!(
+            polyline_shape(&outline, 1.0)
+                .as_polyline()
+                .unwrap()
+                .num_segments(),
+            8
+        );
+    }
+
+    #[test]
+    fn skips_flat_rings() {
+        // a strip of three tiles as geo closes it, then squashed onto its top edge
+        let strip = [
+            (0.0, 0.0),
+            (16.0, 0.0),
+            (32.0, 0.0),
+            (48.0, 0.0),
+            (48.0, -16.0),
+            (32.0, -16.0),
+            (16.0, -16.0),
+            (0.0, -16.0),
+            (0.0, 0.0),
+        ]
+        .into_iter()
+        .map(|(x, y)| Vec2::new(x, y))
+        .collect::<Vec<_>>();
+        let ring = remove_collinear(&strip);
+        assert_eq!(ring.len(), 4);
+        let shapes = polygon_shapes(&ring, 1.0);
+        assert!(shapes
+            .iter()
+            .any(|(position, shape)| shape.contains_point(position, &point!(40.0, -8.0))));
+
+        let flat = strip
+            .iter()
+            .map(|v| Vec2::new(v.x, 0.0))
+            .collect::<Vec<_>>();
+        let ring = remove_collinear(&flat);
+        assert!(ring.len() < 3);
+        assert!(polygon_shapes(&ring, 1.0).is_empty());
+    }
+}
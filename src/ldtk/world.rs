@@ -0,0 +1,319 @@
+use super::{
+    background::parse_color,
+    data::{LdtkData, Level, WorldLayout},
+};
+use bevy::{prelude::*, sprite::Rect};
+
+// direction of "down" in a level, from its "gravity" field (String or Enum)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GravityDirection {
+    Down,
+    Left,
+    Right,
+    Up,
+}
+impl Default for GravityDirection {
+    fn default() -> Self {
+        GravityDirection::Down
+    }
+}
+impl GravityDirection {
+    fn from_level(level: &Level) -> Self {
+        let value = level
+            .field_instances
+            .iter()
+            .find(|field_instance| field_instance.identifier == "gravity")
+            .and_then(|field_instance| field_instance.value.as_ref())
+            .and_then(|value| value.as_str());
+        match value.map(|value| value.to_ascii_lowercase()).as_deref() {
+            Some("left") => GravityDirection::Left,
+            Some("right") => GravityDirection::Right,
+            Some("up") => GravityDirection::Up,
+            _ => GravityDirection::Down,
+        }
+    }
+    // rotation from the default frame, where down is -y, in radians
+    pub fn angle(&self) -> f32 {
+        match self {
+            GravityDirection::Down => 0.0,
+            GravityDirection::Left => -std::f32::consts::FRAC_PI_2,
+            GravityDirection::Right => std::f32::consts::FRAC_PI_2,
+            GravityDirection::Up => std::f32::consts::PI,
+        }
+    }
+    pub fn rotation(&self) -> Mat2 {
+        Mat2::from_angle(self.angle())
+    }
+    pub fn down(&self) -> Vec2 {
+        self.rotation() * -Vec2::Y
+    }
+    // the direction actors walk to when moving right
+    pub fn right(&self) -> Vec2 {
+        self.rotation() * Vec2::X
+    }
+}
+
+// timing targets of a level in seconds, from its "parTime", "bronzeTime", "silverTime"
+// and "goldTime" fields (Float)
+#[derive(Debug, Clone, Default)]
+pub struct LevelSettings {
+    pub par_time: Option<f32>,
+    pub bronze_time: Option<f32>,
+    pub silver_time: Option<f32>,
+    pub gold_time: Option<f32>,
+}
+impl LevelSettings {
+    fn from_level(level: &Level) -> Self {
+        let time = |identifier: &str| {
+            level
+                .field_instances
+                .iter()
+                .find(|field_instance| field_instance.identifier == identifier)
+                .and_then(|field_instance| field_instance.value.as_ref())
+                .and_then(|value| value.as_f64())
+                .map(|value| value as f32)
+        };
+        Self {
+            par_time: time("parTime"),
+            bronze_time: time("bronzeTime"),
+            silver_time: time("silverTime"),
+            gold_time: time("goldTime"),
+        }
+    }
+}
+
+// what a level asks of the player, from its "defeatAll" and "reachExit" (Bool) and "keys"
+// (Int) fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    DefeatAll,
+    ReachExit,
+    CollectKeys(u32),
+}
+impl Objective {
+    fn from_level(level: &Level) -> Vec<Self> {
+        let value = |identifier: &str| {
+            level
+                .field_instances
+                .iter()
+                .find(|field_instance| field_instance.identifier == identifier)
+                .and_then(|field_instance| field_instance.value.as_ref())
+        };
+        let flag = |identifier: &str| value(identifier).and_then(|value| value.as_bool());
+        let mut objectives = vec![];
+        if flag("defeatAll") == Some(true) {
+            objectives.push(Objective::DefeatAll);
+        }
+        if flag("reachExit") == Some(true) {
+            objectives.push(Objective::ReachExit);
+        }
+        if let Some(keys) = value("keys").and_then(|value| value.as_u64()) {
+            if keys > 0 {
+                objectives.push(Objective::CollectKeys(keys as u32));
+            }
+        }
+        objectives
+    }
+}
+
+// what happens to defeated enemies of a level, from its "enemyRespawn" field (String or Enum:
+// "never", "enter" or "timer") and "enemyRespawnTime" (Float, seconds)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnemyRespawn {
+    // gone for good, kept in the save
+    Never,
+    // back when the player enters the level again
+    Enter,
+    Timer(f32),
+}
+impl Default for EnemyRespawn {
+    fn default() -> Self {
+        EnemyRespawn::Enter
+    }
+}
+impl EnemyRespawn {
+    fn from_level(level: &Level) -> Self {
+        let value = |identifier: &str| {
+            level
+                .field_instances
+                .iter()
+                .find(|field_instance| field_instance.identifier == identifier)
+                .and_then(|field_instance| field_instance.value.as_ref())
+        };
+        let rule = value("enemyRespawn")
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_ascii_lowercase());
+        match rule.as_deref() {
+            Some("never") => EnemyRespawn::Never,
+            Some("timer") => EnemyRespawn::Timer(
+                value("enemyRespawnTime")
+                    .and_then(|value| value.as_f64())
+                    .unwrap_or(30.0) as f32,
+            ),
+            _ => EnemyRespawn::Enter,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorldLevel {
+    pub uid: i64,
+    pub identifier: String,
+    // world space in pixels, y up
+    pub rect: Rect,
+    pub neighbours: Vec<(char, i64)>,
+    pub gravity: GravityDirection,
+    pub settings: LevelSettings,
+    pub objectives: Vec<Objective>,
+    pub enemy_respawn: EnemyRespawn,
+    // from `__bgColor`, which falls back to the project's default
+    pub background: Color,
+    // from the "darkness" field (Float), 0 is fully lit, see LightingPlugin
+    pub darkness: f32,
+    // hours the world clock is pinned to in this level, see WorldClockPlugin
+    pub time_of_day: Option<f32>,
+    // track looped while the level is active, from the "music" field (String, an asset path)
+    pub music: Option<String>,
+}
+
+impl WorldLevel {
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.rect.min.x
+            && point.x < self.rect.max.x
+            && point.y >= self.rect.min.y
+            && point.y < self.rect.max.y
+    }
+    // top left corner, which is the origin of ldtk level coordinates
+    pub fn origin(&self) -> Vec2 {
+        Vec2::new(self.rect.min.x, self.rect.max.y)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WorldMap {
+    pub levels: Vec<WorldLevel>,
+    pub active: Option<i64>,
+}
+
+// the "TimeOfDay" field, Float hours or a name
+fn time_of_day(level: &Level) -> Option<f32> {
+    let value = level
+        .field_instances
+        .iter()
+        .find(|field_instance| field_instance.identifier == "TimeOfDay")
+        .and_then(|field_instance| field_instance.value.as_ref())?;
+    if let Some(hours) = value.as_f64() {
+        return Some((hours as f32).rem_euclid(24.0));
+    }
+    match value.as_str()?.to_ascii_lowercase().as_str() {
+        "dawn" => Some(6.5),
+        "day" => Some(12.0),
+        "dusk" => Some(19.5),
+        "night" => Some(0.0),
+        _ => None,
+    }
+}
+
+impl WorldMap {
+    pub fn new(data: &LdtkData) -> Self {
+        let mut offset = Vec2::ZERO;
+        let levels = data
+            .levels
+            .iter()
+            .map(|level| {
+                let size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+                // linear layouts don't provide world coordinates
+                let position = match data.world_layout {
+                    WorldLayout::LinearHorizontal => {
+                        let position = offset;
+                        offset.x += size.x;
+                        position
+                    }
+                    WorldLayout::LinearVertical => {
+                        let position = offset;
+                        offset.y += size.y;
+                        position
+                    }
+                    WorldLayout::Free | WorldLayout::GridVania => {
+                        Vec2::new(level.world_x as f32, level.world_y as f32)
+                    }
+                };
+                let min = Vec2::new(position.x, -position.y - size.y);
+                WorldLevel {
+                    uid: level.uid,
+                    identifier: level.identifier.clone(),
+                    rect: Rect {
+                        min,
+                        max: min + size,
+                    },
+                    neighbours: level
+                        .neighbours
+                        .iter()
+                        .filter_map(|neighbour| {
+                            neighbour
+                                .dir
+                                .chars()
+                                .next()
+                                .map(|dir| (dir, neighbour.level_uid))
+                        })
+                        .collect(),
+                    gravity: GravityDirection::from_level(level),
+                    settings: LevelSettings::from_level(level),
+                    objectives: Objective::from_level(level),
+                    enemy_respawn: EnemyRespawn::from_level(level),
+                    background: parse_color(&level.bg_color).unwrap_or(Color::BLACK),
+                    darkness: level
+                        .field_instances
+                        .iter()
+                        .find(|field_instance| field_instance.identifier == "darkness")
+                        .and_then(|field_instance| field_instance.value.as_ref())
+                        .and_then(|value| value.as_f64())
+                        .map_or(0.0, |value| value.clamp(0.0, 1.0) as f32),
+                    time_of_day: time_of_day(level),
+                    music: level
+                        .field_instances
+                        .iter()
+                        .find(|field_instance| field_instance.identifier == "music")
+                        .and_then(|field_instance| field_instance.value.as_ref())
+                        .and_then(|value| value.as_str())
+                        .map(str::to_owned),
+                }
+            })
+            .collect();
+        Self {
+            levels,
+            active: None,
+        }
+    }
+
+    pub fn get(&self, uid: i64) -> Option<&WorldLevel> {
+        self.levels.iter().find(|level| level.uid == uid)
+    }
+
+    pub fn find(&self, identifier: &str) -> Option<&WorldLevel> {
+        self.levels
+            .iter()
+            .find(|level| level.identifier == identifier)
+    }
+
+    pub fn level_at(&self, point: Vec2) -> Option<&WorldLevel> {
+        self.levels.iter().find(|level| level.contains(point))
+    }
+
+    pub fn active_level(&self) -> Option<&WorldLevel> {
+        self.active.and_then(|uid| self.get(uid))
+    }
+
+    pub fn gravity(&self) -> GravityDirection {
+        self.active_level()
+            .map(|level| level.gravity)
+            .unwrap_or_default()
+    }
+
+    pub fn neighbours(&self, uid: i64) -> impl Iterator<Item = &WorldLevel> {
+        self.get(uid)
+            .into_iter()
+            .flat_map(|level| level.neighbours.iter())
+            .filter_map(|(_, uid)| self.get(*uid))
+    }
+}
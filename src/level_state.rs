@@ -0,0 +1,123 @@
+use crate::{
+    damage::{DamageSystem, KillEvent},
+    game_time::GameTime,
+    ldtk::{
+        plugin::LdtkEvent,
+        registry::LdtkEntityRegistry,
+        world::{EnemyRespawn, WorldMap},
+    },
+    world_flags::{FlagValue, WorldFlags},
+};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+// brings defeated ldtk enemies back by the respawn rule of their level
+pub struct LevelStatePlugin;
+impl Plugin for LevelStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelRuntimeState>()
+            // the registry still has the binding until the end of the frame
+            .add_system(defeat_system.after(DamageSystem))
+            .add_system(level_entered_system)
+            .add_system(respawn_system.after(level_entered_system));
+    }
+}
+
+struct DefeatedEnemy {
+    level_uid: i64,
+    // only for EnemyRespawn::Timer, the others wait for the level to be entered
+    timer: Option<Timer>,
+    ready: bool,
+}
+
+// defeated enemies waiting to respawn this session, keyed by ldtk iid
+#[derive(Default)]
+pub struct LevelRuntimeState {
+    defeated: HashMap<String, DefeatedEnemy>,
+}
+
+fn defeat_system(
+    mut events: EventReader<KillEvent>,
+    registry: Res<LdtkEntityRegistry>,
+    world_map: Res<WorldMap>,
+    mut state: ResMut<LevelRuntimeState>,
+    mut flags: ResMut<WorldFlags>,
+) {
+    for event in events.iter() {
+        // enemies spawned outside of ldtk, e.g. in the sandbox, just die
+        let record = match registry.iid(event.target).and_then(|iid| registry.get(iid)) {
+            Some(record) => record,
+            None => continue,
+        };
+        let rule = world_map
+            .get(record.level_uid)
+            .map(|level| level.enemy_respawn)
+            .unwrap_or_default();
+        let timer = match rule {
+            EnemyRespawn::Never => {
+                flags.set(&record.iid, "defeated", FlagValue::Bool(true));
+                continue;
+            }
+            EnemyRespawn::Enter => None,
+            EnemyRespawn::Timer(seconds) => Some(Timer::from_seconds(seconds, false)),
+        };
+        state.defeated.insert(
+            record.iid.clone(),
+            DefeatedEnemy {
+                level_uid: record.level_uid,
+                timer,
+                ready: false,
+            },
+        );
+    }
+}
+
+// apart from respawn_system, which sends ldtk events too
+fn level_entered_system(
+    mut events: EventReader<LdtkEvent>,
+    world_map: Res<WorldMap>,
+    mut state: ResMut<LevelRuntimeState>,
+) {
+    for event in events.iter() {
+        let level = match event {
+            LdtkEvent::LevelActivated(identifier) => world_map.find(identifier),
+            _ => None,
+        };
+        if let Some(level) = level {
+            for defeated in state.defeated.values_mut() {
+                if defeated.timer.is_none() && defeated.level_uid == level.uid {
+                    defeated.ready = true;
+                }
+            }
+        }
+    }
+}
+
+fn respawn_system(
+    time: Res<GameTime>,
+    registry: Res<LdtkEntityRegistry>,
+    mut state: ResMut<LevelRuntimeState>,
+    mut event_writer: EventWriter<LdtkEvent>,
+) {
+    let mut respawned = vec![];
+    for (iid, defeated) in state.defeated.iter_mut() {
+        if let Some(timer) = defeated.timer.as_mut() {
+            defeated.ready = timer.tick(time.delta()).finished();
+        }
+        if defeated.ready {
+            respawned.push(iid.clone());
+        }
+    }
+    for iid in respawned {
+        state.defeated.remove(&iid);
+        let record = match registry.get(&iid) {
+            Some(record) => record,
+            None => continue,
+        };
+        event_writer.send(LdtkEvent::SpawnEnemy {
+            name: record.name.clone().unwrap_or_default(),
+            position: record.position.extend(0.0),
+            iid: Some(iid),
+        });
+    }
+}
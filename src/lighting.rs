@@ -0,0 +1,186 @@
+use crate::{game_time::GameTime, ldtk::world::WorldMap, Player};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use geo_booleanop::boolean::BooleanOp;
+
+// above actors and the foreground layer, below the hud
+const Z_DARKNESS: f32 = 50.0;
+const PLAYER_LIGHT_RADIUS: f32 = 40.0;
+const CIRCLE_SEGMENTS: usize = 24;
+// stacked darkness layers with shrinking holes, for a soft edge around lights
+const DARKNESS_LAYERS: usize = 3;
+// of a light's radius, where the innermost layer starts
+const INNER_RADIUS: f32 = 0.5;
+// flicker speed, in radians per second
+const FLICKER_SPEED: f32 = 9.0;
+
+// levels with a "darkness" field (Float, 0 to 1) are covered by darkness with holes around the
+// player and ldtk "Light" entities
+pub struct LightingPlugin;
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_system)
+            .add_system(player_light_system)
+            .add_system(darkness_system);
+    }
+}
+
+#[derive(Component)]
+pub struct Light {
+    // pixels
+    pub radius: f32,
+    // 0 is steady, 1 swings all the way between nothing and double the radius
+    pub flicker: f32,
+    // offset into the flicker, so lights don't pulse in sync
+    pub phase: f32,
+}
+impl Light {
+    pub fn new(radius: f32, flicker: f32) -> Self {
+        Self {
+            radius,
+            flicker,
+            phase: 0.0,
+        }
+    }
+
+    fn current_radius(&self, seconds: f32) -> f32 {
+        let t = seconds * FLICKER_SPEED + self.phase;
+        // a couple of sines that don't line up read as a flame
+        let noise = (t.sin() + (t * 2.3 + 1.7).sin() * 0.5) / 1.5;
+        self.radius * (1.0 + noise * self.flicker).max(0.0)
+    }
+}
+
+#[derive(Component)]
+struct Darkness {
+    // 0 for the outermost layer
+    layer: usize,
+}
+
+pub fn spawn_light(commands: &mut Commands, position: Vec3, radius: f32, flicker: f32) -> Entity {
+    commands
+        .spawn_bundle((
+            Transform::from_translation(position),
+            GlobalTransform::identity(),
+        ))
+        .insert(Light {
+            phase: position.x * 0.37 + position.y * 0.11,
+            ..Light::new(radius, flicker)
+        })
+        .id()
+}
+
+fn setup_system(mut commands: Commands) {
+    for layer in 0..DARKNESS_LAYERS {
+        commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &shapes::Rectangle::default(),
+                DrawMode::Fill(FillMode {
+                    options: FillOptions::even_odd(),
+                    color: Color::NONE,
+                }),
+                Transform::from_xyz(0.0, 0.0, Z_DARKNESS + layer as f32 * 0.01),
+            ))
+            .insert(Darkness { layer })
+            .insert(Visibility { is_visible: false });
+    }
+}
+
+fn player_light_system(mut commands: Commands, players: Query<Entity, Added<Player>>) {
+    for entity in players.iter() {
+        commands
+            .entity(entity)
+            .insert(Light::new(PLAYER_LIGHT_RADIUS, 0.05));
+    }
+}
+
+fn circle(center: Vec2, radius: f32) -> geo::MultiPolygon<f64> {
+    let points = (0..CIRCLE_SEGMENTS)
+        .map(|index| {
+            let angle = index as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            let point = center + Vec2::new(angle.cos(), angle.sin()) * radius;
+            geo::Coordinate {
+                x: point.x as f64,
+                y: point.y as f64,
+            }
+        })
+        .collect::<Vec<_>>();
+    geo::MultiPolygon(vec![geo::Polygon::new(
+        geo::LineString::from(points),
+        vec![],
+    )])
+}
+
+fn add_ring(path_builder: &mut PathBuilder, ring: &geo::LineString<f64>) {
+    let mut points = ring.points();
+    if let Some(first) = points.next() {
+        path_builder.move_to(Vec2::new(first.x() as f32, first.y() as f32));
+        for point in points {
+            path_builder.line_to(Vec2::new(point.x() as f32, point.y() as f32));
+        }
+        path_builder.close();
+    }
+}
+
+fn darkness_system(
+    time: Res<GameTime>,
+    world_map: Res<WorldMap>,
+    lights: Query<(&Light, &GlobalTransform)>,
+    mut layers: Query<(&Darkness, &mut Path, &mut DrawMode, &mut Visibility)>,
+) {
+    let level = world_map
+        .active_level()
+        .filter(|level| level.darkness > 0.0);
+    let level = match level {
+        Some(level) => level,
+        None => {
+            for (_, _, _, mut visibility) in layers.iter_mut() {
+                visibility.is_visible = false;
+            }
+            return;
+        }
+    };
+
+    let seconds = time.seconds_since_startup() as f32;
+    let lights = lights
+        .iter()
+        .map(|(light, global_transform)| {
+            (
+                global_transform.translation.truncate(),
+                light.current_radius(seconds),
+            )
+        })
+        .filter(|(_, radius)| *radius > 0.0)
+        .collect::<Vec<_>>();
+    // so all layers stacked reach the level's darkness
+    let alpha = 1.0 - (1.0 - level.darkness.min(1.0)).powf(1.0 / DARKNESS_LAYERS as f32);
+    for (darkness, mut path, mut draw_mode, mut visibility) in layers.iter_mut() {
+        // the outermost layer has the full radius, inner ones shrink towards INNER_RADIUS
+        let t = darkness.layer as f32 / (DARKNESS_LAYERS - 1).max(1) as f32;
+        let scale = 1.0 - t * (1.0 - INNER_RADIUS);
+        // overlapping lights merge into one hole
+        let holes = lights
+            .iter()
+            .map(|(center, radius)| circle(*center, radius * scale))
+            .reduce(|acc, circle| acc.union(&circle));
+
+        let mut path_builder = PathBuilder::new();
+        path_builder.move_to(level.rect.min);
+        path_builder.line_to(Vec2::new(level.rect.max.x, level.rect.min.y));
+        path_builder.line_to(level.rect.max);
+        path_builder.line_to(Vec2::new(level.rect.min.x, level.rect.max.y));
+        path_builder.close();
+        for polygon in holes.iter().flat_map(|holes| holes.0.iter()) {
+            add_ring(&mut path_builder, polygon.exterior());
+            for interior in polygon.interiors() {
+                add_ring(&mut path_builder, interior);
+            }
+        }
+        *path = path_builder.build();
+        *draw_mode = DrawMode::Fill(FillMode {
+            options: FillOptions::even_odd(),
+            color: Color::rgba(0.0, 0.0, 0.0, alpha),
+        });
+        visibility.is_visible = true;
+    }
+}
@@ -1,12 +1,135 @@
+mod actor;
+mod alert;
 mod animation;
+mod asset_error;
+mod audio;
+mod bitmap_font;
+mod breakable;
+#[cfg(debug_assertions)]
+mod bugreport;
+mod camera_zone;
+#[cfg(debug_assertions)]
+mod cheat;
+mod collision;
+mod combo;
+mod conveyor;
+mod damage;
 mod debug;
+mod despawn;
+mod determinism;
+#[cfg(feature = "devtools")]
+mod devtools;
+mod effects;
+mod equipment;
+mod footsteps;
+mod game_config;
+mod game_time;
+mod ghost;
+mod input;
+mod interact;
 mod ldtk;
-use animation::{AnimationSprite, Aseprite, AsepritePlugin};
+mod level_state;
+mod lighting;
+mod minimap;
+mod nav;
+mod nine_slice;
+mod npc;
+mod objectives;
+mod options;
+mod pickup;
+mod portal;
+mod postfx;
+mod practice;
+mod procgen;
+mod props;
+mod puzzle;
+mod replay;
+mod respawn;
+mod rng;
+mod rope;
+mod sandbox;
+mod save;
+#[cfg(debug_assertions)]
+mod savestate;
+mod score;
+mod settings;
+mod speedrun;
+mod sprite_effects;
+mod transitions;
+mod trigger;
+mod turret;
+mod vision;
+mod water;
+mod wind;
+mod world_clock;
+mod world_flags;
+use actor::{ActorBundle, ActorParts, ActorPlugin, Grounded};
+use alert::{Aggro, AlertPlugin};
+use animation::{AlwaysAnimate, AnimationSprite, Aseprite, AsepritePlugin};
+use asset_error::AssetErrorPlugin;
+use audio::{AudioListener, SfxEvent, SoundPlugin};
 use bevy::prelude::*;
 use bevy_prototype_lyon::prelude::*;
 use bevy_rapier2d::prelude::*;
+use bitmap_font::BitmapFontPlugin;
+use breakable::BreakablePlugin;
+use camera_zone::{CameraTarget, CameraZonePlugin, CameraZones};
+use collision::{CollisionLayers, CollisionLookup};
+use combo::{Combo, ComboPlugin};
+use conveyor::ConveyorPlugin;
+use damage::{
+    BossPhases, DamageEvent, DamagePlugin, DamageType, EnemyDefinitions, Health, Resistances,
+    Slowed,
+};
 use debug::*;
-use ldtk::plugin::{Ldtk, LdtkEvent, LdtkPlugin};
+use despawn::DespawnPlugin;
+use determinism::DeterminismPlugin;
+use effects::{Afterimage, EffectsPlugin};
+use equipment::{Equipment, EquipmentPlugin};
+use footsteps::FootstepPlugin;
+use game_config::{GameConfig, GameConfigPlugin};
+use game_time::{GameTime, GameTimePlugin};
+use ghost::GhostPlugin;
+use input::{Action, ActionInput, InputMapPlugin};
+use interact::InteractPlugin;
+use ldtk::{
+    plugin::{Ldtk, LdtkEvent, LdtkPlugin},
+    registry::LdtkEntityRegistry,
+    world::WorldMap,
+};
+use level_state::LevelStatePlugin;
+use lighting::LightingPlugin;
+use minimap::MinimapPlugin;
+use nav::{NavPlugin, PathFollower};
+use nine_slice::NineSlicePlugin;
+use npc::NpcPlugin;
+use objectives::ObjectivesPlugin;
+use options::OptionsPlugin;
+use pickup::PickupPlugin;
+use portal::PortalPlugin;
+use postfx::PostFxPlugin;
+use practice::PracticePlugin;
+use procgen::ProcgenPlugin;
+use props::PropsPlugin;
+use puzzle::PuzzlePlugin;
+use replay::ReplayPlugin;
+use respawn::RespawnPlugin;
+use rng::RngPlugin;
+use rope::{Climbing, RopePlugin};
+use sandbox::SandboxPlugin;
+use save::SavePlugin;
+use score::ScorePlugin;
+use settings::SettingsPlugin;
+use speedrun::SpeedrunPlugin;
+use sprite_effects::{Palettes, SpriteEffects, SpriteEffectsPlugin};
+use transitions::TransitionsPlugin;
+use trigger::TriggerPlugin;
+use turret::TurretPlugin;
+use vision::{Vision, VisionPlugin};
+use water::{Submerged, WaterPlugin};
+use wind::{WindPlugin, Windswept, WIND_DRIFT_TIME};
+use world_clock::WorldClockPlugin;
+use world_flags::{WorldFlags, WorldFlagsPlugin};
 
 fn main() {
     App::new()
@@ -22,10 +145,75 @@ fn main() {
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
         //.add_plugin(RapierRenderPlugin)
         .add_plugin(ShapePlugin)
+        // first, the other plugins read it while building
+        .add_plugin(SettingsPlugin)
         .add_plugin(LdtkPlugin)
+        .add_plugin(LevelStatePlugin)
+        .add_plugin(LightingPlugin)
+        .add_plugin(ActorPlugin)
+        .add_plugin(AlertPlugin)
+        .add_plugin(AssetErrorPlugin)
         .add_plugin(DebugPlugin)
-        .add_plugin(AsepritePlugin)
+        .add_plugin(DespawnPlugin)
+        .add_plugin(DeterminismPlugin)
+        .add_plugin(CameraZonePlugin)
+        .add_plugin(AsepritePlugin::default())
+        .add_plugin(BitmapFontPlugin)
+        .add_plugin(BreakablePlugin)
+        .add_plugin(EffectsPlugin)
+        .add_plugin(InputMapPlugin)
+        .add_plugin(InteractPlugin)
+        .add_plugin(SoundPlugin)
+        .add_plugin(ComboPlugin)
+        .add_plugin(ConveyorPlugin)
+        .add_plugin(DamagePlugin)
+        .add_plugin(EquipmentPlugin)
+        .add_plugin(FootstepPlugin)
+        .add_plugin(GameConfigPlugin)
+        .add_plugin(GameTimePlugin)
+        .add_plugin(GhostPlugin)
+        .add_plugin(MinimapPlugin)
+        .add_plugin(NavPlugin)
+        .add_plugin(NineSlicePlugin)
+        .add_plugin(NpcPlugin)
+        .add_plugin(ObjectivesPlugin)
+        .add_plugin(OptionsPlugin)
+        .add_plugin(PickupPlugin)
+        .add_plugin(PortalPlugin)
+        .add_plugin(PostFxPlugin)
+        .add_plugin(PracticePlugin)
+        .add_plugin(ProcgenPlugin)
+        .add_plugin(PropsPlugin)
+        .add_plugin(PuzzlePlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(RespawnPlugin)
+        .add_plugin(RngPlugin)
+        .add_plugin(RopePlugin)
+        .add_plugin(SandboxPlugin)
+        .add_plugin(SavePlugin)
+        .add_plugin(ScorePlugin)
+        .add_plugin(SpeedrunPlugin)
+        .add_plugin(SpriteEffectsPlugin)
+        .add_plugin(TransitionsPlugin)
+        .add_plugin(TriggerPlugin)
+        .add_plugin(TurretPlugin)
+        .add_plugin(VisionPlugin)
+        .add_plugin(WaterPlugin)
+        .add_plugin(WindPlugin)
+        .add_plugin(WorldClockPlugin)
+        .add_plugin(WorldFlagsPlugin)
+        .add_state(if std::env::args().any(|arg| arg == "--sandbox") {
+            AppState::Sandbox
+        } else if std::env::args().any(|arg| arg == "--practice") {
+            AppState::Practice
+        } else if std::env::args().any(|arg| arg == "--run" || arg == "--resume") {
+            AppState::Run
+        } else {
+            AppState::InGame
+        })
         .add_startup_system(setup_system)
+        .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(load_level_system))
+        .add_system_set(SystemSet::on_enter(AppState::Run).with_system(load_level_system))
         .add_system(player_system)
         .add_system(camera_system)
         .add_system(on_collision_event_system)
@@ -35,14 +223,37 @@ fn main() {
 
 const RAPIER_SCALE: f32 = 32.0; // 1m = 32px
 const Z_COLLISION: f32 = 10.0;
+// above the tile layers, which the 2d camera sees from 0 up, and below the foreground
+const Z_ACTORS: f32 = 2.0;
+const DASH_DURATION: f32 = 0.15;
+const DASH_COOLDOWN: f32 = 0.6;
+const DOUBLE_TAP_TIME: f64 = 0.25;
+// gameplay freeze when an attack lands
+const HITSTOP_DURATION: f32 = 0.08;
+const MAX_SLOPE_ANGLE: f32 = 50.0 * std::f32::consts::PI / 180.0;
+// pixels per second into the ground when walking downhill
+const GROUND_SNAP_SPEED: f32 = 32.0;
+// in hearts, like what pickups heal
+const PLAYER_HEALTH: f32 = 5.0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum AppState {
+    InGame,
+    // flat ground and spawn hotkeys for physics tuning, see sandbox.rs
+    Sandbox,
+    // arena with a training dummy and hit data, see practice.rs
+    Practice,
+    // rooms of the levels stitched into a new map, see procgen.rs
+    Run,
+}
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum Direction {
     Left,
     Right,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum PlayerState {
     Wait,
     Walk,
@@ -51,20 +262,37 @@ enum PlayerState {
     Guard,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 struct Player {
     state: PlayerState,
+    // running while dashing, which is also the invulnerability window
+    dash: Timer,
+    dash_cooldown: Timer,
+    // direction and time of the last move key press, for double taps
+    last_tap: Option<(Direction, f64)>,
+    crouching: bool,
+    attack_type: DamageType,
+    // attacks started so far, the practice room times them
+    attacks: u32,
 }
 impl Default for Player {
     fn default() -> Self {
         Self {
             state: PlayerState::Wait,
+            dash: Timer::from_seconds(0.0, false),
+            dash_cooldown: Timer::from_seconds(0.0, false),
+            last_tap: None,
+            crouching: false,
+            attack_type: DamageType::Physical,
+            attacks: 0,
         }
     }
 }
 
 #[derive(Component)]
-struct Enemy;
+struct Enemy {
+    name: String,
+}
 
 #[derive(Component)]
 struct Actor {
@@ -81,11 +309,24 @@ impl Actor {
 #[derive(Component)]
 struct VirtualPosition(Vec3);
 
-fn setup_system(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut rapier_config: ResMut<RapierConfiguration>,
-) {
+// capsule body, a hurtbox a little larger than it and a foot sensor just below
+fn actor_bundle(position: Vec2, body: ColliderShape, layer: u32) -> ActorBundle {
+    ActorBundle::new(position, body, layer)
+        .with_hurtbox(Vec2::new(5.0, 11.0), Vec2::ZERO)
+        .with_foot_sensor(Vec2::new(3.0, 1.0), Vec2::new(0.0, -10.0))
+}
+
+// capsule from -10 to 10px, or to 2px while crouching, the bottom stays in place
+fn player_shape(crouching: bool) -> ColliderShape {
+    let top = if crouching { -2.0 } else { 6.0 };
+    ColliderShape::capsule(
+        (Vec2::new(0.0, top) / RAPIER_SCALE).into(),
+        (Vec2::new(0.0, -6.0) / RAPIER_SCALE).into(),
+        4.0 / RAPIER_SCALE,
+    )
+}
+
+fn setup_system(mut commands: Commands, mut rapier_config: ResMut<RapierConfiguration>) {
     rapier_config.scale = RAPIER_SCALE;
 
     // origin for debug
@@ -101,70 +342,124 @@ fn setup_system(
                     Transform::identity(),
                 ),
         )
-        .insert(DebugTarget)
+        .insert(DebugTarget(DebugGroup::Origin))
         .insert(Visibility { is_visible: false });
 
+    // camera, drawn into the postfx screen texture instead of the window
+    let mut camera = OrthographicCameraBundle::new_2d();
+    camera.camera.name = Some(postfx::WORLD_CAMERA.to_string());
+    commands
+        .spawn_bundle(camera)
+        .insert(VirtualPosition(Vec3::ZERO))
+        .insert(AudioListener::default());
+    commands.spawn_bundle(UiCameraBundle::default());
+}
+fn load_level_system(mut commands: Commands, asset_server: Res<AssetServer>) {
     let scene: Handle<Ldtk> = asset_server.load("levels.ldtk");
     commands.insert_resource(scene);
-
-    // camera
-    commands
-        .spawn_bundle(OrthographicCameraBundle::new_2d())
-        .insert(VirtualPosition(Vec3::ZERO));
 }
 fn camera_system(
     mut cameras: Query<(&mut Transform, &mut VirtualPosition), (With<Camera>, Without<Player>)>,
     players: Query<&Transform, With<Player>>,
+    world_map: Res<WorldMap>,
+    camera_zones: CameraZones,
+    config: Res<GameConfig>,
+    timings: Res<SystemTimings>,
 ) {
+    let _span = timings.span("camera");
     if cameras.is_empty() || players.is_empty() {
         return;
     }
     let (mut camera_transform, mut position) = cameras.single_mut();
     let player_transform = players.single();
 
-    // lerp
-    let ratio = 0.05;
-    let mut x = position.0.x * (1.0 - ratio) + player_transform.translation.x * ratio;
-    position.0.x = x;
+    // lerp along the level's horizontal axis, which is y in rotated gravity levels
+    let gravity = world_map.gravity();
+    let right = gravity.right();
+    let ratio = config.camera_lerp;
+    let current = position.0.truncate();
+    let mut x = current.dot(right) * (1.0 - ratio)
+        + player_transform.translation.truncate().dot(right) * ratio;
+    let across = current - right * current.dot(right);
+    position.0 = (across + right * x).extend(position.0.z);
 
     // align pixel
     //x = (x * 2.0).round() / 2.0;
 
-    camera_transform.translation.x = x;
+    // camera zones only offset the view, following keeps going underneath
+    let (translation, zoom) = camera_zones.apply(across + right * x);
+    camera_transform.translation.x = translation.x;
+    camera_transform.translation.y = translation.y;
+    camera_transform.scale = Vec3::new(1.0 / zoom, 1.0 / zoom, 1.0);
+    camera_transform.rotation = Quat::from_rotation_z(gravity.angle());
 }
 
 fn player_system(
     mut commands: Commands,
     mut players: Query<
         (
+            Entity,
+            &mut Player,
             &mut Actor,
-            &Children,
             &RigidBodyPositionComponent,
             &mut RigidBodyVelocityComponent,
             &RigidBodyMassPropsComponent,
             &mut ColliderMaterialComponent,
+            &mut ColliderShapeComponent,
+            Option<&Submerged>,
+            Option<&Grounded>,
+            Option<&Windswept>,
+            Option<&Slowed>,
+            &Equipment,
         ),
-        With<Player>,
+        (With<Player>, Without<Climbing>),
     >,
     mut sprites: Query<(
         &mut Transform,
         &mut AnimationSprite,
         &mut TextureAtlasSprite,
     )>,
-    enemies: Query<&Enemy>,
-    keyboard_input: Res<Input<KeyCode>>,
+    collision_lookup: CollisionLookup,
+    actor_parts: ActorParts,
+    actions: ActionInput,
+    mut game_time: ResMut<GameTime>,
     rapier_config: Res<RapierConfiguration>,
+    config: Res<GameConfig>,
     query_pipeline: Res<QueryPipeline>,
     collider_query: QueryPipelineColliderComponentsQuery,
+    timings: Res<SystemTimings>,
+    mut sfx_events: EventWriter<SfxEvent>,
+    mut combo: ResMut<Combo>,
+    mut damage_events: EventWriter<DamageEvent>,
+    world_map: Res<WorldMap>,
 ) {
-    if players.is_empty() {
+    let _span = timings.span("player");
+    // input during hitstop is dropped
+    if players.is_empty() || game_time.frozen() {
         return;
     }
-    let (mut actor, children, rb_position, mut rb_velocity, rb_mass_props, mut collider_material) =
-        players.single_mut();
+    let (
+        entity,
+        mut player,
+        mut actor,
+        rb_position,
+        mut rb_velocity,
+        rb_mass_props,
+        mut collider_material,
+        mut collider_shape,
+        submerged,
+        grounded,
+        windswept,
+        slowed,
+        equipment,
+    ) = players.single_mut();
+    let weapon = &equipment.weapon_definition;
+    let movement = &config.movement;
+    let swimming = submerged.is_some();
+    let slow = slowed.map_or(1.0, |slowed| slowed.factor);
 
-    let left = keyboard_input.pressed(KeyCode::A) || keyboard_input.pressed(KeyCode::Left);
-    let right = keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right);
+    let left = actions.pressed(Action::Left);
+    let right = actions.pressed(Action::Right);
     let x_axis = -(left as i8) + right as i8;
     let mut move_delta = Vec2::new(x_axis as f32, 0.0);
     if move_delta != Vec2::ZERO {
@@ -173,45 +468,201 @@ fn player_system(
     } else {
         collider_material.friction = 1.0;
     }
-    let jump = keyboard_input.just_pressed(KeyCode::Space);
-    let attack = keyboard_input.just_pressed(KeyCode::Z);
+    let jump = actions.just_pressed(Action::Jump);
+    let attack = actions.just_pressed(Action::Attack);
+
+    // movement is written in the level's frame, rotated for levels where down isn't -y
+    let rotation = world_map.gravity().rotation();
 
-    let hold = keyboard_input.pressed(KeyCode::LShift);
-    if !hold && left {
-        actor.direction = Direction::Left;
-    } else if !hold && right {
-        actor.direction = Direction::Right;
+    // with mouse controls the player faces the cursor instead of the walking direction
+    let translation = rb_position.position.translation.vector;
+    let aim = actions
+        .aim(Vec2::new(translation.x, translation.y) * rapier_config.scale)
+        .map(|aim| rotation.transpose() * aim);
+    let hold = actions.pressed(Action::Hold);
+    match aim {
+        Some(aim) if !hold && aim.x < 0.0 => actor.direction = Direction::Left,
+        Some(aim) if !hold && aim.x > 0.0 => actor.direction = Direction::Right,
+        Some(_) => {}
+        None if !hold && left => actor.direction = Direction::Left,
+        None if !hold && right => actor.direction = Direction::Right,
+        None => {}
     }
     let flip_x = if actor.direction == Direction::Left {
         -1.0
     } else {
         1.0
     };
+    let collider_set = QueryPipelineColliderComponentsSet(&collider_query);
+    let own_collider = entity.handle();
+    let filter = |handle: ColliderHandle| handle != own_collider;
+
+    // crouch on Down, standing up needs room above the crouching capsule
+    let down = actions.pressed(Action::Down);
+    if down && !player.crouching {
+        player.crouching = true;
+        collider_shape.0 = player_shape(true);
+    } else if !down && player.crouching {
+        let shape = Cuboid::new((Vec2::new(3.5, 4.0) / RAPIER_SCALE).into());
+        let offset: Isometry<Real> = (Vec2::new(0.0, 6.0) / RAPIER_SCALE).into();
+        let ceiling = query_pipeline.intersection_with_shape(
+            &collider_set,
+            &(rb_position.position * offset),
+            &shape,
+            CollisionLayers::solid_query(),
+            Some(&filter),
+        );
+        if ceiling.is_none() {
+            player.crouching = false;
+            collider_shape.0 = player_shape(false);
+        }
+    }
+
+    // dash on its own key or a double tap towards the facing direction
+    player.dash.tick(game_time.delta());
+    player.dash_cooldown.tick(game_time.delta());
+    let now = game_time.seconds_since_startup();
+    let tap = if actions.just_pressed(Action::Left) {
+        Some(Direction::Left)
+    } else if actions.just_pressed(Action::Right) {
+        Some(Direction::Right)
+    } else {
+        None
+    };
+    let double_tap = tap.map_or(false, |direction| {
+        let double_tap = matches!(
+            player.last_tap,
+            Some((last, at)) if last == direction && now - at < DOUBLE_TAP_TIME
+        );
+        player.last_tap = Some((direction, now));
+        double_tap
+    });
+    let mut dashing = !player.dash.finished();
+    if (double_tap || actions.just_pressed(Action::Dash))
+        && !dashing
+        && player.dash_cooldown.finished()
+    {
+        player.dash = Timer::from_seconds(DASH_DURATION, false);
+        player.dash_cooldown = Timer::from_seconds(DASH_COOLDOWN, false);
+        commands
+            .entity(entity)
+            .insert(Afterimage::new(DASH_DURATION));
+        dashing = true;
+    }
+    // a wall right ahead ends the dash, the capsule is 4px wide on each side
+    if dashing {
+        let ray = Ray::new(
+            rb_position.position.translation.vector.into(),
+            (rotation * Vec2::new(flip_x, 0.0)).into(),
+        );
+        if query_pipeline
+            .cast_ray(
+                &collider_set,
+                &ray,
+                6.0 / rapier_config.scale,
+                true,
+                CollisionLayers::solid_query(),
+                Some(&filter),
+            )
+            .is_some()
+        {
+            player.dash = Timer::from_seconds(0.0, false);
+            dashing = false;
+        }
+    }
 
-    rb_velocity.linvel.x = move_delta.x * 24.0;
-    if jump {
-        let force = Vec2::new(0.0, 8.0) / rapier_config.scale;
+    let mut local_velocity =
+        rotation.transpose() * Vec2::new(rb_velocity.linvel.x, rb_velocity.linvel.y);
+    if dashing {
+        // no falling either while dashing
+        local_velocity = Vec2::new(
+            movement.dash_speed * slow * flip_x / rapier_config.scale,
+            0.0,
+        );
+    } else {
+        let ratio = if player.crouching {
+            movement.crouch_speed_ratio
+        } else if swimming {
+            movement.swim_speed_ratio
+        } else {
+            1.0
+        };
+        let speed = move_delta.x * movement.walk_speed * ratio * slow;
+        let max_slope_cos = MAX_SLOPE_ANGLE.cos();
+        match grounded.map(|grounded| rotation.transpose() * grounded.normal) {
+            // walk along the ground instead of into or off it, unless leaving it
+            Some(normal) if !swimming && local_velocity.y <= 0.0 && normal.y >= max_slope_cos => {
+                let tangent = Vec2::new(normal.y, -normal.x);
+                local_velocity = tangent * speed;
+                // press into the slope downhill, so the body doesn't lift off
+                if tangent.y * speed < 0.0 {
+                    local_velocity -= normal * GROUND_SNAP_SPEED / rapier_config.scale;
+                }
+            }
+            // too steep to walk up
+            Some(normal) if normal.y < max_slope_cos && speed * normal.x < 0.0 => {
+                local_velocity.x = 0.0;
+            }
+            _ => local_velocity.x = speed,
+        }
+        // walking sets the horizontal velocity, so wind pushes against it as a drift
+        if let Some(windswept) = windswept {
+            let drift = rotation.transpose() * windswept.0 * WIND_DRIFT_TIME / rapier_config.scale;
+            local_velocity.x += drift.x;
+        }
+    }
+    rb_velocity.linvel = (rotation * local_velocity).into();
+    if jump && !player.crouching && (grounded.is_some() || swimming) {
+        // strokes in water can be repeated to swim up
+        let impulse = if swimming {
+            movement.swim_stroke
+        } else {
+            movement.jump_impulse
+        };
+        let force = rotation * Vec2::new(0.0, impulse) / rapier_config.scale;
         rb_velocity.apply_impulse(&rb_mass_props, force.into());
+        sfx_events.send(SfxEvent::new("audio/sfx/jump.wav").with_emitter(entity));
     }
     if attack {
-        let force = Vec2::new(32.0 * flip_x, 0.0) / rapier_config.scale;
+        player.attacks += 1;
+        let force =
+            rotation * Vec2::new(movement.attack_impulse * flip_x, 0.0) / rapier_config.scale;
         rb_velocity.apply_impulse(&rb_mass_props, force.into());
+        commands.entity(entity).insert(Afterimage::new(0.25));
+        let damage_type = player.attack_type;
 
-        let collider_set = QueryPipelineColliderComponentsSet(&collider_query);
-        let shape = Cuboid::new((Vec2::new(16.0, 16.0) / RAPIER_SCALE).into());
-        let mut shape_pos = (Vec2::new(16.0 * flip_x, 0.0) / RAPIER_SCALE).into();
-        shape_pos *= rb_position.position;
+        // turned up or down towards the cursor, the hitbox is defined facing right and mirrored
+        // before it turns, like its shape
+        let angle = aim.map_or(0.0, |aim| aim.y.atan2(aim.x.abs())) * flip_x;
+        let hitbox_offset =
+            Mat2::from_angle(angle) * (weapon.hitbox.offset() * Vec2::new(flip_x, 1.0));
+        let shape = Cuboid::new((weapon.hitbox.half_extents() / RAPIER_SCALE).into());
+        // local to the body, which turns with the level's gravity
+        let shape_pos =
+            rb_position.position * Isometry::new((hitbox_offset / RAPIER_SCALE).into(), angle);
 
         query_pipeline.intersections_with_shape(
             &collider_set,
             &shape_pos,
             &shape,
-            InteractionGroups::all(),
+            CollisionLayers::hurtbox_query(),
             None,
             |handle| {
-                let entity = handle.entity();
-                if let Ok(enemy) = enemies.get(entity) {
-                    commands.entity(entity).despawn_recursive();
+                let target = collision_lookup
+                    .enemy(handle)
+                    .or_else(|| collision_lookup.breakable(handle));
+                if let Some(target) = target {
+                    damage_events.send(DamageEvent {
+                        target,
+                        amount: weapon.damage,
+                        damage_type,
+                    });
+                    combo.hit();
+                    game_time.hitstop(HITSTOP_DURATION);
+                    // panned towards the target
+                    for sfx in combo.hit_sfx() {
+                        sfx_events.send(sfx.with_emitter(target));
+                    }
                 }
                 true
             },
@@ -219,17 +670,24 @@ fn player_system(
     }
 
     // animate sprite
-    if let Some((mut transform, mut animation_sprite, mut texture_atlas_sprite)) = children
-        .iter()
-        .next()
-        .and_then(|child| sprites.get_mut(*child).ok())
+    if let Some((mut transform, mut animation_sprite, mut texture_atlas_sprite)) = actor_parts
+        .sprite(entity)
+        .and_then(|sprite| sprites.get_mut(sprite).ok())
     {
-        if attack {
-            animation_sprite.set_animation("attack", false);
+        if dashing {
+            animation_sprite.set_animation("dash");
+        } else if attack {
+            animation_sprite.set_animation(&weapon.animation);
+        } else if swimming && !player.crouching {
+            animation_sprite.set_animation("swim");
+        } else if player.crouching && x_axis != 0 {
+            animation_sprite.set_animation("crawl");
+        } else if player.crouching {
+            animation_sprite.set_animation("crouch");
         } else if x_axis != 0 {
-            animation_sprite.set_animation("walk", true);
+            animation_sprite.set_animation("walk");
         } else {
-            animation_sprite.set_animation("wait", false);
+            animation_sprite.set_animation("wait");
         }
         texture_atlas_sprite.flip_x = flip_x < 0.0;
         transform.translation.x = transform.translation.x.abs() * flip_x;
@@ -238,12 +696,27 @@ fn player_system(
 fn on_collision_event_system(
     mut intersection_events: EventReader<IntersectionEvent>,
     mut contact_events: EventReader<ContactEvent>,
+    collision_lookup: CollisionLookup,
 ) {
     for event in intersection_events.iter() {
-        println!("{:?}", event);
+        debug!(
+            "{:?} {:?} {:?}",
+            event,
+            collision_lookup.kind(event.collider1),
+            collision_lookup.kind(event.collider2)
+        );
     }
     for event in contact_events.iter() {
-        println!("{:?}", event);
+        let (collider1, collider2) = match event {
+            ContactEvent::Started(collider1, collider2)
+            | ContactEvent::Stopped(collider1, collider2) => (*collider1, *collider2),
+        };
+        debug!(
+            "{:?} {:?} {:?}",
+            event,
+            collision_lookup.kind(collider1),
+            collision_lookup.kind(collider2)
+        );
     }
 }
 
@@ -252,174 +725,92 @@ fn on_ldtk_event_system(
     asset_server: Res<AssetServer>,
     mut cameras: Query<(&mut VirtualPosition, &mut Transform), With<Camera>>,
     mut events: EventReader<LdtkEvent>,
+    mut registry: ResMut<LdtkEntityRegistry>,
+    enemy_definitions: Res<EnemyDefinitions>,
+    mut palettes: ResMut<Palettes>,
+    mut images: ResMut<Assets<Image>>,
+    flags: Res<WorldFlags>,
 ) {
     for event in events.iter() {
+        let _span = debug_span!("spawn", ?event).entered();
         match event {
             LdtkEvent::SpawnPlayer(position) => {
                 let aseprite: Handle<Aseprite> = asset_server.load("images/character.json");
 
                 // spawn player
-                commands
-                    .spawn()
-                    .insert_bundle(RigidBodyBundle {
-                        position: (position.truncate() / RAPIER_SCALE).into(),
-                        mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
-                        ..Default::default()
-                    })
-                    .insert_bundle(ColliderBundle {
-                        shape: ColliderShape::capsule(
-                            (Vec2::new(0.0, 6.0) / RAPIER_SCALE).into(),
-                            (Vec2::new(0.0, -6.0) / RAPIER_SCALE).into(),
-                            4.0 / RAPIER_SCALE,
-                        )
-                        .into(),
-                        material: ColliderMaterial::new(1.0, 0.0).into(),
-                        ..Default::default()
-                    })
-                    .insert(ColliderPositionSync::Discrete)
-                    .insert(Actor::new())
-                    .insert(Player::default())
-                    .with_children(|parent| {
-                        parent
-                            .spawn_bundle(SpriteSheetBundle {
-                                transform: Transform::from_xyz(4.0, 6.0, 0.0),
-                                ..Default::default()
-                            })
-                            .insert(AnimationSprite::new(aseprite.clone()));
-
-                        // debug collider
-                        parent
-                            .spawn_bundle(
-                                GeometryBuilder::new()
-                                    .add(&shapes::Circle {
-                                        radius: 4.0,
-                                        center: Vec2::new(0.0, 6.0),
-                                    })
-                                    .add(&shapes::Circle {
-                                        radius: 4.0,
-                                        center: Vec2::new(0.0, -6.0),
-                                    })
-                                    .add(&shapes::Rectangle {
-                                        extents: Vec2::new(8.0, 12.0),
-                                        origin: RectangleOrigin::Center,
-                                    })
-                                    .build(
-                                        DrawMode::Fill(FillMode {
-                                            options: FillOptions::non_zero(),
-                                            color: Color::rgba(1.0, 0.0, 1.0, 0.2),
-                                        }),
-                                        Transform::from_xyz(0.0, 0.0, Z_COLLISION),
-                                    ),
-                            )
-                            .insert(DebugTarget)
-                            .insert(Visibility { is_visible: false });
+                actor_bundle(
+                    position.truncate(),
+                    player_shape(false),
+                    CollisionLayers::PLAYER,
+                )
+                .spawn(
+                    &mut commands,
+                    AnimationSprite::new(aseprite.clone()),
+                    "player",
+                )
+                .insert(Player::default())
+                .insert(Health::new(PLAYER_HEALTH))
+                .insert(Equipment::default())
+                .insert(AlwaysAnimate);
 
-                        // debug text
-                        parent
-                            .spawn_bundle(Text2dBundle {
-                                text: Text::with_section(
-                                    "player".to_string(),
-                                    TextStyle {
-                                        font: asset_server.load("fonts/hack.ttf"),
-                                        font_size: 6.0,
-                                        color: Color::rgb(1.0, 0.0, 1.0),
-                                    },
-                                    TextAlignment {
-                                        horizontal: HorizontalAlign::Center,
-                                        vertical: VerticalAlign::Center,
-                                    },
-                                ),
-                                transform: Transform::from_xyz(0.0, 28.0, Z_COLLISION + 1.0),
-                                ..Default::default()
-                            })
-                            .insert(DebugTarget)
-                            .insert(Visibility { is_visible: false });
-
-                        let (mut camera_position, mut camera_transform) = cameras.single_mut();
-                        camera_position.0.x = position.x;
-                        camera_transform.translation.x = position.x;
-                    });
+                let (mut camera_position, mut camera_transform) = cameras.single_mut();
+                camera_position.0.x = position.x;
+                camera_transform.translation.x = position.x;
             }
-            LdtkEvent::SpawnEnemy { name, position } if name == "test" => {
+            LdtkEvent::SpawnEnemy {
+                name,
+                position,
+                iid,
+            } if enemy_definitions.base(name) == "test" => {
+                // defeated for good in an earlier session
+                if iid
+                    .as_ref()
+                    .map_or(false, |iid| flags.bool(iid, "defeated") == Some(true))
+                {
+                    continue;
+                }
                 let aseprite: Handle<Aseprite> = asset_server.load("images/character.json");
-                // spawn player
-                commands
-                    .spawn()
-                    .insert_bundle(RigidBodyBundle {
-                        position: (position.truncate() / RAPIER_SCALE).into(),
-                        mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
-                        ..Default::default()
-                    })
-                    .insert_bundle(ColliderBundle {
-                        shape: ColliderShape::capsule(
-                            (Vec2::new(0.0, 6.0) / RAPIER_SCALE).into(),
-                            (Vec2::new(0.0, -6.0) / RAPIER_SCALE).into(),
-                            4.0 / RAPIER_SCALE,
-                        )
-                        .into(),
-                        material: ColliderMaterial::new(1.0, 0.0).into(),
-                        ..Default::default()
-                    })
-                    .insert(ColliderPositionSync::Discrete)
-                    .insert(Actor::new())
-                    .insert(Enemy)
-                    .with_children(|parent| {
-                        parent
-                            .spawn_bundle(SpriteSheetBundle {
-                                transform: Transform::from_xyz(4.0, 6.0, 0.0),
-                                ..Default::default()
-                            })
-                            .insert(AnimationSprite::new(aseprite.clone()));
-                        // collision debug
-                        parent
-                            .spawn_bundle(
-                                GeometryBuilder::new()
-                                    .add(&shapes::Circle {
-                                        radius: 4.0,
-                                        center: Vec2::new(0.0, 6.0),
-                                    })
-                                    .add(&shapes::Circle {
-                                        radius: 4.0,
-                                        center: Vec2::new(0.0, -6.0),
-                                    })
-                                    .add(&shapes::Rectangle {
-                                        extents: Vec2::new(8.0, 12.0),
-                                        origin: RectangleOrigin::Center,
-                                    })
-                                    .build(
-                                        DrawMode::Fill(FillMode {
-                                            options: FillOptions::non_zero(),
-                                            color: Color::rgba(1.0, 0.0, 1.0, 0.2),
-                                        }),
-                                        Transform::from_xyz(0.0, 0.0, Z_COLLISION),
-                                    ),
-                            )
-                            .insert(DebugTarget)
-                            .insert(Visibility { is_visible: false });
-                        parent
-                            .spawn_bundle(Text2dBundle {
-                                text: Text::with_section(
-                                    name,
-                                    TextStyle {
-                                        font: asset_server.load("fonts/hack.ttf"),
-                                        font_size: 6.0,
-                                        color: Color::rgb(1.0, 0.0, 1.0),
-                                    },
-                                    TextAlignment {
-                                        horizontal: HorizontalAlign::Center,
-                                        vertical: VerticalAlign::Center,
-                                    },
-                                ),
-                                transform: Transform::from_xyz(0.0, 28.0, Z_COLLISION + 1.0),
-                                ..Default::default()
-                            })
-                            .insert(DebugTarget)
-                            .insert(Visibility { is_visible: false });
+                let definition = enemy_definitions.get(name);
+                let palette = palettes.get(&mut images, name, &definition.palette);
+                // spawn enemy
+                let enemy = actor_bundle(
+                    position.truncate(),
+                    ColliderShape::capsule(
+                        (Vec2::new(0.0, 6.0) / RAPIER_SCALE).into(),
+                        (Vec2::new(0.0, -6.0) / RAPIER_SCALE).into(),
+                        4.0 / RAPIER_SCALE,
+                    ),
+                    CollisionLayers::ENEMY,
+                )
+                .with_sprite_effects(SpriteEffects {
+                    palette,
+                    ..Default::default()
+                })
+                .spawn(&mut commands, AnimationSprite::new(aseprite.clone()), name)
+                .insert(Enemy { name: name.clone() })
+                .insert(Health::new(definition.health))
+                .insert(Resistances(definition.resistances))
+                .insert(PathFollower::default())
+                .insert(Aggro::default())
+                .id();
+                if let Some(vision) = definition.vision {
+                    commands.entity(enemy).insert(Vision::from(vision));
+                }
+                if let Some(weight) = definition.camera_weight {
+                    commands.entity(enemy).insert(CameraTarget { weight });
+                }
+                if !definition.phases.is_empty() {
+                    commands
+                        .entity(enemy)
+                        .insert(BossPhases::new(definition.phases));
+                }
+                if let Some(iid) = iid {
+                    registry.bind(iid, enemy);
+                }
 
-                        let (mut camera_position, mut camera_transform) = cameras.single_mut();
-                        camera_position.0.x = position.x;
-                        camera_transform.translation.x = position.x;
-                    });
+                let (mut camera_position, mut camera_transform) = cameras.single_mut();
+                camera_position.0.x = position.x;
+                camera_transform.translation.x = position.x;
             }
             _ => {}
         }
@@ -0,0 +1,251 @@
+use crate::{
+    ldtk::{plugin::Ldtk, world::WorldMap},
+    save::SaveData,
+    Player,
+};
+use bevy::{prelude::*, sprite::Rect as SpriteRect};
+use std::collections::HashSet;
+
+const MINIMAP_WIDTH: f32 = 80.0;
+const MINIMAP_HEIGHT: f32 = 48.0;
+const MINIMAP_MARGIN: f32 = 2.0;
+
+pub struct MinimapPlugin;
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VisitedLevels>()
+            .add_startup_system(setup_system)
+            .add_system(visit_system)
+            .add_system(rebuild_system)
+            .add_system(player_marker_system)
+            .add_system(toggle_system);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VisitedLevels(pub HashSet<i64>);
+
+#[derive(Component)]
+struct Minimap {
+    bounds: SpriteRect,
+    scale: f32,
+}
+impl Minimap {
+    fn to_minimap(&self, point: Vec2) -> Vec2 {
+        (point - self.bounds.min) * self.scale
+    }
+}
+
+#[derive(Component)]
+struct MinimapCell;
+
+#[derive(Component)]
+struct MinimapPlayer;
+
+fn setup_system(mut commands: Commands) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(MINIMAP_MARGIN),
+                    top: Val::Px(MINIMAP_MARGIN),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(MINIMAP_WIDTH), Val::Px(MINIMAP_HEIGHT)),
+                display: Display::None,
+                ..Default::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            ..Default::default()
+        })
+        .insert(Minimap {
+            bounds: SpriteRect {
+                min: Vec2::ZERO,
+                max: Vec2::ONE,
+            },
+            scale: 1.0,
+        });
+}
+
+fn visit_system(world_map: Res<WorldMap>, mut visited: ResMut<VisitedLevels>) {
+    if let Some(uid) = world_map.active {
+        if !visited.0.contains(&uid) {
+            visited.0.insert(uid);
+        }
+    }
+}
+
+fn rebuild_system(
+    mut commands: Commands,
+    world_map: Res<WorldMap>,
+    visited: Res<VisitedLevels>,
+    save: Res<SaveData>,
+    ldtk_handle: Option<Res<Handle<Ldtk>>>,
+    ldtks: Res<Assets<Ldtk>>,
+    mut minimaps: Query<(Entity, &mut Minimap)>,
+    cells: Query<Entity, With<MinimapCell>>,
+) {
+    if !world_map.is_changed() && !visited.is_changed() && !save.is_changed() {
+        return;
+    }
+    let (minimap_entity, mut minimap) = match minimaps.get_single_mut() {
+        Ok(minimap) => minimap,
+        Err(_) => return,
+    };
+    for entity in cells.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let bounds = match world_map
+        .levels
+        .iter()
+        .map(|level| SpriteRect {
+            min: level.rect.min,
+            max: level.rect.max,
+        })
+        .reduce(|a, b| SpriteRect {
+            min: a.min.min(b.min),
+            max: a.max.max(b.max),
+        }) {
+        Some(bounds) => bounds,
+        None => return,
+    };
+    let size = bounds.max - bounds.min;
+    minimap.bounds = bounds;
+    minimap.scale = (MINIMAP_WIDTH / size.x).min(MINIMAP_HEIGHT / size.y);
+
+    let ldtk = ldtk_handle.and_then(|handle| ldtks.get(&*handle));
+    commands.entity(minimap_entity).with_children(|parent| {
+        // level rects
+        for level in world_map.levels.iter() {
+            let is_visited = visited.0.contains(&level.uid);
+            let color = if world_map.active == Some(level.uid) {
+                Color::rgba(1.0, 1.0, 1.0, 0.5)
+            } else if is_visited {
+                Color::rgba(1.0, 1.0, 1.0, 0.3)
+            } else {
+                Color::rgba(1.0, 1.0, 1.0, 0.1)
+            };
+            spawn_cell(parent, &minimap, &level.rect, color);
+
+            // medal in the top right corner
+            if let Some(medal) = save.medals.get(&level.identifier) {
+                let size = Vec2::splat(3.0 / minimap.scale);
+                let max = level.rect.max - Vec2::splat(1.0 / minimap.scale);
+                spawn_cell(
+                    parent,
+                    &minimap,
+                    &SpriteRect {
+                        min: max - size,
+                        max,
+                    },
+                    medal.color(),
+                );
+            }
+
+            // terrain from int grid layers, merged per row
+            let layer_instances = ldtk
+                .filter(|_| is_visited)
+                .and_then(|ldtk| {
+                    ldtk.data
+                        .levels
+                        .iter()
+                        .find(|ldtk_level| ldtk_level.uid == level.uid)
+                })
+                .and_then(|ldtk_level| ldtk_level.layer_instances.as_ref());
+            for layer_instance in layer_instances
+                .into_iter()
+                .flatten()
+                .filter(|layer_instance| layer_instance.layer_instance_type == "IntGrid")
+            {
+                let grid_size = layer_instance.grid_size as f32;
+                let c_wid = layer_instance.c_wid.max(1) as usize;
+                for (y, row) in layer_instance.int_grid_csv.chunks(c_wid).enumerate() {
+                    let mut x = 0;
+                    while x < row.len() {
+                        if row[x] == 0 {
+                            x += 1;
+                            continue;
+                        }
+                        let start = x;
+                        while x < row.len() && row[x] != 0 {
+                            x += 1;
+                        }
+                        let min = level.origin()
+                            + Vec2::new(start as f32 * grid_size, -((y + 1) as f32) * grid_size);
+                        let max = min + Vec2::new((x - start) as f32 * grid_size, grid_size);
+                        spawn_cell(
+                            parent,
+                            &minimap,
+                            &SpriteRect { min, max },
+                            Color::rgba(1.0, 1.0, 1.0, 0.8),
+                        );
+                    }
+                }
+            }
+        }
+
+        // player
+        parent
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Px(2.0), Val::Px(2.0)),
+                    ..Default::default()
+                },
+                color: Color::RED.into(),
+                ..Default::default()
+            })
+            .insert(MinimapCell)
+            .insert(MinimapPlayer);
+    });
+}
+
+fn spawn_cell(parent: &mut ChildBuilder, minimap: &Minimap, rect: &SpriteRect, color: Color) {
+    let min = minimap.to_minimap(rect.min);
+    let size = (rect.max - rect.min) * minimap.scale;
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(min.x),
+                    bottom: Val::Px(min.y),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(size.x.max(1.0)), Val::Px(size.y.max(1.0))),
+                ..Default::default()
+            },
+            color: color.into(),
+            ..Default::default()
+        })
+        .insert(MinimapCell);
+}
+
+fn player_marker_system(
+    minimaps: Query<&Minimap>,
+    players: Query<&Transform, With<Player>>,
+    mut markers: Query<&mut Style, With<MinimapPlayer>>,
+) {
+    if let (Ok(minimap), Ok(transform)) = (minimaps.get_single(), players.get_single()) {
+        let position = minimap.to_minimap(transform.translation.truncate());
+        for mut style in markers.iter_mut() {
+            style.position.left = Val::Px(position.x - 1.0);
+            style.position.bottom = Val::Px(position.y - 1.0);
+        }
+    }
+}
+
+fn toggle_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut minimaps: Query<&mut Style, With<Minimap>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        for mut style in minimaps.iter_mut() {
+            style.display = match style.display {
+                Display::None => Display::Flex,
+                Display::Flex => Display::None,
+            };
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{
+    actor::{mirror_sprite, ActorParts, Grounded},
+    animation::AnimationSprite,
+    damage::Slowed,
+    game_time::GameTime,
+    Actor, Direction,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+// in cells, how far a jump link reaches
+const JUMP_ACROSS: i32 = 4;
+const JUMP_UP: i32 = 3;
+const JUMP_DOWN: i32 = 4;
+// search budget per query
+const MAX_EXPANDED: usize = 4096;
+const REPATH_INTERVAL: f32 = 0.5;
+// pixels per second
+const CHASE_SPEED: f32 = 32.0;
+// how close a step counts as reached, the body center sits above the cell center
+const ARRIVE_DISTANCE: Vec2 = Vec2::new(3.0, 12.0);
+
+// a* over the "Collision" intgrid layers, for actors following a target across platforms.
+// grids assume the default gravity
+pub struct NavPlugin;
+impl Plugin for NavPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(follow_system);
+    }
+}
+
+type Cell = (i32, i32);
+
+// how a path step is reached from the one before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavLink {
+    Walk,
+    Jump,
+    Fall,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NavStep {
+    // center of the cell, world space in pixels
+    pub position: Vec2,
+    pub link: NavLink,
+}
+
+// solid cells of one level, row major from the top like the ldtk csv
+#[derive(Component)]
+pub struct NavGrid {
+    // top left corner, world space in pixels
+    origin: Vec2,
+    cell_size: f32,
+    width: i32,
+    height: i32,
+    solid: Vec<bool>,
+}
+
+pub fn spawn_nav_grid(
+    commands: &mut Commands,
+    origin: Vec2,
+    cell_size: f32,
+    width: usize,
+    solid: Vec<bool>,
+) -> Entity {
+    let height = solid.len() / width.max(1);
+    commands
+        .spawn()
+        .insert(NavGrid {
+            origin,
+            cell_size,
+            width: width as i32,
+            height: height as i32,
+            solid,
+        })
+        .id()
+}
+
+impl NavGrid {
+    pub fn contains(&self, position: Vec2) -> bool {
+        let (x, y) = self.cell(position);
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    fn cell(&self, position: Vec2) -> Cell {
+        let offset = (position - self.origin) / self.cell_size;
+        (offset.x.floor() as i32, (-offset.y).floor() as i32)
+    }
+
+    fn center(&self, (x, y): Cell) -> Vec2 {
+        self.origin + Vec2::new(x as f32 + 0.5, -(y as f32 + 0.5)) * self.cell_size
+    }
+
+    // the sides block so paths stay in the level, above and below are open
+    fn solid(&self, (x, y): Cell) -> bool {
+        if x < 0 || x >= self.width {
+            return true;
+        }
+        if y < 0 || y >= self.height {
+            return false;
+        }
+        self.solid[(y * self.width + x) as usize]
+    }
+
+    fn standable(&self, (x, y): Cell) -> bool {
+        !self.solid((x, y)) && self.solid((x, y + 1))
+    }
+
+    // where something in the cell lands when it falls
+    fn ground(&self, (x, y): Cell) -> Option<Cell> {
+        (y.max(0)..self.height)
+            .map(|y| (x, y))
+            .take_while(|cell| !self.solid(*cell))
+            .find(|cell| self.standable(*cell))
+    }
+
+    // up from the start, across one row above the higher end and down onto the target
+    fn jump_clear(&self, from: Cell, to: Cell) -> bool {
+        let peak = from.1.min(to.1) - 1;
+        (peak..=from.1).all(|y| !self.solid((from.0, y)))
+            && (from.0.min(to.0)..=from.0.max(to.0)).all(|x| !self.solid((x, peak)))
+            && (peak..=to.1).all(|y| !self.solid((to.0, y)))
+    }
+
+    fn links(&self, (x, y): Cell) -> Vec<(Cell, NavLink, u32)> {
+        let mut links = vec![];
+        for direction in [-1, 1] {
+            let side = (x + direction, y);
+            if self.standable(side) {
+                links.push((side, NavLink::Walk, 10));
+            } else if !self.solid(side) {
+                if let Some(landing) = self.ground(side) {
+                    links.push((landing, NavLink::Fall, 10 + 10 * (landing.1 - y) as u32));
+                }
+            }
+            for across in 1..=JUMP_ACROSS {
+                for rise in -JUMP_DOWN..=JUMP_UP {
+                    let target = (x + direction * across, y - rise);
+                    if (across == 1 && rise == 0)
+                        || !self.standable(target)
+                        || !self.jump_clear((x, y), target)
+                    {
+                        continue;
+                    }
+                    links.push((
+                        target,
+                        NavLink::Jump,
+                        20 + 10 * (across + rise.abs()) as u32,
+                    ));
+                }
+            }
+        }
+        links
+    }
+
+    // steps after the start, the goal is where the target would land
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<NavStep>> {
+        let start = self.ground(self.cell(from))?;
+        let goal = self.ground(self.cell(to))?;
+        let heuristic =
+            |cell: Cell| 10 * ((cell.0 - goal.0).abs() + (cell.1 - goal.1).abs()) as u32;
+
+        let mut open = BinaryHeap::new();
+        let mut costs = HashMap::new();
+        let mut came_from: HashMap<Cell, (Cell, NavLink)> = HashMap::new();
+        open.push(Reverse((heuristic(start), start)));
+        costs.insert(start, 0);
+        let mut expanded = 0;
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == goal {
+                let mut steps = vec![];
+                let mut current = goal;
+                while let Some((previous, link)) = came_from.get(&current) {
+                    steps.push(NavStep {
+                        position: self.center(current),
+                        link: *link,
+                    });
+                    current = *previous;
+                }
+                steps.reverse();
+                return Some(steps);
+            }
+            expanded += 1;
+            if expanded > MAX_EXPANDED {
+                break;
+            }
+            let cost = costs[&cell];
+            for (next, link, step_cost) in self.links(cell) {
+                let next_cost = cost + step_cost;
+                if costs.get(&next).map_or(true, |known| next_cost < *known) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, (cell, link));
+                    open.push(Reverse((next_cost + heuristic(next), next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+// walks and jumps along a path to the target, repathing as it moves
+#[derive(Component)]
+pub struct PathFollower {
+    pub target: Option<Vec2>,
+    path: Vec<NavStep>,
+    repath: Timer,
+    // cell of the target at the last search
+    searched: Option<Cell>,
+    // horizontal speed of the current jump, fast enough to make the gap
+    air_speed: Option<f32>,
+}
+impl Default for PathFollower {
+    fn default() -> Self {
+        Self {
+            target: None,
+            path: vec![],
+            repath: Timer::from_seconds(REPATH_INTERVAL, true),
+            searched: None,
+            air_speed: None,
+        }
+    }
+}
+
+fn follow_system(
+    time: Res<GameTime>,
+    rapier_config: Res<RapierConfiguration>,
+    grids: Query<&NavGrid>,
+    mut followers: Query<(
+        Entity,
+        &mut PathFollower,
+        &mut Actor,
+        &GlobalTransform,
+        &mut RigidBodyVelocityComponent,
+        Option<&Grounded>,
+        Option<&Slowed>,
+    )>,
+    actor_parts: ActorParts,
+    mut sprites: Query<(
+        &mut Transform,
+        &mut AnimationSprite,
+        &mut TextureAtlasSprite,
+    )>,
+) {
+    if time.frozen() {
+        return;
+    }
+    let gravity = rapier_config.gravity.norm() * rapier_config.scale;
+    for (entity, mut follower, mut actor, global_transform, mut rb_velocity, grounded, slowed) in
+        followers.iter_mut()
+    {
+        let target = match follower.target {
+            Some(target) => target,
+            None => {
+                follower.path.clear();
+                follower.searched = None;
+                continue;
+            }
+        };
+        let position = global_transform.translation.truncate();
+        let grid = match grids.iter().find(|grid| grid.contains(position)) {
+            Some(grid) => grid,
+            None => continue,
+        };
+        // an empty path is there or unreachable, only worth another search once the target
+        // moved to another cell
+        let target_cell = grid.cell(target);
+        if follower.repath.tick(time.delta()).just_finished()
+            || (follower.path.is_empty() && follower.searched != Some(target_cell))
+        {
+            follower.path = grid.find_path(position, target).unwrap_or_default();
+            follower.searched = Some(target_cell);
+        }
+        while let Some(step) = follower.path.first() {
+            let offset = (step.position - position).abs();
+            if offset.x > ARRIVE_DISTANCE.x || offset.y > ARRIVE_DISTANCE.y {
+                break;
+            }
+            follower.path.remove(0);
+        }
+
+        let mut velocity =
+            Vec2::new(rb_velocity.linvel.x, rb_velocity.linvel.y) * rapier_config.scale;
+        let next = follower.path.first().copied();
+        let walk = next.map_or(0.0, |step| (step.position.x - position.x).signum());
+        if grounded.is_some() && velocity.y <= 0.0 {
+            follower.air_speed = None;
+            if let Some(step) = next.filter(|step| step.link == NavLink::Jump) {
+                // clear one cell above the higher end, then fall onto the target
+                let height = step.position.y - position.y;
+                let rise = height.max(0.0) + grid.cell_size;
+                let speed = (2.0 * gravity * rise).sqrt();
+                let flight = speed / gravity + (2.0 * (rise - height) / gravity).sqrt();
+                velocity.y = speed;
+                follower.air_speed =
+                    Some(((step.position.x - position.x).abs() / flight).max(CHASE_SPEED));
+            }
+        }
+        // jumps keep the speed they need to make the gap
+        let chase_speed = CHASE_SPEED * slowed.map_or(1.0, |slowed| slowed.factor);
+        velocity.x = walk * follower.air_speed.unwrap_or(chase_speed);
+        rb_velocity.linvel = (velocity / rapier_config.scale).into();
+
+        if walk < 0.0 {
+            actor.direction = Direction::Left;
+        } else if walk > 0.0 {
+            actor.direction = Direction::Right;
+        }
+        if let Some((mut transform, mut animation_sprite, mut texture_atlas_sprite)) = actor_parts
+            .sprite(entity)
+            .and_then(|sprite| sprites.get_mut(sprite).ok())
+        {
+            if walk != 0.0 {
+                animation_sprite.set_animation("walk");
+            } else {
+                animation_sprite.set_animation("wait");
+            }
+            mirror_sprite(&mut transform, &mut texture_atlas_sprite, actor.direction);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{
+    animation::{Aseprite, SheetSlice},
+    asset_error::AssetErrorEvent,
+};
+use anyhow::{anyhow, Result};
+use bevy::{prelude::*, sprite::Rect};
+
+// panels for dialogue boxes and menus, cut from an aseprite slice with a center (a 9-slice in
+// aseprite's slice properties). the corners keep their size, the edges and the center stretch
+pub struct NineSlicePlugin;
+impl Plugin for NineSlicePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(build_system)
+            .add_system(layout_system.after(build_system));
+    }
+}
+
+#[derive(Component)]
+pub struct NineSlice {
+    pub aseprite: Handle<Aseprite>,
+    pub slice: String,
+    // of the whole panel in pixels, never smaller than the corners
+    pub size: Vec2,
+    pub color: Color,
+}
+impl NineSlice {
+    pub fn new(aseprite: Handle<Aseprite>, slice: &str, size: Vec2) -> Self {
+        Self {
+            aseprite,
+            slice: slice.to_string(),
+            size,
+            color: Color::WHITE,
+        }
+    }
+}
+
+// the part sprites, from the top left row by row. empty when the slice couldn't be used
+#[derive(Component)]
+struct NineSliceParts {
+    parts: Vec<Entity>,
+    // widths of the left and right columns, heights of the top and bottom rows
+    borders: [f32; 4],
+}
+
+#[derive(Component)]
+struct NineSlicePart;
+
+// the nine rects of a sliced panel, in sheet pixels
+fn part_rects(slice: &SheetSlice) -> Option<Vec<Rect>> {
+    let center = slice.center.as_ref()?;
+    let xs = [
+        slice.bounds.min.x,
+        center.min.x,
+        center.max.x,
+        slice.bounds.max.x,
+    ];
+    let ys = [
+        slice.bounds.min.y,
+        center.min.y,
+        center.max.y,
+        slice.bounds.max.y,
+    ];
+    let mut rects = vec![];
+    for row in ys.windows(2) {
+        for column in xs.windows(2) {
+            rects.push(Rect {
+                min: Vec2::new(column[0], row[0]),
+                max: Vec2::new(column[1], row[1]),
+            });
+        }
+    }
+    Some(rects)
+}
+
+fn create_texture_atlas(
+    aseprite: &Aseprite,
+    slice: &str,
+    asset_server: &AssetServer,
+) -> Result<(TextureAtlas, [f32; 4])> {
+    let sheet_slice = aseprite
+        .slices
+        .get(slice)
+        .ok_or_else(|| anyhow!("no slice {}", slice))?;
+    let rects = part_rects(sheet_slice).ok_or_else(|| anyhow!("slice {} has no center", slice))?;
+    let mut texture_atlas =
+        TextureAtlas::new_empty(aseprite.texture(asset_server)?, aseprite.size());
+    for rect in rects.iter() {
+        texture_atlas.add_texture(*rect);
+    }
+    let borders = [
+        rects[0].width(),
+        rects[2].width(),
+        rects[0].height(),
+        rects[6].height(),
+    ];
+    Ok((texture_atlas, borders))
+}
+
+// spawns the parts once the aseprite is loaded
+fn build_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    aseprites: Res<Assets<Aseprite>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    panels: Query<(Entity, &NineSlice), Without<NineSliceParts>>,
+    mut error_events: EventWriter<AssetErrorEvent>,
+) {
+    for (entity, nine_slice) in panels.iter() {
+        let aseprite = match aseprites.get(&nine_slice.aseprite) {
+            Some(aseprite) => aseprite,
+            None => continue,
+        };
+        let (texture_atlas, borders) =
+            match create_texture_atlas(aseprite, &nine_slice.slice, &asset_server) {
+                Ok(texture_atlas) => texture_atlas,
+                Err(err) => {
+                    error_events.send(AssetErrorEvent::new(
+                        aseprite.file_path.display().to_string(),
+                        &err,
+                    ));
+                    commands.entity(entity).insert(NineSliceParts {
+                        parts: vec![],
+                        borders: [0.0; 4],
+                    });
+                    continue;
+                }
+            };
+        let texture_atlas = texture_atlases.add(texture_atlas);
+        let mut parts = vec![];
+        commands.entity(entity).with_children(|parent| {
+            for index in 0..9 {
+                parts.push(
+                    parent
+                        .spawn_bundle(SpriteSheetBundle {
+                            sprite: TextureAtlasSprite::new(index),
+                            texture_atlas: texture_atlas.clone(),
+                            ..Default::default()
+                        })
+                        .insert(NineSlicePart)
+                        .id(),
+                );
+            }
+        });
+        commands
+            .entity(entity)
+            .insert(NineSliceParts { parts, borders });
+    }
+}
+
+// stretches the edges and the center to the panel size, centered on the entity
+fn layout_system(
+    panels: Query<(&NineSlice, &NineSliceParts), Or<(Changed<NineSlice>, Added<NineSliceParts>)>>,
+    mut parts: Query<(&mut Transform, &mut TextureAtlasSprite), With<NineSlicePart>>,
+) {
+    for (nine_slice, nine_slice_parts) in panels.iter() {
+        let [left, right, top, bottom] = nine_slice_parts.borders;
+        let size = nine_slice.size.max(Vec2::new(left + right, top + bottom));
+        let half = size * 0.5;
+        // column and row edges, the image's top row is the panel's upper one
+        let xs = [-half.x, -half.x + left, half.x - right, half.x];
+        let ys = [half.y, half.y - top, -half.y + bottom, -half.y];
+        for (index, part) in nine_slice_parts.parts.iter().enumerate() {
+            let (column, row) = (index % 3, index / 3);
+            if let Ok((mut transform, mut sprite)) = parts.get_mut(*part) {
+                let min = Vec2::new(xs[column], ys[row + 1]);
+                let max = Vec2::new(xs[column + 1], ys[row]);
+                transform.translation = ((min + max) * 0.5).extend(0.0);
+                sprite.custom_size = Some(max - min);
+                sprite.color = nine_slice.color;
+            }
+        }
+    }
+}
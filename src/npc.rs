@@ -0,0 +1,274 @@
+use crate::{
+    actor::{mirror_sprite, ActorBundle, ActorParts},
+    animation::{AnimationSprite, Aseprite},
+    bitmap_font::{text_size, BitmapText, BitmapTextBundle},
+    collision::CollisionLayers,
+    game_time::GameTime,
+    interact::{InteractEvent, Interactable},
+    ldtk::{plugin::LdtkEvent, registry::LdtkEntityRegistry, world::WorldMap},
+    nine_slice::NineSlice,
+    player_shape,
+    rng::GameRng,
+    world_flags::{FlagValue, WorldFlags},
+    Actor, Direction, Player,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+
+// pixels per second
+const WALK_SPEED: f32 = 24.0;
+// seconds standing around between walks
+const IDLE_TIME: (f32, f32) = (1.5, 4.0);
+// gives up on a walk target it can't reach, e.g. behind a wall
+const WALK_TIMEOUT: f32 = 4.0;
+const ARRIVE_DISTANCE: f32 = 2.0;
+// stops and turns to the player within this distance
+const FACE_DISTANCE: f32 = 40.0;
+const BUBBLE_OFFSET: Vec3 = Vec3::new(0.0, 24.0, 1.0);
+const BUBBLE_DURATION: f32 = 3.0;
+// pixels between the text and the panel edge
+const BUBBLE_PADDING: Vec2 = Vec2::new(4.0, 3.0);
+
+// friendly actors that wander around where they were placed and talk when interacted with
+pub struct NpcPlugin;
+impl Plugin for NpcPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_system)
+            .add_system(wander_system)
+            .add_system(dialogue_system)
+            .add_system(bubble_system);
+    }
+}
+
+enum NpcState {
+    Idle(Timer),
+    // offset from home along the level's right axis, in pixels
+    Walk { target: f32, timeout: Timer },
+}
+
+#[derive(Component)]
+pub struct Npc {
+    pub name: String,
+    home: Vec2,
+    radius: f32,
+    state: NpcState,
+    dialogue: Vec<String>,
+    // next line to say
+    line: usize,
+}
+
+#[derive(Component)]
+struct SpeechBubble(Timer);
+
+fn spawn_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut events: EventReader<LdtkEvent>,
+    mut registry: ResMut<LdtkEntityRegistry>,
+    flags: Res<WorldFlags>,
+) {
+    for event in events.iter() {
+        let (name, position, dialogue, radius, iid) = match event {
+            LdtkEvent::SpawnNpc {
+                name,
+                position,
+                dialogue,
+                radius,
+                iid,
+            } => (name, position, dialogue, radius, iid),
+            _ => continue,
+        };
+        // picks up the conversation where it was left
+        let line = iid
+            .as_ref()
+            .and_then(|iid| flags.int(iid, "line"))
+            .unwrap_or(0)
+            .max(0) as usize;
+        let aseprite: Handle<Aseprite> = asset_server.load("images/character.json");
+        // no hurtbox, out of combat
+        let npc = ActorBundle::new(
+            position.truncate(),
+            player_shape(false),
+            CollisionLayers::NPC,
+        )
+        .with_foot_sensor(Vec2::new(3.0, 1.0), Vec2::new(0.0, -10.0))
+        .spawn(&mut commands, AnimationSprite::new(aseprite), name)
+        .insert(Npc {
+            name: name.clone(),
+            home: position.truncate(),
+            radius: radius.max(0.0),
+            state: NpcState::Idle(Timer::from_seconds(IDLE_TIME.0, false)),
+            dialogue: dialogue.clone(),
+            line,
+        })
+        .insert(Interactable {
+            label: Some("talk".to_string()),
+        })
+        .id();
+        if let Some(iid) = iid {
+            registry.bind(iid, npc);
+        }
+    }
+}
+
+fn wander_system(
+    time: Res<GameTime>,
+    mut rng: ResMut<GameRng>,
+    world_map: Res<WorldMap>,
+    rapier_config: Res<RapierConfiguration>,
+    players: Query<&GlobalTransform, With<Player>>,
+    mut npcs: Query<(
+        Entity,
+        &mut Npc,
+        &mut Actor,
+        &RigidBodyPositionComponent,
+        &mut RigidBodyVelocityComponent,
+    )>,
+    actor_parts: ActorParts,
+    mut sprites: Query<(
+        &mut Transform,
+        &mut AnimationSprite,
+        &mut TextureAtlasSprite,
+    )>,
+) {
+    if time.frozen() {
+        return;
+    }
+    let rotation = world_map.gravity().rotation();
+    let to_local = rotation.transpose();
+    let player_position = players
+        .get_single()
+        .ok()
+        .map(|global_transform| global_transform.translation.truncate());
+
+    for (entity, mut npc, mut actor, rb_position, mut rb_velocity) in npcs.iter_mut() {
+        let translation = rb_position.position.translation.vector;
+        let position = Vec2::new(translation.x, translation.y) * rapier_config.scale;
+        let offset = (to_local * (position - npc.home)).x;
+        let to_player = player_position
+            .map(|player_position| to_local * (player_position - position))
+            .filter(|to_player| to_player.length() <= FACE_DISTANCE);
+
+        let mut walk = 0.0;
+        if let Some(to_player) = to_player {
+            actor.direction = if to_player.x < 0.0 {
+                Direction::Left
+            } else {
+                Direction::Right
+            };
+        } else {
+            let radius = npc.radius;
+            let next_state = match &mut npc.state {
+                NpcState::Idle(timer) => {
+                    timer.tick(time.delta()).finished().then(|| NpcState::Walk {
+                        target: rng.gen_range(-radius..=radius),
+                        timeout: Timer::from_seconds(WALK_TIMEOUT, false),
+                    })
+                }
+                NpcState::Walk { target, timeout } => {
+                    let distance = *target - offset;
+                    if distance.abs() <= ARRIVE_DISTANCE || timeout.tick(time.delta()).finished() {
+                        let idle_time = rng.gen_range(IDLE_TIME.0..=IDLE_TIME.1);
+                        Some(NpcState::Idle(Timer::from_seconds(idle_time, false)))
+                    } else {
+                        walk = distance.signum();
+                        None
+                    }
+                }
+            };
+            if let Some(next_state) = next_state {
+                npc.state = next_state;
+            }
+            if walk < 0.0 {
+                actor.direction = Direction::Left;
+            } else if walk > 0.0 {
+                actor.direction = Direction::Right;
+            }
+        }
+
+        // only the walking axis, gravity keeps the other one
+        let mut local_velocity = to_local * Vec2::new(rb_velocity.linvel.x, rb_velocity.linvel.y);
+        local_velocity.x = walk * WALK_SPEED / rapier_config.scale;
+        rb_velocity.linvel = (rotation * local_velocity).into();
+
+        if let Some((mut transform, mut animation_sprite, mut texture_atlas_sprite)) = actor_parts
+            .sprite(entity)
+            .and_then(|sprite| sprites.get_mut(sprite).ok())
+        {
+            if walk != 0.0 {
+                animation_sprite.set_animation("walk");
+            } else {
+                animation_sprite.set_animation("wait");
+            }
+            mirror_sprite(&mut transform, &mut texture_atlas_sprite, actor.direction);
+        }
+    }
+}
+
+// each interaction says the next line above the npc, looping around
+fn dialogue_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut events: EventReader<InteractEvent>,
+    registry: Res<LdtkEntityRegistry>,
+    mut flags: ResMut<WorldFlags>,
+    mut npcs: Query<(&mut Npc, Option<&Children>)>,
+    bubbles: Query<Entity, With<SpeechBubble>>,
+) {
+    for event in events.iter() {
+        let (mut npc, children) = match npcs.get_mut(event.entity) {
+            Ok(npc) => npc,
+            Err(_) => continue,
+        };
+        if npc.dialogue.is_empty() {
+            continue;
+        }
+        let text = npc.dialogue[npc.line % npc.dialogue.len()].clone();
+        npc.line = (npc.line + 1) % npc.dialogue.len();
+        if let Some(iid) = registry.iid(event.entity) {
+            flags.set(iid, "line", FlagValue::Int(npc.line as i64));
+        }
+        info!("{}: {}", npc.name, text);
+
+        for child in children.into_iter().flat_map(|children| children.iter()) {
+            if bubbles.get(*child).is_ok() {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        let bubble = commands
+            .spawn_bundle(BitmapTextBundle::new(
+                BitmapText::new(text, Color::WHITE).with_anchor(Vec2::new(0.0, -0.5)),
+                Transform::from_translation(BUBBLE_OFFSET),
+            ))
+            .insert(SpeechBubble(Timer::from_seconds(BUBBLE_DURATION, false)))
+            .with_children(|parent| {
+                // behind the glyphs, from the bottom of the text up
+                let size = text_size(&text) + BUBBLE_PADDING * 2.0;
+                parent
+                    .spawn_bundle((
+                        Transform::from_xyz(0.0, size.y * 0.5 - BUBBLE_PADDING.y, -0.5),
+                        GlobalTransform::identity(),
+                    ))
+                    .insert(NineSlice::new(
+                        asset_server.load("images/panel.json"),
+                        "bubble",
+                        size,
+                    ));
+            })
+            .id();
+        commands.entity(event.entity).push_children(&[bubble]);
+    }
+}
+
+fn bubble_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut bubbles: Query<(Entity, &mut SpeechBubble)>,
+) {
+    for (entity, mut bubble) in bubbles.iter_mut() {
+        if bubble.0.tick(time.delta()).just_finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{
+    audio::{Stinger, StingerEvent},
+    bitmap_font::{BitmapText, BitmapTextBundle, ScreenPosition, SCREEN_HALF_SIZE},
+    damage::{DamageSystem, KillEvent},
+    despawn::MarkedForDespawn,
+    ldtk::{
+        plugin::LdtkEvent,
+        world::{Objective, WorldMap},
+    },
+    pickup::{CollectEvent, PickupKind},
+    speedrun::GoalReachedEvent,
+    Enemy,
+};
+use bevy::prelude::*;
+
+// progress on the objectives of the active level, shown at the top of the screen
+pub struct ObjectivesPlugin;
+impl Plugin for ObjectivesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ObjectiveTracker>()
+            .add_startup_system(setup_system)
+            .add_system(tracker_system.after(DamageSystem))
+            .add_system(hud_system);
+    }
+}
+
+#[derive(Default)]
+pub struct ObjectiveTracker {
+    pub objectives: Vec<Objective>,
+    pub defeated: u32,
+    // enemies still alive in the level
+    pub remaining: u32,
+    pub keys: u32,
+    pub exit_reached: bool,
+    completed: bool,
+}
+impl ObjectiveTracker {
+    // current and required count
+    pub fn progress(&self, objective: Objective) -> (u32, u32) {
+        match objective {
+            Objective::DefeatAll => (self.defeated, self.defeated + self.remaining),
+            Objective::ReachExit => (self.exit_reached as u32, 1),
+            Objective::CollectKeys(count) => (self.keys.min(count), count),
+        }
+    }
+
+    pub fn complete(&self, objective: Objective) -> bool {
+        let (current, required) = self.progress(objective);
+        current >= required
+    }
+
+    pub fn all_complete(&self) -> bool {
+        self.objectives
+            .iter()
+            .all(|objective| self.complete(*objective))
+    }
+}
+
+#[derive(Component)]
+struct ObjectivesHud;
+
+fn setup_system(mut commands: Commands) {
+    commands
+        .spawn_bundle(BitmapTextBundle::new(
+            BitmapText::new("", Color::WHITE).with_anchor(Vec2::new(0.0, 0.5)),
+            Transform::identity(),
+        ))
+        .insert(ScreenPosition(Vec2::new(0.0, SCREEN_HALF_SIZE.y - 2.0)))
+        .insert(ObjectivesHud);
+}
+
+fn tracker_system(
+    mut ldtk_events: EventReader<LdtkEvent>,
+    mut kill_events: EventReader<KillEvent>,
+    mut collect_events: EventReader<CollectEvent>,
+    mut goal_events: EventReader<GoalReachedEvent>,
+    mut stinger_events: EventWriter<StingerEvent>,
+    world_map: Res<WorldMap>,
+    mut tracker: ResMut<ObjectiveTracker>,
+    enemies: Query<(Entity, &GlobalTransform, Option<&MarkedForDespawn>), With<Enemy>>,
+) {
+    for event in ldtk_events.iter() {
+        if let LdtkEvent::LevelActivated(identifier) = event {
+            *tracker = ObjectiveTracker {
+                objectives: world_map
+                    .find(identifier)
+                    .map(|level| level.objectives.clone())
+                    .unwrap_or_default(),
+                ..Default::default()
+            };
+        }
+    }
+    let level = match world_map.active_level() {
+        Some(level) => level,
+        None => return,
+    };
+    let in_level = |global_transform: &GlobalTransform| {
+        level.contains(global_transform.translation.truncate())
+    };
+
+    // read in the frame of the kill, after the damage system. the killed enemies are still there
+    // and only marked for despawn at the end of the stage
+    let killed = kill_events
+        .iter()
+        .filter_map(|event| enemies.get(event.target).ok())
+        .filter(|(_, global_transform, _)| in_level(global_transform))
+        .map(|(entity, _, _)| entity)
+        .collect::<Vec<_>>();
+    let remaining = enemies
+        .iter()
+        .filter(|(entity, global_transform, marked)| {
+            marked.is_none() && !killed.contains(entity) && in_level(global_transform)
+        })
+        .count() as u32;
+    let keys = collect_events
+        .iter()
+        .filter(|event| event.kind == PickupKind::Key)
+        .count() as u32;
+    let exit_reached = goal_events.iter().count() > 0;
+
+    // only touch the tracker on changes, the hud follows its change detection
+    if !killed.is_empty() || keys > 0 || remaining != tracker.remaining {
+        tracker.defeated += killed.len() as u32;
+        tracker.remaining = remaining;
+        tracker.keys += keys;
+    }
+    if exit_reached {
+        tracker.exit_reached = true;
+    }
+    if !tracker.completed && !tracker.objectives.is_empty() && tracker.all_complete() {
+        tracker.completed = true;
+        info!("{} objectives complete", level.identifier);
+        stinger_events.send(StingerEvent(Stinger::ItemGet));
+    }
+}
+
+fn hud_system(
+    tracker: Res<ObjectiveTracker>,
+    mut query: Query<&mut BitmapText, With<ObjectivesHud>>,
+) {
+    if !tracker.is_changed() {
+        return;
+    }
+    let value = tracker
+        .objectives
+        .iter()
+        .map(|objective| {
+            let (current, required) = tracker.progress(*objective);
+            let check = if tracker.complete(*objective) {
+                "x"
+            } else {
+                " "
+            };
+            let name = match objective {
+                Objective::DefeatAll => "defeat all enemies",
+                Objective::ReachExit => "reach the exit",
+                Objective::CollectKeys(_) => "collect keys",
+            };
+            match objective {
+                Objective::ReachExit => format!("[{}] {}", check, name),
+                _ => format!("[{}] {} {}/{}", check, name, current, required),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    for mut text in query.iter_mut() {
+        text.text = value.clone();
+    }
+}
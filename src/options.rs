@@ -0,0 +1,308 @@
+use crate::{
+    game_time::GameTime,
+    input::{key_from_name, key_name, Action},
+    settings::Settings,
+    speedrun::RunTimer,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use std::marker::PhantomData;
+
+const VOLUME_STEP: f32 = 0.1;
+const SLIDER_WIDTH: usize = 10;
+
+// P or start pauses the game into the options, which edit the settings live
+pub struct OptionsPlugin;
+impl Plugin for OptionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OptionsMenu>()
+            .add_startup_system(setup_system)
+            .add_system(options_system)
+            .add_system(options_ui_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OptionItem {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    Fullscreen,
+    MouseControls,
+    Crt,
+    ScreenEffects,
+    Ghost,
+    Binding(Action),
+    Resume,
+}
+impl OptionItem {
+    fn all() -> Vec<OptionItem> {
+        let mut items = vec![
+            OptionItem::MasterVolume,
+            OptionItem::MusicVolume,
+            OptionItem::SfxVolume,
+            OptionItem::Fullscreen,
+            OptionItem::MouseControls,
+            OptionItem::Crt,
+            OptionItem::ScreenEffects,
+            OptionItem::Ghost,
+        ];
+        items.extend(Action::ALL.into_iter().map(OptionItem::Binding));
+        items.push(OptionItem::Resume);
+        items
+    }
+}
+
+#[derive(Default)]
+pub struct OptionsMenu {
+    pub open: bool,
+    selected: usize,
+    // waiting for the next key press to bind to the action
+    rebinding: Option<Action>,
+}
+
+#[derive(Component)]
+struct OptionsText;
+
+// menu navigation from the keyboard or any gamepad, fixed so rebinding can't lock anyone out
+#[derive(SystemParam)]
+struct MenuInput<'w, 's> {
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    gamepad_input: Res<'w, Input<GamepadButton>>,
+    gamepads: Res<'w, Gamepads>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+impl<'w, 's> MenuInput<'w, 's> {
+    fn just_pressed(&self, key_codes: &[KeyCode], button_type: GamepadButtonType) -> bool {
+        key_codes
+            .iter()
+            .any(|key_code| self.keyboard_input.just_pressed(*key_code))
+            || self.gamepads.iter().any(|gamepad| {
+                self.gamepad_input
+                    .just_pressed(GamepadButton(*gamepad, button_type))
+            })
+    }
+
+    fn toggle(&self) -> bool {
+        self.just_pressed(&[KeyCode::P], GamepadButtonType::Start)
+    }
+    fn up(&self) -> bool {
+        self.just_pressed(&[KeyCode::Up, KeyCode::W], GamepadButtonType::DPadUp)
+    }
+    fn down(&self) -> bool {
+        self.just_pressed(&[KeyCode::Down, KeyCode::S], GamepadButtonType::DPadDown)
+    }
+    fn left(&self) -> bool {
+        self.just_pressed(&[KeyCode::Left, KeyCode::A], GamepadButtonType::DPadLeft)
+    }
+    fn right(&self) -> bool {
+        self.just_pressed(&[KeyCode::Right, KeyCode::D], GamepadButtonType::DPadRight)
+    }
+    fn confirm(&self) -> bool {
+        self.just_pressed(&[KeyCode::Return, KeyCode::Space], GamepadButtonType::South)
+    }
+    fn back(&self) -> bool {
+        self.just_pressed(&[KeyCode::Back], GamepadButtonType::East)
+    }
+
+    // the first key pressed this frame that can be bound
+    fn bindable_key(&self) -> Option<KeyCode> {
+        self.keyboard_input
+            .get_just_pressed()
+            .copied()
+            .find(|key_code| key_from_name(&key_name(*key_code)).is_some())
+    }
+}
+
+fn setup_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.0),
+                    top: Val::Px(0.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(OptionsText)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("fonts/hack.ttf"),
+                            font_size: 8.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    visibility: Visibility { is_visible: false },
+                    ..Default::default()
+                })
+                .insert(OptionsText);
+        });
+}
+
+fn options_system(
+    input: MenuInput,
+    mut menu: ResMut<OptionsMenu>,
+    mut settings: ResMut<Settings>,
+    mut game_time: ResMut<GameTime>,
+    mut run_timer: ResMut<RunTimer>,
+) {
+    if let Some(action) = menu.rebinding {
+        if input.back() {
+            menu.rebinding = None;
+        } else if let Some(key_code) = input.bindable_key() {
+            settings.bindings.insert(action, vec![key_name(key_code)]);
+            menu.rebinding = None;
+        }
+        return;
+    }
+
+    if input.toggle() || (menu.open && input.back()) {
+        menu.open = !menu.open;
+        menu.selected = 0;
+        game_time.paused = menu.open;
+        run_timer.paused = menu.open;
+        return;
+    }
+    if !menu.open {
+        return;
+    }
+
+    let items = OptionItem::all();
+    if input.up() {
+        menu.selected = (menu.selected + items.len() - 1) % items.len();
+    }
+    if input.down() {
+        menu.selected = (menu.selected + 1) % items.len();
+    }
+    let step = if input.left() {
+        -VOLUME_STEP
+    } else if input.right() {
+        VOLUME_STEP
+    } else {
+        0.0
+    };
+    let confirm = input.confirm();
+    match items[menu.selected] {
+        OptionItem::MasterVolume if step != 0.0 => {
+            settings.master_volume = adjust(settings.master_volume, step);
+        }
+        OptionItem::MusicVolume if step != 0.0 => {
+            settings.music_volume = adjust(settings.music_volume, step);
+        }
+        OptionItem::SfxVolume if step != 0.0 => {
+            settings.sfx_volume = adjust(settings.sfx_volume, step);
+        }
+        OptionItem::Fullscreen if confirm || step != 0.0 => {
+            settings.fullscreen = !settings.fullscreen;
+        }
+        OptionItem::MouseControls if confirm || step != 0.0 => {
+            settings.mouse_controls = !settings.mouse_controls;
+        }
+        OptionItem::Crt if confirm || step != 0.0 => settings.crt = !settings.crt,
+        OptionItem::ScreenEffects if confirm || step != 0.0 => {
+            settings.screen_effects = !settings.screen_effects;
+        }
+        OptionItem::Ghost if confirm || step != 0.0 => settings.ghost = !settings.ghost,
+        OptionItem::Binding(action) if confirm => menu.rebinding = Some(action),
+        OptionItem::Resume if confirm => {
+            menu.open = false;
+            game_time.paused = false;
+            run_timer.paused = false;
+        }
+        _ => {}
+    }
+}
+
+// steps snap to tenths, so repeated steps don't drift
+fn adjust(volume: f32, step: f32) -> f32 {
+    ((volume + step) * 10.0).round().clamp(0.0, 10.0) / 10.0
+}
+
+fn slider(volume: f32) -> String {
+    let filled = (volume * SLIDER_WIDTH as f32)
+        .round()
+        .clamp(0.0, SLIDER_WIDTH as f32) as usize;
+    format!(
+        "[{}{}] {:>3}%",
+        "#".repeat(filled),
+        "-".repeat(SLIDER_WIDTH - filled),
+        (volume * 100.0).round()
+    )
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn options_ui_system(
+    menu: Res<OptionsMenu>,
+    settings: Res<Settings>,
+    mut query: Query<(Option<&mut Text>, &mut Visibility), With<OptionsText>>,
+) {
+    if !menu.is_changed() && !settings.is_changed() {
+        return;
+    }
+    let lines = OptionItem::all()
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let line = match item {
+                OptionItem::MasterVolume => format!("master  {}", slider(settings.master_volume)),
+                OptionItem::MusicVolume => format!("music   {}", slider(settings.music_volume)),
+                OptionItem::SfxVolume => format!("sfx     {}", slider(settings.sfx_volume)),
+                OptionItem::Fullscreen => format!("fullscreen {}", on_off(settings.fullscreen)),
+                OptionItem::MouseControls => {
+                    format!("mouse      {}", on_off(settings.mouse_controls))
+                }
+                OptionItem::Crt => format!("crt        {}", on_off(settings.crt)),
+                OptionItem::ScreenEffects => {
+                    format!("effects    {}", on_off(settings.screen_effects))
+                }
+                OptionItem::Ghost => format!("ghost      {}", on_off(settings.ghost)),
+                OptionItem::Binding(action) if menu.rebinding == Some(action) => {
+                    format!(
+                        "{:<10} press a key...",
+                        format!("{:?}", action).to_lowercase()
+                    )
+                }
+                OptionItem::Binding(action) => format!(
+                    "{:<10} {}",
+                    format!("{:?}", action).to_lowercase(),
+                    settings
+                        .bindings
+                        .get(&action)
+                        .map(|keys| keys.join(" "))
+                        .unwrap_or_default()
+                ),
+                OptionItem::Resume => "resume".to_string(),
+            };
+            let cursor = if index == menu.selected { ">" } else { " " };
+            format!("{} {}", cursor, line)
+        })
+        .collect::<Vec<_>>();
+    let value = format!("options\n\n{}", lines.join("\n"));
+    for (text, mut visibility) in query.iter_mut() {
+        visibility.is_visible = menu.open;
+        if let Some(mut text) = text {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{
+    collision::CollisionLayers,
+    damage::{DamageSystem, DropEntry, EnemyDefinitions, Health, KillEvent},
+    despawn::MarkedForDespawn,
+    game_time::GameTime,
+    ldtk::world::WorldMap,
+    rng::GameRng,
+    score::PickupEvent,
+    Enemy, Player, Z_ACTORS,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+const PICKUP_RADIUS: f32 = 3.0;
+const COLLECT_DISTANCE: f32 = 12.0;
+// so the scatter plays out before the player can grab them
+const COLLECT_DELAY: f32 = 0.4;
+const LIFETIME: f32 = 15.0;
+// pixels per second, up against gravity and to either side
+const SCATTER_UP: f32 = 128.0;
+const SCATTER_SIDE: f32 = 64.0;
+const COIN_POINTS: u32 = 10;
+const HEART_HEALTH: f32 = 1.0;
+
+// enemies roll their drop table on death and scatter what they dropped, keys are placed in
+// ldtk
+pub struct PickupPlugin;
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CollectEvent>()
+            // the killed enemy is still around until the end of the frame
+            .add_system(drop_system.after(DamageSystem))
+            .add_system(collect_system)
+            .add_system(lifetime_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PickupKind {
+    Coin,
+    Heart,
+    Key,
+}
+
+#[derive(Component)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    delay: Timer,
+}
+
+// drops disappear after a while, placed pickups stay
+#[derive(Component)]
+struct Lifetime(Timer);
+
+pub struct CollectEvent {
+    pub kind: PickupKind,
+}
+
+// each entry's chance is its weight over the total weight
+fn roll<'a>(drops: &'a [DropEntry], rng: &mut GameRng) -> Option<&'a DropEntry> {
+    let total = drops.iter().map(|entry| entry.weight).sum::<u32>();
+    if total == 0 {
+        return None;
+    }
+    let mut pick = rng.gen_range(0..total);
+    for entry in drops {
+        if pick < entry.weight {
+            return Some(entry);
+        }
+        pick -= entry.weight;
+    }
+    None
+}
+
+fn drop_system(
+    mut commands: Commands,
+    mut events: EventReader<KillEvent>,
+    mut rng: ResMut<GameRng>,
+    enemy_definitions: Res<EnemyDefinitions>,
+    rapier_config: Res<RapierConfiguration>,
+    world_map: Res<WorldMap>,
+    enemies: Query<(&Enemy, &GlobalTransform)>,
+) {
+    let rotation = world_map.gravity().rotation();
+    for event in events.iter() {
+        let (enemy, global_transform) = match enemies.get(event.target) {
+            Ok(enemy) => enemy,
+            Err(_) => continue,
+        };
+        let definition = enemy_definitions.get(&enemy.name);
+        let (kind, count) = match roll(&definition.drops, &mut rng) {
+            Some(DropEntry {
+                item: Some(kind),
+                count,
+                ..
+            }) => (*kind, *count),
+            _ => continue,
+        };
+        let position = global_transform.translation.truncate();
+        for _ in 0..count {
+            let scatter = Vec2::new(
+                rng.gen_range(-SCATTER_SIDE..=SCATTER_SIDE),
+                rng.gen_range(SCATTER_UP * 0.5..=SCATTER_UP),
+            );
+            let pickup = spawn_pickup(
+                &mut commands,
+                &rapier_config,
+                position,
+                rotation * scatter,
+                kind,
+            );
+            commands
+                .entity(pickup)
+                .insert(Lifetime(Timer::from_seconds(LIFETIME, false)));
+        }
+    }
+}
+
+pub fn spawn_pickup(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    position: Vec2,
+    velocity: Vec2,
+    kind: PickupKind,
+) -> Entity {
+    let color = match kind {
+        PickupKind::Coin => Color::GOLD,
+        PickupKind::Heart => Color::CRIMSON,
+        PickupKind::Key => Color::CYAN,
+    };
+    commands
+        .spawn_bundle(RigidBodyBundle {
+            position: (position / rapier_config.scale).into(),
+            velocity: RigidBodyVelocity {
+                linvel: (velocity / rapier_config.scale).into(),
+                angvel: 0.0,
+            }
+            .into(),
+            mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
+            ..Default::default()
+        })
+        .insert_bundle(ColliderBundle {
+            shape: ColliderShape::ball(PICKUP_RADIUS / rapier_config.scale).into(),
+            flags: ColliderFlags {
+                collision_groups: CollisionLayers::pickup(),
+                ..Default::default()
+            }
+            .into(),
+            material: ColliderMaterial {
+                restitution: 0.4,
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert_bundle(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius: PICKUP_RADIUS,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Fill(FillMode::color(color)),
+            Transform::from_translation(position.extend(Z_ACTORS)),
+        ))
+        .insert(ColliderPositionSync::Discrete)
+        .insert(Pickup {
+            kind,
+            delay: Timer::from_seconds(COLLECT_DELAY, false),
+        })
+        .id()
+}
+
+// hearts heal the player
+fn collect_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut pickups: Query<(Entity, &mut Pickup, &GlobalTransform), Without<MarkedForDespawn>>,
+    mut players: Query<(&GlobalTransform, &mut Health), With<Player>>,
+    mut pickup_events: EventWriter<PickupEvent>,
+    mut collect_events: EventWriter<CollectEvent>,
+) {
+    let (player_transform, mut health) = match players.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let player_position = player_transform.translation.truncate();
+    for (entity, mut pickup, global_transform) in pickups.iter_mut() {
+        let distance = global_transform
+            .translation
+            .truncate()
+            .distance(player_position);
+        if !pickup.delay.tick(time.delta()).finished() || distance > COLLECT_DISTANCE {
+            continue;
+        }
+        match pickup.kind {
+            PickupKind::Coin => pickup_events.send(PickupEvent {
+                points: COIN_POINTS,
+            }),
+            PickupKind::Heart => {
+                health.current = (health.current + HEART_HEALTH).min(health.max);
+            }
+            PickupKind::Key => {}
+        }
+        collect_events.send(CollectEvent { kind: pickup.kind });
+        commands.entity(entity).insert(MarkedForDespawn);
+    }
+}
+
+fn lifetime_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut pickups: Query<(Entity, &mut Lifetime)>,
+) {
+    for (entity, mut lifetime) in pickups.iter_mut() {
+        if lifetime.0.tick(time.delta()).just_finished() {
+            commands.entity(entity).insert(MarkedForDespawn);
+        }
+    }
+}
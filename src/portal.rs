@@ -0,0 +1,195 @@
+use crate::{
+    collision::CollisionLayers,
+    game_time::GameTime,
+    ldtk::registry::{LdtkEntityRegistry, LdtkTarget},
+    transitions::{TransitionEvent, TransitionKind},
+    Player, VirtualPosition, Z_ACTORS,
+};
+use bevy::{prelude::*, sprite::Rect as SpriteRect};
+use bevy_rapier2d::prelude::*;
+
+// seconds to fade out, and again to fade back in at the destination
+const FADE_TIME: f32 = 0.2;
+// seconds after arriving before another portal takes the player
+const COOLDOWN: f32 = 1.0;
+
+pub struct PortalPlugin;
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(portal_system)
+            .add_system(teleport_system)
+            .add_system(cooldown_system);
+    }
+}
+
+// sensor that sends the player to its destination, usually another portal
+#[derive(Component)]
+pub struct Portal {
+    pub destination: Option<LdtkTarget>,
+    // keep moving on arrival instead of coming out standing
+    pub preserve_velocity: bool,
+    // world space in pixels
+    center: Vec2,
+}
+
+// on the player from entering a portal until the fade in at the destination is done
+#[derive(Component)]
+struct Teleport {
+    // world space in pixels
+    destination: Vec2,
+    // physics units, none stops the player on arrival
+    velocity: Option<Vec2>,
+    timer: Timer,
+    arrived: bool,
+}
+
+#[derive(Component)]
+struct PortalCooldown(Timer);
+
+// sensor over a world space rect in pixels
+pub fn spawn_portal(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    rect: &SpriteRect,
+    destination: Option<LdtkTarget>,
+    preserve_velocity: bool,
+) -> Entity {
+    let half_extents = (rect.max - rect.min) * 0.5;
+    let center = rect.min + half_extents;
+    commands
+        .spawn_bundle(ColliderBundle {
+            collider_type: ColliderType::Sensor.into(),
+            shape: ColliderShape::cuboid(
+                half_extents.x / rapier_config.scale,
+                half_extents.y / rapier_config.scale,
+            )
+            .into(),
+            position: (center / rapier_config.scale).into(),
+            flags: ColliderFlags {
+                collision_groups: CollisionLayers::sensor(),
+                active_events: ActiveEvents::INTERSECTION_EVENTS,
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.7, 0.3, 1.0, 0.4),
+                custom_size: Some(half_extents * 2.0),
+                ..Default::default()
+            },
+            // behind the actors passing through
+            transform: Transform::from_translation(center.extend(Z_ACTORS - 0.5)),
+            ..Default::default()
+        })
+        .insert(Portal {
+            destination,
+            preserve_velocity,
+            center,
+        })
+        .id()
+}
+
+fn portal_system(
+    mut commands: Commands,
+    mut intersection_events: EventReader<IntersectionEvent>,
+    registry: Res<LdtkEntityRegistry>,
+    portals: Query<&Portal>,
+    players: Query<
+        &RigidBodyVelocityComponent,
+        (With<Player>, Without<Teleport>, Without<PortalCooldown>),
+    >,
+    mut transition_events: EventWriter<TransitionEvent>,
+) {
+    for event in intersection_events.iter() {
+        if !event.intersecting {
+            continue;
+        }
+        let (a, b) = (event.collider1.entity(), event.collider2.entity());
+        let (portal, player) = match (portals.get(a), portals.get(b)) {
+            (Ok(portal), _) => (portal, b),
+            (_, Ok(portal)) => (portal, a),
+            _ => continue,
+        };
+        let rb_velocity = match players.get(player) {
+            Ok(rb_velocity) => rb_velocity,
+            Err(_) => continue,
+        };
+
+        // the center of a spawned destination portal, or wherever the entity is in ldtk
+        let destination = portal
+            .destination
+            .as_ref()
+            .and_then(|target| registry.resolve(target))
+            .map(|record| {
+                registry
+                    .entity(&record.iid)
+                    .and_then(|entity| portals.get(entity).ok())
+                    .map_or(record.position, |destination| destination.center)
+            });
+        let destination = match destination {
+            Some(destination) => destination,
+            None => {
+                warn!("portal destination not found: {:?}", portal.destination);
+                continue;
+            }
+        };
+        commands.entity(player).insert(Teleport {
+            destination,
+            velocity: portal
+                .preserve_velocity
+                .then(|| Vec2::new(rb_velocity.linvel.x, rb_velocity.linvel.y)),
+            timer: Timer::from_seconds(FADE_TIME * 2.0, false),
+            arrived: false,
+        });
+        transition_events.send(TransitionEvent::new(TransitionKind::Fade, FADE_TIME));
+    }
+}
+
+fn teleport_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    rapier_config: Res<RapierConfiguration>,
+    mut players: Query<(
+        Entity,
+        &mut Teleport,
+        &mut RigidBodyPositionComponent,
+        &mut RigidBodyVelocityComponent,
+    )>,
+    mut cameras: Query<(&mut VirtualPosition, &mut Transform), With<Camera>>,
+) {
+    for (entity, mut teleport, mut rb_position, mut rb_velocity) in players.iter_mut() {
+        teleport.timer.tick(time.delta());
+        // moved while the screen is black
+        if !teleport.arrived && teleport.timer.elapsed_secs() >= FADE_TIME {
+            teleport.arrived = true;
+            let position = teleport.destination / rapier_config.scale;
+            rb_position.position.translation.vector = position.into();
+            rb_position.next_position.translation.vector = position.into();
+            rb_velocity.linvel = teleport.velocity.unwrap_or(Vec2::ZERO).into();
+            for (mut camera_position, mut camera_transform) in cameras.iter_mut() {
+                camera_position.0.x = teleport.destination.x;
+                camera_transform.translation.x = teleport.destination.x;
+            }
+        }
+        if teleport.timer.finished() {
+            commands
+                .entity(entity)
+                .remove::<Teleport>()
+                .insert(PortalCooldown(Timer::from_seconds(COOLDOWN, false)));
+        }
+    }
+}
+
+fn cooldown_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut players: Query<(Entity, &mut PortalCooldown)>,
+) {
+    for (entity, mut cooldown) in players.iter_mut() {
+        if cooldown.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<PortalCooldown>();
+        }
+    }
+}
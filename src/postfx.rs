@@ -0,0 +1,439 @@
+use crate::{
+    bitmap_font::SCREEN_HALF_SIZE, damage::DamageEvent, game_time::GameTime, settings::Settings,
+    Player,
+};
+use bevy::{
+    core_pipeline::{self, draw_2d_graph, Transparent2d},
+    ecs::system::{lifetimeless::SRes, SystemParamItem},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        camera::{ActiveCameras, ExtractedCameraNames},
+        render_asset::{PrepareAssetError, RenderAsset, RenderAssets},
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotValue},
+        render_phase::RenderPhase,
+        render_resource::{
+            std140::{AsStd140, Std140},
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer,
+            BufferBindingType, BufferInitDescriptor, BufferSize, BufferUsages, Extent3d,
+            SamplerBindingType, ShaderStages, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureSampleType, TextureUsages, TextureViewDimension,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::{BevyDefault, TextureCache},
+        view::ViewTarget,
+        RenderApp, RenderStage,
+    },
+    sprite::{Material2d, Material2dPipeline, Material2dPlugin, MaterialMesh2dBundle},
+};
+
+const SHADER_PATH: &str = "shaders/postfx.wgsl";
+// the world is drawn by this camera into the screen texture, the default 2d camera only
+// draws the screen texture into the window
+pub const WORLD_CAMERA: &str = "world_camera";
+const WORLD_PASS_DRIVER: &str = "world_pass_driver";
+// far from any level, so neither camera sees what the other one draws
+const SCREEN_POSITION: Vec2 = Vec2::new(-100_000.0, -100_000.0);
+const VIGNETTE_ALPHA: f32 = 0.6;
+const HIT_DURATION: f32 = 0.25;
+// pixels red and blue are pulled apart at the edges at the start of a hit
+const HIT_OFFSET: f32 = 2.0;
+// flashbacks are drawn in these, darkest to brightest
+const FLASHBACK_PALETTE: [Color; 4] = [
+    Color::rgb(0.16, 0.11, 0.07),
+    Color::rgb(0.42, 0.31, 0.2),
+    Color::rgb(0.7, 0.57, 0.4),
+    Color::rgb(0.93, 0.86, 0.7),
+];
+
+// the 320x240 screen is rendered to a texture and drawn into the window through a post shader:
+// crt scanlines, bulge and vignette, chromatic aberration when the player gets hit and a
+// grayscale palette swap for flashbacks
+pub struct PostFxPlugin;
+impl Plugin for PostFxPlugin {
+    fn build(&self, app: &mut App) {
+        app.world
+            .get_resource_mut::<ActiveCameras>()
+            .unwrap()
+            .add(WORLD_CAMERA);
+        app.add_plugin(Material2dPlugin::<PostFxMaterial>::default())
+            .init_resource::<PostFx>()
+            .init_resource::<ScreenTexture>()
+            .add_startup_system(setup_system)
+            .add_system(hit_system)
+            .add_system(material_system);
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_system)
+            // after the window targets are prepared
+            .add_system_to_stage(RenderStage::Queue, view_target_system);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node(WORLD_PASS_DRIVER, WorldPassDriverNode);
+        graph
+            .add_node_edge(core_pipeline::node::CLEAR_PASS_DRIVER, WORLD_PASS_DRIVER)
+            .unwrap();
+        graph
+            .add_node_edge(WORLD_PASS_DRIVER, core_pipeline::node::MAIN_PASS_DRIVER)
+            .unwrap();
+    }
+}
+
+// set by cutscenes for a flashback, the hit is started by damage to the player
+#[derive(Default)]
+pub struct PostFx {
+    pub flashback: bool,
+    // seconds left of the hit aberration
+    hit: f32,
+}
+
+// what the world camera renders to, at the virtual resolution
+#[derive(Clone)]
+struct ScreenTexture {
+    image: Handle<Image>,
+    size: Extent3d,
+}
+impl FromWorld for ScreenTexture {
+    fn from_world(world: &mut World) -> Self {
+        let size = Extent3d {
+            width: (SCREEN_HALF_SIZE.x * 2.0) as u32,
+            height: (SCREEN_HALF_SIZE.y * 2.0) as u32,
+            depth_or_array_layers: 1,
+        };
+        let mut image = Image::new(
+            size,
+            TextureDimension::D2,
+            vec![0; (size.width * size.height * 4) as usize],
+            // the format the 2d pipelines draw in
+            TextureFormat::bevy_default(),
+        );
+        image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::RENDER_ATTACHMENT;
+        let mut images = world.get_resource_mut::<Assets<Image>>().unwrap();
+        Self {
+            image: images.add(image),
+            size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, TypeUuid)]
+#[uuid = "0f6d2c4e-8b1a-4e57-93c2-5d7a1e4b9f30"]
+pub struct PostFxMaterial {
+    pub screen: Handle<Image>,
+    pub palette: Handle<Image>,
+    // 0 or 1, scanlines and the bulge
+    pub crt: f32,
+    // how much the corners are darkened
+    pub vignette: f32,
+    // pixels red and blue are pulled apart at the edges
+    pub aberration: f32,
+    // 0 to 1 towards the flashback palette
+    pub flashback: f32,
+}
+
+#[derive(Clone, AsStd140)]
+struct PostFxUniform {
+    screen_size: Vec2,
+    crt: f32,
+    vignette: f32,
+    aberration: f32,
+    flashback: f32,
+}
+
+pub struct GpuPostFxMaterial {
+    _buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+impl RenderAsset for PostFxMaterial {
+    type ExtractedAsset = PostFxMaterial;
+    type PreparedAsset = GpuPostFxMaterial;
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<Material2dPipeline<PostFxMaterial>>,
+        SRes<RenderAssets<Image>>,
+    );
+
+    fn extract_asset(&self) -> Self::ExtractedAsset {
+        self.clone()
+    }
+
+    fn prepare_asset(
+        material: Self::ExtractedAsset,
+        (render_device, pipeline, gpu_images): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>> {
+        let (gpu_screen, gpu_palette) = match (
+            gpu_images.get(&material.screen),
+            gpu_images.get(&material.palette),
+        ) {
+            (Some(gpu_screen), Some(gpu_palette)) => (gpu_screen, gpu_palette),
+            _ => return Err(PrepareAssetError::RetryNextUpdate(material)),
+        };
+        let uniform = PostFxUniform {
+            screen_size: SCREEN_HALF_SIZE * 2.0,
+            crt: material.crt,
+            vignette: material.vignette,
+            aberration: material.aberration,
+            flashback: material.flashback,
+        };
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("postfx_material_uniform_buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: uniform.as_std140().as_bytes(),
+        });
+        let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&gpu_screen.texture_view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(&gpu_screen.sampler),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(&gpu_palette.texture_view),
+                },
+            ],
+            label: Some("postfx_material_bind_group"),
+            layout: &pipeline.material2d_layout,
+        });
+        Ok(GpuPostFxMaterial {
+            _buffer: buffer,
+            bind_group,
+        })
+    }
+}
+
+impl Material2d for PostFxMaterial {
+    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load(SHADER_PATH))
+    }
+
+    fn bind_group(render_asset: &<Self as RenderAsset>::PreparedAsset) -> &BindGroup {
+        &render_asset.bind_group
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: BufferSize::new(
+                            PostFxUniform::std140_size_static() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // only loaded from, never sampled
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("postfx_material_layout"),
+        })
+    }
+}
+
+fn setup_system(
+    mut commands: Commands,
+    screen: Res<ScreenTexture>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<PostFxMaterial>>,
+) {
+    let palette = FLASHBACK_PALETTE
+        .iter()
+        .flat_map(|color| {
+            let [r, g, b, a] = color.as_rgba_f32();
+            [r, g, b, a].map(|channel| (channel * 255.0).round() as u8)
+        })
+        .collect();
+    let palette = images.add(Image::new(
+        Extent3d {
+            width: FLASHBACK_PALETTE.len() as u32,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        palette,
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+
+    // the default 2d camera, looking at nothing but the screen quad
+    let mut camera = OrthographicCameraBundle::new_2d();
+    camera.transform.translation = SCREEN_POSITION.extend(camera.transform.translation.z);
+    commands.spawn_bundle(camera);
+    commands.spawn_bundle(MaterialMesh2dBundle {
+        mesh: meshes
+            .add(Mesh::from(shape::Quad::new(SCREEN_HALF_SIZE * 2.0)))
+            .into(),
+        material: materials.add(PostFxMaterial {
+            screen: screen.image.clone(),
+            palette,
+            crt: 0.0,
+            vignette: 0.0,
+            aberration: 0.0,
+            flashback: 0.0,
+        }),
+        transform: Transform::from_translation(SCREEN_POSITION.extend(0.0)),
+        ..Default::default()
+    });
+}
+
+fn hit_system(
+    time: Res<GameTime>,
+    mut events: EventReader<DamageEvent>,
+    mut postfx: ResMut<PostFx>,
+    players: Query<(), With<Player>>,
+) {
+    let hit = events
+        .iter()
+        .filter(|event| players.get(event.target).is_ok())
+        .count()
+        > 0;
+    if hit {
+        postfx.hit = HIT_DURATION;
+    } else if postfx.hit > 0.0 {
+        postfx.hit = (postfx.hit - time.delta_seconds()).max(0.0);
+    }
+}
+
+fn material_system(
+    settings: Res<Settings>,
+    postfx: Res<PostFx>,
+    mut materials: ResMut<Assets<PostFxMaterial>>,
+    screens: Query<&Handle<PostFxMaterial>>,
+) {
+    if !settings.is_changed() && !postfx.is_changed() {
+        return;
+    }
+    let effects = settings.screen_effects as u8 as f32;
+    for material in screens.iter() {
+        let material = match materials.get_mut(material) {
+            Some(material) => material,
+            None => continue,
+        };
+        material.crt = settings.crt as u8 as f32;
+        material.vignette = (settings.crt || postfx.flashback) as u8 as f32 * VIGNETTE_ALPHA;
+        material.aberration = effects * HIT_OFFSET * postfx.hit / HIT_DURATION;
+        material.flashback = effects * postfx.flashback as u8 as f32;
+    }
+}
+
+// the screen texture and a transparent 2d phase for the world camera, which the core pipeline
+// only adds to the default 2d camera
+fn extract_system(
+    mut commands: Commands,
+    active_cameras: Res<ActiveCameras>,
+    screen: Res<ScreenTexture>,
+) {
+    if let Some(camera) = active_cameras
+        .get(WORLD_CAMERA)
+        .and_then(|camera| camera.entity)
+    {
+        commands
+            .get_or_spawn(camera)
+            .insert(RenderPhase::<Transparent2d>::default());
+    }
+    commands.insert_resource(screen.clone());
+}
+
+// the world camera was given the window as its target like every camera, swapped for the screen
+// texture here
+fn view_target_system(
+    screen: Res<ScreenTexture>,
+    msaa: Res<Msaa>,
+    gpu_images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    mut texture_cache: ResMut<TextureCache>,
+    camera_names: Res<ExtractedCameraNames>,
+    mut view_targets: Query<&mut ViewTarget>,
+) {
+    let gpu_image = match gpu_images.get(&screen.image) {
+        Some(gpu_image) => gpu_image,
+        None => return,
+    };
+    let mut view_target = match camera_names
+        .entities
+        .get(WORLD_CAMERA)
+        .and_then(|camera| view_targets.get_mut(*camera).ok())
+    {
+        Some(view_target) => view_target,
+        None => return,
+    };
+    // the 2d pipelines are specialized for the msaa sample count, so the world pass needs a
+    // multisampled texture to resolve into the screen texture
+    let sampled_target = (msaa.samples > 1).then(|| {
+        texture_cache
+            .get(
+                &render_device,
+                TextureDescriptor {
+                    label: Some("postfx_sampled_screen_texture"),
+                    size: screen.size,
+                    mip_level_count: 1,
+                    sample_count: msaa.samples,
+                    dimension: TextureDimension::D2,
+                    format: TextureFormat::bevy_default(),
+                    usage: TextureUsages::RENDER_ATTACHMENT,
+                },
+            )
+            .default_view
+    });
+    *view_target = ViewTarget {
+        view: gpu_image.texture_view.clone(),
+        sampled_target,
+    };
+}
+
+// runs the 2d pass for the world camera, before the main pass draws the screen texture
+struct WorldPassDriverNode;
+impl Node for WorldPassDriverNode {
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        _render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let camera_names = world.get_resource::<ExtractedCameraNames>().unwrap();
+        if let Some(camera) = camera_names.entities.get(WORLD_CAMERA) {
+            graph.run_sub_graph(draw_2d_graph::NAME, vec![SlotValue::Entity(*camera)])?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{
+    alert::Aggro,
+    combo::Combo,
+    damage::{DamageEvent, Health, Resistances},
+    equipment::Equipment,
+    game_time::GameTime,
+    ldtk::plugin::LdtkEvent,
+    nav::PathFollower,
+    sandbox::spawn_ground,
+    AppState, Enemy, Player,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+const ARENA_WIDTH: f32 = 320.0;
+const ARENA_HEIGHT: f32 = 160.0;
+const WALL_THICKNESS: f32 = 16.0;
+// never runs out, it's refilled after every hit anyway
+const DUMMY_HEALTH: f32 = 1000.0;
+// frames after an attack's recovery until the next one starts a new sequence
+const SEQUENCE_GAP: u32 = 20;
+
+// hit data in the arena for tuning combat, R resets it
+pub struct PracticePlugin;
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PracticeStats>()
+            .add_system_set(SystemSet::on_enter(AppState::Practice).with_system(setup_system))
+            .add_system_set(
+                SystemSet::on_update(AppState::Practice)
+                    .with_system(dummy_system)
+                    .with_system(attack_timing_system)
+                    .with_system(reset_system)
+                    .with_system(overlay_system),
+            );
+    }
+}
+
+// every enemy in the practice room takes hits without dying
+#[derive(Component)]
+struct Dummy;
+
+#[derive(Component)]
+struct PracticeOverlay;
+
+#[derive(Default)]
+struct PracticeStats {
+    last_damage: f32,
+    total_damage: f32,
+    hits: u32,
+    best_combo: u32,
+    attacks: u32,
+    // start of each attack in the last sequence, in frames since its first attack
+    sequence: Vec<u32>,
+    sequence_start: f64,
+}
+
+fn setup_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    rapier_config: Res<RapierConfiguration>,
+    mut event_writer: EventWriter<LdtkEvent>,
+) {
+    // floor and walls
+    let half_width = (ARENA_WIDTH + WALL_THICKNESS) * 0.5;
+    let half_height = (ARENA_HEIGHT + WALL_THICKNESS) * 0.5;
+    spawn_ground(
+        &mut commands,
+        &rapier_config,
+        Vec2::new(0.0, -64.0 - WALL_THICKNESS * 0.5),
+        Vec2::new(half_width + WALL_THICKNESS * 0.5, WALL_THICKNESS * 0.5),
+    );
+    for x in [-half_width, half_width] {
+        spawn_ground(
+            &mut commands,
+            &rapier_config,
+            Vec2::new(x, -64.0 + ARENA_HEIGHT * 0.5),
+            Vec2::new(WALL_THICKNESS * 0.5, half_height),
+        );
+    }
+
+    event_writer.send(LdtkEvent::SpawnPlayer(Vec3::new(-64.0, 0.0, 0.0)));
+    event_writer.send(LdtkEvent::SpawnEnemy {
+        name: "test".to_string(),
+        position: Vec3::new(64.0, 0.0, 0.0),
+        iid: None,
+    });
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(2.0),
+                    bottom: Val::Px(2.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/hack.ttf"),
+                    font_size: 8.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(PracticeOverlay);
+}
+
+fn dummy_system(
+    mut commands: Commands,
+    mut events: EventReader<DamageEvent>,
+    mut stats: ResMut<PracticeStats>,
+    combo: Res<Combo>,
+    spawned: Query<Entity, (Added<Enemy>, Without<Dummy>)>,
+    mut dummies: Query<(&mut Health, Option<&Resistances>), With<Dummy>>,
+) {
+    for entity in spawned.iter() {
+        // and stays put
+        commands
+            .entity(entity)
+            .insert(Dummy)
+            .insert(Health::new(DUMMY_HEALTH))
+            .remove::<Aggro>()
+            .remove::<PathFollower>();
+    }
+
+    // the damage actually taken, after resistances
+    for event in events.iter() {
+        if let Ok((mut health, resistances)) = dummies.get_mut(event.target) {
+            let multiplier =
+                resistances.map_or(1.0, |resistances| resistances.multiplier(event.damage_type));
+            stats.last_damage = event.amount * multiplier;
+            stats.total_damage += stats.last_damage;
+            stats.hits += 1;
+            health.current = health.max;
+        }
+    }
+    stats.best_combo = stats.best_combo.max(combo.count);
+}
+
+fn attack_timing_system(
+    time: Res<GameTime>,
+    mut stats: ResMut<PracticeStats>,
+    players: Query<(&Player, &Equipment), Changed<Player>>,
+) {
+    let (player, equipment) = match players.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    if player.attacks == stats.attacks {
+        return;
+    }
+    stats.attacks = player.attacks;
+
+    let now = time.seconds_since_startup();
+    let frame = ((now - stats.sequence_start) * 60.0) as u32;
+    let frames = equipment.weapon_definition.hitbox.frames;
+    let continues = stats
+        .sequence
+        .last()
+        .map_or(false, |last| frame <= last + frames.total() + SEQUENCE_GAP);
+    if continues {
+        stats.sequence.push(frame);
+    } else {
+        stats.sequence = vec![0];
+        stats.sequence_start = now;
+    }
+}
+
+fn reset_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut stats: ResMut<PracticeStats>,
+    mut combo: ResMut<Combo>,
+    mut dummies: Query<&mut Health, With<Dummy>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::R) {
+        return;
+    }
+    *stats = PracticeStats {
+        attacks: stats.attacks,
+        ..Default::default()
+    };
+    combo.count = 0;
+    for mut health in dummies.iter_mut() {
+        health.current = health.max;
+    }
+}
+
+fn overlay_system(
+    stats: Res<PracticeStats>,
+    combo: Res<Combo>,
+    players: Query<&Equipment, With<Player>>,
+    mut query: Query<&mut Text, With<PracticeOverlay>>,
+) {
+    let frames = players
+        .get_single()
+        .map(|equipment| equipment.weapon_definition.hitbox.frames)
+        .unwrap_or_default();
+    let sequence = stats
+        .sequence
+        .iter()
+        .map(|frame| frame.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let value = format!(
+        "practice, R resets\n\
+        damage {:.2} total {:.2} hits {}\n\
+        combo {} best {}\n\
+        startup {} active {} recovery {} total {}\n\
+        sequence {}",
+        stats.last_damage,
+        stats.total_damage,
+        stats.hits,
+        combo.count,
+        stats.best_combo,
+        frames.startup,
+        frames.active,
+        frames.recovery,
+        frames.total(),
+        sequence
+    );
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
@@ -0,0 +1,427 @@
+use crate::{
+    asset_error::AssetErrorEvent,
+    ldtk::{
+        data::{EntityInstance, Level},
+        plugin::{
+            Ldtk, LdtkColliderConfig, LdtkEvent, LdtkLayerZConfig, LdtkSpawnConfig, LevelPlacement,
+        },
+        registry::LdtkEntityRegistry,
+        world::{WorldLevel, WorldMap},
+    },
+    rng::GameRng,
+    save::SaveData,
+    world_flags::WorldFlags,
+    AppState, Player,
+};
+use bevy::{ecs::system::SystemParam, prelude::*, sprite::Rect};
+use bevy_rapier2d::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+// rooms in a run, the start room included
+const RUN_ROOMS: usize = 8;
+
+// roguelite runs: the ldtk levels are room templates, joined at their "RoomExit" entities into
+// one map. an exit is on the level edge nearest to it and joins exits on the opposite edge with
+// the same "tag" field (String), exits left over lead nowhere. runs start in a room with a
+// PlayerStart, and every room brings its own nav grid
+pub struct ProcgenPlugin;
+impl Plugin for ProcgenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<GenerateRun>()
+            .add_system_set(SystemSet::on_enter(AppState::Run).with_system(setup_system))
+            .add_system_set(SystemSet::on_update(AppState::Run).with_system(start_run_system))
+            .add_system(generate_run_system);
+    }
+}
+
+// replaces the current run, if there is one
+#[derive(Debug)]
+pub struct GenerateRun {
+    pub seed: u64,
+    pub rooms: usize,
+}
+
+// parent of every room of a run
+#[derive(Component)]
+pub struct ProcgenRun {
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    North,
+    East,
+    South,
+    West,
+}
+impl Side {
+    fn opposite(self) -> Self {
+        match self {
+            Side::North => Side::South,
+            Side::East => Side::West,
+            Side::South => Side::North,
+            Side::West => Side::East,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RoomExit {
+    side: Side,
+    // on the room's edge, in pixels from its top left corner with y up
+    position: Vec2,
+    tag: String,
+}
+impl RoomExit {
+    // `px` in level pixels, y down like ldtk
+    fn new(px: Vec2, size: Vec2, tag: String) -> Self {
+        let (side, _) = [
+            (Side::North, px.y),
+            (Side::East, size.x - px.x),
+            (Side::South, size.y - px.y),
+            (Side::West, px.x),
+        ]
+        .into_iter()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap();
+        let edge = match side {
+            Side::North => Vec2::new(px.x, 0.0),
+            Side::East => Vec2::new(size.x, px.y),
+            Side::South => Vec2::new(px.x, size.y),
+            Side::West => Vec2::new(0.0, px.y),
+        };
+        Self {
+            side,
+            position: Vec2::new(edge.x, -edge.y),
+            tag,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RoomTemplate {
+    identifier: String,
+    size: Vec2,
+    exits: Vec<RoomExit>,
+    start: bool,
+}
+impl RoomTemplate {
+    // levels without exits aren't rooms
+    fn from_level(level: &Level) -> Option<Self> {
+        let size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+        let mut exits = vec![];
+        let mut start = false;
+        for layer_instance in level.layer_instances.iter().flatten() {
+            for entity_instance in &layer_instance.entity_instances {
+                match entity_instance.identifier.as_str() {
+                    "PlayerStart" => start = true,
+                    "RoomExit" => {
+                        let px = Vec2::new(
+                            (entity_instance.px[0] + layer_instance.px_total_offset_x) as f32,
+                            (entity_instance.px[1] + layer_instance.px_total_offset_y) as f32,
+                        );
+                        exits.push(RoomExit::new(px, size, exit_tag(entity_instance)));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        (!exits.is_empty()).then(|| Self {
+            identifier: level.identifier.clone(),
+            size,
+            exits,
+            start,
+        })
+    }
+}
+
+fn exit_tag(entity_instance: &EntityInstance) -> String {
+    entity_instance
+        .field_instances
+        .iter()
+        .find(|field_instance| field_instance.identifier == "tag")
+        .and_then(|field_instance| field_instance.value.as_ref())
+        .and_then(|value| value.as_str())
+        .unwrap_or("")
+        .to_string()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PlacedRoom {
+    template: usize,
+    // top left corner, world space in pixels
+    origin: Vec2,
+}
+impl PlacedRoom {
+    fn rect(&self, templates: &[RoomTemplate]) -> Rect {
+        let size = templates[self.template].size;
+        Rect {
+            min: self.origin - Vec2::new(0.0, size.y),
+            max: self.origin + Vec2::new(size.x, 0.0),
+        }
+    }
+}
+
+// touching edges don't overlap
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.min.x < b.max.x && b.min.x < a.max.x && a.min.y < b.max.y && b.min.y < a.max.y
+}
+
+// grows the map from a start room at the origin, joining a room to a random open exit at a
+// time until there are `rooms` or no exit takes another room
+fn generate(templates: &[RoomTemplate], rooms: usize, rng: &mut GameRng) -> Vec<PlacedRoom> {
+    let starts = (0..templates.len())
+        .filter(|index| templates[*index].start)
+        .collect::<Vec<_>>();
+    let start = match starts.choose(rng) {
+        Some(start) => *start,
+        None => return vec![],
+    };
+    let mut placed = vec![PlacedRoom {
+        template: start,
+        origin: Vec2::ZERO,
+    }];
+    // by placed room and exit index
+    let mut open = (0..templates[start].exits.len())
+        .map(|exit| (0, exit))
+        .collect::<Vec<_>>();
+    while placed.len() < rooms && !open.is_empty() {
+        let (room, exit) = open.swap_remove(rng.gen_range(0..open.len()));
+        let from = &templates[placed[room].template].exits[exit];
+        let point = placed[room].origin + from.position;
+        let mut candidates = templates
+            .iter()
+            .enumerate()
+            .flat_map(|(template, room_template)| {
+                room_template
+                    .exits
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, to)| to.side == from.side.opposite() && to.tag == from.tag)
+                    .map(move |(to, _)| (template, to))
+            })
+            .collect::<Vec<_>>();
+        candidates.shuffle(rng);
+        let fit = candidates.into_iter().find_map(|(template, to)| {
+            let room = PlacedRoom {
+                template,
+                origin: point - templates[template].exits[to].position,
+            };
+            let rect = room.rect(templates);
+            placed
+                .iter()
+                .all(|other| !overlaps(&rect, &other.rect(templates)))
+                .then(|| (room, to))
+        });
+        if let Some((room, to)) = fit {
+            let index = placed.len();
+            open.extend(
+                (0..templates[room.template].exits.len())
+                    .filter(|exit| *exit != to)
+                    .map(|exit| (index, exit)),
+            );
+            placed.push(room);
+        }
+    }
+    placed
+}
+
+// what Ldtk::load needs
+#[derive(SystemParam)]
+struct RoomSpawner<'w, 's> {
+    commands: Commands<'w, 's>,
+    asset_server: Res<'w, AssetServer>,
+    texture_atlases: ResMut<'w, Assets<TextureAtlas>>,
+    rapier_config: Res<'w, RapierConfiguration>,
+    z_config: Res<'w, LdtkLayerZConfig>,
+    collider_config: Res<'w, LdtkColliderConfig>,
+    registry: ResMut<'w, LdtkEntityRegistry>,
+    flags: Res<'w, WorldFlags>,
+    ldtk_events: EventWriter<'w, 's, LdtkEvent>,
+}
+
+fn setup_system(mut commands: Commands) {
+    commands.insert_resource(LdtkSpawnConfig { spawn_world: false });
+}
+
+// the first run starts once the project is loaded, from a fresh seed unless "--resume" picks
+// the saved run back up
+fn start_run_system(
+    mut asset_events: EventReader<AssetEvent<Ldtk>>,
+    mut save: ResMut<SaveData>,
+    mut events: EventWriter<GenerateRun>,
+) {
+    for event in asset_events.iter() {
+        if let AssetEvent::Created { .. } = event {
+            let resume = std::env::args().any(|arg| arg == "--resume");
+            let seed = match save.seed {
+                Some(seed) if resume => seed,
+                _ => rand::random(),
+            };
+            // kept with the save, the rooms reseed the rng from it as they activate
+            save.seed = Some(seed);
+            if let Err(err) = save.write() {
+                error!("{:?}", err);
+            }
+            events.send(GenerateRun {
+                seed,
+                rooms: RUN_ROOMS,
+            });
+        }
+    }
+}
+
+fn generate_run_system(
+    mut events: EventReader<GenerateRun>,
+    handle: Option<Res<Handle<Ldtk>>>,
+    ldtks: Res<Assets<Ldtk>>,
+    mut spawner: RoomSpawner,
+    mut world_map: ResMut<WorldMap>,
+    runs: Query<Entity, With<ProcgenRun>>,
+    players: Query<Entity, With<Player>>,
+    parents: Query<(), With<Parent>>,
+    mut error_events: EventWriter<AssetErrorEvent>,
+) {
+    for event in events.iter() {
+        let _span = info_span!("run", seed = event.seed).entered();
+        let ldtk = match handle.as_ref().and_then(|handle| ldtks.get(&**handle)) {
+            Some(ldtk) => ldtk,
+            None => {
+                warn!("no ldtk project to generate a run from");
+                continue;
+            }
+        };
+
+        // the previous run, the start room spawns a new player
+        for entity in runs.iter().chain(players.iter()) {
+            spawner.commands.entity(entity).despawn_recursive();
+        }
+        for level in &ldtk.data.levels {
+            for entity in spawner.registry.remove_level(level.uid) {
+                if parents.get(entity).is_err() {
+                    spawner.commands.entity(entity).despawn_recursive();
+                }
+            }
+        }
+
+        let templates = ldtk
+            .data
+            .levels
+            .iter()
+            .filter_map(RoomTemplate::from_level)
+            .collect::<Vec<_>>();
+        let rooms = generate(&templates, event.rooms, &mut GameRng::new(event.seed));
+        if rooms.is_empty() {
+            warn!("no room with a PlayerStart and exits");
+            continue;
+        }
+
+        let run = spawner
+            .commands
+            .spawn_bundle((Transform::identity(), GlobalTransform::identity()))
+            .insert(ProcgenRun { seed: event.seed })
+            .id();
+        // the rooms replace the levels, so they activate as the player walks through them
+        world_map.levels.clear();
+        world_map.active = None;
+        for room in rooms {
+            let identifier = &templates[room.template].identifier;
+            let world_level = match ldtk.world_map.find(identifier) {
+                Some(world_level) => world_level,
+                None => continue,
+            };
+            let offset = room.origin - world_level.origin();
+            let placement = LevelPlacement {
+                offset,
+                parent: Some(run),
+            };
+            if let Err(err) = ldtk.load(
+                identifier,
+                &placement,
+                &spawner.asset_server,
+                &mut spawner.texture_atlases,
+                &mut spawner.commands,
+                &spawner.rapier_config,
+                &spawner.z_config,
+                &spawner.collider_config,
+                &mut spawner.registry,
+                &spawner.flags,
+                &mut spawner.ldtk_events,
+            ) {
+                error_events.send(AssetErrorEvent::new(identifier, &err));
+                continue;
+            }
+            world_map.levels.push(WorldLevel {
+                rect: Rect {
+                    min: world_level.rect.min + offset,
+                    max: world_level.rect.max + offset,
+                },
+                neighbours: vec![],
+                ..world_level.clone()
+            });
+        }
+        info!("generated {} rooms", world_map.levels.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a square room with an exit in the middle of each of the given sides
+    fn room(identifier: &str, sides: &[Side], start: bool) -> RoomTemplate {
+        let size = Vec2::splat(64.0);
+        let px = |side: &Side| match side {
+            Side::North => Vec2::new(32.0, 0.0),
+            Side::East => Vec2::new(63.0, 32.0),
+            Side::South => Vec2::new(32.0, 63.0),
+            Side::West => Vec2::new(1.0, 32.0),
+        };
+        RoomTemplate {
+            identifier: identifier.to_string(),
+            size,
+            exits: sides
+                .iter()
+                .map(|side| RoomExit::new(px(side), size, String::new()))
+                .collect(),
+            start,
+        }
+    }
+
+    #[test]
+    fn exits_snap_to_the_nearest_edge() {
+        let exit = RoomExit::new(Vec2::new(60.0, 20.0), Vec2::splat(64.0), String::new());
+        assert_eq!(exit.side, Side::East);
+        assert_eq!(exit.position, Vec2::new(64.0, -20.0));
+    }
+
+    #[test]
+    fn rooms_join_at_exits_without_overlapping() {
+        let templates = vec![
+            room("start", &[Side::East], true),
+            room("corridor", &[Side::East, Side::West], false),
+            room(
+                "cross",
+                &[Side::North, Side::East, Side::South, Side::West],
+                false,
+            ),
+        ];
+        let rooms = generate(&templates, 6, &mut GameRng::new(1));
+        assert_eq!(rooms.len(), 6);
+        assert_eq!(rooms[0].origin, Vec2::ZERO);
+        for (index, room) in rooms.iter().enumerate() {
+            let rect = room.rect(&templates);
+            assert!(rooms[..index]
+                .iter()
+                .all(|other| !overlaps(&rect, &other.rect(&templates))));
+        }
+        // the same seed, the same map
+        assert_eq!(rooms, generate(&templates, 6, &mut GameRng::new(1)));
+    }
+
+    #[test]
+    fn needs_a_start_room() {
+        let templates = vec![room("corridor", &[Side::East, Side::West], false)];
+        assert!(generate(&templates, 4, &mut GameRng::new(1)).is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{collision::CollisionLayers, damage::Health, RAPIER_SCALE};
+use bevy::{prelude::*, sprite::Rect as SpriteRect};
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+const CRATE_HEALTH: f32 = 3.0;
+// grips the ground, so a pushed crate stops soon after the player does instead of sliding off
+const CRATE_FRICTION: f32 = 0.8;
+// lighter than actors, the player can shove it at walking speed
+const CRATE_DENSITY: f32 = 0.5;
+
+// dynamic level props, pushed around by actors and broken by attacks
+pub struct PropsPlugin;
+impl Plugin for PropsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(crate_damage_system);
+    }
+}
+
+// rests on terrain and pressure plates, actors stand on and push it
+#[derive(Component)]
+pub struct Crate;
+
+// an ldtk "Crate" entity, rect in world space pixels
+pub fn spawn_crate(commands: &mut Commands, rect: &SpriteRect) -> Entity {
+    let half_extents = (rect.max - rect.min) * 0.5;
+    let center = rect.min + half_extents;
+    let shape = ColliderShape::cuboid(half_extents.x / RAPIER_SCALE, half_extents.y / RAPIER_SCALE);
+    commands
+        .spawn_bundle(RigidBodyBundle {
+            position: (center / RAPIER_SCALE).into(),
+            mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
+            ..Default::default()
+        })
+        .insert_bundle(ColliderBundle {
+            shape: shape.clone().into(),
+            material: ColliderMaterial {
+                friction: CRATE_FRICTION,
+                restitution: 0.0,
+                friction_combine_rule: CoefficientCombineRule::Max,
+                restitution_combine_rule: CoefficientCombineRule::Min,
+            }
+            .into(),
+            mass_properties: ColliderMassProps::Density(CRATE_DENSITY).into(),
+            flags: ColliderFlags {
+                collision_groups: CollisionLayers::terrain(),
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert(ColliderPositionSync::Discrete)
+        .insert_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: half_extents * 2.0,
+                origin: RectangleOrigin::Center,
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::rgb(0.55, 0.4, 0.25)),
+                outline_mode: StrokeMode::new(Color::rgb(0.3, 0.2, 0.1), 1.0),
+            },
+            Transform::from_translation(center.extend(2.0)),
+        ))
+        .insert(Health::new(CRATE_HEALTH))
+        .insert(Crate)
+        .with_children(|parent| {
+            // what attacks test against, same size as the body
+            let body = parent.parent_entity();
+            parent
+                .spawn_bundle(ColliderBundle {
+                    collider_type: ColliderType::Sensor.into(),
+                    shape: shape.into(),
+                    flags: ColliderFlags {
+                        collision_groups: CollisionLayers::hurtbox(),
+                        ..Default::default()
+                    }
+                    .into(),
+                    ..Default::default()
+                })
+                .insert(ColliderParentComponent(ColliderParent {
+                    handle: body.handle(),
+                    pos_wrt_parent: Vec2::ZERO.into(),
+                }));
+        })
+        .id()
+}
+
+// cracks darken the crate as it takes damage
+fn crate_damage_system(
+    mut crates: Query<(&Health, &mut DrawMode), (With<Crate>, Changed<Health>)>,
+) {
+    for (health, mut draw_mode) in crates.iter_mut() {
+        let ratio = (health.current / health.max).clamp(0.0, 1.0);
+        let shade = 0.5 + ratio * 0.5;
+        *draw_mode = DrawMode::Outlined {
+            fill_mode: FillMode::color(Color::rgb(0.55 * shade, 0.4 * shade, 0.25 * shade)),
+            outline_mode: StrokeMode::new(Color::rgb(0.3, 0.2, 0.1), 1.0),
+        };
+    }
+}
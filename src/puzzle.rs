@@ -0,0 +1,363 @@
+use crate::{
+    collision::CollisionLayers,
+    game_time::GameTime,
+    interact::{InteractEvent, Interactable},
+    ldtk::registry::{LdtkEntityRegistry, LdtkTarget},
+    world_flags::{FlagValue, WorldFlags},
+};
+use bevy::{prelude::*, sprite::Rect as SpriteRect};
+use bevy_rapier2d::prelude::*;
+
+// handle angle of a pulled lever, mirrored when released
+const LEVER_ANGLE: f32 = std::f32::consts::FRAC_PI_6;
+// radians per second
+const LEVER_SPEED: f32 = 8.0;
+
+pub struct PuzzlePlugin;
+impl Plugin for PuzzlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(lever_system)
+            .add_system(lever_handle_system)
+            .add_event::<SignalEvent>()
+            .add_system(pressure_plate_system)
+            .add_system(door_signal_system.after(pressure_plate_system))
+            .add_system(trigger_door_system);
+    }
+}
+
+#[derive(Component)]
+pub struct Lever {
+    pub pulled: bool,
+    // the door, see LdtkEntityRegistry::resolve
+    pub target: Option<LdtkTarget>,
+}
+
+#[derive(Component)]
+struct LeverHandle {
+    angle: f32,
+}
+
+// signals its targets on while bodies heavy enough rest on it, and off once they leave
+#[derive(Component)]
+pub struct PressurePlate {
+    pub pressed: bool,
+    // total mass of the bodies on it needed to press it, in rapier units
+    pub weight: f32,
+    // e.g. doors, see LdtkEntityRegistry::resolve
+    pub targets: Vec<LdtkTarget>,
+    // unpressed frame, the pressed one follows it in the atlas
+    frame: Option<usize>,
+}
+
+// on or off, sent by pressure plates to the entities they link to
+pub struct SignalEvent {
+    pub target: Entity,
+    pub on: bool,
+}
+
+#[derive(Component)]
+pub struct TriggerDoor {
+    pub open: bool,
+}
+
+pub fn spawn_lever(
+    commands: &mut Commands,
+    position: Vec3,
+    target: Option<LdtkTarget>,
+    pulled: bool,
+) -> Entity {
+    let angle = if pulled { -LEVER_ANGLE } else { LEVER_ANGLE };
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.4, 0.4, 0.45),
+                custom_size: Some(Vec2::new(10.0, 4.0)),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(position + Vec3::new(0.0, 2.0, 4.0)),
+            ..Default::default()
+        })
+        .insert(Lever { pulled, target })
+        .insert(Interactable {
+            label: Some("pull".to_string()),
+        })
+        .with_children(|parent| {
+            // rotates around the base
+            parent
+                .spawn_bundle((
+                    Transform::from_rotation(Quat::from_rotation_z(angle)),
+                    GlobalTransform::identity(),
+                ))
+                .insert(LeverHandle { angle })
+                .with_children(|parent| {
+                    parent.spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgb(0.8, 0.3, 0.2),
+                            custom_size: Some(Vec2::new(2.0, 10.0)),
+                            ..Default::default()
+                        },
+                        transform: Transform::from_xyz(0.0, 5.0, -0.1),
+                        ..Default::default()
+                    });
+                });
+        })
+        .id()
+}
+
+// sensor over a world space rect in pixels, drawn with the ldtk entity's tile when it has
+// one, `frame` is its atlas and unpressed index
+pub fn spawn_pressure_plate(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    rect: &SpriteRect,
+    weight: f32,
+    targets: Vec<LdtkTarget>,
+    frame: Option<(Handle<TextureAtlas>, usize)>,
+) -> Entity {
+    let half_extents = (rect.max - rect.min) * 0.5;
+    let center = rect.min + half_extents;
+    let transform = Transform::from_translation(center.extend(3.0));
+    let mut entity_commands = commands.spawn();
+    match &frame {
+        Some((texture_atlas, index)) => entity_commands.insert_bundle(SpriteSheetBundle {
+            texture_atlas: texture_atlas.clone(),
+            sprite: TextureAtlasSprite::new(*index),
+            transform,
+            ..Default::default()
+        }),
+        None => entity_commands.insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: plate_color(false),
+                custom_size: Some(half_extents * 2.0),
+                ..Default::default()
+            },
+            transform,
+            ..Default::default()
+        }),
+    };
+    entity_commands
+        .insert_bundle(ColliderBundle {
+            collider_type: ColliderType::Sensor.into(),
+            shape: ColliderShape::cuboid(
+                half_extents.x / rapier_config.scale,
+                half_extents.y / rapier_config.scale,
+            )
+            .into(),
+            position: (center / rapier_config.scale).into(),
+            flags: ColliderFlags {
+                collision_groups: CollisionLayers::plate(),
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert(PressurePlate {
+            pressed: false,
+            weight,
+            targets,
+            frame: frame.map(|(_, index)| index),
+        })
+        .id()
+}
+
+fn plate_color(pressed: bool) -> Color {
+    if pressed {
+        Color::rgb(0.3, 0.6, 0.3)
+    } else {
+        Color::rgb(0.5, 0.5, 0.55)
+    }
+}
+
+// static collider over a world space rect in pixels
+pub fn spawn_trigger_door(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    rect: &SpriteRect,
+    open: bool,
+) -> Entity {
+    let half_extents = (rect.max - rect.min) * 0.5;
+    let center = rect.min + half_extents;
+    commands
+        .spawn_bundle(ColliderBundle {
+            shape: ColliderShape::cuboid(
+                half_extents.x / rapier_config.scale,
+                half_extents.y / rapier_config.scale,
+            )
+            .into(),
+            position: (center / rapier_config.scale).into(),
+            flags: ColliderFlags {
+                collision_groups: door_groups(open),
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: door_color(open),
+                custom_size: Some(half_extents * 2.0),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(center.extend(3.0)),
+            ..Default::default()
+        })
+        .insert(TriggerDoor { open })
+        .id()
+}
+
+// open doors collide with nothing
+fn door_groups(open: bool) -> InteractionGroups {
+    if open {
+        InteractionGroups::none()
+    } else {
+        CollisionLayers::terrain()
+    }
+}
+
+fn door_color(open: bool) -> Color {
+    if open {
+        Color::rgba(0.6, 0.5, 0.3, 0.2)
+    } else {
+        Color::rgb(0.6, 0.5, 0.3)
+    }
+}
+
+fn lever_system(
+    mut interact_events: EventReader<InteractEvent>,
+    registry: Res<LdtkEntityRegistry>,
+    mut flags: ResMut<WorldFlags>,
+    mut levers: Query<&mut Lever>,
+    mut doors: Query<&mut TriggerDoor>,
+) {
+    for event in interact_events.iter() {
+        let mut lever = match levers.get_mut(event.entity) {
+            Ok(lever) => lever,
+            Err(_) => continue,
+        };
+        lever.pulled = !lever.pulled;
+        if let Some(iid) = registry.iid(event.entity) {
+            flags.set(iid, "pulled", FlagValue::Bool(lever.pulled));
+        }
+
+        let record = lever
+            .target
+            .as_ref()
+            .and_then(|target| registry.resolve(target));
+        let door = record.and_then(|record| {
+            registry
+                .entity(&record.iid)
+                .and_then(|door| doors.get_mut(door).ok())
+                .map(|door| (record, door))
+        });
+        match door {
+            Some((record, mut door)) => {
+                door.open = !door.open;
+                flags.set(&record.iid, "open", FlagValue::Bool(door.open));
+            }
+            None => warn!("lever target not found: {:?}", lever.target),
+        }
+    }
+}
+
+fn pressure_plate_system(
+    narrow_phase: Res<NarrowPhase>,
+    registry: Res<LdtkEntityRegistry>,
+    bodies: Query<&RigidBodyMassPropsComponent>,
+    mut plates: Query<(
+        Entity,
+        &mut PressurePlate,
+        Option<&mut Sprite>,
+        Option<&mut TextureAtlasSprite>,
+    )>,
+    mut signal_events: EventWriter<SignalEvent>,
+) {
+    for (entity, mut plate, sprite, atlas_sprite) in plates.iter_mut() {
+        // mass of the bodies overlapping the sensor, once each for bodies with several colliders
+        let mut on_plate = narrow_phase
+            .intersections_with(entity.handle())
+            .filter(|(_, _, intersecting)| *intersecting)
+            .map(|(collider1, collider2, _)| {
+                if collider1.entity() == entity {
+                    collider2.entity()
+                } else {
+                    collider1.entity()
+                }
+            })
+            .collect::<Vec<_>>();
+        on_plate.sort();
+        on_plate.dedup();
+        let mass = on_plate
+            .into_iter()
+            .filter_map(|other| bodies.get(other).ok())
+            .map(|mass_props| mass_props.mass())
+            .sum::<f32>();
+        let pressed = mass > 0.0 && mass >= plate.weight;
+        if plate.pressed == pressed {
+            continue;
+        }
+        plate.pressed = pressed;
+        if let Some(mut sprite) = sprite {
+            sprite.color = plate_color(pressed);
+        }
+        if let (Some(mut atlas_sprite), Some(frame)) = (atlas_sprite, plate.frame) {
+            atlas_sprite.index = frame + pressed as usize;
+        }
+        for target in &plate.targets {
+            match registry
+                .resolve(target)
+                .and_then(|record| registry.entity(&record.iid))
+            {
+                Some(target) => signal_events.send(SignalEvent {
+                    target,
+                    on: pressed,
+                }),
+                None => warn!("pressure plate target not found: {:?}", target),
+            }
+        }
+    }
+}
+
+// signaled doors are open while the signal is on
+fn door_signal_system(
+    mut signal_events: EventReader<SignalEvent>,
+    mut doors: Query<&mut TriggerDoor>,
+) {
+    for event in signal_events.iter() {
+        if let Ok(mut door) = doors.get_mut(event.target) {
+            door.open = event.on;
+        }
+    }
+}
+
+fn lever_handle_system(
+    time: Res<GameTime>,
+    levers: Query<(&Lever, &Children)>,
+    mut handles: Query<(&mut LeverHandle, &mut Transform)>,
+) {
+    for (lever, children) in levers.iter() {
+        let target = if lever.pulled {
+            -LEVER_ANGLE
+        } else {
+            LEVER_ANGLE
+        };
+        for child in children.iter() {
+            if let Ok((mut handle, mut transform)) = handles.get_mut(*child) {
+                let step = LEVER_SPEED * time.delta_seconds();
+                handle.angle += (target - handle.angle).clamp(-step, step);
+                transform.rotation = Quat::from_rotation_z(handle.angle);
+            }
+        }
+    }
+}
+
+fn trigger_door_system(
+    mut doors: Query<
+        (&TriggerDoor, &mut ColliderFlagsComponent, &mut Sprite),
+        Changed<TriggerDoor>,
+    >,
+) {
+    for (door, mut collider_flags, mut sprite) in doors.iter_mut() {
+        collider_flags.collision_groups = door_groups(door.open);
+        sprite.color = door_color(door.open);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{
+    actor::ActorParts,
+    ldtk::plugin::LdtkEvent,
+    rng::GameRng,
+    speedrun::{GoalReachedEvent, RunTimer},
+    Player,
+};
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const REPLAY_DIR: &str = "replays";
+
+// records how the player moved through the active level and keeps the fastest run of each
+// level on disk, see ghost.rs for playing it back
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayRecorder>()
+            .init_resource::<BestReplay>()
+            .add_system(level_system)
+            .add_system(record_system.after(level_system))
+            .add_system(goal_system.after(record_system));
+    }
+}
+
+// a completed run of one level, the player's root and sprite frame per rendered frame
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Replay {
+    pub level: String,
+    // run timer seconds at the goal
+    pub time: f32,
+    // the generator as the level started, runs only replay the same with the same rolls
+    #[serde(default)]
+    pub rng: Option<GameRng>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    // run timer seconds
+    pub time: f32,
+    // world space in pixels
+    pub position: Vec2,
+    // into the player's texture atlas
+    pub index: usize,
+    pub flip_x: bool,
+}
+
+impl Replay {
+    fn path(level: &str) -> PathBuf {
+        Path::new(REPLAY_DIR).join(format!("{}.json", level))
+    }
+
+    pub fn load(level: &str) -> Result<Option<Self>> {
+        let path = Self::path(level);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = fs::read(&path).with_context(|| format!("failed to read {:?}", path))?;
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .with_context(|| format!("failed to parse {:?}", path))
+    }
+
+    pub fn write(&self) -> Result<()> {
+        let path = Self::path(&self.level);
+        fs::create_dir_all(REPLAY_DIR)
+            .with_context(|| format!("failed to create {:?}", REPLAY_DIR))?;
+        let bytes = serde_json::to_vec(self)?;
+        fs::write(&path, bytes).with_context(|| format!("failed to write {:?}", path))
+    }
+
+    // the frame shown at a run timer time, with the position blended towards the next one.
+    // none before the first and after the last frame
+    pub fn sample(&self, time: f32) -> Option<ReplayFrame> {
+        let next = self.frames.partition_point(|frame| frame.time <= time);
+        let frame = *self.frames.get(next.checked_sub(1)?)?;
+        let next = self.frames.get(next)?;
+        let t = ((time - frame.time) / (next.time - frame.time).max(f32::EPSILON)).min(1.0);
+        Some(ReplayFrame {
+            position: frame.position.lerp(next.position, t),
+            ..frame
+        })
+    }
+}
+
+// the run in progress, dropped once the level changes
+#[derive(Default)]
+struct ReplayRecorder {
+    replay: Option<Replay>,
+}
+
+// fastest recorded run of the active level
+#[derive(Default)]
+pub struct BestReplay(pub Option<Replay>);
+
+fn level_system(
+    mut events: EventReader<LdtkEvent>,
+    rng: Res<GameRng>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut best: ResMut<BestReplay>,
+) {
+    for event in events.iter() {
+        if let LdtkEvent::LevelActivated(identifier) = event {
+            recorder.replay = Some(Replay {
+                level: identifier.clone(),
+                // already reseeded for the level in PreUpdate
+                rng: Some(rng.clone()),
+                ..Default::default()
+            });
+            best.0 = Replay::load(identifier).unwrap_or_else(|err| {
+                warn!("ignoring replay: {:?}", err);
+                None
+            });
+        }
+    }
+}
+
+fn record_system(
+    run_timer: Res<RunTimer>,
+    mut recorder: ResMut<ReplayRecorder>,
+    actor_parts: ActorParts,
+    players: Query<(Entity, &Transform), With<Player>>,
+    sprites: Query<&TextureAtlasSprite>,
+) {
+    if run_timer.finished || run_timer.paused {
+        return;
+    }
+    let replay = match recorder.replay.as_mut() {
+        Some(replay) => replay,
+        None => return,
+    };
+    let (player, transform) = match players.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let sprite = match actor_parts
+        .sprite(player)
+        .and_then(|sprite| sprites.get(sprite).ok())
+    {
+        Some(sprite) => sprite,
+        None => return,
+    };
+    // nothing new while the timer stands still
+    if replay
+        .frames
+        .last()
+        .map_or(false, |frame| frame.time >= run_timer.elapsed)
+    {
+        return;
+    }
+    replay.frames.push(ReplayFrame {
+        time: run_timer.elapsed,
+        position: transform.translation.truncate(),
+        index: sprite.index,
+        flip_x: sprite.flip_x,
+    });
+}
+
+fn goal_system(
+    mut events: EventReader<GoalReachedEvent>,
+    run_timer: Res<RunTimer>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut best: ResMut<BestReplay>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    let mut replay = match recorder.replay.take() {
+        Some(replay) => replay,
+        None => return,
+    };
+    if best
+        .0
+        .as_ref()
+        .map_or(false, |best| best.time <= run_timer.elapsed)
+    {
+        return;
+    }
+    replay.time = run_timer.elapsed;
+    if let Err(err) = replay.write() {
+        error!("{:?}", err);
+    }
+    best.0 = Some(replay);
+}
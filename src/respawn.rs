@@ -0,0 +1,134 @@
+use crate::{
+    audio::{Stinger, StingerEvent},
+    damage::Health,
+    ldtk::{plugin::LdtkEvent, world::WorldMap},
+    transitions::{TransitionEvent, TransitionKind},
+    Player,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// how far past the level edge, along gravity, the player counts as fallen out
+const OUT_OF_BOUNDS_MARGIN: f32 = 64.0;
+// seconds the screen stays black after a death, then for the wipe to open it up again
+const DEATH_HOLD: f32 = 0.2;
+const DEATH_REVEAL: f32 = 0.5;
+
+pub struct RespawnPlugin;
+impl Plugin for RespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RespawnPoint>()
+            .add_event::<PlayerDeathEvent>()
+            .add_system(respawn_point_system)
+            .add_system(out_of_bounds_system)
+            .add_system(respawn_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+    OutOfBounds,
+    Killed,
+}
+
+pub struct PlayerDeathEvent {
+    pub cause: DeathCause,
+}
+
+// where the player spawned or entered the active level, world space in pixels
+#[derive(Default)]
+pub struct RespawnPoint(pub Option<Vec2>);
+
+// entering a level is the checkpoint
+fn respawn_point_system(
+    mut events: EventReader<LdtkEvent>,
+    mut respawn_point: ResMut<RespawnPoint>,
+    players: Query<&Transform, With<Player>>,
+    mut stinger_events: EventWriter<StingerEvent>,
+    // the level the player spawned in was entered, the ones after it are checkpoints
+    mut spawn_level_entered: Local<bool>,
+) {
+    for event in events.iter() {
+        match event {
+            LdtkEvent::SpawnPlayer(position) => {
+                respawn_point.0 = Some(position.truncate());
+                *spawn_level_entered = false;
+            }
+            LdtkEvent::LevelActivated(_) => {
+                if let Ok(transform) = players.get_single() {
+                    if *spawn_level_entered {
+                        stinger_events.send(StingerEvent(Stinger::Checkpoint));
+                    }
+                    *spawn_level_entered = true;
+                    respawn_point.0 = Some(transform.translation.truncate());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn out_of_bounds_system(
+    world_map: Res<WorldMap>,
+    players: Query<&Transform, With<Player>>,
+    mut event_writer: EventWriter<PlayerDeathEvent>,
+) {
+    let (transform, level) = match (players.get_single(), world_map.active_level()) {
+        (Ok(transform), Some(level)) => (transform, level),
+        _ => return,
+    };
+    let position = transform.translation.truncate();
+    if world_map.level_at(position).is_some() {
+        return;
+    }
+
+    // depth past the level's bottom edge, which follows its gravity
+    let down = level.gravity.down();
+    let half_extents = (level.rect.max - level.rect.min) * 0.5;
+    let center = level.rect.min + half_extents;
+    let depth = (position - center).dot(down) - half_extents.dot(down.abs());
+    if depth > OUT_OF_BOUNDS_MARGIN {
+        event_writer.send(PlayerDeathEvent {
+            cause: DeathCause::OutOfBounds,
+        });
+    }
+}
+
+fn respawn_system(
+    mut events: EventReader<PlayerDeathEvent>,
+    respawn_point: Res<RespawnPoint>,
+    rapier_config: Res<RapierConfiguration>,
+    mut players: Query<
+        (
+            &mut RigidBodyPositionComponent,
+            &mut RigidBodyVelocityComponent,
+            &mut Health,
+        ),
+        With<Player>,
+    >,
+    mut stinger_events: EventWriter<StingerEvent>,
+    mut transition_events: EventWriter<TransitionEvent>,
+) {
+    for event in events.iter() {
+        info!("player died: {:?}", event.cause);
+        stinger_events.send(StingerEvent(Stinger::Death));
+        let (point, mut rb_position, mut rb_velocity, mut health) =
+            match (respawn_point.0, players.get_single_mut()) {
+                (Some(point), Ok((rb_position, rb_velocity, health))) => {
+                    (point, rb_position, rb_velocity, health)
+                }
+                _ => continue,
+            };
+        health.current = health.max;
+        let position = point / rapier_config.scale;
+        rb_position.position.translation.vector = position.into();
+        rb_position.next_position.translation.vector = position.into();
+        rb_velocity.linvel = Vec2::ZERO.into();
+        transition_events.send(TransitionEvent {
+            kind: TransitionKind::CircleWipe,
+            cover: 0.0,
+            hold: DEATH_HOLD,
+            reveal: DEATH_REVEAL,
+        });
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{ldtk::plugin::LdtkEvent, save::SaveData};
+use bevy::prelude::*;
+use rand::{rand_core::impls, RngCore};
+use serde::{Deserialize, Serialize};
+
+const SEED: u64 = 0xcbf2_9ce4_8422_2325;
+
+// gameplay randomness from one generator, reseeded from the save's seed and the level
+// identifier on activation so the same inputs play a level out the same way
+pub struct RngPlugin;
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GameRng::new(SEED))
+            .add_system_to_stage(CoreStage::PreUpdate, reseed_system);
+    }
+}
+
+// xorshift64*, its own implementation so the sequence doesn't change with the rand version
+// or platform. serializable, a restored generator continues the same sequence
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRng {
+    pub seed: u64,
+    state: u64,
+}
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        // splitmix64, so close seeds don't start out alike and the state is never zero
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        state ^= state >> 31;
+        Self {
+            seed,
+            state: state.max(1),
+        }
+    }
+}
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// fnv-1a, stable across runs and platforms unlike the std hasher
+fn level_seed(seed: u64, identifier: &str) -> u64 {
+    identifier.bytes().fold(seed, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn reseed_system(
+    mut events: EventReader<LdtkEvent>,
+    save: Res<SaveData>,
+    mut rng: ResMut<GameRng>,
+) {
+    for event in events.iter() {
+        if let LdtkEvent::LevelActivated(identifier) = event {
+            *rng = GameRng::new(level_seed(save.seed.unwrap_or(SEED), identifier));
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use crate::{
+    actor::ActorParts,
+    animation::AnimationSprite,
+    collision::CollisionLayers,
+    game_time::GameTime,
+    input::{Action, ActionInput},
+    Player,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// pixels between joints
+const SEGMENT_LENGTH: f32 = 8.0;
+// how close to a segment the player has to be to grab the rope, in pixels
+const GRAB_DISTANCE: f32 = 8.0;
+// pixels per second along the rope
+const CLIMB_SPEED: f32 = 48.0;
+// impulse on the held segment per second while swinging, in rapier units
+const SWING_FORCE: f32 = 0.6;
+const JUMP_OFF_IMPULSE: Vec2 = Vec2::new(3.0, 6.0);
+const PLATFORM_HEIGHT: f32 = 4.0;
+
+// ldtk "Rope" entities: a chain of small bodies hanging from the entity's top, as long as its
+// height, that the player climbs with up and down and optionally carries a platform
+pub struct RopePlugin;
+impl Plugin for RopePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(grab_system)
+            .add_system(climb_system.after(grab_system))
+            .add_system(render_system);
+    }
+}
+
+// on the anchor, segments top to bottom
+#[derive(Component)]
+pub struct Rope {
+    segments: Vec<Entity>,
+}
+
+// on the player while holding on, player_system leaves them alone
+#[derive(Component)]
+pub struct Climbing {
+    rope: Entity,
+    // pixels down the rope from the anchor
+    distance: f32,
+}
+
+// a static anchor at `top` in world space pixels, `platform` is the width of a platform
+// hanging from the bottom
+pub fn spawn_rope(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    top: Vec2,
+    length: f32,
+    platform: Option<f32>,
+) -> Entity {
+    let scale = rapier_config.scale;
+    let half_segment = SEGMENT_LENGTH * 0.5 / scale;
+    let count = (length / SEGMENT_LENGTH).ceil().max(1.0) as usize;
+
+    let anchor = commands
+        .spawn_bundle(RigidBodyBundle {
+            body_type: RigidBodyType::Static.into(),
+            position: (top / scale).into(),
+            ..Default::default()
+        })
+        .id();
+    let mut previous = (anchor, Vec2::ZERO);
+    let mut segments = vec![];
+    // joints, segments and the platform
+    let mut parts = vec![];
+    for index in 0..count {
+        let position = top - Vec2::new(0.0, (index as f32 + 0.5) * SEGMENT_LENGTH);
+        // the collider only gives the segment mass, it collides with nothing
+        let segment = commands
+            .spawn_bundle(RigidBodyBundle {
+                position: (position / scale).into(),
+                damping: RigidBodyDamping {
+                    linear_damping: 0.5,
+                    angular_damping: 1.0,
+                }
+                .into(),
+                ..Default::default()
+            })
+            .insert_bundle(ColliderBundle {
+                shape: ColliderShape::ball(half_segment).into(),
+                flags: ColliderFlags {
+                    collision_groups: InteractionGroups::none(),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            })
+            .id();
+        parts.push(joint(
+            commands,
+            previous,
+            (segment, Vec2::new(0.0, half_segment)),
+        ));
+        parts.push(segment);
+        previous = (segment, Vec2::new(0.0, -half_segment));
+        segments.push(segment);
+    }
+
+    if let Some(width) = platform {
+        let half_extents = Vec2::new(width * 0.5, PLATFORM_HEIGHT * 0.5);
+        let position = top - Vec2::new(0.0, count as f32 * SEGMENT_LENGTH + half_extents.y);
+        let platform = commands
+            .spawn_bundle(RigidBodyBundle {
+                position: (position / scale).into(),
+                mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
+                ..Default::default()
+            })
+            .insert_bundle(ColliderBundle {
+                shape: ColliderShape::cuboid(half_extents.x / scale, half_extents.y / scale).into(),
+                flags: ColliderFlags {
+                    collision_groups: CollisionLayers::terrain(),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            })
+            .insert(ColliderPositionSync::Discrete)
+            .insert_bundle(GeometryBuilder::build_as(
+                &shapes::Rectangle {
+                    extents: half_extents * 2.0,
+                    origin: RectangleOrigin::Center,
+                },
+                DrawMode::Fill(FillMode::color(Color::rgb(0.5, 0.35, 0.2))),
+                // at the anchor's z, being its child
+                Transform::from_translation(position.extend(0.0)),
+            ))
+            .id();
+        parts.push(joint(
+            commands,
+            previous,
+            (platform, Vec2::new(0.0, half_extents.y / scale)),
+        ));
+        parts.push(platform);
+    }
+
+    commands
+        .entity(anchor)
+        .insert_bundle(GeometryBuilder::build_as(
+            &shapes::Polygon {
+                points: vec![],
+                closed: false,
+            },
+            DrawMode::Stroke(StrokeMode::new(Color::rgb(0.7, 0.6, 0.4), 1.0)),
+            Transform::from_xyz(0.0, 0.0, 2.0),
+        ))
+        .insert(Rope { segments })
+        // the anchor stays at the origin, so the parts go with it when a level is despawned
+        .push_children(&parts)
+        .id()
+}
+
+// anchors are local to each body, in rapier units
+fn joint(
+    commands: &mut Commands,
+    (entity1, anchor1): (Entity, Vec2),
+    (entity2, anchor2): (Entity, Vec2),
+) -> Entity {
+    commands
+        .spawn()
+        .insert(JointBuilderComponent::new(
+            BallJoint::new(anchor1.into(), anchor2.into()),
+            entity1,
+            entity2,
+        ))
+        .id()
+}
+
+fn segment_position(
+    bodies: &Query<&RigidBodyPositionComponent>,
+    segment: Entity,
+    scale: f32,
+) -> Option<Vec2> {
+    bodies.get(segment).ok().map(|rb_position| {
+        let translation = rb_position.position.translation.vector;
+        Vec2::new(translation.x, translation.y) * scale
+    })
+}
+
+// position along the rope, `distance` pixels from the anchor
+fn rope_point(
+    rope: &Rope,
+    bodies: &Query<&RigidBodyPositionComponent>,
+    distance: f32,
+    scale: f32,
+) -> Option<Vec2> {
+    let last = rope.segments.len().checked_sub(1)?;
+    let t = (distance / SEGMENT_LENGTH - 0.5).clamp(0.0, last as f32);
+    let index = (t.floor() as usize).min(last);
+    let from = segment_position(bodies, rope.segments[index], scale)?;
+    let to = segment_position(bodies, rope.segments[(index + 1).min(last)], scale)?;
+    Some(from.lerp(to, t - index as f32))
+}
+
+fn grab_system(
+    mut commands: Commands,
+    actions: ActionInput,
+    rapier_config: Res<RapierConfiguration>,
+    ropes: Query<(Entity, &Rope)>,
+    bodies: Query<&RigidBodyPositionComponent>,
+    players: Query<(Entity, &GlobalTransform), (With<Player>, Without<Climbing>)>,
+) {
+    if !actions.just_pressed(Action::Up) {
+        return;
+    }
+    let (player, global_transform) = match players.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let player_position = global_transform.translation.truncate();
+    let nearest = ropes
+        .iter()
+        .flat_map(|(entity, rope)| {
+            rope.segments
+                .iter()
+                .enumerate()
+                .map(move |(index, segment)| (entity, index, *segment))
+        })
+        .filter_map(|(entity, index, segment)| {
+            segment_position(&bodies, segment, rapier_config.scale)
+                .map(|position| (entity, index, position.distance(player_position)))
+        })
+        .filter(|(_, _, distance)| *distance < GRAB_DISTANCE)
+        .min_by(|a, b| a.2.total_cmp(&b.2));
+    if let Some((rope, index, _)) = nearest {
+        commands.entity(player).insert(Climbing {
+            rope,
+            distance: (index as f32 + 0.5) * SEGMENT_LENGTH,
+        });
+    }
+}
+
+fn climb_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    actions: ActionInput,
+    rapier_config: Res<RapierConfiguration>,
+    ropes: Query<&Rope>,
+    bodies: Query<&RigidBodyPositionComponent>,
+    mut segments: Query<
+        (
+            &mut RigidBodyVelocityComponent,
+            &RigidBodyMassPropsComponent,
+        ),
+        Without<Player>,
+    >,
+    mut players: Query<
+        (
+            Entity,
+            &mut Climbing,
+            &mut RigidBodyVelocityComponent,
+            &RigidBodyMassPropsComponent,
+        ),
+        With<Player>,
+    >,
+    mut sprites: Query<&mut AnimationSprite>,
+    actor_parts: ActorParts,
+) {
+    let (entity, mut climbing, mut rb_velocity, rb_mass_props) = match players.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let rope = match ropes.get(climbing.rope) {
+        Ok(rope) => rope,
+        Err(_) => {
+            commands.entity(entity).remove::<Climbing>();
+            return;
+        }
+    };
+    let scale = rapier_config.scale;
+    let delta = time.delta_seconds();
+    // paused or in hitstop
+    if delta <= 0.0 {
+        return;
+    }
+    let x_axis = -(actions.pressed(Action::Left) as i8) + actions.pressed(Action::Right) as i8;
+
+    if actions.just_pressed(Action::Jump) {
+        commands.entity(entity).remove::<Climbing>();
+        let impulse = Vec2::new(JUMP_OFF_IMPULSE.x * x_axis as f32, JUMP_OFF_IMPULSE.y) / scale;
+        rb_velocity.apply_impulse(rb_mass_props, impulse.into());
+        return;
+    }
+
+    let y_axis = actions.pressed(Action::Down) as i8 - actions.pressed(Action::Up) as i8;
+    let length = rope.segments.len() as f32 * SEGMENT_LENGTH;
+    climbing.distance =
+        (climbing.distance + y_axis as f32 * CLIMB_SPEED * delta).clamp(0.0, length);
+
+    // swing by pushing the held segment
+    let index = ((climbing.distance / SEGMENT_LENGTH) as usize).min(rope.segments.len() - 1);
+    if x_axis != 0 {
+        if let Ok((mut segment_velocity, segment_mass_props)) =
+            segments.get_mut(rope.segments[index])
+        {
+            let impulse = Vec2::new(x_axis as f32 * SWING_FORCE * delta, 0.0);
+            segment_velocity.apply_impulse(segment_mass_props, impulse.into());
+        }
+    }
+
+    // follow the rope by velocity, so the player still collides on the way
+    let (target, current) = match (
+        rope_point(rope, &bodies, climbing.distance, scale),
+        segment_position(&bodies, entity, scale),
+    ) {
+        (Some(target), Some(current)) => (target, current),
+        _ => return,
+    };
+    rb_velocity.linvel = ((target - current) / scale / delta).into();
+
+    if let Some(mut animation_sprite) = actor_parts
+        .sprite(entity)
+        .and_then(|sprite| sprites.get_mut(sprite).ok())
+    {
+        animation_sprite.set_animation("wait");
+    }
+}
+
+fn render_system(
+    rapier_config: Res<RapierConfiguration>,
+    bodies: Query<&RigidBodyPositionComponent>,
+    mut ropes: Query<(Entity, &Rope, &mut Path)>,
+) {
+    for (anchor, rope, mut path) in ropes.iter_mut() {
+        let points = std::iter::once(anchor)
+            .chain(rope.segments.iter().copied())
+            .filter_map(|body| segment_position(&bodies, body, rapier_config.scale))
+            .collect::<Vec<_>>();
+        let mut path_builder = PathBuilder::new();
+        for (index, point) in points.into_iter().enumerate() {
+            if index == 0 {
+                path_builder.move_to(point);
+            } else {
+                path_builder.line_to(point);
+            }
+        }
+        *path = path_builder.build();
+    }
+}
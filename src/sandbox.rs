@@ -0,0 +1,294 @@
+use crate::{
+    collision::CollisionLayers, despawn::MarkedForDespawn, effects::SpriteFlash,
+    game_time::GameTime, input::ActionInput, ldtk::plugin::LdtkEvent, Actor, AppState, Direction,
+    Player, Z_ACTORS,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+const GROUND_WIDTH: f32 = 640.0;
+const GROUND_HEIGHT: f32 = 16.0;
+const PROJECTILE_LIFETIME: f32 = 3.0;
+
+pub struct SandboxPlugin;
+impl Plugin for SandboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Sandbox).with_system(setup_system))
+            .add_system_set(
+                SystemSet::on_update(AppState::Sandbox)
+                    .with_system(spawn_system)
+                    .with_system(lifetime_system),
+            );
+    }
+}
+
+#[derive(Component)]
+struct SandboxProp;
+
+#[derive(Component)]
+struct Lifetime(Timer);
+
+fn setup_system(
+    mut commands: Commands,
+    rapier_config: Res<RapierConfiguration>,
+    mut event_writer: EventWriter<LdtkEvent>,
+) {
+    // flat ground
+    spawn_ground(
+        &mut commands,
+        &rapier_config,
+        Vec2::new(0.0, -64.0),
+        Vec2::new(GROUND_WIDTH, GROUND_HEIGHT) * 0.5,
+    );
+
+    event_writer.send(LdtkEvent::SpawnPlayer(Vec3::new(0.0, 0.0, 0.0)));
+}
+
+// static outlined box, in pixels
+pub fn spawn_ground(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    position: Vec2,
+    half_extents: Vec2,
+) {
+    commands
+        .spawn_bundle(ColliderBundle {
+            shape: ColliderShape::cuboid(
+                half_extents.x / rapier_config.scale,
+                half_extents.y / rapier_config.scale,
+            )
+            .into(),
+            position: (position / rapier_config.scale).into(),
+            flags: ColliderFlags {
+                collision_groups: CollisionLayers::terrain(),
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: half_extents * 2.0,
+                origin: RectangleOrigin::Center,
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::rgba(1.0, 1.0, 1.0, 0.2)),
+                outline_mode: StrokeMode::new(Color::WHITE, 1.0),
+            },
+            Transform::from_translation(position.extend(Z_ACTORS)),
+        ))
+        .insert(ColliderPositionSync::Discrete);
+}
+
+fn spawn_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    actions: ActionInput,
+    rapier_config: Res<RapierConfiguration>,
+    players: Query<(Entity, &Transform, &Actor), With<Player>>,
+    mut attackers: Query<&mut Player>,
+    mut event_writer: EventWriter<LdtkEvent>,
+) {
+    let (origin, flip_x) = players
+        .get_single()
+        .map(|(_, transform, actor)| {
+            let flip_x = if actor.direction == Direction::Left {
+                -1.0
+            } else {
+                1.0
+            };
+            (transform.translation.truncate(), flip_x)
+        })
+        .unwrap_or((Vec2::ZERO, 1.0));
+    let position = origin + Vec2::new(24.0 * flip_x, 16.0);
+
+    // flash effects on the player
+    if let Ok((player, _, _)) = players.get_single() {
+        if keyboard_input.just_pressed(KeyCode::H) {
+            commands.entity(player).insert(SpriteFlash::damage());
+        }
+        if keyboard_input.just_pressed(KeyCode::I) {
+            commands.entity(player).insert(SpriteFlash::blink(2.0));
+        }
+    }
+
+    // cycle the player's attack damage type
+    if keyboard_input.just_pressed(KeyCode::K) {
+        for mut player in attackers.iter_mut() {
+            player.attack_type = player.attack_type.next();
+            info!("sandbox: attack type {:?}", player.attack_type);
+        }
+    }
+
+    // enemy
+    if keyboard_input.just_pressed(KeyCode::E) {
+        event_writer.send(LdtkEvent::SpawnEnemy {
+            name: "test".to_string(),
+            position: position.extend(0.0),
+            iid: None,
+        });
+    }
+    // npc
+    if keyboard_input.just_pressed(KeyCode::N) {
+        event_writer.send(LdtkEvent::SpawnNpc {
+            name: "npc".to_string(),
+            position: position.extend(0.0),
+            dialogue: vec!["hello!".to_string(), "nice weather.".to_string()],
+            radius: 32.0,
+            iid: None,
+        });
+    }
+    // crate
+    if keyboard_input.just_pressed(KeyCode::C) {
+        let half_extents = Vec2::splat(8.0);
+        spawn_prop(
+            &mut commands,
+            &rapier_config,
+            position,
+            RigidBodyBundle::default(),
+            ColliderBundle {
+                shape: ColliderShape::cuboid(
+                    half_extents.x / rapier_config.scale,
+                    half_extents.y / rapier_config.scale,
+                )
+                .into(),
+                material: ColliderMaterial::new(0.8, 0.0).into(),
+                flags: ColliderFlags {
+                    collision_groups: CollisionLayers::terrain(),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            },
+            &shapes::Rectangle {
+                extents: half_extents * 2.0,
+                origin: RectangleOrigin::Center,
+            },
+            Color::ORANGE,
+        );
+    }
+    // spring
+    if keyboard_input.just_pressed(KeyCode::B) {
+        let half_extents = Vec2::new(12.0, 4.0);
+        let position = Vec2::new(position.x, origin.y - 8.0);
+        spawn_prop(
+            &mut commands,
+            &rapier_config,
+            position,
+            RigidBodyBundle {
+                body_type: RigidBodyType::Static.into(),
+                ..Default::default()
+            },
+            ColliderBundle {
+                shape: ColliderShape::cuboid(
+                    half_extents.x / rapier_config.scale,
+                    half_extents.y / rapier_config.scale,
+                )
+                .into(),
+                material: ColliderMaterial {
+                    restitution: 1.5,
+                    restitution_combine_rule: CoefficientCombineRule::Max,
+                    ..Default::default()
+                }
+                .into(),
+                flags: ColliderFlags {
+                    collision_groups: CollisionLayers::terrain(),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            },
+            &shapes::Rectangle {
+                extents: half_extents * 2.0,
+                origin: RectangleOrigin::Center,
+            },
+            Color::LIME_GREEN,
+        );
+    }
+    // projectile
+    if keyboard_input.just_pressed(KeyCode::F) {
+        let radius = 2.0;
+        // at the cursor with mouse controls
+        let direction = actions
+            .aim(position)
+            .unwrap_or_else(|| Vec2::new(flip_x, 0.0));
+        let entity = spawn_prop(
+            &mut commands,
+            &rapier_config,
+            position,
+            RigidBodyBundle {
+                velocity: RigidBodyVelocity {
+                    linvel: (direction * 256.0 / rapier_config.scale).into(),
+                    angvel: 0.0,
+                }
+                .into(),
+                forces: RigidBodyForces {
+                    gravity_scale: 0.0,
+                    ..Default::default()
+                }
+                .into(),
+                ccd: RigidBodyCcd {
+                    ccd_enabled: true,
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            },
+            ColliderBundle {
+                shape: ColliderShape::ball(radius / rapier_config.scale).into(),
+                flags: ColliderFlags {
+                    collision_groups: CollisionLayers::projectile(),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            },
+            &shapes::Circle {
+                radius,
+                center: Vec2::ZERO,
+            },
+            Color::YELLOW,
+        );
+        commands
+            .entity(entity)
+            .insert(Lifetime(Timer::from_seconds(PROJECTILE_LIFETIME, false)));
+    }
+}
+
+fn spawn_prop(
+    commands: &mut Commands,
+    rapier_config: &Res<RapierConfiguration>,
+    position: Vec2,
+    rigid_body: RigidBodyBundle,
+    collider: ColliderBundle,
+    shape: &impl Geometry,
+    color: Color,
+) -> Entity {
+    commands
+        .spawn_bundle(RigidBodyBundle {
+            position: (position / rapier_config.scale).into(),
+            ..rigid_body
+        })
+        .insert_bundle(collider)
+        .insert_bundle(GeometryBuilder::build_as(
+            shape,
+            DrawMode::Fill(FillMode::color(color)),
+            Transform::from_translation(position.extend(Z_ACTORS)),
+        ))
+        .insert(ColliderPositionSync::Discrete)
+        .insert(SandboxProp)
+        .id()
+}
+
+fn lifetime_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut query: Query<(Entity, &mut Lifetime)>,
+) {
+    for (entity, mut lifetime) in query.iter_mut() {
+        if lifetime.0.tick(time.delta()).just_finished() {
+            commands.entity(entity).insert(MarkedForDespawn);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{speedrun::Medal, world_flags::WorldFlags};
+use anyhow::{Context, Result};
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::Path};
+
+const SAVE_PATH: &str = "save.json";
+
+pub struct SavePlugin;
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        let save = match SaveData::load(Path::new(SAVE_PATH)) {
+            Ok(save) => save,
+            Err(err) => {
+                warn!("starting without save data: {:?}", err);
+                SaveData::default()
+            }
+        };
+        app.insert_resource(save)
+            .add_system_to_stage(CoreStage::Last, exit_system);
+    }
+}
+
+// persisted progress, keyed by level identifier
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SaveData {
+    pub medals: HashMap<String, Medal>,
+    pub best_times: HashMap<String, f32>,
+    #[serde(default)]
+    pub high_score: u32,
+    #[serde(default)]
+    pub world_flags: WorldFlags,
+    // of the latest run, written when it starts and reused to resume it. levels roll their
+    // randomness from this, the same default seed for every save without one
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+impl SaveData {
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let bytes = fs::read(path).with_context(|| format!("failed to read {:?}", path))?;
+        serde_json::from_slice(&bytes).with_context(|| format!("failed to parse {:?}", path))
+    }
+
+    pub fn write(&self) -> Result<()> {
+        let path = Path::new(SAVE_PATH);
+        let bytes = serde_json::to_vec_pretty(self)?;
+        fs::write(path, bytes).with_context(|| format!("failed to write {:?}", path))
+    }
+}
+
+// whatever was kept in memory only, like the high score
+fn exit_system(mut events: EventReader<AppExit>, save: Res<SaveData>) {
+    if events.iter().count() > 0 {
+        if let Err(err) = save.write() {
+            error!("{:?}", err);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{
+    actor::ActorParts,
+    animation::AnimationSprite,
+    damage::{Burning, Health, Slowed},
+    despawn::MarkedForDespawn,
+    ldtk::{plugin::LdtkEvent, registry::LdtkEntityRegistry},
+    rng::GameRng,
+    world_flags::WorldFlags,
+    Actor, Direction, Enemy, Player, VirtualPosition,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+pub struct SavestatePlugin;
+impl Plugin for SavestatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Savestate>()
+            .add_system(savestate_system);
+    }
+}
+
+// the body and what runs on it: health, status effects and where its animation is
+struct BodySnapshot {
+    position: RigidBodyPosition,
+    velocity: RigidBodyVelocity,
+    direction: Direction,
+    health: Option<f32>,
+    slowed: Option<Slowed>,
+    burning: Option<Burning>,
+    animation: Option<AnimationSprite>,
+}
+
+struct Snapshot {
+    // with its state, dash and cooldown timers
+    player: Option<(Player, BodySnapshot)>,
+    // entity, name and iid of the enemies alive at the save. the ones killed since respawn
+    // fresh from their level data, with full health and no status effects
+    enemies: Vec<(Entity, String, Option<String>, BodySnapshot)>,
+    camera_x: Option<f32>,
+    // drops and ai roll the same after a load
+    rng: GameRng,
+    // doors, switches and pickups as they were
+    world_flags: WorldFlags,
+}
+
+// in memory only, F5 saves and F9 loads, unrelated to the on-disk save format
+#[derive(Default)]
+struct Savestate {
+    snapshot: Option<Snapshot>,
+}
+
+type BodyQuery<'a> = (
+    &'a mut RigidBodyPositionComponent,
+    &'a mut RigidBodyVelocityComponent,
+    &'a mut Actor,
+    Option<&'a mut Health>,
+    Option<&'a Slowed>,
+    Option<&'a Burning>,
+);
+
+fn snapshot_body(
+    (rb_position, rb_velocity, actor, health, slowed, burning): (
+        &RigidBodyPositionComponent,
+        &RigidBodyVelocityComponent,
+        &Actor,
+        Option<&Health>,
+        Option<&Slowed>,
+        Option<&Burning>,
+    ),
+    animation: Option<&AnimationSprite>,
+) -> BodySnapshot {
+    BodySnapshot {
+        position: rb_position.0,
+        velocity: rb_velocity.0,
+        direction: actor.direction,
+        health: health.map(|health| health.current),
+        slowed: slowed.cloned(),
+        burning: burning.cloned(),
+        animation: animation.cloned(),
+    }
+}
+
+fn restore_body(
+    commands: &mut Commands,
+    entity: Entity,
+    (mut rb_position, mut rb_velocity, mut actor, health, _, _): (
+        Mut<RigidBodyPositionComponent>,
+        Mut<RigidBodyVelocityComponent>,
+        Mut<Actor>,
+        Option<Mut<Health>>,
+        Option<&Slowed>,
+        Option<&Burning>,
+    ),
+    sprite: Option<Mut<AnimationSprite>>,
+    snapshot: &BodySnapshot,
+) {
+    rb_position.0 = snapshot.position;
+    rb_velocity.0 = snapshot.velocity;
+    actor.direction = snapshot.direction;
+    if let (Some(mut health), Some(current)) = (health, snapshot.health) {
+        health.current = current;
+    }
+    let mut entity_commands = commands.entity(entity);
+    match &snapshot.slowed {
+        Some(slowed) => entity_commands.insert(slowed.clone()),
+        None => entity_commands.remove::<Slowed>(),
+    };
+    match &snapshot.burning {
+        Some(burning) => entity_commands.insert(burning.clone()),
+        None => entity_commands.remove::<Burning>(),
+    };
+    if let (Some(mut sprite), Some(animation)) = (sprite, &snapshot.animation) {
+        *sprite = animation.clone();
+    }
+}
+
+fn savestate_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    rapier_config: Res<RapierConfiguration>,
+    mut savestate: ResMut<Savestate>,
+    mut rng: ResMut<GameRng>,
+    mut world_flags: ResMut<WorldFlags>,
+    registry: Res<LdtkEntityRegistry>,
+    mut players: Query<(Entity, &mut Player, BodyQuery)>,
+    mut enemies: Query<(Entity, &Enemy, BodyQuery), (Without<Player>, Without<MarkedForDespawn>)>,
+    actor_parts: ActorParts,
+    mut sprites: Query<&mut AnimationSprite>,
+    mut cameras: Query<(&mut Transform, &mut VirtualPosition), With<Camera>>,
+    mut event_writer: EventWriter<LdtkEvent>,
+) {
+    let sprite_of = |actor: Entity| actor_parts.sprite(actor);
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        savestate.snapshot = Some(Snapshot {
+            player: players.get_single().ok().map(|(entity, player, body)| {
+                let animation = sprite_of(entity).and_then(|sprite| sprites.get(sprite).ok());
+                (player.clone(), snapshot_body(body, animation))
+            }),
+            enemies: enemies
+                .iter()
+                .map(|(entity, enemy, body)| {
+                    let animation = sprite_of(entity).and_then(|sprite| sprites.get(sprite).ok());
+                    (
+                        entity,
+                        enemy.name.clone(),
+                        registry.iid(entity).map(|iid| iid.to_string()),
+                        snapshot_body(body, animation),
+                    )
+                })
+                .collect(),
+            camera_x: cameras
+                .get_single()
+                .ok()
+                .map(|(_, virtual_position)| virtual_position.0.x),
+            rng: rng.clone(),
+            world_flags: world_flags.clone(),
+        });
+        info!("savestate: saved");
+    }
+
+    if keyboard_input.just_pressed(KeyCode::F9) {
+        let snapshot = match &savestate.snapshot {
+            Some(snapshot) => snapshot,
+            None => {
+                warn!("savestate: nothing saved");
+                return;
+            }
+        };
+        if let (Some((saved_player, saved_body)), Ok((entity, mut player, body))) =
+            (&snapshot.player, players.get_single_mut())
+        {
+            *player = saved_player.clone();
+            let animation = sprite_of(entity).and_then(|sprite| sprites.get_mut(sprite).ok());
+            restore_body(&mut commands, entity, body, animation, saved_body);
+        }
+
+        // enemies spawned after the save go away, the ones killed since come back
+        for (entity, _, body) in enemies.iter_mut() {
+            match snapshot
+                .enemies
+                .iter()
+                .find(|(saved, _, _, _)| *saved == entity)
+            {
+                Some((_, _, _, enemy)) => {
+                    let animation =
+                        sprite_of(entity).and_then(|sprite| sprites.get_mut(sprite).ok());
+                    restore_body(&mut commands, entity, body, animation, enemy);
+                }
+                None => {
+                    commands.entity(entity).insert(MarkedForDespawn);
+                }
+            }
+        }
+        for (_, name, iid, enemy) in snapshot
+            .enemies
+            .iter()
+            .filter(|(saved, _, _, _)| enemies.get(*saved).is_err())
+        {
+            let translation = enemy.position.position.translation.vector;
+            event_writer.send(LdtkEvent::SpawnEnemy {
+                name: name.clone(),
+                position: Vec3::new(translation.x, translation.y, 0.0) * rapier_config.scale,
+                iid: iid.clone(),
+            });
+        }
+
+        if let (Some(camera_x), Ok((mut transform, mut virtual_position))) =
+            (snapshot.camera_x, cameras.get_single_mut())
+        {
+            virtual_position.0.x = camera_x;
+            transform.translation.x = camera_x;
+        }
+        *rng = snapshot.rng.clone();
+        *world_flags = snapshot.world_flags.clone();
+        info!("savestate: loaded");
+    }
+}
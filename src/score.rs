@@ -0,0 +1,98 @@
+use crate::{
+    bitmap_font::{BitmapText, BitmapTextBundle, ScreenPosition, SCREEN_HALF_SIZE},
+    combo::Combo,
+    damage::{DamageSystem, KillEvent},
+    save::SaveData,
+    Enemy,
+};
+use bevy::prelude::*;
+
+const KILL_POINTS: u32 = 100;
+
+pub struct ScorePlugin;
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>()
+            .add_event::<PickupEvent>()
+            .add_startup_system(setup_system)
+            // killed enemies are only despawned at the end of the frame
+            .add_system(score_system.after(DamageSystem))
+            .add_system(high_score_system)
+            .add_system(hud_system);
+    }
+}
+
+#[derive(Default)]
+pub struct Score {
+    pub points: u32,
+}
+impl Score {
+    fn add(&mut self, points: u32, combo: &Combo) {
+        self.points += (points as f32 * combo.multiplier()).round() as u32;
+    }
+}
+
+// sent by whatever the player picks up, worth points times the combo multiplier
+pub struct PickupEvent {
+    pub points: u32,
+}
+
+#[derive(Component)]
+struct ScoreHud;
+
+fn setup_system(mut commands: Commands) {
+    commands
+        .spawn_bundle(BitmapTextBundle::new(
+            BitmapText::new("", Color::WHITE).with_anchor(Vec2::new(0.5, -0.5)),
+            Transform::identity(),
+        ))
+        .insert(ScreenPosition(
+            SCREEN_HALF_SIZE * Vec2::new(1.0, -1.0) + Vec2::new(-2.0, 2.0),
+        ))
+        .insert(ScoreHud);
+}
+
+fn score_system(
+    mut kill_events: EventReader<KillEvent>,
+    mut pickup_events: EventReader<PickupEvent>,
+    mut score: ResMut<Score>,
+    combo: Res<Combo>,
+    enemies: Query<(), With<Enemy>>,
+) {
+    // not broken props
+    for _ in kill_events
+        .iter()
+        .filter(|event| enemies.get(event.target).is_ok())
+    {
+        score.add(KILL_POINTS, &combo);
+    }
+    for event in pickup_events.iter() {
+        score.add(event.points, &combo);
+    }
+}
+
+// kept up to date here, the save is written on exit
+fn high_score_system(score: Res<Score>, mut save: ResMut<SaveData>) {
+    if score.points > save.high_score {
+        save.high_score = score.points;
+    }
+}
+
+fn hud_system(
+    score: Res<Score>,
+    combo: Res<Combo>,
+    save: Res<SaveData>,
+    mut query: Query<&mut BitmapText, With<ScoreHud>>,
+) {
+    if !score.is_changed() && !combo.is_changed() && !save.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.text = format!(
+            "score {} x{:.1}\nhigh {}",
+            score.points,
+            combo.multiplier(),
+            save.high_score
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{
+    audio::Mixer,
+    debug::{DebugConfig, DebugGroup, DebugGroups},
+    input::{key_from_name, key_name, Action, InputMap},
+};
+use anyhow::{Context, Result};
+use bevy::{prelude::*, window::WindowMode};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::Path};
+
+const SETTINGS_PATH: &str = "settings.json";
+
+// loaded before the other plugins read their defaults, written back whenever it changes
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = match Settings::load(Path::new(SETTINGS_PATH)) {
+            Ok(settings) => settings,
+            Err(err) => {
+                warn!("using default settings: {:?}", err);
+                Settings::default()
+            }
+        };
+        app.insert_resource(settings)
+            .add_startup_system(debug_defaults_system)
+            .add_system(settings_system);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    // key names per action, e.g. "jump": ["Space"]
+    pub bindings: HashMap<Action, Vec<String>>,
+    pub fullscreen: bool,
+    // aim at the cursor and attack with the left mouse button
+    pub mouse_controls: bool,
+    // scanlines, bulge and a vignette on the screen
+    pub crt: bool,
+    // chromatic aberration when the player gets hit and the flashback palette
+    pub screen_effects: bool,
+    // the best recorded run of the level played back next to the player
+    pub ghost: bool,
+    // debug group names shown at startup, e.g. "sensors"
+    pub debug_groups: Vec<String>,
+    pub debug: DebugConfig,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        let mixer = Mixer::default();
+        let input_map = InputMap::default();
+        Self {
+            master_volume: mixer.master_volume,
+            music_volume: mixer.music_volume,
+            sfx_volume: mixer.sfx_volume,
+            bindings: Action::ALL
+                .into_iter()
+                .map(|action| {
+                    let keys = input_map.keys(action).iter().copied().map(key_name);
+                    (action, keys.collect())
+                })
+                .collect(),
+            fullscreen: false,
+            mouse_controls: false,
+            crt: false,
+            screen_effects: true,
+            ghost: true,
+            debug_groups: vec![],
+            debug: DebugConfig::default(),
+        }
+    }
+}
+impl Settings {
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let bytes = fs::read(path).with_context(|| format!("failed to read {:?}", path))?;
+        serde_json::from_slice(&bytes).with_context(|| format!("failed to parse {:?}", path))
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let bytes = serde_json::to_vec_pretty(self)?;
+        fs::write(path, bytes).with_context(|| format!("failed to write {:?}", path))
+    }
+
+    // actions missing from the file keep their default keys
+    pub fn input_map(&self) -> InputMap {
+        let mut input_map = InputMap::default();
+        for (action, names) in self.bindings.iter() {
+            let keys = names
+                .iter()
+                .filter_map(|name| {
+                    let key_code = key_from_name(name);
+                    if key_code.is_none() {
+                        warn!("unknown key in settings: {}", name);
+                    }
+                    key_code
+                })
+                .collect();
+            input_map.bindings.insert(*action, keys);
+        }
+        input_map.mouse = self.mouse_controls;
+        input_map
+    }
+}
+
+fn debug_defaults_system(settings: Res<Settings>, mut debug_groups: ResMut<DebugGroups>) {
+    for name in settings.debug_groups.iter() {
+        match DebugGroup::from_name(name) {
+            Some(group) => {
+                debug_groups.visible.insert(group);
+            }
+            None => warn!("unknown debug group in settings: {}", name),
+        }
+    }
+}
+
+// applies the settings, on the first frame too, and saves every later change
+fn settings_system(
+    settings: Res<Settings>,
+    mut mixer: ResMut<Mixer>,
+    mut input_map: ResMut<InputMap>,
+    mut windows: ResMut<Windows>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    mixer.master_volume = settings.master_volume;
+    mixer.music_volume = settings.music_volume;
+    mixer.sfx_volume = settings.sfx_volume;
+    *input_map = settings.input_map();
+    if let Some(window) = windows.get_primary_mut() {
+        let mode = if settings.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        };
+        if window.mode() != mode {
+            window.set_mode(mode);
+        }
+    }
+
+    if !settings.is_added() {
+        if let Err(err) = settings.save(Path::new(SETTINGS_PATH)) {
+            error!("{:?}", err);
+        }
+    }
+}
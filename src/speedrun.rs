@@ -0,0 +1,193 @@
+use crate::{
+    bitmap_font::{BitmapText, BitmapTextBundle, ScreenPosition, SCREEN_HALF_SIZE},
+    ldtk::{
+        plugin::LdtkEvent,
+        world::{LevelSettings, WorldMap},
+    },
+    save::SaveData,
+    Player,
+};
+use bevy::{prelude::*, sprite::Rect as SpriteRect};
+use serde::{Deserialize, Serialize};
+
+pub struct SpeedrunPlugin;
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunTimer>()
+            .add_event::<GoalReachedEvent>()
+            .add_startup_system(setup_system)
+            .add_system(run_timer_system)
+            .add_system(goal_system)
+            .add_system(timer_hud_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+}
+impl Medal {
+    // the best medal whose time limit is met
+    pub fn award(settings: &LevelSettings, time: f32) -> Option<Self> {
+        [
+            (Medal::Gold, settings.gold_time),
+            (Medal::Silver, settings.silver_time),
+            (Medal::Bronze, settings.bronze_time),
+        ]
+        .into_iter()
+        .find(|(_, limit)| limit.map_or(false, |limit| time <= limit))
+        .map(|(medal, _)| medal)
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Medal::Bronze => Color::rgb(0.8, 0.5, 0.2),
+            Medal::Silver => Color::rgb(0.75, 0.75, 0.8),
+            Medal::Gold => Color::rgb(1.0, 0.85, 0.1),
+        }
+    }
+}
+
+// time spent in the active level, stopped at its goal
+#[derive(Default)]
+pub struct RunTimer {
+    pub level: Option<String>,
+    pub elapsed: f32,
+    pub finished: bool,
+    // set by whatever pauses the game, menus and cutscenes don't count
+    pub paused: bool,
+    // shown in the hud, toggled with T
+    pub visible: bool,
+}
+
+#[derive(Component)]
+struct RunTimerHud;
+
+// m:ss.cc
+fn format_time(time: f32) -> String {
+    let minutes = (time / 60.0).floor();
+    format!("{}:{:05.2}", minutes, time - minutes * 60.0)
+}
+
+// completes the level when the player enters its rect, world space in pixels
+#[derive(Component)]
+pub struct Goal {
+    pub rect: SpriteRect,
+}
+
+pub struct GoalReachedEvent;
+
+pub fn spawn_goal(commands: &mut Commands, rect: SpriteRect) -> Entity {
+    commands.spawn().insert(Goal { rect }).id()
+}
+
+fn setup_system(mut commands: Commands) {
+    commands
+        .spawn_bundle(BitmapTextBundle {
+            visibility: Visibility { is_visible: false },
+            ..BitmapTextBundle::new(
+                BitmapText::new("", Color::WHITE).with_anchor(Vec2::new(-0.5, 0.5)),
+                Transform::identity(),
+            )
+        })
+        .insert(ScreenPosition(
+            SCREEN_HALF_SIZE * Vec2::new(-1.0, 1.0) + Vec2::new(2.0, -2.0),
+        ))
+        .insert(RunTimerHud);
+}
+
+fn run_timer_system(
+    time: Res<Time>,
+    mut events: EventReader<LdtkEvent>,
+    mut run_timer: ResMut<RunTimer>,
+) {
+    for event in events.iter() {
+        if let LdtkEvent::LevelActivated(identifier) = event {
+            *run_timer = RunTimer {
+                level: Some(identifier.clone()),
+                visible: run_timer.visible,
+                ..Default::default()
+            };
+        }
+    }
+    if run_timer.level.is_some() && !run_timer.finished && !run_timer.paused {
+        run_timer.elapsed += time.delta_seconds();
+    }
+}
+
+fn goal_system(
+    world_map: Res<WorldMap>,
+    mut run_timer: ResMut<RunTimer>,
+    mut save: ResMut<SaveData>,
+    players: Query<&Transform, With<Player>>,
+    goals: Query<&Goal>,
+    mut event_writer: EventWriter<GoalReachedEvent>,
+) {
+    if run_timer.finished {
+        return;
+    }
+    let position = match players.get_single() {
+        Ok(transform) => transform.translation.truncate(),
+        Err(_) => return,
+    };
+    let reached = goals.iter().any(|goal| {
+        position.x >= goal.rect.min.x
+            && position.x <= goal.rect.max.x
+            && position.y >= goal.rect.min.y
+            && position.y <= goal.rect.max.y
+    });
+    let level = match (reached, world_map.active_level()) {
+        (true, Some(level)) => level,
+        _ => return,
+    };
+    run_timer.finished = true;
+    event_writer.send(GoalReachedEvent);
+
+    let time = run_timer.elapsed;
+    let medal = Medal::award(&level.settings, time);
+    info!(
+        "{} completed in {:.2}s, par {:?}, medal {:?}",
+        level.identifier, time, level.settings.par_time, medal
+    );
+    let best_time = save
+        .best_times
+        .entry(level.identifier.clone())
+        .or_insert(time);
+    *best_time = best_time.min(time);
+    if let Some(medal) = medal {
+        let best = save.medals.entry(level.identifier.clone()).or_insert(medal);
+        *best = (*best).max(medal);
+    }
+    if let Err(err) = save.write() {
+        error!("{:?}", err);
+    }
+}
+
+fn timer_hud_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut run_timer: ResMut<RunTimer>,
+    save: Res<SaveData>,
+    mut query: Query<(&mut BitmapText, &mut Visibility), With<RunTimerHud>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::T) {
+        run_timer.visible = !run_timer.visible;
+    }
+    let best_time = run_timer
+        .level
+        .as_ref()
+        .and_then(|level| save.best_times.get(level));
+    let mut value = format_time(run_timer.elapsed);
+    if let Some(best_time) = best_time {
+        value.push_str(&format!("\nbest {}", format_time(*best_time)));
+    }
+    for (mut text, mut visibility) in query.iter_mut() {
+        if visibility.is_visible != run_timer.visible {
+            visibility.is_visible = run_timer.visible;
+        }
+        if run_timer.visible && text.text != value {
+            text.text = value.clone();
+        }
+    }
+}
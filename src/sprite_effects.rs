@@ -0,0 +1,485 @@
+use crate::{
+    actor::ActorParts,
+    damage::{DamageEvent, DamageSystem, KillEvent},
+    game_time::GameTime,
+    ldtk::background::parse_color,
+};
+use bevy::{
+    ecs::system::{lifetimeless::SRes, SystemParamItem},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        render_asset::{PrepareAssetError, RenderAsset, RenderAssets},
+        render_resource::{
+            std140::{AsStd140, Std140},
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer,
+            BufferBindingType, BufferInitDescriptor, BufferSize, BufferUsages, Extent3d,
+            SamplerBindingType, ShaderStages, TextureDimension, TextureFormat, TextureSampleType,
+            TextureViewDimension,
+        },
+        renderer::RenderDevice,
+        view::ComputedVisibility,
+    },
+    sprite::{Material2d, Material2dPipeline, Material2dPlugin, Mesh2dHandle},
+    utils::HashMap,
+};
+
+const SHADER_PATH: &str = "shaders/sprite_effects.wgsl";
+// seconds for a hit flash to fade from white
+const FLASH_TIME: f32 = 0.15;
+const DISSOLVE_TIME: f32 = 0.6;
+
+// outline, palette swaps, hue shift, white flashes and dissolving for actor sprites without
+// extra frames
+pub struct SpriteEffectsPlugin;
+impl Plugin for SpriteEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<SpriteEffectMaterial>::default())
+            .init_resource::<Palettes>()
+            .add_system(attach_system)
+            .add_system(hit_flash_system)
+            // copies the sprite before the actor is despawned at the end of the frame
+            .add_system(death_dissolve_system.after(DamageSystem))
+            .add_system(dissolve_system)
+            // after animation and sprite color changes made during Update
+            .add_system_to_stage(CoreStage::PostUpdate, sync_system);
+    }
+}
+
+// on a sprite root, its atlas sprite keeps animating but is drawn through a
+// SpriteEffectMaterial quad instead, with the atlas sprite's color as the tint
+#[derive(Component, Clone)]
+pub struct SpriteEffects {
+    pub outline: Option<Color>,
+    // radians around the color wheel
+    pub hue_shift: f32,
+    // 0 is the plain sprite, 1 dissolved completely
+    pub dissolve: f32,
+    // 0 to 1 towards white
+    pub flash: f32,
+    // taken over from the atlas sprite's color, which is left fully transparent
+    pub tint: Color,
+    // from Palettes, none draws the artwork's own colors
+    pub palette: Option<Handle<Image>>,
+}
+impl Default for SpriteEffects {
+    fn default() -> Self {
+        Self {
+            outline: None,
+            hue_shift: 0.0,
+            dissolve: 0.0,
+            flash: 0.0,
+            tint: Color::WHITE,
+            palette: None,
+        }
+    }
+}
+
+// lookup textures for palette swaps, a column per swapped color with the artwork's color on
+// the first row and its replacement on the second
+pub struct Palettes {
+    // matches nothing
+    empty: Handle<Image>,
+    by_enemy: HashMap<String, Option<Handle<Image>>>,
+}
+impl FromWorld for Palettes {
+    fn from_world(world: &mut World) -> Self {
+        let mut images = world.get_resource_mut::<Assets<Image>>().unwrap();
+        Self {
+            empty: images.add(palette_image(&[(Color::NONE, Color::NONE)])),
+            by_enemy: HashMap::default(),
+        }
+    }
+}
+impl Palettes {
+    // built once per enemy from its definition's "#rrggbb" pairs, none without any
+    pub fn get(
+        &mut self,
+        images: &mut Assets<Image>,
+        name: &str,
+        colors: &std::collections::HashMap<String, String>,
+    ) -> Option<Handle<Image>> {
+        self.by_enemy
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                let mut pairs = colors
+                    .iter()
+                    .filter_map(|(from, to)| match (parse_color(from), parse_color(to)) {
+                        (Some(from), Some(to)) => Some((from, to)),
+                        _ => {
+                            warn!("invalid palette color for {}: {} -> {}", name, from, to);
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                if pairs.is_empty() {
+                    return None;
+                }
+                // same texture for the same palette whatever order the asset is read in
+                pairs.sort_by_key(|(from, _)| color_bytes(*from));
+                Some(images.add(palette_image(&pairs)))
+            })
+            .clone()
+    }
+}
+
+fn color_bytes(color: Color) -> [u8; 4] {
+    let [r, g, b, a] = color.as_rgba_f32();
+    [r, g, b, a].map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+fn palette_image(pairs: &[(Color, Color)]) -> Image {
+    let data = pairs
+        .iter()
+        .flat_map(|(from, _)| color_bytes(*from))
+        .chain(pairs.iter().flat_map(|(_, to)| color_bytes(*to)))
+        .collect();
+    Image::new(
+        Extent3d {
+            width: pairs.len() as u32,
+            height: 2,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+// the last frame of a killed actor, dissolving before it's despawned
+#[derive(Component)]
+struct Dissolving(Timer);
+
+#[derive(Debug, Clone, PartialEq, TypeUuid)]
+#[uuid = "5c3b7e62-3f0e-4d8a-9a41-7b8f0c6d2e19"]
+pub struct SpriteEffectMaterial {
+    pub texture: Handle<Image>,
+    pub palette: Handle<Image>,
+    // min and max of the frame in uv, swapped on an axis to flip it
+    pub uv_rect: Vec4,
+    pub tint: Color,
+    pub outline_color: Color,
+    pub texel_size: Vec2,
+    pub outline_width: f32,
+    pub hue_shift: f32,
+    pub dissolve: f32,
+    pub flash: f32,
+}
+
+#[derive(Clone, AsStd140)]
+struct SpriteEffectUniform {
+    uv_rect: Vec4,
+    tint: Vec4,
+    outline_color: Vec4,
+    texel_size: Vec2,
+    outline_width: f32,
+    hue_shift: f32,
+    dissolve: f32,
+    flash: f32,
+}
+
+pub struct GpuSpriteEffectMaterial {
+    _buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+impl RenderAsset for SpriteEffectMaterial {
+    type ExtractedAsset = SpriteEffectMaterial;
+    type PreparedAsset = GpuSpriteEffectMaterial;
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<Material2dPipeline<SpriteEffectMaterial>>,
+        SRes<RenderAssets<Image>>,
+    );
+
+    fn extract_asset(&self) -> Self::ExtractedAsset {
+        self.clone()
+    }
+
+    fn prepare_asset(
+        material: Self::ExtractedAsset,
+        (render_device, pipeline, gpu_images): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>> {
+        let (gpu_image, gpu_palette) = match (
+            gpu_images.get(&material.texture),
+            gpu_images.get(&material.palette),
+        ) {
+            (Some(gpu_image), Some(gpu_palette)) => (gpu_image, gpu_palette),
+            _ => return Err(PrepareAssetError::RetryNextUpdate(material)),
+        };
+        let uniform = SpriteEffectUniform {
+            uv_rect: material.uv_rect,
+            tint: material.tint.as_linear_rgba_f32().into(),
+            outline_color: material.outline_color.as_linear_rgba_f32().into(),
+            texel_size: material.texel_size,
+            outline_width: material.outline_width,
+            hue_shift: material.hue_shift,
+            dissolve: material.dissolve,
+            flash: material.flash,
+        };
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("sprite_effect_material_uniform_buffer"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: uniform.as_std140().as_bytes(),
+        });
+        let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&gpu_image.texture_view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(&gpu_image.sampler),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(&gpu_palette.texture_view),
+                },
+            ],
+            label: Some("sprite_effect_material_bind_group"),
+            layout: &pipeline.material2d_layout,
+        });
+        Ok(GpuSpriteEffectMaterial {
+            _buffer: buffer,
+            bind_group,
+        })
+    }
+}
+
+impl Material2d for SpriteEffectMaterial {
+    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+        Some(asset_server.load(SHADER_PATH))
+    }
+
+    fn bind_group(render_asset: &<Self as RenderAsset>::PreparedAsset) -> &BindGroup {
+        &render_asset.bind_group
+    }
+
+    fn bind_group_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: BufferSize::new(
+                            SpriteEffectUniform::std140_size_static() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // only loaded from, never sampled
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("sprite_effect_material_layout"),
+        })
+    }
+}
+
+fn attach_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<SpriteEffectMaterial>>,
+    sprites: Query<Entity, Added<SpriteEffects>>,
+) {
+    for entity in sprites.iter() {
+        // the quad and texture are filled in by sync_system once the atlas is there
+        let material = materials.add(SpriteEffectMaterial {
+            texture: Handle::default(),
+            palette: Handle::default(),
+            uv_rect: Vec4::new(0.0, 0.0, 1.0, 1.0),
+            tint: Color::WHITE,
+            outline_color: Color::NONE,
+            texel_size: Vec2::ONE,
+            outline_width: 0.0,
+            hue_shift: 0.0,
+            dissolve: 0.0,
+            flash: 0.0,
+        });
+        commands
+            .entity(entity)
+            .insert(Mesh2dHandle::default())
+            .insert(material)
+            .insert(ComputedVisibility::default());
+    }
+}
+
+fn sync_system(
+    mut quads: Local<HashMap<(u32, u32), Handle<Mesh>>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<SpriteEffectMaterial>>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    palettes: Res<Palettes>,
+    mut sprites: Query<(
+        &mut SpriteEffects,
+        &mut TextureAtlasSprite,
+        &Handle<TextureAtlas>,
+        &Handle<SpriteEffectMaterial>,
+        &mut Mesh2dHandle,
+    )>,
+) {
+    for (mut effects, mut sprite, texture_atlas, material, mut mesh) in sprites.iter_mut() {
+        // whoever recolored the sprite meant the material
+        if sprite.color != Color::NONE {
+            effects.tint = sprite.color;
+            sprite.color = Color::NONE;
+        }
+        let (texture_atlas, rect) = match texture_atlases
+            .get(texture_atlas)
+            .and_then(|atlas| atlas.textures.get(sprite.index).map(|rect| (atlas, *rect)))
+        {
+            Some(frame) => frame,
+            None => continue,
+        };
+
+        let size = rect.max - rect.min;
+        let key = (size.x as u32, size.y as u32);
+        let quad = quads
+            .entry(key)
+            .or_insert_with(|| meshes.add(Mesh::from(shape::Quad::new(size))))
+            .clone();
+        if mesh.0 != quad {
+            mesh.0 = quad;
+        }
+
+        let mut min = rect.min / texture_atlas.size;
+        let mut max = rect.max / texture_atlas.size;
+        if sprite.flip_x {
+            std::mem::swap(&mut min.x, &mut max.x);
+        }
+        if sprite.flip_y {
+            std::mem::swap(&mut min.y, &mut max.y);
+        }
+        let updated = SpriteEffectMaterial {
+            texture: texture_atlas.texture.clone(),
+            palette: effects
+                .palette
+                .clone()
+                .unwrap_or_else(|| palettes.empty.clone()),
+            uv_rect: Vec4::new(min.x, min.y, max.x, max.y),
+            tint: effects.tint,
+            outline_color: effects.outline.unwrap_or(Color::NONE),
+            texel_size: Vec2::ONE / texture_atlas.size,
+            outline_width: effects.outline.map_or(0.0, |_| 1.0),
+            hue_shift: effects.hue_shift,
+            dissolve: effects.dissolve,
+            flash: effects.flash,
+        };
+        // every change prepares the material again
+        if materials.get(material) != Some(&updated) {
+            if let Some(material) = materials.get_mut(material) {
+                *material = updated;
+            }
+        }
+    }
+}
+
+fn hit_flash_system(
+    time: Res<GameTime>,
+    mut events: EventReader<DamageEvent>,
+    actor_parts: ActorParts,
+    mut sprites: Query<&mut SpriteEffects>,
+) {
+    for event in events.iter() {
+        if let Some(mut effects) = actor_parts
+            .sprite(event.target)
+            .and_then(|sprite| sprites.get_mut(sprite).ok())
+        {
+            effects.flash = 1.0;
+        }
+    }
+    for mut effects in sprites.iter_mut() {
+        if effects.flash > 0.0 {
+            effects.flash = (effects.flash - time.delta_seconds() / FLASH_TIME).max(0.0);
+        }
+    }
+}
+
+// the actor itself goes away right away, a copy of its last frame dissolves in its place
+fn death_dissolve_system(
+    mut commands: Commands,
+    mut events: EventReader<KillEvent>,
+    actor_parts: ActorParts,
+    mut materials: ResMut<Assets<SpriteEffectMaterial>>,
+    sprites: Query<(
+        &Handle<SpriteEffectMaterial>,
+        &Mesh2dHandle,
+        &GlobalTransform,
+    )>,
+) {
+    for event in events.iter() {
+        let (material, mesh, global_transform) = match actor_parts
+            .sprite(event.target)
+            .and_then(|sprite| sprites.get(sprite).ok())
+        {
+            Some(sprite) => sprite,
+            None => continue,
+        };
+        let material = match materials.get(material) {
+            Some(material) => SpriteEffectMaterial {
+                flash: 0.0,
+                ..material.clone()
+            },
+            None => continue,
+        };
+        commands
+            .spawn_bundle((
+                Transform::from(*global_transform),
+                GlobalTransform::identity(),
+                Visibility::default(),
+                ComputedVisibility::default(),
+                mesh.clone(),
+                materials.add(material),
+            ))
+            .insert(Dissolving(Timer::from_seconds(DISSOLVE_TIME, false)));
+    }
+}
+
+fn dissolve_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut materials: ResMut<Assets<SpriteEffectMaterial>>,
+    mut corpses: Query<(Entity, &mut Dissolving, &Handle<SpriteEffectMaterial>)>,
+) {
+    for (entity, mut dissolving, material) in corpses.iter_mut() {
+        dissolving.0.tick(time.delta());
+        if let Some(material) = materials.get_mut(material) {
+            material.dissolve = dissolving.0.percent();
+        }
+        if dissolving.0.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{
+    bitmap_font::SCREEN_HALF_SIZE, game_time::GameTime, ldtk::plugin::LdtkEvent, VirtualPosition,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+// in front of the world and the hud
+const Z_TRANSITION: f32 = 200.0;
+const CIRCLE_SEGMENTS: usize = 32;
+// pixels, dissolve cells are squares of this size
+const DISSOLVE_CELL: f32 = 10.0;
+const LEVEL_REVEAL: f32 = 0.4;
+
+// screen transitions that cover the view and uncover it again, for level changes, portals,
+// deaths, cutscenes...
+pub struct TransitionsPlugin;
+impl Plugin for TransitionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Transition>()
+            .add_event::<TransitionEvent>()
+            .add_event::<TransitionCoveredEvent>()
+            .add_system(setup_system)
+            .add_system(level_transition_system.before(transition_system))
+            .add_system(transition_system)
+            .add_system(fade_system.after(transition_system))
+            .add_system(wipe_system.after(transition_system))
+            .add_system(dissolve_system.after(transition_system));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionKind {
+    Fade,
+    // a shrinking circle around the screen center
+    CircleWipe,
+    Dissolve,
+}
+
+// starts a transition, replacing the current one without jumping back to a clear screen
+#[derive(Debug, Clone, Copy)]
+pub struct TransitionEvent {
+    pub kind: TransitionKind,
+    // seconds to cover the screen, zero starts covered
+    pub cover: f32,
+    // seconds it stays covered
+    pub hold: f32,
+    // seconds to uncover it again
+    pub reveal: f32,
+}
+impl TransitionEvent {
+    pub fn new(kind: TransitionKind, seconds: f32) -> Self {
+        Self {
+            kind,
+            cover: seconds,
+            hold: 0.0,
+            reveal: seconds,
+        }
+    }
+
+    // uncovers a screen that is instantly covered, for hiding what changed this frame
+    pub fn reveal(kind: TransitionKind, seconds: f32) -> Self {
+        Self {
+            kind,
+            cover: 0.0,
+            hold: 0.0,
+            reveal: seconds,
+        }
+    }
+
+    fn coverage(&self, elapsed: f32) -> Option<f32> {
+        if elapsed < self.cover {
+            Some(elapsed / self.cover)
+        } else if elapsed <= self.cover + self.hold {
+            Some(1.0)
+        } else if elapsed < self.cover + self.hold + self.reveal {
+            Some(1.0 - (elapsed - self.cover - self.hold) / self.reveal)
+        } else {
+            None
+        }
+    }
+}
+
+// the screen just got fully covered, whatever should happen out of sight can happen now
+pub struct TransitionCoveredEvent(pub TransitionKind);
+
+#[derive(Default)]
+pub struct Transition {
+    current: Option<(TransitionEvent, f32)>,
+    covered: bool,
+}
+impl Transition {
+    // 0 is a clear screen and 1 is fully covered
+    pub fn coverage(&self) -> f32 {
+        self.current
+            .and_then(|(event, elapsed)| event.coverage(elapsed))
+            .unwrap_or(0.0)
+    }
+
+    pub fn kind(&self) -> Option<TransitionKind> {
+        self.current.map(|(event, _)| event.kind)
+    }
+}
+
+#[derive(Component)]
+struct FadeQuad;
+
+#[derive(Component)]
+struct WipeMask;
+
+#[derive(Component)]
+struct DissolveCell {
+    // coverage at which the cell shows
+    threshold: f32,
+}
+
+fn setup_system(
+    mut commands: Commands,
+    cameras: Query<(Entity, &Transform), (Added<Camera>, With<VirtualPosition>)>,
+) {
+    // covers the view through the camera's zoom and rotation
+    let half_size = SCREEN_HALF_SIZE.length();
+    for (camera, transform) in cameras.iter() {
+        let z = Z_TRANSITION - transform.translation.z;
+        let mut children = vec![
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::NONE,
+                        custom_size: Some(Vec2::splat(half_size * 2.0)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_xyz(0.0, 0.0, z),
+                    ..Default::default()
+                })
+                .insert(FadeQuad)
+                .id(),
+            commands
+                .spawn_bundle(GeometryBuilder::build_as(
+                    &shapes::Rectangle::default(),
+                    DrawMode::Fill(FillMode {
+                        options: FillOptions::even_odd(),
+                        color: Color::BLACK,
+                    }),
+                    Transform::from_xyz(0.0, 0.0, z),
+                ))
+                .insert(WipeMask)
+                .insert(Visibility { is_visible: false })
+                .id(),
+        ];
+
+        let cells = (half_size * 2.0 / DISSOLVE_CELL).ceil() as usize;
+        for y in 0..cells {
+            for x in 0..cells {
+                let position = (Vec2::new(x as f32, y as f32) + 0.5) * DISSOLVE_CELL - half_size;
+                children.push(
+                    commands
+                        .spawn_bundle(SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::BLACK,
+                                custom_size: Some(Vec2::splat(DISSOLVE_CELL)),
+                                ..Default::default()
+                            },
+                            transform: Transform::from_translation(position.extend(z)),
+                            visibility: Visibility { is_visible: false },
+                            ..Default::default()
+                        })
+                        .insert(DissolveCell {
+                            threshold: cell_threshold(x, y),
+                        })
+                        .id(),
+                );
+            }
+        }
+        commands.entity(camera).push_children(&children);
+    }
+}
+
+// scattered but the same every time, in 0..1
+fn cell_threshold(x: usize, y: usize) -> f32 {
+    let hash = (x as u32)
+        .wrapping_mul(0x9e37_79b9)
+        .wrapping_add((y as u32).wrapping_mul(0x85eb_ca6b));
+    let hash = (hash ^ (hash >> 15)).wrapping_mul(0x2c1b_3c6d);
+    let hash = hash ^ (hash >> 12);
+    (hash & 0xffff) as f32 / 0x1_0000 as f32
+}
+
+fn level_transition_system(
+    mut events: EventReader<LdtkEvent>,
+    mut event_writer: EventWriter<TransitionEvent>,
+) {
+    for event in events.iter() {
+        if let LdtkEvent::LevelActivated(_) = event {
+            event_writer.send(TransitionEvent::reveal(
+                TransitionKind::Dissolve,
+                LEVEL_REVEAL,
+            ));
+        }
+    }
+}
+
+fn transition_system(
+    time: Res<GameTime>,
+    mut events: EventReader<TransitionEvent>,
+    mut transition: ResMut<Transition>,
+    mut event_writer: EventWriter<TransitionCoveredEvent>,
+) {
+    for event in events.iter() {
+        // pick up from the current coverage
+        let elapsed = transition.coverage().min(1.0) * event.cover;
+        transition.current = Some((*event, elapsed));
+        transition.covered = false;
+    }
+
+    let (event, elapsed) = match transition.current {
+        Some(current) => current,
+        None => return,
+    };
+    if !transition.covered && elapsed >= event.cover {
+        transition.covered = true;
+        event_writer.send(TransitionCoveredEvent(event.kind));
+    }
+    // kept for a frame past the end with no coverage, so the renderers clear the screen
+    transition.current = event
+        .coverage(elapsed)
+        .map(|_| (event, elapsed + time.delta_seconds()));
+}
+
+fn coverage_of(transition: &Transition, kind: TransitionKind) -> f32 {
+    if transition.kind() == Some(kind) {
+        transition.coverage()
+    } else {
+        0.0
+    }
+}
+
+fn fade_system(transition: Res<Transition>, mut quads: Query<&mut Sprite, With<FadeQuad>>) {
+    if !transition.is_changed() {
+        return;
+    }
+    let alpha = coverage_of(&transition, TransitionKind::Fade);
+    for mut sprite in quads.iter_mut() {
+        sprite.color = Color::rgba(0.0, 0.0, 0.0, alpha);
+    }
+}
+
+fn wipe_system(
+    transition: Res<Transition>,
+    mut masks: Query<(&mut Path, &mut Visibility), With<WipeMask>>,
+) {
+    if !transition.is_changed() {
+        return;
+    }
+    let coverage = coverage_of(&transition, TransitionKind::CircleWipe);
+    let half_size = SCREEN_HALF_SIZE.length();
+    let radius = half_size * (1.0 - coverage);
+    for (mut path, mut visibility) in masks.iter_mut() {
+        visibility.is_visible = coverage > 0.0;
+        if !visibility.is_visible {
+            continue;
+        }
+        let mut path_builder = PathBuilder::new();
+        path_builder.move_to(Vec2::new(-half_size, -half_size));
+        path_builder.line_to(Vec2::new(half_size, -half_size));
+        path_builder.line_to(Vec2::new(half_size, half_size));
+        path_builder.line_to(Vec2::new(-half_size, half_size));
+        path_builder.close();
+        if radius > 0.0 {
+            for index in 0..CIRCLE_SEGMENTS {
+                let angle = index as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                let point = Vec2::new(angle.cos(), angle.sin()) * radius;
+                if index == 0 {
+                    path_builder.move_to(point);
+                } else {
+                    path_builder.line_to(point);
+                }
+            }
+            path_builder.close();
+        }
+        *path = path_builder.build();
+    }
+}
+
+fn dissolve_system(
+    transition: Res<Transition>,
+    mut cells: Query<(&DissolveCell, &mut Visibility)>,
+) {
+    if !transition.is_changed() {
+        return;
+    }
+    let coverage = coverage_of(&transition, TransitionKind::Dissolve);
+    for (cell, mut visibility) in cells.iter_mut() {
+        let visible = coverage > 0.0 && cell.threshold < coverage;
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+    }
+}
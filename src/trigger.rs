@@ -0,0 +1,83 @@
+use crate::collision::{CollisionLayers, CollisionLookup};
+use bevy::{prelude::*, sprite::Rect as SpriteRect};
+use bevy_rapier2d::prelude::*;
+
+pub struct TriggerPlugin;
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TriggerEvent>().add_system(trigger_system);
+    }
+}
+
+// sensor volume placed in ldtk, for cutscenes, music changes, tutorials...
+#[derive(Component)]
+pub struct Trigger {
+    // the "id" field, or the iid when there is none
+    pub id: String,
+}
+
+// an actor body entered or left a trigger
+#[derive(Debug, Clone)]
+pub enum TriggerEvent {
+    Enter { trigger_id: String, other: Entity },
+    Exit { trigger_id: String, other: Entity },
+}
+
+// sensor over a world space rect in pixels
+pub fn spawn_trigger(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    rect: &SpriteRect,
+    id: String,
+) -> Entity {
+    let half_extents = (rect.max - rect.min) * 0.5;
+    let center = rect.min + half_extents;
+    commands
+        .spawn_bundle(ColliderBundle {
+            collider_type: ColliderType::Sensor.into(),
+            shape: ColliderShape::cuboid(
+                half_extents.x / rapier_config.scale,
+                half_extents.y / rapier_config.scale,
+            )
+            .into(),
+            position: (center / rapier_config.scale).into(),
+            flags: ColliderFlags {
+                collision_groups: CollisionLayers::sensor(),
+                active_events: ActiveEvents::INTERSECTION_EVENTS,
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert(Trigger { id })
+        .id()
+}
+
+fn trigger_system(
+    mut intersection_events: EventReader<IntersectionEvent>,
+    triggers: Query<&Trigger>,
+    collision_lookup: CollisionLookup,
+    mut event_writer: EventWriter<TriggerEvent>,
+) {
+    for event in intersection_events.iter() {
+        let (trigger, other) = match (
+            triggers.get(event.collider1.entity()),
+            triggers.get(event.collider2.entity()),
+        ) {
+            (Ok(trigger), _) => (trigger, event.collider2),
+            (_, Ok(trigger)) => (trigger, event.collider1),
+            _ => continue,
+        };
+        // actors only, through their body collider
+        let other = match collision_lookup.actor(other) {
+            Some(actor) => actor,
+            None => continue,
+        };
+        let trigger_id = trigger.id.clone();
+        event_writer.send(if event.intersecting {
+            TriggerEvent::Enter { trigger_id, other }
+        } else {
+            TriggerEvent::Exit { trigger_id, other }
+        });
+    }
+}
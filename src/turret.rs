@@ -0,0 +1,376 @@
+use crate::{
+    actor::{mirror_sprite, ActorParts},
+    actor_bundle,
+    animation::{AnimationFrameEvent, AnimationSprite, Aseprite},
+    breakable::BreakTilesEvent,
+    collision::{CollisionLayers, CollisionLookup},
+    damage::{DamageEvent, DamageType, EnemyDefinitions, Health, Resistances},
+    despawn::MarkedForDespawn,
+    game_time::GameTime,
+    ldtk::{plugin::LdtkEvent, registry::LdtkEntityRegistry},
+    vision::Vision,
+    world_flags::WorldFlags,
+    Actor, Direction, Enemy, Player, RAPIER_SCALE, Z_ACTORS,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// seconds between the start of wind-ups
+const FIRE_INTERVAL: f32 = 2.0;
+// pixels per second
+const PROJECTILE_SPEED: f32 = 96.0;
+const PROJECTILE_RADIUS: f32 = 2.0;
+const PROJECTILE_LIFETIME: f32 = 3.0;
+const PROJECTILE_DAMAGE: f32 = 1.0;
+const KNOCKBACK: f32 = 4.0;
+// where shots leave the sprite, facing right
+const MUZZLE_OFFSET: Vec2 = Vec2::new(8.0, 4.0);
+
+// stationary enemies, ldtk "Enemy" entities named "turret", that wind up and shoot at the
+// player while they can see it
+pub struct TurretPlugin;
+impl Plugin for TurretPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_system)
+            .add_system(aim_system)
+            .add_system(shoot_system)
+            .add_system(projectile_system);
+    }
+}
+
+#[derive(Component)]
+struct Turret {
+    interval: Timer,
+}
+
+#[derive(Component)]
+struct TurretProjectile {
+    lifetime: Timer,
+}
+
+fn spawn_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut events: EventReader<LdtkEvent>,
+    mut registry: ResMut<LdtkEntityRegistry>,
+    enemy_definitions: Res<EnemyDefinitions>,
+    flags: Res<WorldFlags>,
+) {
+    for event in events.iter() {
+        let (name, position, iid) = match event {
+            LdtkEvent::SpawnEnemy {
+                name,
+                position,
+                iid,
+            } if name == "turret" => (name, position, iid),
+            _ => continue,
+        };
+        // defeated for good in an earlier session
+        if iid
+            .as_ref()
+            .map_or(false, |iid| flags.bool(iid, "defeated") == Some(true))
+        {
+            continue;
+        }
+        let aseprite: Handle<Aseprite> = asset_server.load("images/character.json");
+        let definition = enemy_definitions.get(name);
+        let turret = actor_bundle(
+            position.truncate(),
+            // the standing capsule of other enemies, a turret never changes shape
+            ColliderShape::capsule(
+                (Vec2::new(0.0, 6.0) / RAPIER_SCALE).into(),
+                (Vec2::new(0.0, -6.0) / RAPIER_SCALE).into(),
+                4.0 / RAPIER_SCALE,
+            ),
+            CollisionLayers::ENEMY,
+        )
+        .spawn(&mut commands, AnimationSprite::new(aseprite), name)
+        // mounted where it was placed
+        .insert(RigidBodyTypeComponent(RigidBodyType::Static))
+        .insert(Enemy { name: name.clone() })
+        .insert(Health::new(definition.health))
+        .insert(Resistances(definition.resistances))
+        .insert(Turret {
+            interval: Timer::from_seconds(FIRE_INTERVAL, false),
+        })
+        .id();
+        if let Some(vision) = definition.vision {
+            commands.entity(turret).insert(Vision::from(vision));
+        }
+        if let Some(iid) = iid {
+            registry.bind(iid, turret);
+        }
+    }
+}
+
+// turns to the player and starts a wind-up when it's ready, the shot comes from its frame
+// event
+fn aim_system(
+    time: Res<GameTime>,
+    players: Query<&GlobalTransform, With<Player>>,
+    mut turrets: Query<(Entity, &mut Turret, &mut Actor, &Vision, &GlobalTransform)>,
+    actor_parts: ActorParts,
+    mut sprites: Query<(
+        &mut Transform,
+        &mut AnimationSprite,
+        &mut TextureAtlasSprite,
+    )>,
+) {
+    let player_position = match players.get_single() {
+        Ok(global_transform) => global_transform.translation.truncate(),
+        Err(_) => return,
+    };
+    for (entity, mut turret, mut actor, vision, global_transform) in turrets.iter_mut() {
+        turret.interval.tick(time.delta());
+        if !vision.sees_player {
+            continue;
+        }
+        actor.direction = if player_position.x < global_transform.translation.x {
+            Direction::Left
+        } else {
+            Direction::Right
+        };
+        let (mut transform, mut animation_sprite, mut texture_atlas_sprite) = match actor_parts
+            .sprite(entity)
+            .and_then(|sprite| sprites.get_mut(sprite).ok())
+        {
+            Some(sprite) => sprite,
+            None => continue,
+        };
+        mirror_sprite(&mut transform, &mut texture_atlas_sprite, actor.direction);
+        if turret.interval.finished() {
+            turret.interval.reset();
+            animation_sprite.set_animation("windup");
+        }
+    }
+}
+
+fn shoot_system(
+    mut commands: Commands,
+    mut events: EventReader<AnimationFrameEvent>,
+    rapier_config: Res<RapierConfiguration>,
+    parents: Query<&Parent>,
+    players: Query<&GlobalTransform, With<Player>>,
+    turrets: Query<(&Actor, &GlobalTransform), With<Turret>>,
+) {
+    let player_position = match players.get_single() {
+        Ok(global_transform) => global_transform.translation.truncate(),
+        Err(_) => return,
+    };
+    for event in events.iter().filter(|event| event.event_name == "shoot") {
+        // sent by the sprite, below the actor root
+        let (actor, global_transform) = match parents
+            .get(event.entity)
+            .ok()
+            .and_then(|parent| turrets.get(parent.0).ok())
+        {
+            Some(turret) => turret,
+            None => continue,
+        };
+        let flip_x = if actor.direction == Direction::Left {
+            -1.0
+        } else {
+            1.0
+        };
+        let position =
+            global_transform.translation.truncate() + MUZZLE_OFFSET * Vec2::new(flip_x, 1.0);
+        let direction = (player_position - position).normalize_or_zero();
+        spawn_projectile(
+            &mut commands,
+            &rapier_config,
+            position,
+            direction * PROJECTILE_SPEED,
+        );
+    }
+}
+
+fn spawn_projectile(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    position: Vec2,
+    velocity: Vec2,
+) {
+    commands
+        .spawn_bundle(RigidBodyBundle {
+            body_type: RigidBodyType::KinematicVelocityBased.into(),
+            position: (position / rapier_config.scale).into(),
+            velocity: RigidBodyVelocity {
+                linvel: (velocity / rapier_config.scale).into(),
+                angvel: 0.0,
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert_bundle(ColliderBundle {
+            collider_type: ColliderType::Sensor.into(),
+            shape: ColliderShape::ball(PROJECTILE_RADIUS / rapier_config.scale).into(),
+            flags: ColliderFlags {
+                collision_groups: CollisionLayers::hostile_projectile(),
+                active_collision_types: ActiveCollisionTypes::default()
+                    | ActiveCollisionTypes::KINEMATIC_STATIC,
+                active_events: ActiveEvents::INTERSECTION_EVENTS,
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert_bundle(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius: PROJECTILE_RADIUS,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Fill(FillMode::color(Color::ORANGE_RED)),
+            Transform::from_translation(position.extend(Z_ACTORS)),
+        ))
+        .insert(ColliderPositionSync::Discrete)
+        .insert(TurretProjectile {
+            lifetime: Timer::from_seconds(PROJECTILE_LIFETIME, false),
+        });
+}
+
+// shots end on the first thing they touch, the player takes damage and gets pushed away and
+// breakable tiles break
+fn projectile_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    rapier_config: Res<RapierConfiguration>,
+    mut intersection_events: EventReader<IntersectionEvent>,
+    collision_lookup: CollisionLookup,
+    mut projectiles: Query<
+        (
+            Entity,
+            &mut TurretProjectile,
+            &RigidBodyVelocityComponent,
+            &GlobalTransform,
+        ),
+        Without<MarkedForDespawn>,
+    >,
+    mut players: Query<
+        (
+            &mut RigidBodyVelocityComponent,
+            &RigidBodyMassPropsComponent,
+        ),
+        (With<Player>, Without<TurretProjectile>),
+    >,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut break_events: EventWriter<BreakTilesEvent>,
+) {
+    for event in intersection_events
+        .iter()
+        .filter(|event| event.intersecting)
+    {
+        let (projectile, other) = if projectiles.get(event.collider1.entity()).is_ok() {
+            (event.collider1.entity(), event.collider2)
+        } else if projectiles.get(event.collider2.entity()).is_ok() {
+            (event.collider2.entity(), event.collider1)
+        } else {
+            continue;
+        };
+        let (direction, position) = match projectiles.get(projectile) {
+            Ok((_, _, rb_velocity, global_transform)) => (
+                Vec2::new(rb_velocity.linvel.x, rb_velocity.linvel.y).normalize_or_zero(),
+                global_transform.translation.truncate(),
+            ),
+            Err(_) => continue,
+        };
+        if let Some(player) = collision_lookup.player(other) {
+            damage_events.send(DamageEvent {
+                target: player,
+                amount: PROJECTILE_DAMAGE,
+                damage_type: DamageType::Physical,
+            });
+            if let Ok((mut rb_velocity, rb_mass_props)) = players.get_mut(player) {
+                let force = direction * KNOCKBACK / rapier_config.scale;
+                rb_velocity.apply_impulse(rb_mass_props, force.into());
+            }
+        } else {
+            // just inside what it touched
+            break_events.send(BreakTilesEvent(
+                position + direction * (PROJECTILE_RADIUS + 1.0),
+            ));
+        }
+        commands.entity(projectile).insert(MarkedForDespawn);
+    }
+    for (entity, mut projectile, _, _) in projectiles.iter_mut() {
+        if projectile.lifetime.tick(time.delta()).just_finished() {
+            commands.entity(entity).insert(MarkedForDespawn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audio::StingerEvent, damage::DamagePlugin, respawn::PlayerDeathEvent, DASH_DURATION,
+    };
+    use bevy::app::Events;
+
+    // the app, player and projectile two frames after a shot hit the player
+    fn shoot(player: Player) -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.init_resource::<GameTime>()
+            .insert_resource(RapierConfiguration::default())
+            .add_event::<IntersectionEvent>()
+            .add_event::<PlayerDeathEvent>()
+            .add_event::<BreakTilesEvent>()
+            .add_event::<StingerEvent>()
+            .add_plugin(DamagePlugin)
+            .add_system(projectile_system);
+
+        let player = app
+            .world
+            .spawn()
+            .insert(player)
+            .insert(Actor::new())
+            .insert(Health::new(3.0))
+            .insert(GlobalTransform::default())
+            .insert(RigidBodyVelocityComponent::default())
+            .insert(RigidBodyMassPropsComponent::default())
+            .id();
+        let projectile = app
+            .world
+            .spawn()
+            .insert(TurretProjectile {
+                lifetime: Timer::from_seconds(PROJECTILE_LIFETIME, false),
+            })
+            .insert(RigidBodyVelocityComponent::from(RigidBodyVelocity {
+                linvel: Vec2::X.into(),
+                angvel: 0.0,
+            }))
+            .insert(GlobalTransform::default())
+            .id();
+        app.world
+            .get_resource_mut::<Events<IntersectionEvent>>()
+            .unwrap()
+            .send(IntersectionEvent {
+                collider1: projectile.handle(),
+                collider2: player.handle(),
+                intersecting: true,
+            });
+        // the damage event is read on the next frame at the latest
+        app.update();
+        app.update();
+        (app, player, projectile)
+    }
+
+    #[test]
+    fn shots_hurt_the_player() {
+        let (app, player, projectile) = shoot(Player::default());
+        let health = app.world.get::<Health>(player).unwrap();
+        assert_eq!(health.current, 3.0 - PROJECTILE_DAMAGE);
+        assert!(app.world.get::<MarkedForDespawn>(projectile).is_some());
+    }
+
+    #[test]
+    fn dashing_through_shots() {
+        let (app, player, projectile) = shoot(Player {
+            dash: Timer::from_seconds(DASH_DURATION, false),
+            ..Default::default()
+        });
+        let health = app.world.get::<Health>(player).unwrap();
+        assert_eq!(health.current, 3.0);
+        assert!(app.world.get::<MarkedForDespawn>(projectile).is_some());
+    }
+}
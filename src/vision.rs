@@ -0,0 +1,171 @@
+use crate::{
+    actor::ActorParts,
+    collision::CollisionLayers,
+    debug::{DebugGroup, DebugTarget},
+    ldtk::world::WorldMap,
+    Actor, Direction, Player, Z_COLLISION,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+
+// eyes above the body center, in pixels
+const EYE_HEIGHT: f32 = 6.0;
+const CONE_SEGMENTS: usize = 12;
+
+// enemies look for the player in a cone in front of them, walls block the view
+pub struct VisionPlugin;
+impl Plugin for VisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(cone_setup_system)
+            .add_system(vision_system)
+            .add_system(cone_system);
+    }
+}
+
+// from enemies.json, the half angle in degrees
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct VisionDefinition {
+    pub range: f32,
+    pub half_angle: f32,
+}
+
+#[derive(Component)]
+pub struct Vision {
+    // pixels
+    pub range: f32,
+    // radians either side of the facing direction
+    pub half_angle: f32,
+    pub sees_player: bool,
+}
+impl From<VisionDefinition> for Vision {
+    fn from(definition: VisionDefinition) -> Self {
+        Self {
+            range: definition.range,
+            half_angle: definition.half_angle.to_radians(),
+            sees_player: false,
+        }
+    }
+}
+
+// debug drawing of a vision cone, pointing along +x
+#[derive(Component)]
+struct VisionCone {
+    owner: Entity,
+    sees_player: bool,
+}
+
+fn cone_color(sees_player: bool) -> Color {
+    if sees_player {
+        Color::rgba(1.0, 0.2, 0.2, 0.25)
+    } else {
+        Color::rgba(1.0, 1.0, 0.2, 0.15)
+    }
+}
+
+fn cone_setup_system(
+    mut commands: Commands,
+    visions: Query<(Entity, &Vision), Added<Vision>>,
+    actor_parts: ActorParts,
+) {
+    for (entity, vision) in visions.iter() {
+        let mut points = vec![Vec2::ZERO];
+        points.extend((0..=CONE_SEGMENTS).map(|index| {
+            let t = index as f32 / CONE_SEGMENTS as f32;
+            let angle = -vision.half_angle + t * vision.half_angle * 2.0;
+            Vec2::new(angle.cos(), angle.sin()) * vision.range
+        }));
+        let cone = commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &shapes::Polygon {
+                    points,
+                    closed: true,
+                },
+                DrawMode::Fill(FillMode::color(cone_color(false))),
+                Transform::from_xyz(0.0, EYE_HEIGHT, Z_COLLISION),
+            ))
+            .insert(VisionCone {
+                owner: entity,
+                sees_player: false,
+            })
+            .insert(DebugTarget(DebugGroup::AiVision))
+            .insert(Visibility { is_visible: false })
+            .id();
+        let parent = actor_parts.debug(entity).unwrap_or(entity);
+        commands.entity(parent).push_children(&[cone]);
+    }
+}
+
+fn vision_system(
+    world_map: Res<WorldMap>,
+    query_pipeline: Res<QueryPipeline>,
+    collider_query: QueryPipelineColliderComponentsQuery,
+    rapier_config: Res<RapierConfiguration>,
+    players: Query<&GlobalTransform, With<Player>>,
+    mut visions: Query<(&mut Vision, &Actor, &GlobalTransform)>,
+) {
+    let player_position = players
+        .get_single()
+        .ok()
+        .map(|global_transform| global_transform.translation.truncate());
+    let gravity = world_map.gravity();
+    let collider_set = QueryPipelineColliderComponentsSet(&collider_query);
+
+    for (mut vision, actor, global_transform) in visions.iter_mut() {
+        let eye = global_transform.translation.truncate() - gravity.down() * EYE_HEIGHT;
+        let facing = match actor.direction {
+            Direction::Left => -gravity.right(),
+            Direction::Right => gravity.right(),
+        };
+        let sees_player = player_position.map_or(false, |player_position| {
+            let to_player = player_position - eye;
+            let distance = to_player.length();
+            if distance > vision.range || facing.angle_between(to_player).abs() > vision.half_angle
+            {
+                return false;
+            }
+            // only level geometry blocks the view
+            let ray = Ray::new(
+                (eye / rapier_config.scale).into(),
+                (to_player / distance.max(f32::EPSILON)).into(),
+            );
+            query_pipeline
+                .cast_ray(
+                    &collider_set,
+                    &ray,
+                    distance / rapier_config.scale,
+                    true,
+                    CollisionLayers::sight_query(),
+                    None,
+                )
+                .is_none()
+        });
+        if vision.sees_player != sees_player {
+            vision.sees_player = sees_player;
+        }
+    }
+}
+
+fn cone_system(
+    world_map: Res<WorldMap>,
+    visions: Query<(&Vision, &Actor)>,
+    mut cones: Query<(&mut VisionCone, &mut Transform, &mut DrawMode)>,
+) {
+    let gravity = world_map.gravity();
+    for (mut cone, mut transform, mut draw_mode) in cones.iter_mut() {
+        let (vision, actor) = match visions.get(cone.owner) {
+            Ok(vision) => vision,
+            Err(_) => continue,
+        };
+        let facing = match actor.direction {
+            Direction::Left => std::f32::consts::PI,
+            Direction::Right => 0.0,
+        };
+        transform.rotation = Quat::from_rotation_z(gravity.angle() + facing);
+        if cone.sees_player != vision.sees_player {
+            cone.sees_player = vision.sees_player;
+            *draw_mode = DrawMode::Fill(FillMode::color(cone_color(vision.sees_player)));
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{collision::CollisionLayers, game_time::GameTime};
+use bevy::{prelude::*, sprite::Rect as SpriteRect};
+use bevy_rapier2d::prelude::*;
+
+// ratio of gravity pushing submerged bodies back up, above 1 floats
+const BUOYANCY: f32 = 1.2;
+// velocity ratio lost per second in water
+const DRAG: f32 = 3.0;
+const SPLASH_PARTICLES: usize = 8;
+const SPLASH_LIFETIME: f32 = 0.4;
+
+pub struct WaterPlugin;
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(submerge_system)
+            .add_system(buoyancy_system)
+            .add_system(splash_system);
+    }
+}
+
+#[derive(Component)]
+pub struct Water;
+
+// on bodies touching water, with the number of water volumes they overlap
+#[derive(Component)]
+pub struct Submerged(pub usize);
+
+#[derive(Component)]
+struct SplashParticle {
+    velocity: Vec2,
+    timer: Timer,
+}
+
+// sensor volume from a world space rect in pixels
+pub fn spawn_water(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    rect: &SpriteRect,
+) -> Entity {
+    let half_extents = (rect.max - rect.min) * 0.5;
+    let center = rect.min + half_extents;
+    commands
+        .spawn_bundle(ColliderBundle {
+            collider_type: ColliderType::Sensor.into(),
+            shape: ColliderShape::cuboid(
+                half_extents.x / rapier_config.scale,
+                half_extents.y / rapier_config.scale,
+            )
+            .into(),
+            position: (center / rapier_config.scale).into(),
+            flags: ColliderFlags {
+                collision_groups: CollisionLayers::sensor(),
+                active_events: ActiveEvents::INTERSECTION_EVENTS,
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.2, 0.4, 1.0, 0.3),
+                custom_size: Some(half_extents * 2.0),
+                ..Default::default()
+            },
+            // in front of the actors
+            transform: Transform::from_translation(center.extend(4.0)),
+            ..Default::default()
+        })
+        .insert(Water)
+        .id()
+}
+
+fn submerge_system(
+    mut commands: Commands,
+    mut intersection_events: EventReader<IntersectionEvent>,
+    waters: Query<(), With<Water>>,
+    mut bodies: Query<(&RigidBodyPositionComponent, Option<&mut Submerged>)>,
+    rapier_config: Res<RapierConfiguration>,
+) {
+    for event in intersection_events.iter() {
+        let (a, b) = (event.collider1.entity(), event.collider2.entity());
+        let body = if waters.get(a).is_ok() {
+            b
+        } else if waters.get(b).is_ok() {
+            a
+        } else {
+            continue;
+        };
+        let (rb_position, submerged) = match bodies.get_mut(body) {
+            Ok(body) => body,
+            Err(_) => continue,
+        };
+        match (event.intersecting, submerged) {
+            (true, Some(mut submerged)) => submerged.0 += 1,
+            (true, None) => {
+                commands.entity(body).insert(Submerged(1));
+            }
+            (false, Some(mut submerged)) if submerged.0 > 1 => submerged.0 -= 1,
+            (false, Some(_)) => {
+                commands.entity(body).remove::<Submerged>();
+            }
+            (false, None) => continue,
+        }
+
+        // splash on entering or leaving
+        let translation = rb_position.position.translation.vector;
+        let position = Vec2::new(translation.x, translation.y) * rapier_config.scale;
+        for index in 0..SPLASH_PARTICLES {
+            let angle = std::f32::consts::PI * (index as f32 + 0.5) / SPLASH_PARTICLES as f32;
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(0.6, 0.8, 1.0, 0.8),
+                        custom_size: Some(Vec2::splat(2.0)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(position.extend(5.0)),
+                    ..Default::default()
+                })
+                .insert(SplashParticle {
+                    velocity: Vec2::new(angle.cos(), angle.sin()) * 48.0,
+                    timer: Timer::from_seconds(SPLASH_LIFETIME, false),
+                });
+        }
+    }
+}
+
+fn buoyancy_system(
+    time: Res<GameTime>,
+    rapier_config: Res<RapierConfiguration>,
+    mut bodies: Query<(&mut RigidBodyVelocityComponent, &RigidBodyTypeComponent), With<Submerged>>,
+) {
+    let delta = time.delta_seconds();
+    let gravity = Vec2::new(rapier_config.gravity.x, rapier_config.gravity.y);
+    for (mut rb_velocity, rb_type) in bodies.iter_mut() {
+        if rb_type.0 != RigidBodyType::Dynamic {
+            continue;
+        }
+        let linvel = Vec2::new(rb_velocity.linvel.x, rb_velocity.linvel.y);
+        let linvel = (linvel - gravity * BUOYANCY * delta) * (1.0 - DRAG * delta).max(0.0);
+        rb_velocity.linvel = linvel.into();
+    }
+}
+
+fn splash_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut particles: Query<(Entity, &mut SplashParticle, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+        particle.timer.tick(time.delta());
+        particle.velocity.y -= 160.0 * time.delta_seconds();
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.0);
+        sprite.color.set_a(0.8 * (1.0 - particle.timer.percent()));
+        if particle.timer.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
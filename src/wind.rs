@@ -0,0 +1,181 @@
+use crate::{collision::CollisionLayers, game_time::GameTime};
+use bevy::{prelude::*, sprite::Rect as SpriteRect, utils::HashMap};
+use bevy_rapier2d::prelude::*;
+
+// one streak per this many square pixels of zone
+const STREAK_AREA: f32 = 1024.0;
+const STREAK_LENGTH: f32 = 6.0;
+// streaks drift at this ratio of the force, so stronger wind visibly blows faster
+const STREAK_SPEED_RATIO: f32 = 0.25;
+const MIN_STREAK_SPEED: f32 = 24.0;
+// the player walks with the velocity the wind would add over this long, since walking sets
+// the horizontal velocity every frame
+pub const WIND_DRIFT_TIME: f32 = 0.25;
+
+pub struct WindPlugin;
+impl Plugin for WindPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(wind_system).add_system(streak_system);
+    }
+}
+
+// sensor volume that accelerates the bodies inside it
+#[derive(Component)]
+pub struct WindZone {
+    // pixels per second squared, regardless of mass like gravity
+    pub force: Vec2,
+    half_extents: Vec2,
+}
+
+// on bodies inside wind zones, with the summed force of all of them
+#[derive(Component)]
+pub struct Windswept(pub Vec2);
+
+// position within its zone, relative to the zone center
+#[derive(Component)]
+struct WindStreak(Vec2);
+
+// sensor volume from a world space rect in pixels, force in pixels per second squared
+pub fn spawn_wind_zone(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    rect: &SpriteRect,
+    force: Vec2,
+) -> Entity {
+    let half_extents = (rect.max - rect.min) * 0.5;
+    let center = rect.min + half_extents;
+    let streaks = ((half_extents.x * half_extents.y * 4.0) / STREAK_AREA).ceil() as usize;
+    let rotation = Quat::from_rotation_z(force.y.atan2(force.x));
+    commands
+        .spawn_bundle(ColliderBundle {
+            collider_type: ColliderType::Sensor.into(),
+            shape: ColliderShape::cuboid(
+                half_extents.x / rapier_config.scale,
+                half_extents.y / rapier_config.scale,
+            )
+            .into(),
+            position: (center / rapier_config.scale).into(),
+            flags: ColliderFlags {
+                collision_groups: CollisionLayers::wind(),
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        // in front of the actors
+        .insert(Transform::from_translation(center.extend(4.0)))
+        .insert(GlobalTransform::identity())
+        .insert(WindZone {
+            force,
+            half_extents,
+        })
+        .with_children(|parent| {
+            // spread evenly instead of randomly, so levels look the same every time
+            for index in 0..streaks {
+                let offset = Vec2::new(
+                    (index as f32 * 0.618_034).fract(),
+                    (index as f32 + 0.5) / streaks as f32,
+                ) * 2.0
+                    - Vec2::ONE;
+                let offset = offset * half_extents;
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(1.0, 1.0, 1.0, 0.3),
+                            custom_size: Some(Vec2::new(STREAK_LENGTH, 1.0)),
+                            ..Default::default()
+                        },
+                        transform: Transform {
+                            translation: offset.extend(0.0),
+                            rotation,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .insert(WindStreak(offset));
+            }
+        })
+        .id()
+}
+
+fn wind_system(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    narrow_phase: Res<NarrowPhase>,
+    rapier_config: Res<RapierConfiguration>,
+    zones: Query<(Entity, &WindZone)>,
+    mut bodies: Query<(
+        Entity,
+        &mut RigidBodyVelocityComponent,
+        &RigidBodyTypeComponent,
+        Option<&Windswept>,
+    )>,
+) {
+    // summed force per body, once per zone for bodies with several colliders in it
+    let mut forces = HashMap::<Entity, Vec2>::default();
+    for (zone, wind_zone) in zones.iter() {
+        let mut inside = narrow_phase
+            .intersections_with(zone.handle())
+            .filter(|(_, _, intersecting)| *intersecting)
+            .map(|(collider1, collider2, _)| {
+                if collider1.entity() == zone {
+                    collider2.entity()
+                } else {
+                    collider1.entity()
+                }
+            })
+            .collect::<Vec<_>>();
+        inside.sort();
+        inside.dedup();
+        for body in inside {
+            *forces.entry(body).or_insert(Vec2::ZERO) += wind_zone.force;
+        }
+    }
+
+    let delta = time.delta_seconds();
+    for (entity, mut rb_velocity, rb_type, windswept) in bodies.iter_mut() {
+        let force = match forces.get(&entity) {
+            Some(force) => *force,
+            None => {
+                if windswept.is_some() {
+                    commands.entity(entity).remove::<Windswept>();
+                }
+                continue;
+            }
+        };
+        if windswept.map_or(true, |windswept| windswept.0 != force) {
+            commands.entity(entity).insert(Windswept(force));
+        }
+        if rb_type.0 != RigidBodyType::Dynamic {
+            continue;
+        }
+        let linvel = Vec2::new(rb_velocity.linvel.x, rb_velocity.linvel.y)
+            + force * delta / rapier_config.scale;
+        rb_velocity.linvel = linvel.into();
+    }
+}
+
+fn streak_system(
+    time: Res<GameTime>,
+    zones: Query<&WindZone>,
+    mut streaks: Query<(&Parent, &mut WindStreak, &mut Transform)>,
+) {
+    for (parent, mut streak, mut transform) in streaks.iter_mut() {
+        let zone = match zones.get(parent.0) {
+            Ok(zone) => zone,
+            Err(_) => continue,
+        };
+        let length = zone.force.length();
+        if length == 0.0 {
+            continue;
+        }
+        let speed = (length * STREAK_SPEED_RATIO).max(MIN_STREAK_SPEED);
+        let offset = streak.0 + zone.force / length * speed * time.delta_seconds();
+        // wrap around to the other side of the zone
+        let size = zone.half_extents * 2.0;
+        let offset = offset + zone.half_extents;
+        streak.0 =
+            Vec2::new(offset.x.rem_euclid(size.x), offset.y.rem_euclid(size.y)) - zone.half_extents;
+        transform.translation = streak.0.extend(0.0);
+    }
+}
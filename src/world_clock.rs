@@ -0,0 +1,102 @@
+use crate::{game_time::GameTime, ldtk::world::WorldMap};
+use bevy::prelude::*;
+
+// in game hours per second, a full day takes 8 minutes
+const HOURS_PER_SECOND: f32 = 24.0 / 480.0;
+const START_HOUR: f32 = 10.0;
+// of the remaining difference per second, when the target tint jumps, e.g. on a level change
+const TRANSITION_RATE: f32 = 2.0;
+// ambient tint by hour, wrapping around midnight
+const KEYFRAMES: [(f32, [f32; 3]); 6] = [
+    (0.0, [0.35, 0.4, 0.6]),
+    (5.5, [0.35, 0.4, 0.6]),
+    (7.0, [0.95, 0.75, 0.65]),
+    (9.0, [1.0, 1.0, 1.0]),
+    (18.0, [1.0, 1.0, 1.0]),
+    (20.0, [0.9, 0.6, 0.5]),
+];
+
+// time of day tinting the level tiles and background, a level's "TimeOfDay" field (Float hours
+// or "dawn", "day", "dusk", "night") pins it for that level
+pub struct WorldClockPlugin;
+impl Plugin for WorldClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldClock>()
+            .add_system(clock_system)
+            .add_system(tint_system.after(clock_system));
+    }
+}
+
+pub struct WorldClock {
+    // 0 to 24
+    pub hours: f32,
+    pub hours_per_second: f32,
+    // eased towards the tint of the current hour
+    pub tint: Color,
+}
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self {
+            hours: START_HOUR,
+            hours_per_second: HOURS_PER_SECOND,
+            tint: tint_at(START_HOUR),
+        }
+    }
+}
+impl WorldClock {
+    pub fn apply(&self, color: Color) -> Color {
+        Color::rgba(
+            color.r() * self.tint.r(),
+            color.g() * self.tint.g(),
+            color.b() * self.tint.b(),
+            color.a(),
+        )
+    }
+}
+
+// untinted color of a sprite the ambient tint applies to
+#[derive(Component)]
+pub struct Tinted(pub Color);
+
+pub fn tint_at(hours: f32) -> Color {
+    let hours = hours.rem_euclid(24.0);
+    let next = KEYFRAMES
+        .iter()
+        .position(|(hour, _)| *hour > hours)
+        .unwrap_or(0);
+    let (from_hour, from) = KEYFRAMES[(next + KEYFRAMES.len() - 1) % KEYFRAMES.len()];
+    let (to_hour, to) = KEYFRAMES[next];
+    let span = (to_hour - from_hour).rem_euclid(24.0).max(f32::EPSILON);
+    let t = ((hours - from_hour).rem_euclid(24.0) / span).clamp(0.0, 1.0);
+    let channel = |index: usize| from[index] + (to[index] - from[index]) * t;
+    Color::rgb(channel(0), channel(1), channel(2))
+}
+
+fn clock_system(time: Res<GameTime>, world_map: Res<WorldMap>, mut clock: ResMut<WorldClock>) {
+    clock.hours = (clock.hours + clock.hours_per_second * time.delta_seconds()).rem_euclid(24.0);
+    let hours = world_map
+        .active_level()
+        .and_then(|level| level.time_of_day)
+        .unwrap_or(clock.hours);
+    let target = Vec4::from(tint_at(hours));
+    let current = Vec4::from(clock.tint);
+    // within a visible step is close enough, so tiles aren't retinted every frame
+    if (target - current).abs().max_element() > 1.0 / 255.0 {
+        let ratio = (TRANSITION_RATE * time.delta_seconds()).min(1.0);
+        clock.tint = Color::from(current + (target - current) * ratio);
+    }
+}
+
+fn tint_system(
+    clock: Res<WorldClock>,
+    mut applied: Local<Option<Color>>,
+    mut query: Query<(&Tinted, ChangeTrackers<Tinted>, &mut TextureAtlasSprite)>,
+) {
+    let retint = *applied != Some(clock.tint);
+    *applied = Some(clock.tint);
+    for (Tinted(color), trackers, mut sprite) in query.iter_mut() {
+        if retint || trackers.is_added() {
+            sprite.color = clock.apply(*color);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use crate::save::SaveData;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// puzzle and progress state of ldtk entities, read when they spawn and kept in the save
+pub struct WorldFlagsPlugin;
+impl Plugin for WorldFlagsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldFlags>()
+            .add_startup_system(load_system)
+            .add_system_to_stage(CoreStage::Last, save_system);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FlagValue {
+    Bool(bool),
+    Int(i64),
+}
+
+// flags by ldtk iid, then by name, e.g. "open" of a door or "defeated" of an enemy
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldFlags(HashMap<String, HashMap<String, FlagValue>>);
+impl WorldFlags {
+    pub fn get(&self, iid: &str, name: &str) -> Option<FlagValue> {
+        self.0.get(iid).and_then(|flags| flags.get(name)).copied()
+    }
+
+    pub fn bool(&self, iid: &str, name: &str) -> Option<bool> {
+        match self.get(iid, name) {
+            Some(FlagValue::Bool(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn int(&self, iid: &str, name: &str) -> Option<i64> {
+        match self.get(iid, name) {
+            Some(FlagValue::Int(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, iid: &str, name: &str, value: FlagValue) {
+        self.0
+            .entry(iid.to_string())
+            .or_default()
+            .insert(name.to_string(), value);
+    }
+}
+
+fn load_system(save: Res<SaveData>, mut flags: ResMut<WorldFlags>) {
+    *flags = save.world_flags.clone();
+}
+
+fn save_system(flags: Res<WorldFlags>, mut save: ResMut<SaveData>) {
+    if !flags.is_changed() || save.world_flags == *flags {
+        return;
+    }
+    save.world_flags = flags.clone();
+    if let Err(err) = save.write() {
+        error!("{:?}", err);
+    }
+}